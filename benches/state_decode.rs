@@ -0,0 +1,70 @@
+//! State decoding throughput: `Value` round trip vs. direct decode
+//!
+//! Decodes one second of traffic at 20 Hz from 4 players.
+//!
+//! ```sh
+//! cargo bench --bench state_decode
+//! ```
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use nostr_arena::migrate::{decode_event, decode_state, encode_event};
+use nostr_arena::{EventContent, StateEventContent};
+use serde::{Deserialize, Serialize};
+use std::hint::black_box;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct GameState {
+    score: i64,
+    position: (f32, f32),
+    velocity: (f32, f32),
+    inputs: Vec<u8>,
+    name: String,
+}
+
+const PLAYERS: usize = 4;
+const RATE_HZ: usize = 20;
+
+fn messages() -> Vec<String> {
+    (0..PLAYERS * RATE_HZ)
+        .map(|i| {
+            let state = GameState {
+                score: i as i64,
+                position: (i as f32 * 0.5, 12.25),
+                velocity: (1.0, -0.5),
+                inputs: vec![1, 0, 1, 1, 0, 0, 1, 0],
+                name: format!("player-{}", i % PLAYERS),
+            };
+            encode_event(&EventContent::State(StateEventContent {
+                game_state: serde_json::to_value(state).unwrap(),
+            }))
+            .unwrap()
+        })
+        .collect()
+}
+
+fn state_decode(c: &mut Criterion) {
+    let messages = messages();
+    let mut group = c.benchmark_group("state_decode");
+    group.throughput(Throughput::Elements(messages.len() as u64));
+
+    group.bench_function("via_value", |b| {
+        b.iter(|| {
+            for json in &messages {
+                if let Ok(EventContent::State(s)) = decode_event(json) {
+                    black_box(serde_json::from_value::<GameState>(s.game_state).unwrap());
+                }
+            }
+        })
+    });
+    group.bench_function("direct", |b| {
+        b.iter(|| {
+            for json in &messages {
+                black_box(decode_state::<GameState>(json).unwrap().unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, state_decode);
+criterion_main!(benches);
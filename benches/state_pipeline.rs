@@ -0,0 +1,98 @@
+//! State hot path: serialize, sign, receive and decode
+//!
+//! Simulates one second of a match: every player sends `rate` states; we
+//! sign ours and handle everyone else's as the room subscription would,
+//! through the `bench` feature's offline hooks (no relays involved).
+//!
+//! ```sh
+//! cargo bench --bench state_pipeline --features bench
+//! ```
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use nostr_arena::{Arena, ArenaConfig};
+use serde::{Deserialize, Serialize};
+use std::hint::black_box;
+use tokio::runtime::Runtime;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct GameState {
+    score: i64,
+    position: (f32, f32),
+    velocity: (f32, f32),
+    /// Board or inventory payload; sets the message size
+    cells: Vec<u8>,
+}
+
+/// Payload sizes: a shooter's position update and a puzzle board
+const SIZES: &[(&str, usize)] = &[("small", 8), ("large", 2048)];
+const PLAYERS: &[usize] = &[2, 4, 8];
+const RATES_HZ: &[usize] = &[10, 30, 60];
+
+fn state(cells: usize, i: usize) -> GameState {
+    GameState {
+        score: i as i64,
+        position: (i as f32 * 0.5, 12.25),
+        velocity: (1.0, -0.5),
+        cells: (0..cells).map(|c| (c + i) as u8).collect(),
+    }
+}
+
+/// Our arena and the other players', all in the same room
+async fn room(players: usize) -> (Arena<GameState>, Vec<Arena<GameState>>) {
+    let config = ArenaConfig::new("bench");
+    let mut arenas = Vec::new();
+    for _ in 0..players {
+        arenas.push(Arena::new(config.clone()).await.unwrap());
+    }
+    let pubkeys: Vec<String> = arenas.iter().map(|a| a.public_key()).collect();
+    for arena in &arenas {
+        let peers: Vec<String> = pubkeys
+            .iter()
+            .filter(|p| **p != arena.public_key())
+            .cloned()
+            .collect();
+        arena.bench_enter("room", &peers).await;
+        while arena.try_recv().await.is_some() {}
+    }
+    let me = arenas.remove(0);
+    (me, arenas)
+}
+
+fn state_pipeline(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("state_pipeline");
+    group.sample_size(20);
+
+    for &(size, cells) in SIZES {
+        for &players in PLAYERS {
+            let (me, peers) = rt.block_on(room(players));
+            for &rate in RATES_HZ {
+                group.throughput(Throughput::Elements((players * rate) as u64));
+                let id = BenchmarkId::new(size, format!("{players}p@{rate}Hz"));
+                group.bench_function(id, |b| {
+                    b.iter(|| {
+                        rt.block_on(async {
+                            for tick in 0..rate {
+                                black_box(me.bench_state_event(&state(cells, tick)).await.unwrap());
+                                let mut events = Vec::with_capacity(peers.len());
+                                for peer in &peers {
+                                    events.push(
+                                        peer.bench_state_event(&state(cells, tick)).await.unwrap(),
+                                    );
+                                }
+                                me.bench_receive(events).await;
+                                while let Some(event) = me.try_recv().await {
+                                    black_box(event);
+                                }
+                            }
+                        })
+                    })
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, state_pipeline);
+criterion_main!(benches);
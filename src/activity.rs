@@ -0,0 +1,167 @@
+//! Cross-game activity
+//!
+//! A player's activity in each game is published as a NIP-38 user status
+//! (kind 30315) with the `d` tag `arena-{gameId}`, so every game built on
+//! this crate shares one identity and launchers can show what friends are
+//! playing. The content is the human-readable line generic clients display;
+//! the game, status and room travel in tags:
+//!
+//! - `t`: game id
+//! - `status`: `lobby` or `playing`
+//! - `room`: room id (optional)
+//! - `r`: room URL for deep links (optional)
+//!
+//! An empty content clears the status, as in NIP-38.
+
+use crate::client::NostrClient;
+use crate::error::{ArenaError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// `d` tag prefix of arena user statuses
+pub const STATUS_PREFIX: &str = "arena-";
+
+/// What a player is doing in a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityStatus {
+    /// Waiting in a room or browsing rooms
+    Lobby,
+    /// In a running match
+    Playing,
+}
+
+impl ActivityStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityStatus::Lobby => "lobby",
+            ActivityStatus::Playing => "playing",
+        }
+    }
+}
+
+impl fmt::Display for ActivityStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ActivityStatus {
+    type Err = ArenaError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "lobby" => Ok(ActivityStatus::Lobby),
+            "playing" => Ok(ActivityStatus::Playing),
+            _ => Err(ArenaError::InvalidValue(format!(
+                "unknown activity status: {s}"
+            ))),
+        }
+    }
+}
+
+/// A player's activity in one game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Activity {
+    pub pubkey: String,
+    pub game_id: String,
+    pub status: ActivityStatus,
+    pub room_id: Option<String>,
+    /// Link into the room, if the game has a `base_url`
+    pub room_url: Option<String>,
+    /// Publish time in ms
+    pub updated_at: u64,
+}
+
+impl Activity {
+    /// `d` tag of the status event for a game
+    pub fn status_tag(game_id: &str) -> String {
+        format!("{STATUS_PREFIX}{game_id}")
+    }
+
+    /// Human-readable line, e.g. "playing tetris" or "in lobby for sasso"
+    pub fn text(&self) -> String {
+        match self.status {
+            ActivityStatus::Lobby => format!("in lobby for {}", self.game_id),
+            ActivityStatus::Playing => format!("playing {}", self.game_id),
+        }
+    }
+
+    /// Tags of the status event, besides `d`
+    pub fn tags(&self) -> Vec<Vec<String>> {
+        let mut tags = vec![
+            vec!["t".to_string(), self.game_id.clone()],
+            vec!["status".to_string(), self.status.to_string()],
+        ];
+        if let Some(room_id) = &self.room_id {
+            tags.push(vec!["room".to_string(), room_id.clone()]);
+        }
+        if let Some(url) = &self.room_url {
+            tags.push(vec!["r".to_string(), url.clone()]);
+        }
+        tags
+    }
+
+    /// Read an activity back from a status event's fields. Returns `None`
+    /// for other statuses, cleared statuses and malformed tags.
+    pub fn from_status(
+        pubkey: String,
+        content: &str,
+        tags: &[Vec<String>],
+        created_at_ms: u64,
+    ) -> Option<Self> {
+        if content.is_empty() {
+            return None;
+        }
+        let value = |name: &str| {
+            tags.iter()
+                .find(|tag| tag.first().map(String::as_str) == Some(name))
+                .and_then(|tag| tag.get(1).cloned())
+        };
+
+        let game_id = value("d")?.strip_prefix(STATUS_PREFIX)?.to_string();
+        Some(Self {
+            pubkey,
+            status: value("status")?.parse().ok()?,
+            room_id: value("room"),
+            room_url: value("r"),
+            updated_at: created_at_ms,
+            game_id,
+        })
+    }
+}
+
+impl fmt::Display for Activity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+/// Current activity of `pubkeys` across all games on the client's read
+/// relays, one entry per player and game
+pub async fn fetch_activity(client: &NostrClient, pubkeys: &[String]) -> Result<Vec<Activity>> {
+    let events = client.fetch_user_statuses(pubkeys).await?;
+
+    let mut latest: HashMap<(String, String), Activity> = HashMap::new();
+    for event in events {
+        let tags: Vec<Vec<String>> = event.tags.iter().map(|t| t.as_slice().to_vec()).collect();
+        let Some(activity) = Activity::from_status(
+            event.pubkey.to_hex(),
+            &event.content,
+            &tags,
+            event.created_at.as_u64() * 1000,
+        ) else {
+            continue;
+        };
+        let key = (activity.pubkey.clone(), activity.game_id.clone());
+        if latest
+            .get(&key)
+            .is_none_or(|known| known.updated_at < activity.updated_at)
+        {
+            latest.insert(key, activity);
+        }
+    }
+    Ok(latest.into_values().collect())
+}
@@ -1,16 +1,39 @@
 //! Arena - Main game room management
 
+use crate::activity::{Activity, ActivityStatus, fetch_activity};
+use crate::chat::{FloodGuard, sanitize_chat};
 use crate::client::NostrClient;
+use crate::clock::ChessClock;
 use crate::error::{ArenaError, Result};
+use crate::hooks::{MatchEnd, MatchFinished, PlayerJoined, RoomCreated};
+use crate::identity::IdentityLink;
+use crate::invite::{Invite, parse_room_link, room_url};
+use crate::latency::{ArenaStats, LatencyReport, PeerLatency, RelayClocks};
+use crate::migrate::{
+    decode_event, decode_room, decode_state, encode_event, encode_room, message_version,
+};
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+use crate::network::NetworkRoute;
+use crate::notify::{Notice, Notifier};
+use crate::pool::RelayPool;
+use crate::record::{MatchRecord, MatchResult, PlayerOutcome};
+use crate::rng::SeededRng;
+use crate::rng::turn_order;
+use crate::seal::{RoomKey, RoomSeal};
+use crate::shadow::EventShadow;
+use crate::shard::PlayerMap;
 use crate::spawn::spawn;
-use crate::time::{Duration, interval, sleep};
+use crate::time::{Duration, interval, monotonic_at, monotonic_ms, sleep, timeout};
 use crate::types::*;
+use nostr_sdk::EventId;
 use serde::{Serialize, de::DeserializeOwned};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::marker::PhantomData;
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
-use tracing::{info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{RwLock, broadcast, mpsc};
+use tracing::{debug, info, warn};
 
 /// Arena events emitted to the application
 #[derive(Debug, Clone)]
@@ -21,14 +44,112 @@ pub enum ArenaEvent<T> {
     PlayerLeave(String),
     /// Player state updated
     PlayerState { pubkey: String, state: T },
+    /// Public summary a player sent with `send_summary()`
+    PlayerSummary {
+        pubkey: String,
+        summary: serde_json::Value,
+    },
     /// Player disconnected (heartbeat timeout)
     PlayerDisconnect(String),
     /// Player sent game over
     PlayerGameOver {
         pubkey: String,
-        reason: String,
+        reason: Reason,
         final_score: Option<i64>,
     },
+    /// The game state we asked for with `request_snapshot()`, e.g. after
+    /// joining a running game; `states` are kept like received states
+    SnapshotReceived {
+        from: String,
+        states: HashMap<String, T>,
+    },
+    /// The host decided the match from the game overs it collected; the
+    /// same on every peer. `winner` is `None` for a draw.
+    GameEnded {
+        winner: Option<String>,
+        order: Vec<String>,
+    },
+    /// Player changed ready status
+    PlayerReady { pubkey: String, ready: bool },
+    /// The set of ready players changed
+    ReadyChanged { ready_count: usize, total: usize },
+    /// The turn passed to this player
+    TurnChanged(String),
+    /// With `enforce_turns`, replaces `TurnChanged`: it is this player's
+    /// turn, from the start of the game on
+    TurnStart { pubkey: String },
+    /// With `enforce_turns`, the player to move submitted their move
+    TurnReceived {
+        pubkey: String,
+        payload: serde_json::Value,
+    },
+    /// The turn timer ran out on this player's turn again; `strikes` counts
+    /// the timeouts in a row (host only)
+    TurnTimeout { pubkey: String, strikes: u32 },
+    /// The host removed this player after a vote; if it is us, leave the
+    /// room
+    PlayerKicked(String),
+    /// The host or a co-host moved the room to another round or phase
+    PhaseChanged { round: u32, phase: Option<String> },
+    /// A new round started with a fresh seed (`next_round_seed()`)
+    RoundStart { round: u32, seed: u64 },
+    /// The host or a co-host opened a vote
+    VoteProposed { topic: String, options: Vec<String> },
+    /// A player voted
+    VoteCast {
+        pubkey: String,
+        topic: String,
+        choice: String,
+    },
+    /// The host closed a vote; `tally` is in option order
+    VoteResult {
+        topic: String,
+        winner: String,
+        tally: Vec<(String, usize)>,
+    },
+    /// The host resolved competing claims on `claim_id`
+    ClaimResolved { winner: String, claim_id: String },
+    /// A relay's event timestamps are more than `SKEW_THRESHOLD_MS` off
+    /// local time; `created_at`-derived times are corrected by the skew
+    RelayClockSkew { relay: String, skew_ms: i64 },
+    /// A player's time bank dropped to the time control's low time mark
+    TimeLow { pubkey: String },
+    /// A player ran out of time and forfeits; the game is over
+    FlagFall { pubkey: String },
+    /// A player's declared input chain doesn't match the `state` messages
+    /// we received from them (`input_audit`)
+    AuditFailed(String),
+    /// A player invited us to their room; answer with `accept_invite()` or
+    /// `decline_invite()`
+    InviteReceived {
+        from: String,
+        room_id: String,
+        game_id: String,
+    },
+    /// A player accepted our invitation and is joining
+    InviteAccepted { from: String, room_id: String },
+    /// A player declined our invitation
+    InviteDeclined { from: String, room_id: String },
+    /// The whole party is seated in this room
+    PartyMatched(String),
+    /// The party leader gave up on this room; we left it again
+    PartyMatchFailed(String),
+    /// Chat message from a player (sanitized, flood-limited, mutes applied)
+    Chat { pubkey: String, text: String },
+    /// The host set chat slow mode to this many seconds (0 = off)
+    ChatSlowMode(u32),
+    /// The host muted a player for everyone
+    ChatMuted(String),
+    /// The host lifted a player's mute
+    ChatUnmuted(String),
+    /// The host cleared the chat; drop the messages shown so far
+    ChatCleared,
+    /// Message on a named channel nobody subscribed to with `channel()`
+    Message {
+        pubkey: String,
+        channel: String,
+        payload: serde_json::Value,
+    },
     /// Player requested rematch
     RematchRequested(String),
     /// Rematch accepted, new game starting
@@ -40,9 +161,254 @@ pub enum ArenaEvent<T> {
     /// Countdown tick
     CountdownTick(u32),
     /// Game started
-    GameStart,
+    GameStart { config: Option<serde_json::Value> },
+    /// All remote players stopped responding during play
+    MatchAbandoned,
+    /// The room passed its `expires_at`; background tasks stopped and the
+    /// room was left (status `Finished`)
+    RoomExpired(String),
+    /// The network route changed (native only); relays were reconnected
+    /// and our heartbeat and last state resent
+    NetworkChanged,
+    /// Our key joined the room again from another session (a second tab, a
+    /// restarted client), which took over our seat. We stopped sending in
+    /// the room; call `leave()` to reset.
+    SessionSuperseded(String),
+    /// Message of a type this version does not understand
+    UnknownMessage {
+        pubkey: String,
+        message_type: String,
+        payload: serde_json::Value,
+    },
     /// Error occurred
-    Error(String),
+    Error(Reason),
+}
+
+/// How urgently the app should handle an [`ArenaEvent`], most urgent
+/// first, so frontends can handle control events every frame and defer the
+/// rest to idle time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventClass {
+    /// Room and match transitions: joins, leaves, ready, turns, countdown,
+    /// start, game over, invitations, moderation and errors
+    Control,
+    /// Game traffic that later messages supersede or add to: state, chat,
+    /// channel messages and votes
+    Gameplay,
+    /// Informational events: summaries, clock skew, network changes and
+    /// unknown messages
+    Telemetry,
+}
+
+impl EventClass {
+    /// Lowercase name, as bindings expose it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventClass::Control => "control",
+            EventClass::Gameplay => "gameplay",
+            EventClass::Telemetry => "telemetry",
+        }
+    }
+}
+
+impl std::fmt::Display for EventClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<T> ArenaEvent<T> {
+    /// Scheduling class of the event
+    pub fn class(&self) -> EventClass {
+        match self {
+            ArenaEvent::PlayerState { .. }
+            | ArenaEvent::SnapshotReceived { .. }
+            | ArenaEvent::Chat { .. }
+            | ArenaEvent::Message { .. }
+            | ArenaEvent::VoteCast { .. } => EventClass::Gameplay,
+            ArenaEvent::PlayerSummary { .. }
+            | ArenaEvent::RelayClockSkew { .. }
+            | ArenaEvent::NetworkChanged
+            | ArenaEvent::UnknownMessage { .. } => EventClass::Telemetry,
+            ArenaEvent::PlayerJoin(_)
+            | ArenaEvent::PlayerLeave(_)
+            | ArenaEvent::PlayerDisconnect(_)
+            | ArenaEvent::PlayerGameOver { .. }
+            | ArenaEvent::GameEnded { .. }
+            | ArenaEvent::PlayerReady { .. }
+            | ArenaEvent::ReadyChanged { .. }
+            | ArenaEvent::TurnChanged(_)
+            | ArenaEvent::TurnStart { .. }
+            | ArenaEvent::TurnReceived { .. }
+            | ArenaEvent::TurnTimeout { .. }
+            | ArenaEvent::PlayerKicked(_)
+            | ArenaEvent::PhaseChanged { .. }
+            | ArenaEvent::RoundStart { .. }
+            | ArenaEvent::VoteProposed { .. }
+            | ArenaEvent::VoteResult { .. }
+            | ArenaEvent::ClaimResolved { .. }
+            | ArenaEvent::TimeLow { .. }
+            | ArenaEvent::FlagFall { .. }
+            | ArenaEvent::AuditFailed(_)
+            | ArenaEvent::InviteReceived { .. }
+            | ArenaEvent::InviteAccepted { .. }
+            | ArenaEvent::InviteDeclined { .. }
+            | ArenaEvent::PartyMatched(_)
+            | ArenaEvent::PartyMatchFailed(_)
+            | ArenaEvent::ChatSlowMode(_)
+            | ArenaEvent::ChatMuted(_)
+            | ArenaEvent::ChatUnmuted(_)
+            | ArenaEvent::ChatCleared
+            | ArenaEvent::RematchRequested(_)
+            | ArenaEvent::RematchStart(_)
+            | ArenaEvent::AllReady
+            | ArenaEvent::CountdownStart(_)
+            | ArenaEvent::CountdownTick(_)
+            | ArenaEvent::GameStart { .. }
+            | ArenaEvent::MatchAbandoned
+            | ArenaEvent::RoomExpired(_)
+            | ArenaEvent::SessionSuperseded(_)
+            | ArenaEvent::Error(_) => EventClass::Control,
+        }
+    }
+}
+
+/// Outcome of a throttled send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendResult {
+    /// Event was published
+    Sent(EventId),
+    /// Event was dropped by the throttle; sending is allowed again at
+    /// `next_allowed` (ms on `time::monotonic_ms`)
+    Throttled { next_allowed: u64 },
+}
+
+/// Outcome of [`Arena::send_batch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchResult {
+    /// The batch was published, as these events
+    Sent(Vec<EventId>),
+    /// A chat message was refused and nothing was sent; chat is allowed
+    /// again at `next_allowed` (ms on `time::monotonic_ms`)
+    Throttled { next_allowed: u64 },
+}
+
+/// What [`Arena::shutdown`] got out before disconnecting
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Room we left, if we were in one
+    pub room_id: Option<String>,
+    /// Final messages published in time: `leave`, and `room` when hosting
+    pub delivered: Vec<&'static str>,
+    /// Final messages that failed or missed `shutdown_timeout`
+    pub undelivered: Vec<&'static str>,
+}
+
+impl ShutdownReport {
+    /// Whether every final message was published
+    pub fn is_complete(&self) -> bool {
+        self.undelivered.is_empty()
+    }
+
+    fn record<R>(&mut self, message: &'static str, outcome: Option<Result<R>>) {
+        match outcome {
+            Some(Ok(_)) => self.delivered.push(message),
+            Some(Err(e)) => {
+                warn!("Failed to send {} on shutdown: {}", message, e);
+                self.undelivered.push(message);
+            }
+            None => {
+                warn!("Timed out sending {} on shutdown", message);
+                self.undelivered.push(message);
+            }
+        }
+    }
+}
+
+impl SendResult {
+    /// Whether the event was published
+    pub fn is_sent(&self) -> bool {
+        matches!(self, SendResult::Sent(_))
+    }
+}
+
+/// Messages of one tick for [`Arena::send_batch`], in the order receivers
+/// handle them
+#[derive(Debug, Clone)]
+pub struct MessageBatch<T> {
+    entries: Vec<BatchEntry<T>>,
+}
+
+#[derive(Debug, Clone)]
+enum BatchEntry<T> {
+    State(T),
+    Chat(String),
+    Message {
+        channel: String,
+        payload: serde_json::Value,
+    },
+    Heartbeat,
+}
+
+impl<T> Default for MessageBatch<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T> MessageBatch<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(mut self, state: T) -> Self {
+        self.entries.push(BatchEntry::State(state));
+        self
+    }
+
+    pub fn chat(mut self, text: impl Into<String>) -> Self {
+        self.entries.push(BatchEntry::Chat(text.into()));
+        self
+    }
+
+    /// An app-defined message on a named channel, as with `send_message`
+    pub fn message<M: Serialize>(mut self, channel: &str, payload: &M) -> Result<Self> {
+        self.entries.push(BatchEntry::Message {
+            channel: channel.to_string(),
+            payload: serde_json::to_value(payload)?,
+        });
+        Ok(self)
+    }
+
+    pub fn heartbeat(mut self) -> Self {
+        self.entries.push(BatchEntry::Heartbeat);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// What a joiner knows of a room's password
+pub(crate) enum JoinSecret {
+    Password(String),
+    /// Proof for our key handed over in an invitation
+    Proof(String),
+}
+
+/// Host's answer to our join request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SeatReply {
+    Pending,
+    Granted,
+    Denied(ReasonCode),
 }
 
 /// Arena - Manages a multiplayer game room over Nostr
@@ -51,11 +417,42 @@ pub struct Arena<T> {
     config: ArenaConfig,
     client: Arc<NostrClient>,
     room_state: Arc<RwLock<RoomState>>,
-    players: Arc<RwLock<HashMap<String, PlayerPresence>>>,
-    player_states: Arc<RwLock<HashMap<String, T>>>,
+    players: Arc<PlayerMap<PlayerPresence>>,
+    player_states: Arc<PlayerMap<T>>,
     event_tx: mpsc::Sender<ArenaEvent<T>>,
     event_rx: Arc<RwLock<mpsc::Receiver<ArenaEvent<T>>>>,
-    last_state_update: Arc<RwLock<u64>>,
+    last_sent: Arc<RwLock<HashMap<String, u64>>>,
+    /// Random id of this arena's joins; see `JoinEventContent::session`
+    session: String,
+    seat_reply: Arc<RwLock<SeatReply>>,
+    /// Who we asked for a snapshot: the only sender we take one from
+    snapshot_from: Arc<RwLock<Option<String>>>,
+    muted: Arc<RwLock<HashSet<String>>>,
+    channels: Arc<RwLock<HashMap<String, broadcast::Sender<ChannelMessage>>>>,
+    blocked: Arc<RwLock<HashSet<String>>>,
+    chat_guards: Arc<PlayerMap<FloodGuard>>,
+    /// Each player's last chat message, for the host's slow mode
+    slow_guards: Arc<PlayerMap<FloodGuard>>,
+    peer_latency: Arc<PlayerMap<PeerLatency>>,
+    /// States received since the last digest (host of a `Hosted` room)
+    digest_states: Arc<PlayerMap<serde_json::Value>>,
+    input_chains: Arc<PlayerMap<InputChain>>,
+    /// Invitations received and not answered yet, by room id
+    invites: Arc<RwLock<HashMap<String, (String, InviteEventContent)>>>,
+    /// Host of every room looked up so far, by room tag. The first host seen
+    /// for a room is kept, so a newer event from another key can't take it.
+    room_hosts: Arc<RwLock<HashMap<String, String>>>,
+    notifier: Arc<RwLock<Option<Arc<dyn Notifier>>>>,
+    /// Whether the app window has focus; notices are only raised without
+    focused: Arc<AtomicBool>,
+    /// Whether the app is in the background; see [`Arena::set_visible`]
+    away: Arc<AtomicBool>,
+    /// Our last published state, resent when coming back to the foreground
+    last_state: Arc<RwLock<Option<T>>>,
+    /// Control messages seen in the last `resend_window`
+    shadow: Arc<RwLock<EventShadow>>,
+    #[cfg(feature = "raw-events")]
+    raw_tx: broadcast::Sender<nostr_sdk::Event>,
     _marker: PhantomData<T>,
 }
 
@@ -66,19 +463,103 @@ where
     /// Create a new Arena
     pub async fn new(config: ArenaConfig) -> Result<Self> {
         let client = NostrClient::new(config.relays.clone()).await?;
+        Ok(Self::with_client(config, client))
+    }
+
+    /// Create a new Arena with a persistent identity (hex or nsec secret key)
+    pub async fn with_secret_key(config: ArenaConfig, secret_key: &str) -> Result<Self> {
+        let client = NostrClient::with_secret_key(secret_key, config.relays.clone()).await?;
+        Ok(Self::with_client(config, client))
+    }
+
+    /// Create a new Arena with the identity of a NIP-49 `ncryptsec` backup
+    /// made with [`Arena::export_encrypted_key`]
+    pub async fn with_encrypted_key(
+        config: ArenaConfig,
+        ncryptsec: &str,
+        passphrase: &str,
+    ) -> Result<Self> {
+        let client =
+            NostrClient::import_encrypted_key(ncryptsec, passphrase, config.relays.clone()).await?;
+        Ok(Self::with_client(config, client))
+    }
+
+    /// Create a new Arena that plays under a fresh throwaway key, linked to
+    /// the long-term identity `identity_key` (hex or nsec). Peers see the
+    /// link in the join handshake and verify it with `player_identity()`;
+    /// relay observers only see the throwaway key. Use a new Arena for
+    /// every match to get a new key.
+    pub async fn with_linked_identity(mut config: ArenaConfig, identity_key: &str) -> Result<Self> {
+        let identity =
+            nostr_sdk::Keys::parse(identity_key).map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        let client = NostrClient::new(config.relays.clone()).await?;
+        config.identity_link = Some(IdentityLink::sign(
+            &identity,
+            &config.game_id,
+            &client.public_key(),
+        )?);
+        Ok(Self::with_client(config, client))
+    }
+
+    /// Create a new Arena that signs through an external `NostrSigner`
+    /// (e.g. a remote signer) instead of local keys
+    pub async fn with_signer<S>(config: ArenaConfig, signer: S) -> Result<Self>
+    where
+        S: nostr_sdk::prelude::IntoNostrSigner,
+    {
+        let client = NostrClient::with_signer(signer, config.relays.clone()).await?;
+        Ok(Self::with_client(config, client))
+    }
+
+    /// Create a new Arena on a shared [`RelayPool`], reusing its connections
+    /// and identity
+    pub fn with_pool(config: ArenaConfig, pool: &RelayPool) -> Self {
+        let client = NostrClient::with_pool(pool, config.relays.clone());
+        Self::with_client(config, client)
+    }
+
+    fn with_client(config: ArenaConfig, client: NostrClient) -> Self {
+        let client = client
+            .with_relay_sets(config.read_relays.clone(), config.write_relays.clone())
+            .with_extra_tags(&config.extra_tags)
+            .with_client_tag(config.client_tag())
+            .with_thread_tags(config.thread_tags)
+            .with_fetch_timeout(config.fetch_timeout)
+            .with_middleware(config.middleware.clone());
         let (event_tx, event_rx) = mpsc::channel(100);
+        let blocked = config.blocklist.iter().cloned().collect();
 
-        Ok(Self {
+        Self {
             config,
             client: Arc::new(client),
             room_state: Arc::new(RwLock::new(RoomState::default())),
-            players: Arc::new(RwLock::new(HashMap::new())),
-            player_states: Arc::new(RwLock::new(HashMap::new())),
+            players: Arc::new(PlayerMap::new()),
+            player_states: Arc::new(PlayerMap::new()),
             event_tx,
             event_rx: Arc::new(RwLock::new(event_rx)),
-            last_state_update: Arc::new(RwLock::new(0)),
+            last_sent: Arc::new(RwLock::new(HashMap::new())),
+            session: format!("{:016x}", generate_seed()),
+            seat_reply: Arc::new(RwLock::new(SeatReply::Pending)),
+            snapshot_from: Arc::new(RwLock::new(None)),
+            muted: Arc::new(RwLock::new(HashSet::new())),
+            channels: Arc::new(RwLock::new(HashMap::new())),
+            blocked: Arc::new(RwLock::new(blocked)),
+            chat_guards: Arc::new(PlayerMap::new()),
+            slow_guards: Arc::new(PlayerMap::new()),
+            peer_latency: Arc::new(PlayerMap::new()),
+            digest_states: Arc::new(PlayerMap::new()),
+            input_chains: Arc::new(PlayerMap::new()),
+            invites: Arc::new(RwLock::new(HashMap::new())),
+            room_hosts: Arc::new(RwLock::new(HashMap::new())),
+            notifier: Arc::new(RwLock::new(None)),
+            focused: Arc::new(AtomicBool::new(true)),
+            away: Arc::new(AtomicBool::new(false)),
+            last_state: Arc::new(RwLock::new(None)),
+            shadow: Arc::new(RwLock::new(EventShadow::new())),
+            #[cfg(feature = "raw-events")]
+            raw_tx: broadcast::channel(256).0,
             _marker: PhantomData,
-        })
+        }
     }
 
     /// Get the public key
@@ -86,34 +567,409 @@ where
         self.client.public_key()
     }
 
+    pub(crate) fn config(&self) -> &ArenaConfig {
+        &self.config
+    }
+
+    pub(crate) fn client(&self) -> &NostrClient {
+        &self.client
+    }
+
+    /// Deliver an event to the application
+    pub(crate) async fn emit(&self, event: ArenaEvent<T>) {
+        let _ = self.event_tx.send(event).await;
+    }
+
     /// Get current room state
     pub async fn room_state(&self) -> RoomState {
         self.room_state.read().await.clone()
     }
 
+    /// Seed of the current room (changes on rematch)
+    pub async fn seed(&self) -> u64 {
+        self.room_state.read().await.seed
+    }
+
+    /// Deterministic generator for the room seed; derive per-player and
+    /// per-round streams from it with `player()` and `round()`
+    pub async fn rng(&self) -> SeededRng {
+        SeededRng::new(self.seed().await)
+    }
+
     /// Get current players
     pub async fn players(&self) -> Vec<PlayerPresence> {
-        self.players.read().await.values().cloned().collect()
+        self.players.values()
+    }
+
+    /// Long-term identity (hex public key) behind a player's match key, if
+    /// they joined with a valid identity link
+    pub async fn player_identity(&self, pubkey: &str) -> Option<String> {
+        self.players
+            .get(pubkey)?
+            .identity?
+            .verify(&self.config.game_id, pubkey)
+    }
+
+    /// Optional protocol features every player in the room supports: our
+    /// `capabilities` intersected with what each peer announced. Players on
+    /// versions without capability exchange count as JSON only.
+    pub async fn negotiated_capabilities(&self) -> Capabilities {
+        let me = self.public_key();
+        self.players
+            .values()
+            .into_iter()
+            .filter(|p| p.pubkey != me)
+            .fold(self.config.capabilities.clone(), |common, p| {
+                common.intersect(&p.capabilities.unwrap_or_default())
+            })
     }
 
     /// Get player count
     pub async fn player_count(&self) -> usize {
-        self.players.read().await.len()
+        self.players.len()
+    }
+
+    /// Our identity as a passphrase-protected NIP-49 `ncryptsec` string, for
+    /// "back up your game identity" flows; restore it with
+    /// [`Arena::with_encrypted_key`]. Blocks while scrypt runs, see
+    /// [`NostrClient::export_encrypted_key`]
+    pub fn export_encrypted_key(&self, passphrase: &str) -> Result<String> {
+        self.client.export_encrypted_key(passphrase)
+    }
+
+    /// Relay URLs with whether each is connected
+    pub async fn relay_status(&self) -> Vec<(String, bool)> {
+        self.client.relay_status().await
+    }
+
+    /// Per-peer and per-relay latency of inbound room events
+    pub async fn latency_report(&self) -> LatencyReport {
+        self.client.latency_report().await
+    }
+
+    /// How far each relay's event timestamps are from local time
+    pub async fn relay_clocks(&self) -> RelayClocks {
+        self.client.relay_clocks().await
+    }
+
+    /// Heartbeat-based one-way latency and clock offset of each peer
+    pub async fn stats(&self) -> ArenaStats {
+        ArenaStats {
+            peers: self.peer_latency.to_map(),
+        }
+    }
+
+    /// Snapshot of the room, player and latency figures for monitoring
+    #[cfg(feature = "metrics")]
+    pub async fn metrics(&self) -> crate::metrics::ArenaMetrics {
+        use crate::metrics::{ArenaMetrics, MetricKind::*};
+
+        let (room, status, is_host) = {
+            let state = self.room_state.read().await;
+            (
+                state.room_id.clone().unwrap_or_default(),
+                state.status,
+                state.is_host,
+            )
+        };
+        let base = vec![("game", self.config.game_id.clone()), ("room", room)];
+        let with = |key: &'static str, value: &str| {
+            let mut labels = base.clone();
+            labels.push((key, value.to_string()));
+            labels
+        };
+
+        let mut m = ArenaMetrics::new();
+        m.push(
+            "nostr_arena_players",
+            "Players in the room",
+            Gauge,
+            base.clone(),
+            self.players.len() as f64,
+        );
+        m.push(
+            "nostr_arena_ready_players",
+            "Players marked ready",
+            Gauge,
+            base.clone(),
+            self.players.count(|p| p.ready) as f64,
+        );
+        m.push(
+            "nostr_arena_host",
+            "1 while this arena hosts the room",
+            Gauge,
+            base.clone(),
+            f64::from(u8::from(is_host)),
+        );
+        m.push(
+            "nostr_arena_status",
+            "1 for the current room status",
+            Gauge,
+            with("status", status.as_str()),
+            1.0,
+        );
+
+        for (peer, latency) in self.peer_latency.to_map() {
+            m.push(
+                "nostr_arena_peer_one_way_ms",
+                "Heartbeat-based one-way latency per peer",
+                Gauge,
+                with("peer", &peer),
+                latency.one_way_ms as f64,
+            );
+            m.push(
+                "nostr_arena_peer_clock_offset_ms",
+                "Clock offset per peer, positive when ahead",
+                Gauge,
+                with("peer", &peer),
+                latency.clock_offset_ms as f64,
+            );
+        }
+
+        let report = self.client.latency_report().await;
+        m.push(
+            "nostr_arena_events_received_total",
+            "Room events received",
+            Counter,
+            base.clone(),
+            report.overall.count as f64,
+        );
+        m.push(
+            "nostr_arena_event_latency_p50_ms",
+            "Median room event latency",
+            Gauge,
+            base.clone(),
+            report.overall.percentile_ms(50) as f64,
+        );
+        m.push(
+            "nostr_arena_event_latency_p95_ms",
+            "95th percentile room event latency",
+            Gauge,
+            base.clone(),
+            report.overall.percentile_ms(95) as f64,
+        );
+        for (relay, histogram) in &report.by_relay {
+            m.push(
+                "nostr_arena_relay_events_total",
+                "Room events received per relay",
+                Counter,
+                with("relay", relay),
+                histogram.count as f64,
+            );
+            m.push(
+                "nostr_arena_relay_latency_mean_ms",
+                "Mean room event latency per relay",
+                Gauge,
+                with("relay", relay),
+                histogram.mean_ms() as f64,
+            );
+        }
+        for (relay, skew) in &self.client.relay_clocks().await.relays {
+            m.push(
+                "nostr_arena_relay_skew_ms",
+                "Relay timestamp skew, positive when ahead",
+                Gauge,
+                with("relay", relay),
+                skew.skew_ms as f64,
+            );
+        }
+        m
+    }
+
+    /// Number of players marked ready
+    pub async fn ready_count(&self) -> usize {
+        self.players.count(|p| p.ready)
+    }
+
+    /// Whether every player in the room is ready
+    pub async fn is_everyone_ready(&self) -> bool {
+        !self.players.is_empty() && self.players.all(|p| p.ready)
+    }
+
+    /// Public keys of players that are not ready yet
+    pub async fn unready_players(&self) -> Vec<String> {
+        self.players.keys_where(|p| !p.ready)
     }
 
     /// Receive next event (non-blocking)
     pub async fn try_recv(&self) -> Option<ArenaEvent<T>> {
-        self.event_rx.write().await.try_recv().ok()
+        let event = self.event_rx.write().await.try_recv().ok()?;
+        self.raise_notice(&event).await;
+        Some(event)
     }
 
     /// Receive next event (blocking)
     pub async fn recv(&self) -> Option<ArenaEvent<T>> {
-        self.event_rx.write().await.recv().await
+        let event = self.event_rx.write().await.recv().await?;
+        self.raise_notice(&event).await;
+        Some(event)
+    }
+
+    /// Notify the player of invitations, matches and their turn while the
+    /// app is unfocused; see [`crate::notify`]
+    pub async fn set_notifier(&self, notifier: impl Notifier + 'static) {
+        *self.notifier.write().await = Some(Arc::new(notifier));
+    }
+
+    pub async fn clear_notifier(&self) {
+        *self.notifier.write().await = None;
+    }
+
+    /// Tell the arena whether the app window has focus (default: true)
+    pub fn set_focused(&self, focused: bool) {
+        self.focused.store(focused, Ordering::Relaxed);
+    }
+
+    /// Tell the arena whether the app is in the foreground (default: true),
+    /// e.g. on a browser tab's `visibilitychange`.
+    ///
+    /// Background tabs and apps get their timers throttled, so heartbeats
+    /// slow down. Going to the background sends a heartbeat marked `away`,
+    /// and peers then wait `away_timeout` before dropping us. Coming back
+    /// reconnects, renews the room subscription and sends a heartbeat and
+    /// our last state right away.
+    pub async fn set_visible(&self, visible: bool) -> Result<()> {
+        let was_away = self.away.swap(!visible, Ordering::Relaxed);
+        if self.room_state.read().await.room_id.is_none() {
+            return Ok(());
+        }
+
+        if visible && was_away {
+            if !self.client.is_connected().await {
+                self.client.connect().await?;
+            }
+            return self.catch_up().await;
+        }
+        send_heartbeat(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.peer_latency,
+            !visible,
+        )
+        .await
+    }
+
+    /// Renew the room subscription and send a heartbeat and our last state
+    /// at once, after a stretch in which traffic may have been lost
+    async fn catch_up(&self) -> Result<()> {
+        let d_tags = room_channels(&self.config, &*self.room_state.read().await);
+        self.client.resubscribe_room(&d_tags).await?;
+        send_heartbeat(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.peer_latency,
+            self.away.load(Ordering::Relaxed),
+        )
+        .await?;
+        if let Some(state) = self.last_state.read().await.clone() {
+            self.send_state_now(&state).await?;
+        }
+        if self.config.resend_window > 0 {
+            self.request_resend(now_ms().saturating_sub(self.config.resend_window))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Ask the host to publish again the control messages (ready, game
+    /// over, countdown, ...) created since `since` (ms) that it still keeps,
+    /// e.g. after the subscription dropped for a while. Needs
+    /// `resend_window`; copies we already handled are ignored.
+    pub async fn request_resend(&self, since: u64) -> Result<()> {
+        let room_state = self.room_state.read().await;
+        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+        let room_tag = create_room_tag(&self.config.game_id, room_id);
+
+        let content = encode_event(&EventContent::ResendRequest(ResendRequestEventContent {
+            since,
+        }))?;
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+        Ok(())
+    }
+
+    /// Ask for the game state: every player's last state and where the
+    /// match stands. The host answers, or `peer` if given, and we get
+    /// `ArenaEvent::SnapshotReceived`. Late joiners ask on their own.
+    pub async fn request_snapshot(&self, peer: Option<&str>) -> Result<()> {
+        let (room_id, from) = {
+            let state = self.room_state.read().await;
+            let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+            let from = peer.map(str::to_string).or(state.host_pubkey.clone());
+            (room_id, from.ok_or(ArenaError::NotInRoom)?)
+        };
+        *self.snapshot_from.write().await = Some(from);
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+
+        let content = encode_event(&EventContent::SnapshotRequest(
+            SnapshotRequestEventContent {
+                peer: peer.map(str::to_string),
+            },
+        ))?;
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+        Ok(())
+    }
+
+    async fn raise_notice(&self, event: &ArenaEvent<T>) {
+        if self.focused.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(notifier) = self.notifier.read().await.clone() else {
+            return;
+        };
+        let current_room = self.room_state.read().await.room_id.clone();
+
+        let notice = match event {
+            ArenaEvent::InviteReceived { from, room_id, .. } => Notice::Invite {
+                from: from.clone(),
+                room_id: room_id.clone(),
+            },
+            ArenaEvent::PartyMatched(room_id) => Notice::MatchFound {
+                room_id: room_id.clone(),
+            },
+            ArenaEvent::GameStart { .. } => match current_room {
+                Some(room_id) => Notice::MatchFound { room_id },
+                None => return,
+            },
+            ArenaEvent::TurnChanged(pubkey) | ArenaEvent::TurnStart { pubkey }
+                if *pubkey == self.public_key() =>
+            {
+                match current_room {
+                    Some(room_id) => Notice::TurnStart { room_id },
+                    None => return,
+                }
+            }
+            _ => return,
+        };
+        notifier.notify(&notice);
+    }
+
+    /// Subscribe to the raw Nostr events received for the current room,
+    /// including own events, before any parsing or filtering
+    #[cfg(feature = "raw-events")]
+    pub fn raw_events(&self) -> broadcast::Receiver<nostr_sdk::Event> {
+        self.raw_tx.subscribe()
+    }
+
+    /// Attach a read-only debug timeline to the current room
+    #[cfg(feature = "debug-viewer")]
+    pub async fn debug_viewer(&self) -> Result<crate::debug::DebugViewer> {
+        let room_id = self
+            .room_state
+            .read()
+            .await
+            .room_id
+            .clone()
+            .ok_or(ArenaError::NotInRoom)?;
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        crate::debug::DebugViewer::attach(&self.client, &room_tag).await
     }
 
-    /// Connect to relays
+    /// Connect to relays and start listening for invitations
     pub async fn connect(&self) -> Result<()> {
-        self.client.connect().await
+        self.client.connect().await?;
+        self.start_inbox().await
     }
 
     /// Disconnect from relays
@@ -121,6 +977,59 @@ where
         self.client.disconnect().await
     }
 
+    /// Shut down for app exit, instead of letting peers find out through
+    /// heartbeat timeouts: tell the room we're leaving, finalize the room
+    /// event if we host it, leave, and close every subscription and
+    /// connection, which ends the background tasks. The final messages get
+    /// `shutdown_timeout` in total; the report says which went out.
+    pub async fn shutdown(&self) -> ShutdownReport {
+        let deadline = monotonic_ms() + self.config.shutdown_timeout;
+        let remaining = || Duration::from_millis(deadline.saturating_sub(monotonic_ms()));
+        let (room_id, is_host) = {
+            let state = self.room_state.read().await;
+            (state.room_id.clone(), state.is_host)
+        };
+        let mut report = ShutdownReport {
+            room_id: room_id.clone(),
+            ..Default::default()
+        };
+
+        if let Some(room_id) = &room_id {
+            let room_tag = create_room_tag(&self.config.game_id, room_id);
+            let leave = async {
+                let content = encode_event(&EventContent::Leave(LeaveEventContent::default()))?;
+                self.client.publish_ephemeral(&room_tag, &content).await
+            };
+            report.record("leave", timeout(remaining(), leave).await);
+
+            if is_host {
+                // Without its host the room can't go on: unstarted rooms
+                // are withdrawn, started ones end
+                {
+                    let mut state = self.room_state.write().await;
+                    state.status = match state.status {
+                        RoomStatus::Playing | RoomStatus::Finished => RoomStatus::Finished,
+                        _ => RoomStatus::Deleted,
+                    };
+                }
+                let finalize =
+                    republish_room(&self.config, &self.client, &self.room_state, &self.players);
+                report.record("room", timeout(remaining(), finalize).await);
+            }
+            match timeout(remaining(), self.leave()).await {
+                Some(Err(e)) => warn!("Failed to leave the room on shutdown: {}", e),
+                None => warn!("Timed out leaving the room on shutdown"),
+                Some(Ok(())) => {}
+            }
+        }
+
+        if let Err(e) = self.client.shutdown().await {
+            warn!("Failed to shut down relay connections: {}", e);
+        }
+        info!("Arena shut down");
+        report
+    }
+
     /// Check if connected
     pub async fn is_connected(&self) -> bool {
         self.client.is_connected().await
@@ -131,6 +1040,7 @@ where
     // =========================================================================
 
     /// List available rooms
+    #[cfg(feature = "discovery")]
     pub async fn list_rooms(
         game_id: &str,
         relays: Vec<String>,
@@ -141,68 +1051,118 @@ where
         client.connect().await?;
 
         let events = client.fetch_rooms(game_id, limit * 2).await?;
-        let now = now_ms();
+        let mut rooms = listed_rooms(game_id, events, status_filter, 0);
 
-        let mut rooms = Vec::new();
-        for event in events {
-            if let Ok(content) = serde_json::from_str::<RoomEventContent>(&event.content) {
-                // Skip deleted rooms
-                if content.status == RoomStatus::Deleted {
-                    continue;
-                }
+        rooms.truncate(limit);
+        client.disconnect().await?;
+        Ok(rooms)
+    }
+
+    /// List available rooms of this game on the read relays over the
+    /// arena's own connection, skipping rooms hosted by blocked players
+    #[cfg(feature = "discovery")]
+    pub async fn find_rooms(
+        &self,
+        status_filter: Option<RoomStatus>,
+        limit: usize,
+    ) -> Result<Vec<RoomInfo>> {
+        let events = self
+            .client
+            .fetch_rooms(&self.config.game_id, limit * 4)
+            .await?;
+        let skew = self.client.clock_skew().await;
+        let mut rooms = listed_rooms(&self.config.game_id, events, status_filter, skew);
+
+        let blocked = self.blocked.read().await;
+        rooms.retain(|room| {
+            !blocked.contains(&room.host_pubkey)
+                && self.config.is_compatible_client(room.client.as_deref())
+                && self.config.lists_visibility(room.visibility)
+        });
+        rooms.truncate(limit);
+        Ok(rooms)
+    }
 
-                // Skip expired rooms
-                if let Some(expires_at) = content.expires_at
-                    && now > expires_at
+    /// Live room browser: every room event of this game published from now
+    /// on, as `RoomInfo`. Updates include rooms that started, finished or
+    /// were deleted, so browsers can update or drop them. Rooms hosted by
+    /// blocked players or incompatible clients are skipped. Drop the
+    /// receiver to stop watching.
+    #[cfg(feature = "discovery")]
+    pub async fn watch_rooms(&self) -> Result<mpsc::Receiver<RoomInfo>> {
+        let (event_tx, mut event_rx) = mpsc::channel(64);
+        self.client
+            .watch_rooms(&self.config.game_id, event_tx)
+            .await?;
+
+        let (tx, rx) = mpsc::channel(64);
+        let config = self.config.clone();
+        let client = self.client.clone();
+        let blocked = self.blocked.clone();
+        spawn(async move {
+            while let Some(event) = event_rx.recv().await {
+                let Ok(content) = decode_room(&event.content) else {
+                    continue;
+                };
+                let skew = client.clock_skew().await;
+                let room = room_info(&config.game_id, &event, content, skew);
+                if blocked.read().await.contains(&room.host_pubkey)
+                    || !config.is_compatible_client(room.client.as_deref())
+                    || !config.lists_visibility(room.visibility)
                 {
                     continue;
                 }
+                if tx.send(room).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(rx)
+    }
 
-                // Apply status filter
-                if let Some(filter) = status_filter
-                    && content.status != filter
-                {
+    /// Live match browser: public summaries (`send_summary()`) of this
+    /// game's rooms published from now on. Summaries from blocked players
+    /// are skipped. Drop the receiver to stop watching.
+    #[cfg(feature = "discovery")]
+    pub async fn watch_summaries(&self) -> Result<mpsc::Receiver<MatchSummary>> {
+        let (event_tx, mut event_rx) = mpsc::channel(64);
+        self.client
+            .watch_summaries(&self.config.game_id, event_tx)
+            .await?;
+
+        let (tx, rx) = mpsc::channel(64);
+        let config = self.config.clone();
+        let client = self.client.clone();
+        let blocked = self.blocked.clone();
+        spawn(async move {
+            while let Some(event) = event_rx.recv().await {
+                let pubkey = event.pubkey.to_hex();
+                if blocked.read().await.contains(&pubkey) {
                     continue;
                 }
-
-                // Extract room_id from d tag
-                let room_id = event
-                    .tags
-                    .iter()
-                    .find_map(|tag| {
-                        if tag.kind()
-                            == nostr_sdk::TagKind::SingleLetter(
-                                nostr_sdk::SingleLetterTag::lowercase(nostr_sdk::Alphabet::D),
-                            )
-                        {
-                            tag.content().map(|s| {
-                                s.strip_prefix(&format!("{game_id}-"))
-                                    .unwrap_or(s)
-                                    .to_string()
-                            })
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or_default();
-
-                rooms.push(RoomInfo {
-                    room_id,
-                    game_id: game_id.to_string(),
-                    status: content.status,
-                    host_pubkey: content.host_pubkey,
-                    player_count: content.players.len(),
-                    max_players: content.max_players,
-                    created_at: event.created_at.as_u64() * 1000,
-                    expires_at: content.expires_at,
-                    seed: content.seed,
-                });
+                // Invitations share the game tag; keep only summaries
+                let Ok(EventContent::Summary(content)) = decode_event(&event.content) else {
+                    continue;
+                };
+                let Some(room_id) = event.tags.identifier().and_then(|d| {
+                    d.strip_prefix(&format!("{}-", config.game_id))?
+                        .strip_suffix("-summary")
+                }) else {
+                    continue;
+                };
+                let skew = client.clock_skew().await;
+                let summary = MatchSummary {
+                    room_id: room_id.to_string(),
+                    pubkey,
+                    summary: content.summary,
+                    created_at: local_ms(event.created_at.as_u64().saturating_mul(1000), skew),
+                };
+                if tx.send(summary).await.is_err() {
+                    break;
+                }
             }
-        }
-
-        rooms.truncate(limit);
-        client.disconnect().await?;
-        Ok(rooms)
+        });
+        Ok(rx)
     }
 
     // =========================================================================
@@ -211,18 +1171,30 @@ where
 
     /// Create a new room
     pub async fn create(&self) -> Result<String> {
-        if !self.client.is_connected().await {
-            self.client.connect().await?;
+        self.create_room(None).await
+    }
+
+    /// Create a room with the settings of the template `id` we saved with
+    /// [`Self::save_template`], overriding the config's
+    pub async fn create_from_template(&self, id: &str) -> Result<String> {
+        let template = self
+            .templates()
+            .await?
+            .into_iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| ArenaError::InvalidValue(format!("no room template {id}")))?;
+        self.create_room(Some(template)).await
+    }
+
+    async fn create_room(&self, template: Option<RoomTemplate>) -> Result<String> {
+        if !self.client.is_connected().await {
+            self.client.connect().await?;
         }
 
         let room_id = generate_room_id();
         let seed = generate_seed();
         let created_at = now_ms();
-        let expires_at = if self.config.room_expiry > 0 {
-            Some(created_at + self.config.room_expiry)
-        } else {
-            None
-        };
+        let expires_at = expiry_deadline(&self.config, created_at, created_at);
 
         // Update local state
         {
@@ -233,101 +1205,186 @@ where
             state.seed = seed;
             state.created_at = Some(created_at);
             state.expires_at = expires_at;
+            state.host_pubkey = Some(self.public_key());
+            state.seats = vec![self.public_key()];
+            state.sync_mode = self.config.sync_mode;
+            if let Some(template) = &template {
+                state.sync_mode = template.sync_mode;
+                state.start_config = template.start_config.clone();
+                state.chat_slow_mode = template.chat_slow_mode;
+            }
+            state.template = template;
+            state.encrypted = self.config.encrypt_state;
+            state.late_join = self.config.late_join;
         }
+        let seal = if self.config.encrypt_state {
+            RoomSeal::Key(RoomKey::generate())
+        } else {
+            RoomSeal::Off
+        };
+        self.client.set_room_seal(seal).await;
 
         // Add self to players
-        {
-            let mut players = self.players.write().await;
-            players.insert(
-                self.public_key(),
-                PlayerPresence {
-                    pubkey: self.public_key(),
-                    joined_at: created_at,
-                    last_seen: created_at,
-                    ready: false,
-                },
-            );
-        }
+        self.players.insert(
+            self.public_key(),
+            PlayerPresence {
+                pubkey: self.public_key(),
+                joined_at: created_at,
+                last_seen: created_at,
+                seen_at: monotonic_ms(),
+                ready: false,
+                latency_ms: None,
+                away: false,
+                session: None,
+                capabilities: Some(self.config.capabilities.clone()),
+                identity: self.config.identity_link.clone().map(Box::new),
+            },
+        );
 
         // Publish room event
         let room_tag = create_room_tag(&self.config.game_id, &room_id);
-        let content = RoomEventContent {
-            status: RoomStatus::Waiting,
-            seed,
-            host_pubkey: self.public_key(),
-            max_players: self.config.max_players,
-            expires_at,
-            players: self.players.read().await.values().cloned().collect(),
-        };
+        let content = room_content(
+            &self.config,
+            self.public_key(),
+            RoomStatus::Waiting,
+            &*self.room_state.read().await,
+            self.players.values(),
+        );
+
+        self.client
+            .set_room_relays(content.game_relays(self.client.read_relays()))
+            .await;
 
         self.client
             .publish_room(
                 &room_tag,
-                &self.config.game_id,
-                &serde_json::to_string(&content)?,
+                self.config.room_hashtag(),
+                &encode_room(&content)?,
             )
             .await?;
+        self.client
+            .set_room_address(Some(room_address(&self.public_key(), &room_tag)))
+            .await;
 
         // Update status
         {
             let mut state = self.room_state.write().await;
             state.status = RoomStatus::Waiting;
+            self.config.hooks.room_created(&RoomCreated {
+                room_id: room_id.clone(),
+                game_id: self.config.game_id.clone(),
+                host_pubkey: self.public_key(),
+                seed,
+                max_players: state.max_players(&self.config),
+                visibility: self.config.visibility,
+                created_at,
+            });
         }
 
         // Start subscription and heartbeat
         self.start_room_subscription(&room_id).await?;
         self.start_heartbeat().await;
+        self.start_watchdog().await;
+        self.start_network_watch().await;
+        self.start_roster_reconcile().await;
         self.start_presence_update().await;
-
-        // Generate room URL
-        let url = if let Some(base) = &self.config.base_url {
-            format!("{base}/battle/{room_id}")
-        } else {
-            format!("/battle/{room_id}")
-        };
+        self.start_expiry_timer().await;
+        self.start_clock().await;
+        self.start_turn_watch().await;
+        self.start_digest().await;
 
         info!("Created room: {}", room_id);
-        Ok(url)
+        Ok(self.get_room_url().await.unwrap_or_default())
     }
 
-    /// Join an existing room
-    pub async fn join(&self, room_id: &str) -> Result<()> {
-        if !self.client.is_connected().await {
-            self.client.connect().await?;
-        }
-
-        let room_tag = create_room_tag(&self.config.game_id, room_id);
-
-        // Fetch room info
-        let event = self
-            .client
-            .fetch_room(&room_tag)
-            .await?
-            .ok_or(ArenaError::RoomNotFound)?;
+    /// Join an existing room by id or room URL.
+    ///
+    /// Relay hints in the URL are searched for the room and used for the
+    /// session.
+    pub async fn join(&self, room: &str) -> Result<()> {
+        let (room_id, hints) = parse_room_link(room);
+        self.join_with_hints(&room_id, hints, None, None).await
+    }
 
-        let content: RoomEventContent = serde_json::from_str(&event.content)
-            .map_err(|e| ArenaError::InvalidRoomData(e.to_string()))?;
+    /// Join a room that has a password. Fails with `NotAuthorized` when the
+    /// host denies the password.
+    pub async fn join_with_secret(&self, room: &str, secret: &str) -> Result<()> {
+        let (room_id, hints) = parse_room_link(room);
+        let secret = JoinSecret::Password(secret.to_string());
+        self.join_with_hints(&room_id, hints, Some(secret), None)
+            .await
+    }
 
-        // Check room status
-        if content.status == RoomStatus::Deleted {
-            return Err(ArenaError::RoomDeleted);
+    /// Join a room from an `arena1...` invite token, looking for it on the
+    /// invite's relays as well as our own
+    pub async fn parse_invite(&self, token: &str) -> Result<()> {
+        let invite = Invite::decode(token)?;
+        if invite.game_id != self.config.game_id {
+            return Err(ArenaError::InvalidValue(format!(
+                "invite is for game {}",
+                invite.game_id
+            )));
         }
+        let secret = invite.secret.map(JoinSecret::Password);
+        self.join_with_hints(
+            &invite.room_id,
+            invite.relays,
+            secret,
+            invite.host.as_deref(),
+        )
+        .await
+    }
 
-        // Check expiry
-        if let Some(expires_at) = content.expires_at
-            && now_ms() > expires_at
-        {
-            return Err(ArenaError::RoomExpired);
+    /// Join a room, also searching `hints` (relays the room was advertised
+    /// on). Hinted relays are used for the session and dropped on leave.
+    /// Only room events of `host`, if given, are trusted.
+    pub(crate) async fn join_with_hints(
+        &self,
+        room_id: &str,
+        hints: Vec<String>,
+        secret: Option<JoinSecret>,
+        host: Option<&str>,
+    ) -> Result<()> {
+        if !self.client.is_connected().await {
+            self.client.connect().await?;
         }
 
-        // Check player count
-        if content.players.len() >= content.max_players {
-            return Err(ArenaError::RoomFull);
+        let room_tag = create_room_tag(&self.config.game_id, room_id);
+
+        if !hints.is_empty() {
+            self.client.set_room_relays(hints.clone()).await;
         }
+        let fetched =
+            self.fetch_joinable(&room_tag, &hints, host)
+                .await
+                .and_then(|(event, content)| {
+                    let proof = self.join_proof(room_id, &content, secret)?;
+                    Ok((event, content, proof))
+                });
+        let (event, content, proof) = match fetched {
+            Ok(room) => room,
+            Err(e) => {
+                self.client.set_room_relays(Vec::new()).await;
+                return Err(e);
+            }
+        };
 
-        let created_at = event.created_at.as_u64() * 1000;
+        let created_at = local_ms(
+            event.created_at.as_u64().saturating_mul(1000),
+            self.client.clock_skew().await,
+        );
         let now = now_ms();
 
+        // Use the relays the host listens on for game traffic
+        let fallback = if hints.is_empty() {
+            self.client.read_relays().to_vec()
+        } else {
+            hints
+        };
+        self.client
+            .set_room_relays(content.game_relays(&fallback))
+            .await;
+
         // Update local state
         {
             let mut state = self.room_state.write().await;
@@ -337,29 +1394,85 @@ where
             state.seed = content.seed;
             state.created_at = Some(created_at);
             state.expires_at = content.expires_at;
+            state.host_pubkey = Some(content.host_pubkey.clone());
+            state.seats = Vec::new();
+            state.co_hosts = content.co_hosts.clone();
+            state.chat_slow_mode = content.chat_slow_mode;
+            state.chat_muted = content.chat_muted.clone();
+            state.turn = content.turn.clone();
+            state.sync_mode = content.sync_mode;
+            state.round = content.round;
+            state.phase = content.phase.clone();
+            state.scoreboard = content.scoreboard.clone();
+            state.start_config = content.start_config.clone();
+            state.turn_order = content.turn_order.clone();
+            state.encrypted = content.encrypted;
+            state.late_join = content.late_join;
         }
+        // The key comes with our seat
+        let seal = if content.encrypted {
+            RoomSeal::Pending
+        } else {
+            RoomSeal::Off
+        };
+        self.client.set_room_seal(seal).await;
+        *self.seat_reply.write().await = SeatReply::Pending;
+        let host_assigns_seats = content.version >= 2;
+        let countdown_at = content.countdown_at;
+        let late = content.status == RoomStatus::Playing && content.late_join;
+        let roster_compressed = content.is_roster_compressed();
 
         // Add existing players
-        {
-            let mut players = self.players.write().await;
-            for p in content.players {
-                players.insert(p.pubkey.clone(), p);
-            }
-            // Add self
-            players.insert(
-                self.public_key(),
-                PlayerPresence {
-                    pubkey: self.public_key(),
-                    joined_at: now,
-                    last_seen: now,
-                    ready: false,
-                },
-            );
+        for mut p in content.players {
+            p.seen_at = monotonic_at(p.last_seen);
+            self.players.insert(p.pubkey.clone(), p);
         }
-
-        // Send join event
-        let join_content = serde_json::to_string(&EventContent::Join(JoinEventContent {
+        // Add self
+        self.players.insert(
+            self.public_key(),
+            PlayerPresence {
+                pubkey: self.public_key(),
+                joined_at: now,
+                last_seen: now,
+                seen_at: monotonic_ms(),
+                ready: false,
+                latency_ms: None,
+                away: false,
+                session: None,
+                capabilities: Some(self.config.capabilities.clone()),
+                identity: self.config.identity_link.clone().map(Box::new),
+            },
+        );
+        self.client.set_participants(&self.players.keys()).await;
+        self.client
+            .set_room_address(Some(room_address(&content.host_pubkey, &room_tag)))
+            .await;
+
+        // Send join event, with our identity link for the host's eyes only
+        let identity = match &self.config.identity_link {
+            Some(link) => Some(
+                self.client
+                    .nip44_encrypt(&content.host_pubkey, &serde_json::to_string(link)?)
+                    .await?,
+            ),
+            None => None,
+        };
+        // The proof could be brute-forced offline, so only the host sees it;
+        // v2 hosts expect it in the clear
+        let proof = match proof {
+            Some(proof) if content.version >= 3 => Some(
+                self.client
+                    .nip44_encrypt(&content.host_pubkey, &proof)
+                    .await?,
+            ),
+            proof => proof,
+        };
+        let join_content = encode_event(&EventContent::Join(JoinEventContent {
             player_pubkey: self.public_key(),
+            capabilities: Some(self.config.capabilities.clone()),
+            identity,
+            session: Some(self.session.clone()),
+            proof,
         }))?;
 
         self.client
@@ -368,16 +1481,10 @@ where
 
         // Start subscription
         self.start_room_subscription(room_id).await?;
-
-        // Update status
-        {
-            let mut state = self.room_state.write().await;
-            state.status = RoomStatus::Ready;
+        if roster_compressed {
+            self.request_roster(&room_tag).await?;
         }
 
-        // Start heartbeat
-        self.start_heartbeat().await;
-
         // Send additional join events for reliability
         let client = self.client.clone();
         let tag = room_tag.clone();
@@ -389,6 +1496,42 @@ where
             let _ = client.publish_ephemeral(&tag, &content).await;
         });
 
+        // Hosts speaking protocol v2+ confirm seats; older hosts don't answer
+        if host_assigns_seats {
+            self.wait_for_seat().await?;
+        }
+
+        // Update status
+        let start_config = {
+            let mut state = self.room_state.write().await;
+            state.status = if late {
+                RoomStatus::Playing
+            } else {
+                RoomStatus::Ready
+            };
+            state.start_config.clone()
+        };
+        if late {
+            // Catch up on the running game
+            let _ = self
+                .event_tx
+                .send(ArenaEvent::GameStart {
+                    config: start_config,
+                })
+                .await;
+            self.request_snapshot(None).await?;
+        }
+
+        // Start heartbeat
+        self.start_heartbeat().await;
+        self.start_watchdog().await;
+        self.start_network_watch().await;
+        self.start_roster_reconcile().await;
+        self.start_presence_update().await;
+        self.start_expiry_timer().await;
+        self.start_clock().await;
+        self.resume_countdown(countdown_at).await;
+
         // Check if we should auto-start
         self.check_auto_start().await;
 
@@ -396,21 +1539,214 @@ where
         Ok(())
     }
 
+    /// Fetch a room and check that it can be joined
+    async fn fetch_joinable(
+        &self,
+        room_tag: &str,
+        hints: &[String],
+        host: Option<&str>,
+    ) -> Result<(nostr_sdk::Event, RoomEventContent)> {
+        let (event, content) = self
+            .fetch_latest_room(room_tag, hints, host)
+            .await?
+            .ok_or(ArenaError::RoomNotFound)?;
+
+        if let Some(blocker) = self.join_blocker(&content).await {
+            return Err(blocker.into());
+        }
+        Ok((event, content))
+    }
+
+    /// Fetch the authoritative revision of a room from every relay, and
+    /// republish it to the relays that returned an older one (read-repair).
+    ///
+    /// Only events of `host` (or of the host first seen for the room) count.
+    async fn fetch_latest_room(
+        &self,
+        room_tag: &str,
+        hints: &[String],
+        host: Option<&str>,
+    ) -> Result<Option<(nostr_sdk::Event, RoomEventContent)>> {
+        let copies = self.client.fetch_room_copies(room_tag, hints).await?;
+        let pinned = match host {
+            Some(host) => Some(host.to_string()),
+            None => self.room_hosts.read().await.get(room_tag).cloned(),
+        };
+        let latest = latest_room(copies.events(), pinned.as_deref());
+        if let Some((_, content)) = &latest {
+            self.room_hosts
+                .write()
+                .await
+                .entry(room_tag.to_string())
+                .or_insert_with(|| content.host_pubkey.clone());
+        }
+        if self.config.read_repair
+            && let Some((event, _)) = &latest
+        {
+            let lagging = copies.lagging(event);
+            if !lagging.is_empty() {
+                debug!("Repairing {} on {} lagging relays", room_tag, lagging.len());
+                self.client.repair_room(event.clone(), lagging);
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Password proof to join `room_id` with. Only the host can tell
+    /// whether it is right.
+    fn join_proof(
+        &self,
+        room_id: &str,
+        content: &RoomEventContent,
+        secret: Option<JoinSecret>,
+    ) -> Result<Option<String>> {
+        if !content.password {
+            return Ok(None);
+        }
+        match secret {
+            None => Err(ArenaError::NotAuthorized(
+                "room password required".to_string(),
+            )),
+            Some(JoinSecret::Password(password)) => Ok(Some(room_password_proof(
+                room_id,
+                &password,
+                &self.public_key(),
+            ))),
+            Some(JoinSecret::Proof(proof)) => Ok(Some(proof)),
+        }
+    }
+
+    /// First reason `join()` refuses the room. Joining by link doesn't check
+    /// the host's client or protocol version.
+    async fn join_blocker(&self, content: &RoomEventContent) -> Option<JoinBlocker> {
+        if self.blocked.read().await.contains(&content.host_pubkey) {
+            return Some(JoinBlocker::HostBlocked);
+        }
+        if content.status == RoomStatus::Deleted {
+            return Some(JoinBlocker::Deleted);
+        }
+        if let Some(expires_at) = content.expires_at
+            && now_ms() > local_ms(expires_at, self.client.clock_skew().await)
+        {
+            return Some(JoinBlocker::Expired);
+        }
+        if content.player_count() >= content.max_players {
+            return Some(JoinBlocker::Full);
+        }
+        None
+    }
+
+    /// Look a room up (by id or room URL) and report what joining it would
+    /// run into, without joining: whether a seat is free, whether the host
+    /// is compatible, and the relays and latency to expect.
+    ///
+    /// Nothing is published and the room state is left alone, so a pre-join
+    /// screen can call this before committing to `join()`.
+    pub async fn plan_join(&self, room: &str) -> Result<JoinPlan> {
+        if !self.client.is_connected().await {
+            self.client.connect().await?;
+        }
+        let (room_id, hints) = parse_room_link(room);
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+
+        let started = monotonic_ms();
+        let latest = self.fetch_latest_room(&room_tag, &hints, None).await?;
+        let estimated_latency_ms = monotonic_ms().saturating_sub(started);
+        let (event, content) = latest.ok_or(ArenaError::RoomNotFound)?;
+
+        let fallback = if hints.is_empty() {
+            self.client.read_relays().to_vec()
+        } else {
+            hints
+        };
+        let relays = content.game_relays(&fallback);
+        let protocol_version = content.version;
+        let seat_available = content.player_count() < content.max_players;
+        let blocker = self.join_blocker(&content).await;
+        let room = room_info(
+            &self.config.game_id,
+            &event,
+            content,
+            self.client.clock_skew().await,
+        );
+        let protocol_compatible = protocol_version <= PROTOCOL_VERSION
+            && self.config.is_compatible_client(room.client.as_deref());
+
+        Ok(JoinPlan {
+            room,
+            blocker: blocker.or((!protocol_compatible).then_some(JoinBlocker::Incompatible)),
+            seat_available,
+            protocol_compatible,
+            protocol_version,
+            relays,
+            estimated_latency_ms,
+        })
+    }
+
+    /// Ask the host for the player list left out of a large room's event
+    async fn request_roster(&self, room_tag: &str) -> Result<()> {
+        let content = encode_event(&EventContent::Roster(RosterEventContent {
+            action: RosterAction::Request,
+            players: Vec::new(),
+        }))?;
+        self.client.publish_ephemeral(room_tag, &content).await?;
+        Ok(())
+    }
+
     /// Leave the current room
     pub async fn leave(&self) -> Result<()> {
         let mut state = self.room_state.write().await;
         state.room_id = None;
         state.status = RoomStatus::Idle;
         state.is_host = false;
-        self.players.write().await.clear();
-        self.player_states.write().await.clear();
+        state.host_pubkey = None;
+        state.seats.clear();
+        state.co_hosts.clear();
+        state.turn = None;
+        state.countdown_at = None;
+        state.sync_mode = SyncMode::Mesh;
+        state.round = 0;
+        state.phase = None;
+        state.scoreboard.clear();
+        state.scored.clear();
+        state.start_config = None;
+        state.turn_order.clear();
+        state.votes.clear();
+        state.claims.clear();
+        state.finishes.clear();
+        state.game_ended = false;
+        state.clock = None;
+        state.chat_slow_mode = 0;
+        state.chat_muted.clear();
+        state.kicked.clear();
+        state.peer_versions.clear();
+        state.spectating = false;
+        state.spectators.clear();
+        state.template = None;
+        state.summaries = false;
+        state.encrypted = false;
+        state.late_join = false;
+        self.players.clear();
+        self.player_states.clear();
+        *self.last_state.write().await = None;
+        *self.snapshot_from.write().await = None;
+        self.chat_guards.clear();
+        self.slow_guards.clear();
+        self.peer_latency.clear();
+        self.digest_states.clear();
+        self.input_chains.clear();
+        *self.shadow.write().await = EventShadow::new();
+        self.client.set_room_relays(Vec::new()).await;
+        self.client.set_participants(&[]).await;
+        self.client.set_room_address(None).await;
+        self.client.set_room_seal(RoomSeal::Off).await;
         Ok(())
     }
 
-    /// Delete the room (host only)
+    /// Delete the room (host or co-host only)
     pub async fn delete_room(&self) -> Result<()> {
         let state = self.room_state.read().await;
-        if !state.is_host {
+        if !state.is_host && !state.co_hosts.contains(&self.public_key()) {
             return Err(ArenaError::NotAuthorized(
                 "Only host can delete room".to_string(),
             ));
@@ -419,20 +1755,22 @@ where
         let room_id = state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
         let room_tag = create_room_tag(&self.config.game_id, room_id);
 
-        let content = RoomEventContent {
-            status: RoomStatus::Deleted,
-            seed: state.seed,
-            host_pubkey: self.public_key(),
-            max_players: self.config.max_players,
-            expires_at: state.expires_at,
-            players: vec![],
-        };
+        let content = room_content(
+            &self.config,
+            state
+                .host_pubkey
+                .clone()
+                .unwrap_or_else(|| self.public_key()),
+            RoomStatus::Deleted,
+            &state,
+            vec![],
+        );
 
         self.client
             .publish_room(
                 &room_tag,
-                &self.config.game_id,
-                &serde_json::to_string(&content)?,
+                self.config.room_hashtag(),
+                &encode_room(&content)?,
             )
             .await?;
 
@@ -442,517 +1780,5400 @@ where
         Ok(())
     }
 
-    /// Reconnect to a room (e.g., after page refresh or connection drop)
-    pub async fn reconnect(&self, room_id: &str) -> Result<()> {
-        // First, leave any current room cleanly
-        self.leave().await?;
+    /// Authorize a co-host to update the room and start the game (host only).
+    ///
+    /// The room event is republished and peers are told right away.
+    pub async fn add_co_host(&self, pubkey: &str) -> Result<()> {
+        {
+            let mut state = self.room_state.write().await;
+            if !state.is_host {
+                return Err(ArenaError::NotAuthorized(
+                    "Only host can add co-hosts".to_string(),
+                ));
+            }
+            if state.co_hosts.iter().any(|p| p == pubkey) {
+                return Ok(());
+            }
+            state.co_hosts.push(pubkey.to_string());
+        }
+        self.publish_co_hosts().await
+    }
 
-        // Then join the specified room
-        self.join(room_id).await?;
+    /// Revoke a co-host (host only)
+    pub async fn remove_co_host(&self, pubkey: &str) -> Result<()> {
+        {
+            let mut state = self.room_state.write().await;
+            if !state.is_host {
+                return Err(ArenaError::NotAuthorized(
+                    "Only host can remove co-hosts".to_string(),
+                ));
+            }
+            state.co_hosts.retain(|p| p != pubkey);
+        }
+        self.publish_co_hosts().await
+    }
 
-        info!("Reconnected to room: {}", room_id);
-        Ok(())
+    /// Co-hosts of the current room
+    pub async fn co_hosts(&self) -> Vec<String> {
+        self.room_state.read().await.co_hosts.clone()
     }
 
-    // =========================================================================
-    // Game State
-    // =========================================================================
+    /// Republish the room event with the current co-hosts and send it to
+    /// peers already in the room
+    async fn publish_co_hosts(&self) -> Result<()> {
+        let content =
+            republish_room(&self.config, &self.client, &self.room_state, &self.players).await?;
+        let room_id = self
+            .room_state
+            .read()
+            .await
+            .room_id
+            .clone()
+            .ok_or(ArenaError::NotInRoom)?;
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        self.client
+            .publish_ephemeral(
+                &room_tag,
+                &encode_event(&EventContent::Room(Box::new(content)))?,
+            )
+            .await?;
+        Ok(())
+    }
 
-    /// Send game state to other players (throttled)
-    pub async fn send_state(&self, state: &T) -> Result<()> {
-        let now = now_ms();
-        let last = *self.last_state_update.read().await;
+    /// Attach to a room as a co-host without taking a seat.
+    ///
+    /// The room must list this player as a co-host. Co-hosts follow the
+    /// room's events and can start the game in Host mode or delete the room,
+    /// which lets organizers manage rooms they don't play in.
+    pub async fn manage(&self, room: &str) -> Result<()> {
+        if !self.client.is_connected().await {
+            self.client.connect().await?;
+        }
 
-        if now - last < self.config.state_throttle {
-            return Ok(());
+        let (room_id, hints) = parse_room_link(room);
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        if !hints.is_empty() {
+            self.client.set_room_relays(hints.clone()).await;
+        }
+        let Some((event, content)) = self.fetch_latest_room(&room_tag, &hints, None).await? else {
+            self.client.set_room_relays(Vec::new()).await;
+            return Err(ArenaError::RoomNotFound);
+        };
+        if !content.co_hosts.contains(&self.public_key()) {
+            self.client.set_room_relays(Vec::new()).await;
+            return Err(ArenaError::NotAuthorized(
+                "not a co-host of this room".to_string(),
+            ));
         }
 
-        *self.last_state_update.write().await = now;
+        let fallback = if hints.is_empty() {
+            self.client.read_relays().to_vec()
+        } else {
+            hints
+        };
+        self.client
+            .set_room_relays(content.game_relays(&fallback))
+            .await;
 
-        let room_state = self.room_state.read().await;
-        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
-        let room_tag = create_room_tag(&self.config.game_id, room_id);
+        {
+            let mut state = self.room_state.write().await;
+            state.room_id = Some(room_id.clone());
+            state.status = content.status;
+            state.is_host = false;
+            state.seed = content.seed;
+            state.created_at = Some(local_ms(
+                event.created_at.as_u64().saturating_mul(1000),
+                self.client.clock_skew().await,
+            ));
+            state.expires_at = content.expires_at;
+            state.host_pubkey = Some(content.host_pubkey.clone());
+            state.seats = content.players.iter().map(|p| p.pubkey.clone()).collect();
+            state.co_hosts = content.co_hosts.clone();
+            state.chat_slow_mode = content.chat_slow_mode;
+            state.chat_muted = content.chat_muted.clone();
+            state.turn = content.turn.clone();
+            state.sync_mode = content.sync_mode;
+            state.round = content.round;
+            state.phase = content.phase.clone();
+            state.scoreboard = content.scoreboard.clone();
+            state.start_config = content.start_config.clone();
+            state.turn_order = content.turn_order.clone();
+            state.encrypted = content.encrypted;
+            state.late_join = content.late_join;
+        }
+        // The key comes with our seat
+        let seal = if content.encrypted {
+            RoomSeal::Pending
+        } else {
+            RoomSeal::Off
+        };
+        self.client.set_room_seal(seal).await;
+        let countdown_at = content.countdown_at;
+        let roster_compressed = content.is_roster_compressed();
+        self.players
+            .replace(content.players.into_iter().map(|p| (p.pubkey.clone(), p)));
+        self.client.set_participants(&self.players.keys()).await;
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        self.client
+            .set_room_address(Some(room_address(&content.host_pubkey, &room_tag)))
+            .await;
 
-        let content = serde_json::to_string(&EventContent::State(StateEventContent {
-            game_state: serde_json::to_value(state)?,
-        }))?;
+        self.start_room_subscription(&room_id).await?;
+        if roster_compressed {
+            self.request_roster(&create_room_tag(&self.config.game_id, &room_id))
+                .await?;
+        }
+        self.start_presence_update().await;
+        self.start_expiry_timer().await;
+        self.start_clock().await;
+        self.resume_countdown(countdown_at).await;
 
-        self.client.publish_ephemeral(&room_tag, &content).await?;
+        info!("Managing room: {}", room_id);
         Ok(())
     }
 
-    /// Send game over event
-    pub async fn send_game_over(&self, reason: &str, final_score: Option<i64>) -> Result<()> {
-        let room_state = self.room_state.read().await;
-        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
-        let room_tag = create_room_tag(&self.config.game_id, room_id);
+    /// Follow a room (by id or room URL) as a spectator.
+    ///
+    /// Spectators get the players' states, `GameStart` and the other room
+    /// events, but take no seat: they don't count against `max_players`,
+    /// don't appear in `players()` or the ready flow, and can't send state,
+    /// ready or game over. They can chat. The host counts them in the room
+    /// event's `spectator_count`.
+    pub async fn join_as_spectator(&self, room: &str) -> Result<()> {
+        if !self.client.is_connected().await {
+            self.client.connect().await?;
+        }
 
-        let content = serde_json::to_string(&EventContent::GameOver(GameOverEventContent {
-            reason: reason.to_string(),
-            final_score,
-            winner: None,
-        }))?;
+        let (room_id, hints) = parse_room_link(room);
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        let (event, content) = self
+            .fetch_latest_room(&room_tag, &hints, None)
+            .await?
+            .ok_or(ArenaError::RoomNotFound)?;
+        // A full room can still be watched
+        match self.join_blocker(&content).await {
+            Some(JoinBlocker::Full) | None => {}
+            Some(blocker) => return Err(blocker.into()),
+        }
 
-        self.client.publish_ephemeral(&room_tag, &content).await?;
+        let fallback = if hints.is_empty() {
+            self.client.read_relays().to_vec()
+        } else {
+            hints
+        };
+        self.client
+            .set_room_relays(content.game_relays(&fallback))
+            .await;
 
-        drop(room_state);
-        let mut state = self.room_state.write().await;
-        state.status = RoomStatus::Finished;
+        {
+            let mut state = self.room_state.write().await;
+            state.room_id = Some(room_id.clone());
+            state.status = content.status;
+            state.is_host = false;
+            state.spectating = true;
+            state.summaries = content.summaries;
+            state.seed = content.seed;
+            state.created_at = Some(local_ms(
+                event.created_at.as_u64().saturating_mul(1000),
+                self.client.clock_skew().await,
+            ));
+            state.expires_at = content.expires_at;
+            state.host_pubkey = Some(content.host_pubkey.clone());
+            state.seats = content.players.iter().map(|p| p.pubkey.clone()).collect();
+            state.co_hosts = content.co_hosts.clone();
+            state.chat_slow_mode = content.chat_slow_mode;
+            state.chat_muted = content.chat_muted.clone();
+            state.turn = content.turn.clone();
+            state.sync_mode = content.sync_mode;
+            state.round = content.round;
+            state.phase = content.phase.clone();
+            state.scoreboard = content.scoreboard.clone();
+            state.start_config = content.start_config.clone();
+            state.turn_order = content.turn_order.clone();
+            state.encrypted = content.encrypted;
+            state.late_join = content.late_join;
+        }
+        // The key comes with our seat
+        let seal = if content.encrypted {
+            RoomSeal::Pending
+        } else {
+            RoomSeal::Off
+        };
+        self.client.set_room_seal(seal).await;
+        let countdown_at = content.countdown_at;
+        let roster_compressed = content.is_roster_compressed();
+        let summaries = content.summaries;
+        self.players
+            .replace(content.players.into_iter().map(|p| (p.pubkey.clone(), p)));
+        self.client.set_participants(&self.players.keys()).await;
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        self.client
+            .set_room_address(Some(room_address(&content.host_pubkey, &room_tag)))
+            .await;
+
+        self.start_room_subscription(&room_id).await?;
+        // The host answers on the room channel, which summary-only
+        // spectators don't follow
+        if roster_compressed && !summaries {
+            self.request_roster(&room_tag).await?;
+        }
+        self.start_spectate().await;
+        self.start_expiry_timer().await;
+        self.start_clock().await;
+        self.resume_countdown(countdown_at).await;
 
+        info!("Spectating room: {}", room_id);
         Ok(())
     }
 
-    /// Request a rematch
-    pub async fn request_rematch(&self) -> Result<()> {
-        let room_state = self.room_state.read().await;
-        if room_state.status != RoomStatus::Finished {
-            return Ok(());
-        }
+    /// Whether we follow the current room as a spectator
+    pub async fn is_spectating(&self) -> bool {
+        self.room_state.read().await.spectating
+    }
 
-        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
-        let room_tag = create_room_tag(&self.config.game_id, room_id);
+    /// Spectators of the room we host (empty for other peers)
+    pub async fn spectators(&self) -> Vec<String> {
+        self.room_state
+            .read()
+            .await
+            .spectators
+            .keys()
+            .cloned()
+            .collect()
+    }
 
-        let content = serde_json::to_string(&EventContent::Rematch(RematchEventContent {
-            action: RematchAction::Request,
-            new_seed: None,
-        }))?;
+    /// Refuse player actions while spectating
+    async fn ensure_player(&self) -> Result<()> {
+        if self.room_state.read().await.spectating {
+            return Err(ArenaError::NotAuthorized(
+                "Spectators can't play".to_string(),
+            ));
+        }
+        Ok(())
+    }
 
-        self.client.publish_ephemeral(&room_tag, &content).await?;
+    /// Reconnect to a room (e.g., after page refresh or connection drop)
+    pub async fn reconnect(&self, room_id: &str) -> Result<()> {
+        // First, leave any current room cleanly
+        self.leave().await?;
 
-        drop(room_state);
-        let mut state = self.room_state.write().await;
-        state.rematch_requested = true;
+        // Then join the specified room
+        self.join(room_id).await?;
 
+        info!("Reconnected to room: {}", room_id);
         Ok(())
     }
 
-    /// Accept a rematch
-    pub async fn accept_rematch(&self) -> Result<()> {
+    /// Capture the current session so it can be persisted and restored
+    pub async fn snapshot(&self) -> ArenaSnapshot<T> {
+        ArenaSnapshot {
+            public_key: self.public_key(),
+            room: self.room_state.read().await.clone(),
+            players: self.players.values(),
+            player_states: self.player_states.to_map(),
+            taken_at: now_ms(),
+            room_key: match self.client.room_seal().await {
+                RoomSeal::Key(key) => Some(key.to_hex()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Restore a session from a snapshot (e.g. after a process restart).
+    ///
+    /// The arena must use the same identity the snapshot was taken with; see
+    /// [`Arena::with_secret_key`], and must not be in a room already. Every
+    /// player counts as just seen, so peers get a full disconnect threshold
+    /// to show up again however long the restart took.
+    pub async fn restore(&self, snapshot: ArenaSnapshot<T>) -> Result<()> {
+        if snapshot.public_key != self.public_key() {
+            return Err(ArenaError::NotAuthorized(
+                "Snapshot belongs to a different key".to_string(),
+            ));
+        }
+        if self.room_state.read().await.room_id.is_some() {
+            return Err(ArenaError::AlreadyInRoom);
+        }
+
+        if !self.client.is_connected().await {
+            self.client.connect().await?;
+        }
+
+        let room_id = snapshot.room.room_id.clone();
+        // Without the key, state sends fail instead of going out in the clear
+        let seal = match (&snapshot.room_key, snapshot.room.encrypted) {
+            (Some(hex), _) => RoomSeal::Key(RoomKey::from_hex(hex)?),
+            (None, true) => RoomSeal::Pending,
+            (None, false) => RoomSeal::Off,
+        };
+        self.client.set_room_seal(seal).await;
+
+        let host = snapshot.room.host_pubkey.clone();
+        *self.room_state.write().await = snapshot.room;
+        self.players
+            .replace(snapshot.players.into_iter().map(|mut p| {
+                p.touch();
+                (p.pubkey.clone(), p)
+            }));
+        self.client.set_participants(&self.players.keys()).await;
+        self.player_states.replace(snapshot.player_states);
+
+        if let Some(room_id) = room_id {
+            // Pick up the room's relays again
+            let room_tag = create_room_tag(&self.config.game_id, &room_id);
+            if let Some((_, content)) = self
+                .fetch_latest_room(&room_tag, &[], host.as_deref())
+                .await?
+            {
+                self.client
+                    .set_room_relays(content.game_relays(self.client.read_relays()))
+                    .await;
+                self.client
+                    .set_room_address(Some(room_address(&content.host_pubkey, &room_tag)))
+                    .await;
+            }
+
+            self.start_room_subscription(&room_id).await?;
+            self.start_heartbeat().await;
+            self.start_watchdog().await;
+            self.start_network_watch().await;
+            self.start_roster_reconcile().await;
+            self.start_presence_update().await;
+            self.start_expiry_timer().await;
+            self.start_clock().await;
+            self.start_turn_watch().await;
+            self.start_digest().await;
+            info!("Restored room: {}", room_id);
+        }
+
+        Ok(())
+    }
+
+    /// Start a match record for the current room: players in seat order,
+    /// the seed, and the latest state of each player as a keyframe. Add
+    /// inputs and the result as the match goes on; see [`MatchRecord`].
+    pub async fn match_record(&self) -> Result<MatchRecord<T>> {
+        let room = self.room_state.read().await.clone();
+        let room_id = room.room_id.ok_or(ArenaError::NotInRoom)?;
+        let now = now_ms();
+
+        let mut record = MatchRecord::new(
+            &self.config.game_id,
+            room_id,
+            room.seed,
+            room.created_at.unwrap_or(now),
+        );
+        let mut players = room.seats;
+        if players.is_empty() {
+            let mut presences = self.players.values();
+            presences.sort_by_key(|p| p.joined_at);
+            players = presences.into_iter().map(|p| p.pubkey).collect();
+        }
+        for pubkey in players {
+            record = record.player(pubkey);
+        }
+
+        let mut states: Vec<(String, T)> = self.player_states.to_map().into_iter().collect();
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+        for (pubkey, state) in states {
+            record.push_keyframe(now, pubkey, state);
+        }
+        Ok(record)
+    }
+
+    /// Publish `record` without its inputs (see [`MatchRecord::compact`]) as
+    /// an addressable event, so match history can be read from relays with
+    /// [`Self::match_history`]. Republishing the same match replaces it.
+    pub async fn archive_match(&self, record: &MatchRecord<T>) -> Result<EventId> {
+        let d_tag = create_archive_tag(&record.game_id, &record.room_id, record.seed);
+        let content = record.compact().to_json()?;
+        self.client
+            .publish_archive(&d_tag, &record.game_id, &content)
+            .await
+    }
+
+    /// Fetch the matches `pubkey` archived for `game_id`, newest first.
+    /// Needs no room; archives that don't parse are skipped.
+    pub async fn match_history(&self, pubkey: &str, game_id: &str) -> Result<Vec<MatchRecord<T>>> {
+        let events = self.client.fetch_archives(pubkey, game_id).await?;
+        let mut records: Vec<MatchRecord<T>> = events
+            .iter()
+            .filter_map(|event| MatchRecord::from_json(&event.content).ok())
+            .filter(|record| record.game_id == game_id)
+            .collect();
+        records.sort_by(|a, b| {
+            b.ended_at
+                .unwrap_or(b.started_at)
+                .cmp(&a.ended_at.unwrap_or(a.started_at))
+        });
+        Ok(records)
+    }
+
+    /// Save `template` under our key, replacing a saved template with the
+    /// same id, so [`Self::create_from_template`] can recreate the room later
+    /// or on another device
+    pub async fn save_template(&self, template: &RoomTemplate) -> Result<EventId> {
+        if template.game_id != self.config.game_id {
+            return Err(ArenaError::InvalidValue(format!(
+                "template is for game {}",
+                template.game_id
+            )));
+        }
+        if template.max_players == 0 {
+            return Err(ArenaError::InvalidValue(
+                "template needs at least one player".to_string(),
+            ));
+        }
+        let d_tag = create_template_tag(&template.game_id, &template.id);
+        let content = serde_json::to_string(template)?;
+        self.client
+            .publish_template(&d_tag, &template.game_id, &content)
+            .await
+    }
+
+    /// Room templates we saved for this game, by id. Needs no room;
+    /// templates that don't parse are skipped.
+    pub async fn templates(&self) -> Result<Vec<RoomTemplate>> {
+        let mut events = self
+            .client
+            .fetch_templates(&self.public_key(), &self.config.game_id)
+            .await?;
+        // Relays may still hold replaced versions; keep the newest
+        events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        let mut templates: Vec<RoomTemplate> = Vec::new();
+        for event in &events {
+            if let Ok(template) = serde_json::from_str::<RoomTemplate>(&event.content)
+                && template.game_id == self.config.game_id
+                && !templates.iter().any(|t| t.id == template.id)
+            {
+                templates.push(template);
+            }
+        }
+        templates.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(templates)
+    }
+
+    // =========================================================================
+    // Game State
+    // =========================================================================
+
+    /// Send game state to other players (throttled)
+    pub async fn send_state(&self, state: &T) -> Result<SendResult> {
+        self.ensure_player().await?;
+        if let Some(next_allowed) = self.check_throttle("state").await {
+            return Ok(SendResult::Throttled { next_allowed });
+        }
+        self.publish_state(state).await.map(SendResult::Sent)
+    }
+
+    /// Send game state immediately, bypassing the throttle (e.g. for
+    /// critical snapshots). Still counts towards the throttle window.
+    pub async fn send_state_now(&self, state: &T) -> Result<EventId> {
+        self.ensure_player().await?;
+        self.last_sent
+            .write()
+            .await
+            .insert("state".to_string(), monotonic_ms());
+        self.publish_state(state).await
+    }
+
+    async fn publish_state(&self, state: &T) -> Result<EventId> {
+        let (room_tag, content) = self.state_message(state).await?;
+        let id = self.client.publish_ephemeral(&room_tag, &content).await?;
+        *self.last_state.write().await = Some(state.clone());
+        if self.config.input_audit {
+            record_input(&self.input_chains, self.public_key(), &content);
+        }
+        self.echo(ArenaEvent::PlayerState {
+            pubkey: self.public_key(),
+            state: state.clone(),
+        })
+        .await;
+        Ok(id)
+    }
+
+    /// Tag and content of a `state` message in the current room
+    async fn state_message(&self, state: &T) -> Result<(String, String)> {
+        let room_state = self.room_state.read().await;
+        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+        let room_tag = if room_state.sends_to_host() {
+            create_host_tag(&self.config.game_id, room_id)
+        } else {
+            create_room_tag(&self.config.game_id, room_id)
+        };
+
+        let content = encode_event(&EventContent::State(StateEventContent {
+            game_state: serde_json::to_value(state)?,
+        }))?;
+        Ok((room_tag, content))
+    }
+
+    /// Send a compact public summary of the game (score, round, key
+    /// positions) on the room's summary channel, throttled by
+    /// `summary_throttle`. Match browsers see it through
+    /// `watch_summaries()`, and with `spectator_summaries` it is all the
+    /// room's spectators receive.
+    pub async fn send_summary<S: Serialize>(&self, summary: &S) -> Result<SendResult> {
+        self.ensure_player().await?;
+        if let Some(next_allowed) = self.check_throttle("summary").await {
+            return Ok(SendResult::Throttled { next_allowed });
+        }
+
+        let summary_tag = {
+            let state = self.room_state.read().await;
+            let room_id = state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+            create_summary_tag(&self.config.game_id, room_id)
+        };
+        let summary = serde_json::to_value(summary)?;
+        let content = encode_event(&EventContent::Summary(SummaryEventContent {
+            summary: summary.clone(),
+        }))?;
+        let id = self
+            .client
+            .publish_summary(&summary_tag, &self.config.game_id, &content)
+            .await?;
+        self.echo(ArenaEvent::PlayerSummary {
+            pubkey: self.public_key(),
+            summary,
+        })
+        .await;
+        Ok(SendResult::Sent(id))
+    }
+
+    /// Send game over event. `reason` is a [`ReasonCode`], a [`Reason`]
+    /// with detail, or text (parsed with [`Reason::from_text`]).
+    pub async fn send_game_over(
+        &self,
+        reason: impl Into<Reason>,
+        final_score: Option<i64>,
+    ) -> Result<()> {
+        self.ensure_player().await?;
+        let reason = reason.into();
+        let room_state = self.room_state.read().await;
+        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+        let room_tag = create_room_tag(&self.config.game_id, room_id);
+
+        let finished_at = now_ms();
+        let mut game_over = GameOverEventContent::new(reason.clone(), final_score);
+        game_over.at = Some(finished_at);
+        let content = encode_event(&EventContent::GameOver(game_over))?;
+
+        self.client.publish_result(&room_tag, &content).await?;
+        if self.config.input_audit {
+            // Declare our chain and start a new one for the next game
+            let chain = self
+                .input_chains
+                .remove(&self.public_key())
+                .unwrap_or_default();
+            let content = encode_event(&EventContent::Audit(AuditEventContent {
+                inputs: chain.count,
+                chain: chain.hash,
+            }))?;
+            self.client.publish_result(&room_tag, &content).await?;
+        }
+
+        drop(room_state);
+        let is_host = {
+            let mut state = self.room_state.write().await;
+            let was_finished = state.status == RoomStatus::Finished;
+            state.status = RoomStatus::Finished;
+            state.record_score(&self.public_key(), final_score);
+            if !was_finished {
+                let end = MatchEnd::GameOver {
+                    pubkey: self.public_key(),
+                    final_score,
+                };
+                report_match_end(&self.config, &state, end);
+            }
+            state.is_host
+        };
+        if is_host {
+            republish_room(&self.config, &self.client, &self.room_state, &self.players).await?;
+            record_finish(
+                &self.config,
+                &self.client,
+                &self.room_state,
+                &self.event_tx,
+                &self.public_key(),
+                finished_at,
+                reason.code,
+            )
+            .await;
+        }
+        if self.config.archive_matches {
+            let mut record = self.match_record().await?;
+            record.finish(
+                now_ms(),
+                MatchResult {
+                    winner: None,
+                    outcomes: vec![PlayerOutcome {
+                        pubkey: self.public_key(),
+                        reason: reason.to_string(),
+                        final_score,
+                    }],
+                },
+            );
+            if let Err(e) = self.archive_match(&record).await {
+                warn!("Failed to archive match: {}", e);
+            }
+        }
+        self.echo(ArenaEvent::PlayerGameOver {
+            pubkey: self.public_key(),
+            reason,
+            final_score,
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Request a rematch
+    pub async fn request_rematch(&self) -> Result<()> {
+        self.ensure_player().await?;
+        let room_state = self.room_state.read().await;
+        if room_state.status != RoomStatus::Finished {
+            return Ok(());
+        }
+
+        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+        let room_tag = create_room_tag(&self.config.game_id, room_id);
+
+        let content = encode_event(&EventContent::Rematch(RematchEventContent {
+            action: RematchAction::Request,
+            new_seed: None,
+            round: None,
+        }))?;
+
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+        self.echo(ArenaEvent::RematchRequested(self.public_key()))
+            .await;
+
+        drop(room_state);
+        let mut state = self.room_state.write().await;
+        state.rematch_requested = true;
+
+        Ok(())
+    }
+
+    /// Accept a rematch (host or co-host)
+    pub async fn accept_rematch(&self) -> Result<()> {
+        self.ensure_player().await?;
         let room_state = self.room_state.read().await;
         let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+        if !room_state.is_authority(&self.public_key()) {
+            return Err(ArenaError::NotAuthorized(
+                "only the host or a co-host can accept a rematch".to_string(),
+            ));
+        }
         let room_tag = create_room_tag(&self.config.game_id, room_id);
 
         let new_seed = generate_seed();
-        let content = serde_json::to_string(&EventContent::Rematch(RematchEventContent {
+        let content = encode_event(&EventContent::Rematch(RematchEventContent {
             action: RematchAction::Accept,
             new_seed: Some(new_seed),
+            round: None,
         }))?;
 
         self.client.publish_ephemeral(&room_tag, &content).await?;
 
         drop(room_state);
-        self.reset_for_rematch(new_seed).await;
+        self.dispatcher(room_tag).reset_for_rematch(new_seed).await;
+
+        Ok(())
+    }
+
+    // =========================================================================
+    // Rounds
+    // =========================================================================
+
+    /// Current round (0 until the first `advance_round()`)
+    pub async fn round(&self) -> u32 {
+        self.room_state.read().await.round
+    }
+
+    /// Current phase within the round
+    pub async fn phase(&self) -> Option<String> {
+        self.room_state.read().await.phase.clone()
+    }
+
+    /// Start the next round, keeping the current phase (host or co-host
+    /// only). Returns the new round number.
+    pub async fn advance_round(&self) -> Result<u32> {
+        let (round, phase) = {
+            let state = self.room_state.read().await;
+            (state.round + 1, state.phase.clone())
+        };
+        self.update_phase(round, phase).await?;
+        Ok(round)
+    }
+
+    /// Name the phase of the current round, e.g. "draft" (host or co-host
+    /// only)
+    pub async fn set_phase(&self, phase: &str) -> Result<()> {
+        let round = self.room_state.read().await.round;
+        self.update_phase(round, Some(phase.to_string())).await
+    }
+
+    /// Set the round and phase, tell the room and record them in the room
+    /// event
+    async fn update_phase(&self, round: u32, phase: Option<String>) -> Result<()> {
+        let (room_id, is_host) = {
+            let mut state = self.room_state.write().await;
+            let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+            if !state.is_authority(&self.public_key()) {
+                return Err(ArenaError::NotAuthorized(
+                    "only the host or a co-host can change the round or phase".to_string(),
+                ));
+            }
+            state.round = round;
+            state.phase = phase.clone();
+            (room_id, state.is_host)
+        };
+
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        let content = encode_event(&EventContent::Phase(PhaseEventContent {
+            round,
+            phase: phase.clone(),
+        }))?;
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+        if is_host {
+            republish_room(&self.config, &self.client, &self.room_state, &self.players).await?;
+        }
+        self.echo(ArenaEvent::PhaseChanged { round, phase }).await;
+        Ok(())
+    }
+
+    /// Cumulative standings of the session, best first. Every game over
+    /// report adds its `final_score`; players keep their standing after they
+    /// leave, and the host publishes it in the room event for late joiners.
+    pub async fn scoreboard(&self) -> Vec<Standing> {
+        self.room_state.read().await.scoreboard.clone()
+    }
+
+    /// Start the next round with a fresh seed (host or co-host only).
+    ///
+    /// Uses the rematch message, but keeps the room playing: peers adopt the
+    /// seed and get `ArenaEvent::RoundStart`, and the host records both in
+    /// the room event. Returns the new round and seed.
+    pub async fn next_round_seed(&self) -> Result<(u32, u64)> {
+        let (room_id, round, seed, is_host) = {
+            let mut state = self.room_state.write().await;
+            let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+            if !state.is_authority(&self.public_key()) {
+                return Err(ArenaError::NotAuthorized(
+                    "only the host or a co-host can start a round".to_string(),
+                ));
+            }
+            state.round += 1;
+            state.seed = generate_seed();
+            (room_id, state.round, state.seed, state.is_host)
+        };
+
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        let content = encode_event(&EventContent::Rematch(RematchEventContent {
+            action: RematchAction::Round,
+            new_seed: Some(seed),
+            round: Some(round),
+        }))?;
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+        if is_host {
+            republish_room(&self.config, &self.client, &self.room_state, &self.players).await?;
+        }
+        self.echo(ArenaEvent::RoundStart { round, seed }).await;
+        Ok((round, seed))
+    }
+
+    // =========================================================================
+    // Votes
+    // =========================================================================
+
+    /// Open a vote on `topic` (host or co-host only), replacing an earlier
+    /// vote on the same topic
+    pub async fn propose_vote(&self, topic: &str, options: Vec<String>) -> Result<()> {
+        let mut unique = options.clone();
+        unique.sort();
+        unique.dedup();
+        if options.is_empty() || unique.len() != options.len() {
+            return Err(ArenaError::InvalidValue(
+                "a vote needs distinct options".to_string(),
+            ));
+        }
+
+        {
+            let state = self.room_state.read().await;
+            state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+            if !state.is_authority(&self.public_key()) {
+                return Err(ArenaError::NotAuthorized(
+                    "only the host or a co-host can propose a vote".to_string(),
+                ));
+            }
+        }
+        open_vote(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            topic,
+            options.clone(),
+        )
+        .await?;
+        self.echo(ArenaEvent::VoteProposed {
+            topic: topic.to_string(),
+            options,
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Vote for `choice` in the open vote on `topic`. Voting again changes
+    /// the vote. The host closes the vote once every player has voted.
+    pub async fn cast_vote(&self, topic: &str, choice: &str) -> Result<()> {
+        let me = self.public_key();
+        let (room_id, is_host) = {
+            let mut state = self.room_state.write().await;
+            let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+            let is_host = state.is_host;
+            let vote = state
+                .votes
+                .get_mut(topic)
+                .filter(|vote| vote.winner.is_none())
+                .ok_or_else(|| ArenaError::InvalidValue(format!("no open vote on {topic}")))?;
+            if !vote.options.iter().any(|o| o == choice) {
+                return Err(ArenaError::InvalidValue(format!(
+                    "{choice} is not an option of {topic}"
+                )));
+            }
+            if afk_vote_target(topic) == Some(me.as_str()) {
+                return Err(ArenaError::NotAuthorized(
+                    "players can't vote on their own removal".to_string(),
+                ));
+            }
+            vote.ballots.insert(me.clone(), choice.to_string());
+            (room_id, is_host)
+        };
+
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        let content = encode_event(&EventContent::Vote(VoteEventContent {
+            action: VoteAction::Cast,
+            topic: topic.to_string(),
+            options: Vec::new(),
+            choice: Some(choice.to_string()),
+            tally: HashMap::new(),
+        }))?;
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+        self.echo(ArenaEvent::VoteCast {
+            pubkey: me,
+            topic: topic.to_string(),
+            choice: choice.to_string(),
+        })
+        .await;
+
+        if is_host {
+            close_vote_if_complete(
+                &self.config,
+                &self.client,
+                &self.room_state,
+                &self.players,
+                &self.event_tx,
+                topic,
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Close the vote on `topic` now and announce the winner (host only).
+    /// Ties go to the option listed first.
+    pub async fn close_vote(&self, topic: &str) -> Result<String> {
+        if !self.room_state.read().await.is_host {
+            return Err(ArenaError::NotAuthorized(
+                "only the host can close a vote".to_string(),
+            ));
+        }
+        let winner = close_vote(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.event_tx,
+            topic,
+        )
+        .await?;
+        if let Some(target) = afk_vote_target(topic) {
+            settle_afk_vote(
+                &self.config,
+                &self.client,
+                &self.room_state,
+                &self.players,
+                &self.event_tx,
+                target,
+                &winner,
+            )
+            .await?;
+        }
+        Ok(winner)
+    }
+
+    /// Votes of the current room, open and closed
+    pub async fn votes(&self) -> Vec<Vote> {
+        self.room_state
+            .read()
+            .await
+            .votes
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    // =========================================================================
+    // Claims
+    // =========================================================================
+
+    /// Claim `claim_id` now, e.g. to buzz first or take a tile. The host
+    /// collects competing claims for `claim_window`, orders them by claim
+    /// time corrected for each player's clock offset, and everyone gets
+    /// `ClaimResolved` with the winner.
+    pub async fn claim(&self, claim_id: &str) -> Result<()> {
+        self.ensure_player().await?;
+        let (room_id, is_host) = {
+            let state = self.room_state.read().await;
+            let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+            if state
+                .claims
+                .get(claim_id)
+                .is_some_and(|claim| claim.winner.is_some())
+            {
+                return Err(ArenaError::InvalidValue(format!(
+                    "{claim_id} is already resolved"
+                )));
+            }
+            (room_id, state.is_host)
+        };
+
+        if is_host {
+            record_claim(
+                &self.config,
+                &self.client,
+                &self.room_state,
+                &self.event_tx,
+                claim_id,
+                &self.public_key(),
+                now_ms(),
+            )
+            .await;
+            return Ok(());
+        }
+
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        let content = encode_event(&EventContent::Claim(ClaimEventContent {
+            action: ClaimAction::Claim,
+            claim_id: claim_id.to_string(),
+            at: Some(now_ms()),
+            winner: None,
+        }))?;
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Turns
+    // =========================================================================
+
+    /// Time banks of the running game, with `time_control`
+    pub async fn clock(&self) -> Option<ChessClock> {
+        self.room_state.read().await.clock.clone()
+    }
+
+    /// Time a player has left in ms, with `time_control`
+    pub async fn time_left(&self, pubkey: &str) -> Option<u64> {
+        self.room_state
+            .read()
+            .await
+            .clock
+            .as_ref()?
+            .remaining(pubkey, monotonic_ms())
+    }
+
+    /// End this player's turn and pass it to `next`.
+    ///
+    /// Peers get `ArenaEvent::TurnChanged`, and the host records the turn in
+    /// the room event so it outlives everyone going offline. With
+    /// `turn_notifications`, an offline `next` player is also sent a NIP-17
+    /// direct message.
+    pub async fn end_turn(&self, next: &str) -> Result<()> {
+        let room_id = publish_turn(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.players,
+            next,
+            None,
+        )
+        .await?;
+        self.echo(turn_event(&self.config, next)).await;
+
+        // Missing two heartbeats means the player is away, even if they
+        // are not considered disconnected yet
+        let online = self
+            .players
+            .get(next)
+            .is_some_and(|p| p.silent_ms() <= self.config.heartbeat_interval * 2);
+        if self.config.turn_notifications && !online && next != self.public_key() {
+            let link = self.get_room_url().await.unwrap_or(room_id);
+            let text = format!("It's your move in {} room {}", self.config.game_id, link);
+            if let Err(e) = self.client.send_direct_message(next, &text).await {
+                warn!("Failed to send turn notification: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Player whose move it is in the current room
+    pub async fn current_turn(&self) -> Option<String> {
+        self.room_state.read().await.turn.clone()
+    }
+
+    /// Publish our move and pass the turn to the next player in the turn
+    /// order, returning them (see [`crate::turns::TurnManager`])
+    pub(crate) async fn submit_move(&self, payload: serde_json::Value) -> Result<String> {
+        self.ensure_player().await?;
+        let me = self.public_key();
+        let next = {
+            let state = self.room_state.read().await;
+            state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+            if state.status != RoomStatus::Playing || state.turn.as_deref() != Some(me.as_str()) {
+                return Err(ArenaError::NotAuthorized("not your turn".to_string()));
+            }
+            // Alone in the room, the turn comes back to us
+            next_in_turn(&resolve_turn_order(&state, &self.players), &me)
+                .unwrap_or_else(|| me.clone())
+        };
+
+        publish_turn(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.players,
+            &next,
+            Some(payload.clone()),
+        )
+        .await?;
+        self.echo(ArenaEvent::TurnReceived {
+            pubkey: me,
+            payload,
+        })
+        .await;
+        self.echo(turn_event(&self.config, &next)).await;
+        Ok(next)
+    }
+
+    /// Order in which players take turns: the host's, or else derived from
+    /// the seed and the seated players, so every peer agrees on who goes
+    /// first. Fixed when the game starts.
+    pub async fn turn_order(&self) -> Vec<String> {
+        resolve_turn_order(&*self.room_state.read().await, &self.players)
+    }
+
+    /// Override the turn order (host only); `order` must list every seated
+    /// player once. Cleared by a rematch.
+    pub async fn set_turn_order(&self, order: Vec<String>) -> Result<()> {
+        {
+            let mut state = self.room_state.write().await;
+            state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+            if !state.is_host {
+                return Err(ArenaError::NotAuthorized(
+                    "Only host can set the turn order".to_string(),
+                ));
+            }
+            let mut expected = if state.seats.is_empty() {
+                self.players.keys()
+            } else {
+                state.seats.clone()
+            };
+            let mut given = order.clone();
+            expected.sort();
+            given.sort();
+            if given != expected {
+                return Err(ArenaError::InvalidValue(
+                    "turn order must list every seated player once".to_string(),
+                ));
+            }
+            state.turn_order = order;
+        }
+        republish_room(&self.config, &self.client, &self.room_state, &self.players).await?;
+        Ok(())
+    }
+
+    /// Matches of this game on the read relays that are waiting for
+    /// `pubkey` to move, according to the hosts' room events
+    #[cfg(feature = "discovery")]
+    pub async fn pending_turns(&self, pubkey: &str) -> Result<Vec<RoomInfo>> {
+        let events = self.client.fetch_rooms(&self.config.game_id, 100).await?;
+        let skew = self.client.clock_skew().await;
+
+        let mut rooms: Vec<RoomInfo> = Vec::new();
+        for event in events {
+            let Ok(content) = decode_room(&event.content) else {
+                continue;
+            };
+            if event.pubkey.to_hex() != content.host_pubkey
+                || content.status != RoomStatus::Playing
+                || content.turn.as_deref() != Some(pubkey)
+            {
+                continue;
+            }
+            let room = room_info(&self.config.game_id, &event, content, skew);
+            if !rooms.iter().any(|r| r.room_id == room.room_id) {
+                rooms.push(room);
+            }
+        }
+        Ok(rooms)
+    }
+
+    // =========================================================================
+    // Activity
+    // =========================================================================
+
+    /// Publish what this player is doing in this game, with the current room
+    /// (and its URL if `base_url` is set) for deep links. Unlisted arenas
+    /// leave the room out.
+    pub async fn publish_activity(&self, status: ActivityStatus) -> Result<EventId> {
+        let room_id = if self.config.unlisted {
+            None
+        } else {
+            self.room_state.read().await.room_id.clone()
+        };
+        let room_url = if self.config.base_url.is_some() && room_id.is_some() {
+            self.get_room_url().await
+        } else {
+            None
+        };
+        let activity = Activity {
+            pubkey: self.public_key(),
+            game_id: self.config.game_id.clone(),
+            status,
+            room_id,
+            room_url,
+            updated_at: now_ms(),
+        };
+        self.client
+            .publish_user_status(
+                &Activity::status_tag(&self.config.game_id),
+                &activity.text(),
+                &activity.tags(),
+            )
+            .await
+    }
+
+    /// Clear this player's activity for this game
+    pub async fn clear_activity(&self) -> Result<EventId> {
+        self.client
+            .publish_user_status(&Activity::status_tag(&self.config.game_id), "", &[])
+            .await
+    }
+
+    /// What `pubkeys` are doing across all games on this crate
+    pub async fn friends_activity(&self, pubkeys: &[String]) -> Result<Vec<Activity>> {
+        fetch_activity(&self.client, pubkeys).await
+    }
+
+    // =========================================================================
+    // Invitations
+    // =========================================================================
+
+    /// Invite a player to the current room.
+    ///
+    /// The invitee's arena emits `ArenaEvent::InviteReceived` if it is
+    /// connected; their answer arrives as `InviteAccepted` or
+    /// `InviteDeclined`.
+    pub async fn invite(&self, pubkey: &str) -> Result<EventId> {
+        let room_id = self
+            .room_state
+            .read()
+            .await
+            .room_id
+            .clone()
+            .ok_or(ArenaError::NotInRoom)?;
+        let proof = match &self.config.room_password {
+            Some(password) => Some(
+                self.client
+                    .nip44_encrypt(pubkey, &room_password_proof(&room_id, password, pubkey))
+                    .await?,
+            ),
+            None => None,
+        };
+        let content = encode_event(&EventContent::Invite(InviteEventContent {
+            action: InviteAction::Invite,
+            game_id: self.config.game_id.clone(),
+            room_id,
+            relays: self.client.room_relays().await,
+            proof,
+        }))?;
+        self.client
+            .publish_to_inbox(pubkey, &self.config.game_id, &content)
+            .await
+    }
+
+    /// Accept an invitation: tell the inviter and join the room
+    pub async fn accept_invite(&self, room_id: &str) -> Result<()> {
+        let (from, invite) = self.take_invite(room_id).await?;
+        self.answer_invite(&from, &invite, InviteAction::Accept)
+            .await?;
+        let secret = match &invite.proof {
+            Some(proof) => Some(JoinSecret::Proof(
+                self.client.nip44_decrypt(&from, proof).await?,
+            )),
+            None => None,
+        };
+        self.join_with_hints(&invite.room_id, invite.relays, secret, None)
+            .await
+    }
+
+    /// Decline an invitation and tell the inviter
+    pub async fn decline_invite(&self, room_id: &str) -> Result<()> {
+        let (from, invite) = self.take_invite(room_id).await?;
+        self.answer_invite(&from, &invite, InviteAction::Decline)
+            .await?;
+        Ok(())
+    }
+
+    /// Invitations not answered yet, as `(from, room_id)` pairs
+    pub async fn pending_invites(&self) -> Vec<(String, String)> {
+        self.invites
+            .read()
+            .await
+            .iter()
+            .map(|(room_id, (from, _))| (from.clone(), room_id.clone()))
+            .collect()
+    }
+
+    async fn take_invite(&self, room_id: &str) -> Result<(String, InviteEventContent)> {
+        self.invites
+            .write()
+            .await
+            .remove(room_id)
+            .ok_or_else(|| ArenaError::InvalidValue(format!("no invitation to room {room_id}")))
+    }
+
+    async fn answer_invite(
+        &self,
+        to: &str,
+        invite: &InviteEventContent,
+        action: InviteAction,
+    ) -> Result<EventId> {
+        let content = encode_event(&EventContent::Invite(InviteEventContent {
+            action,
+            game_id: invite.game_id.clone(),
+            room_id: invite.room_id.clone(),
+            relays: Vec::new(),
+            proof: None,
+        }))?;
+        self.client
+            .publish_to_inbox(to, &self.config.game_id, &content)
+            .await
+    }
+
+    /// Listen for invitations and answers addressed to us
+    async fn start_inbox(&self) -> Result<()> {
+        let (tx, mut rx) = mpsc::channel(DISPATCH_QUEUE);
+        self.client
+            .subscribe_inbox(&self.config.game_id, tx)
+            .await?;
+
+        let game_id = self.config.game_id.clone();
+        let blocked = self.blocked.clone();
+        let invites = self.invites.clone();
+        let event_tx = self.event_tx.clone();
+        spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let from = event.pubkey.to_hex();
+                if blocked.read().await.contains(&from) {
+                    continue;
+                }
+                let Ok(EventContent::Invite(invite)) = decode_event(&event.content) else {
+                    continue;
+                };
+                if invite.game_id != game_id {
+                    continue;
+                }
+
+                let room_id = invite.room_id.clone();
+                let app_event = match invite.action {
+                    InviteAction::Invite => {
+                        invites
+                            .write()
+                            .await
+                            .insert(room_id.clone(), (from.clone(), invite));
+                        ArenaEvent::InviteReceived {
+                            from,
+                            room_id,
+                            game_id: game_id.clone(),
+                        }
+                    }
+                    InviteAction::Accept => ArenaEvent::InviteAccepted { from, room_id },
+                    InviteAction::Decline => ArenaEvent::InviteDeclined { from, room_id },
+                };
+                let _ = event_tx.send(app_event).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    // =========================================================================
+    // Chat
+    // =========================================================================
+
+    /// Send a chat message.
+    ///
+    /// The text is sanitized and truncated to `chat_max_length`. Sending is
+    /// subject to the `"chat"` throttle and the chat flood limit, which peers
+    /// enforce as well.
+    pub async fn send_chat(&self, text: &str) -> Result<SendResult> {
+        let text = match self.admit_chat(text, true).await? {
+            Ok(text) => text,
+            Err(next_allowed) => return Ok(SendResult::Throttled { next_allowed }),
+        };
+        let room_id = self
+            .room_state
+            .read()
+            .await
+            .room_id
+            .clone()
+            .ok_or(ArenaError::NotInRoom)?;
+
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        let content = encode_event(&EventContent::Chat(ChatEventContent { text: text.clone() }))?;
+        let id = self.client.publish_ephemeral(&room_tag, &content).await?;
+        let me = self.public_key();
+        self.echo(ArenaEvent::Chat { pubkey: me, text }).await;
+        Ok(SendResult::Sent(id))
+    }
+
+    /// Sanitize a chat message of ours and apply mutes, slow mode, flood
+    /// limits and, if `throttled`, the chat throttle. `Err(next_allowed)`
+    /// when it can't be sent yet.
+    async fn admit_chat(
+        &self,
+        text: &str,
+        throttled: bool,
+    ) -> Result<std::result::Result<String, u64>> {
+        let text = sanitize_chat(text, self.config.chat_max_length);
+        if text.is_empty() {
+            return Err(ArenaError::InvalidValue("empty chat message".to_string()));
+        }
+
+        let me = self.public_key();
+        let slow_window = {
+            let state = self.room_state.read().await;
+            if state.room_id.is_none() {
+                return Err(ArenaError::NotInRoom);
+            }
+            if !state.is_host && state.chat_muted.contains(&me) {
+                return Err(ArenaError::NotAuthorized("Muted by the host".to_string()));
+            }
+            if state.is_host {
+                0
+            } else {
+                u64::from(state.chat_slow_mode) * 1000
+            }
+        };
+
+        let now = monotonic_ms();
+        let mut slow = self.slow_guards.get(&me).unwrap_or_default();
+        if slow_window > 0 && !slow.allow(now, 1, slow_window) {
+            return Ok(Err(slow.retry_at(slow_window)));
+        }
+        let (limit, window) = (self.config.chat_flood_limit, self.config.chat_flood_window);
+        let mut guard = self.chat_guards.get(&me).unwrap_or_default();
+        if !guard.allow(now, limit, window) {
+            return Ok(Err(guard.retry_at(window)));
+        }
+        if throttled && let Some(next_allowed) = self.check_throttle("chat").await {
+            return Ok(Err(next_allowed));
+        }
+        self.chat_guards.insert(me.clone(), guard);
+        self.slow_guards.insert(me, slow);
+        Ok(Ok(text))
+    }
+
+    /// Send an app-defined message on a named channel. Throttled with the
+    /// `message:{channel}` throttle.
+    pub async fn send_message<M: Serialize>(
+        &self,
+        channel: &str,
+        payload: &M,
+    ) -> Result<SendResult> {
+        check_channel_name(channel)?;
+        let room_id = self
+            .room_state
+            .read()
+            .await
+            .room_id
+            .clone()
+            .ok_or(ArenaError::NotInRoom)?;
+        if let Some(next_allowed) = self.check_throttle(&format!("message:{channel}")).await {
+            return Ok(SendResult::Throttled { next_allowed });
+        }
+
+        let payload = serde_json::to_value(payload)?;
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        let content = encode_event(&EventContent::Message(MessageEventContent {
+            channel: channel.to_string(),
+            payload: payload.clone(),
+        }))?;
+        let id = self.client.publish_ephemeral(&room_tag, &content).await?;
+        if self.config.echo_self {
+            let message = ChannelMessage {
+                pubkey: self.public_key(),
+                channel: channel.to_string(),
+                payload,
+            };
+            deliver_message(&self.channels, &self.event_tx, message).await;
+        }
+        Ok(SendResult::Sent(id))
+    }
+
+    /// Publish the messages of one tick together: one signed event and one
+    /// relay round trip instead of one per message. `Hosted` rooms publish
+    /// two, as state and heartbeats go to the host's channel. Receivers
+    /// handle the messages in order, as if sent one by one.
+    ///
+    /// Peers must support it: unless every player announced the `batch`
+    /// capability, the messages are published one by one instead. Per-type
+    /// throttles don't apply, but chat still obeys mutes, slow mode and
+    /// flood limits; if a message is refused nothing is sent and
+    /// `BatchResult::Throttled` is returned.
+    pub async fn send_batch(&self, batch: MessageBatch<T>) -> Result<BatchResult> {
+        let (room_id, to_host) = {
+            let state = self.room_state.read().await;
+            let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+            (room_id, state.sends_to_host())
+        };
+
+        for entry in &batch.entries {
+            match entry {
+                BatchEntry::State(_) => self.ensure_player().await?,
+                BatchEntry::Message { channel, .. } => check_channel_name(channel)?,
+                _ => {}
+            }
+        }
+
+        enum Echo<T> {
+            Event(Box<ArenaEvent<T>>),
+            Message(ChannelMessage),
+        }
+        let me = self.public_key();
+        // Messages for the room and for the host's channel
+        let (mut room, mut host) = (Vec::new(), Vec::new());
+        // Every state, in order, for the input audit; the last one is ours
+        let (mut states_sent, mut last_state) = (Vec::new(), None);
+        let mut echoes = Vec::new();
+        for entry in batch.entries {
+            match entry {
+                BatchEntry::State(state) => {
+                    let content = encode_event(&EventContent::State(StateEventContent {
+                        game_state: serde_json::to_value(&state)?,
+                    }))?;
+                    if to_host { &mut host } else { &mut room }.push(content.clone());
+                    echoes.push(Echo::Event(Box::new(ArenaEvent::PlayerState {
+                        pubkey: me.clone(),
+                        state: state.clone(),
+                    })));
+                    states_sent.push(content);
+                    last_state = Some(state);
+                }
+                BatchEntry::Chat(text) => {
+                    let text = match self.admit_chat(&text, false).await? {
+                        Ok(text) => text,
+                        Err(next_allowed) => return Ok(BatchResult::Throttled { next_allowed }),
+                    };
+                    room.push(encode_event(&EventContent::Chat(ChatEventContent {
+                        text: text.clone(),
+                    }))?);
+                    echoes.push(Echo::Event(Box::new(ArenaEvent::Chat {
+                        pubkey: me.clone(),
+                        text,
+                    })));
+                }
+                BatchEntry::Message { channel, payload } => {
+                    room.push(encode_event(&EventContent::Message(MessageEventContent {
+                        channel: channel.clone(),
+                        payload: payload.clone(),
+                    }))?);
+                    echoes.push(Echo::Message(ChannelMessage {
+                        pubkey: me.clone(),
+                        channel,
+                        payload,
+                    }));
+                }
+                BatchEntry::Heartbeat => {
+                    let away = self.away.load(Ordering::Relaxed);
+                    let content = encode_event(&heartbeat_content(&self.peer_latency, away))?;
+                    if to_host { &mut host } else { &mut room }.push(content);
+                }
+            }
+        }
+
+        let batched = self.negotiated_capabilities().await.batch;
+        let mut ids = Vec::new();
+        for (tag, messages) in [
+            (create_host_tag(&self.config.game_id, &room_id), host),
+            (create_room_tag(&self.config.game_id, &room_id), room),
+        ] {
+            if batched && messages.len() > 1 {
+                let content = encode_event(&EventContent::Batch(BatchEventContent { messages }))?;
+                ids.push(self.client.publish_ephemeral(&tag, &content).await?);
+            } else {
+                for content in messages {
+                    ids.push(self.client.publish_ephemeral(&tag, &content).await?);
+                }
+            }
+        }
+
+        if let Some(state) = last_state {
+            self.last_sent
+                .write()
+                .await
+                .insert("state".to_string(), monotonic_ms());
+            *self.last_state.write().await = Some(state);
+        }
+        if self.config.input_audit {
+            // Receivers chain every state of the batch, so we do too
+            for content in &states_sent {
+                record_input(&self.input_chains, me.clone(), content);
+            }
+        }
+        if self.config.echo_self {
+            for echo in echoes {
+                match echo {
+                    Echo::Event(event) => {
+                        let _ = self.event_tx.send(*event).await;
+                    }
+                    Echo::Message(message) => {
+                        deliver_message(&self.channels, &self.event_tx, message).await;
+                    }
+                }
+            }
+        }
+        Ok(BatchResult::Sent(ids))
+    }
+
+    /// Subscribe to a named channel. While it has subscribers, its messages
+    /// go to them instead of the event stream (`ArenaEvent::Message`).
+    pub async fn channel(&self, name: &str) -> broadcast::Receiver<ChannelMessage> {
+        self.channels
+            .write()
+            .await
+            .entry(name.to_string())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Hide a player's chat messages locally without leaving the room
+    pub async fn mute(&self, pubkey: &str) {
+        self.muted.write().await.insert(pubkey.to_string());
+    }
+
+    pub async fn unmute(&self, pubkey: &str) {
+        self.muted.write().await.remove(pubkey);
+    }
+
+    /// Public keys of muted players
+    pub async fn muted(&self) -> Vec<String> {
+        self.muted.read().await.iter().cloned().collect()
+    }
+
+    /// Let each player send one chat message every `seconds` (host only);
+    /// 0 turns slow mode off. The host is exempt.
+    pub async fn set_chat_slow_mode(&self, seconds: u32) -> Result<()> {
+        self.moderate(ModerationEventContent {
+            action: ModerationAction::SlowMode,
+            seconds: Some(seconds),
+            player_pubkey: None,
+        })
+        .await
+    }
+
+    /// Mute a player's chat for everyone in the room (host only).
+    ///
+    /// Unlike [`Arena::mute`], peers drop the player's messages too, and the
+    /// player's own `send_chat` fails.
+    pub async fn mute_player(&self, pubkey: &str) -> Result<()> {
+        self.moderate(ModerationEventContent {
+            action: ModerationAction::Mute,
+            seconds: None,
+            player_pubkey: Some(pubkey.to_string()),
+        })
+        .await
+    }
+
+    /// Lift a mute set with [`Arena::mute_player`] (host only)
+    pub async fn unmute_player(&self, pubkey: &str) -> Result<()> {
+        self.moderate(ModerationEventContent {
+            action: ModerationAction::Unmute,
+            seconds: None,
+            player_pubkey: Some(pubkey.to_string()),
+        })
+        .await
+    }
+
+    /// Tell every peer to clear the chat shown so far (host only)
+    pub async fn clear_chat(&self) -> Result<()> {
+        self.moderate(ModerationEventContent {
+            action: ModerationAction::Clear,
+            seconds: None,
+            player_pubkey: None,
+        })
+        .await
+    }
+
+    /// Chat slow mode set by the host, in seconds (0 = off)
+    pub async fn chat_slow_mode(&self) -> u32 {
+        self.room_state.read().await.chat_slow_mode
+    }
+
+    /// Players the host muted for everyone
+    pub async fn chat_muted(&self) -> Vec<String> {
+        self.room_state.read().await.chat_muted.clone()
+    }
+
+    /// Apply a chat rule, tell peers, and keep it in the room event so
+    /// late joiners get it
+    async fn moderate(&self, content: ModerationEventContent) -> Result<()> {
+        let room_id = {
+            let mut state = self.room_state.write().await;
+            let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+            if !state.is_host {
+                return Err(ArenaError::NotAuthorized(
+                    "Only host can moderate chat".to_string(),
+                ));
+            }
+            if !state.apply_moderation(&content) {
+                return Err(ArenaError::InvalidValue(
+                    "moderation action is missing a value".to_string(),
+                ));
+            }
+            room_id
+        };
+
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        self.client
+            .publish_ephemeral(
+                &room_tag,
+                &encode_event(&EventContent::Moderation(content.clone()))?,
+            )
+            .await?;
+        if content.action != ModerationAction::Clear {
+            republish_room(&self.config, &self.client, &self.room_state, &self.players).await?;
+        }
+        if let Some(event) = moderation_event(&content) {
+            self.echo(event).await;
+        }
+        Ok(())
+    }
+
+    /// Report a player with a NIP-56 report referencing the current room.
+    ///
+    /// The player is also muted and blocked locally: while hosting, blocked
+    /// players are refused a seat. Persist [`Arena::blocked`] to keep the
+    /// list across sessions.
+    pub async fn report_player(&self, pubkey: &str, reason: ReportReason) -> Result<EventId> {
+        let coordinate = {
+            let state = self.room_state.read().await;
+            match (&state.room_id, &state.host_pubkey) {
+                (Some(room_id), Some(host)) => Some(room_address(
+                    host,
+                    &create_room_tag(&self.config.game_id, room_id),
+                )),
+                _ => None,
+            }
+        };
+
+        let id = self
+            .client
+            .publish_report(pubkey, reason.as_str(), coordinate.as_deref())
+            .await?;
+        self.muted.write().await.insert(pubkey.to_string());
+        self.blocked.write().await.insert(pubkey.to_string());
+        Ok(id)
+    }
+
+    /// Block a player: they are refused a seat while hosting, their rooms
+    /// are hidden from [`Arena::find_rooms`] and their room traffic is dropped
+    pub async fn block(&self, pubkey: &str) {
+        self.blocked.write().await.insert(pubkey.to_string());
+    }
+
+    pub async fn unblock(&self, pubkey: &str) {
+        self.blocked.write().await.remove(pubkey);
+    }
+
+    /// Public keys of blocked players, from the config blocklist, `block()`
+    /// and reports
+    pub async fn blocked(&self) -> Vec<String> {
+        self.blocked.read().await.iter().cloned().collect()
+    }
+
+    // =========================================================================
+    // Start Mode
+    // =========================================================================
+
+    /// Send ready signal (for Ready/Countdown modes)
+    pub async fn send_ready(&self, ready: bool) -> Result<()> {
+        self.ensure_player().await?;
+        let room_state = self.room_state.read().await;
+        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+        let room_tag = create_room_tag(&self.config.game_id, room_id);
+
+        let content = encode_event(&EventContent::Ready(ReadyEventContent { ready }))?;
+
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+        self.echo(ArenaEvent::PlayerReady {
+            pubkey: self.public_key(),
+            ready,
+        })
+        .await;
+
+        // Update self ready status
+        drop(room_state);
+        set_ready(&self.players, &self.event_tx, &self.public_key(), ready).await;
+        self.check_all_ready().await;
+
+        Ok(())
+    }
+
+    /// Set the start settings (map, rules, ...) delivered to every player
+    /// with `ArenaEvent::GameStart` (host only). Published in the room
+    /// event, so set them before the game can start.
+    pub async fn set_start_config<C: Serialize>(&self, config: &C) -> Result<()> {
+        let config = serde_json::to_value(config)?;
+        {
+            let mut state = self.room_state.write().await;
+            state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+            if !state.is_host {
+                return Err(ArenaError::NotAuthorized(
+                    "Only host can set the start config".to_string(),
+                ));
+            }
+            state.start_config = Some(config);
+        }
+        republish_room(&self.config, &self.client, &self.room_state, &self.players).await?;
+        Ok(())
+    }
+
+    /// Start settings of the current room
+    pub async fn start_config(&self) -> Option<serde_json::Value> {
+        self.room_state.read().await.start_config.clone()
+    }
+
+    /// Start the game (for Host mode, host or co-host only)
+    pub async fn start_game(&self) -> Result<()> {
+        let room_state = self.room_state.read().await;
+        if !room_state.is_host && !room_state.co_hosts.contains(&self.public_key()) {
+            return Err(ArenaError::NotAuthorized(
+                "Only host can start game".to_string(),
+            ));
+        }
+        if room_state.status == RoomStatus::Playing {
+            return Err(ArenaError::InvalidValue(
+                "the game already started".to_string(),
+            ));
+        }
+
+        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+        let room_tag = create_room_tag(&self.config.game_id, room_id);
+
+        let content = encode_event(&EventContent::GameStart(GameStartEventContent {
+            config: room_state.start_config.clone(),
+            seed: Some(room_state.seed),
+            turn_order: resolve_turn_order(&room_state, &self.players),
+        }))?;
+
+        self.client.publish_ephemeral(&room_tag, &content).await?;
+
+        drop(room_state);
+        begin_play(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.players,
+            &self.event_tx,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    // =========================================================================
+    // QR Code / URL
+    // =========================================================================
+
+    /// Get room URL, with the game relays as hints if `relay_hints` is on
+    pub async fn get_room_url(&self) -> Option<String> {
+        let room_id = self.room_state.read().await.room_id.clone()?;
+        let relays = if self.config.relay_hints {
+            self.client.room_relays().await
+        } else {
+            Vec::new()
+        };
+        Some(room_url(self.config.base_url.as_deref(), &room_id, &relays))
+    }
+
+    /// Get a compact `arena1...` invite token for the room, carrying the
+    /// game id, room id and game relays (and the seed if `include_seed`).
+    /// Tokens of our password-protected rooms carry the password.
+    pub async fn get_room_invite(&self, include_seed: bool) -> Option<String> {
+        let state = self.room_state.read().await;
+        let room_id = state.room_id.as_ref()?;
+
+        let mut invite =
+            Invite::new(&self.config.game_id, room_id).relays(self.client.room_relays().await);
+        if include_seed {
+            invite = invite.seed(state.seed);
+        }
+        if let Some(password) = self.config.room_password.as_ref().filter(|_| state.is_host) {
+            invite = invite.secret(password);
+        }
+        if let Some(host) = &state.host_pubkey {
+            invite = invite.host(host);
+        }
+        Some(invite.encode())
+    }
+
+    /// Get room QR code as SVG
+    #[cfg(feature = "qr")]
+    pub async fn get_room_qr_svg(&self, options: Option<crate::qr::QrOptions>) -> Option<String> {
+        let url = self.get_room_url().await?;
+        crate::qr::generate_qr_svg(&url, &options.unwrap_or_default()).ok()
+    }
+
+    /// Get room QR code as data URL
+    #[cfg(feature = "qr")]
+    pub async fn get_room_qr_data_url(
+        &self,
+        options: Option<crate::qr::QrOptions>,
+    ) -> Option<String> {
+        let url = self.get_room_url().await?;
+        crate::qr::generate_qr_data_url(&url, &options.unwrap_or_default()).ok()
+    }
+
+    // =========================================================================
+    // Private: Event Handling
+    // =========================================================================
+
+    /// Subscribe to the room and start its dispatcher task.
+    ///
+    /// Inbound events are queued on a bounded channel and handled one at a
+    /// time, in arrival order. When the app falls behind, the queue fills up
+    /// and the subscription waits instead of spawning more work.
+    async fn start_room_subscription(&self, room_id: &str) -> Result<()> {
+        let room_tag = create_room_tag(&self.config.game_id, room_id);
+        let (tx, mut rx) = mpsc::channel(DISPATCH_QUEUE);
+        let d_tags = room_channels(&self.config, &*self.room_state.read().await);
+        self.client.subscribe_room(&d_tags, tx).await?;
+
+        let dispatcher = self.dispatcher(room_tag);
+        spawn(async move {
+            while let Some(event) = rx.recv().await {
+                dispatcher.handle(event).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Hand `event` to `recv()` as if it came from the room, for testing
+    /// game code without relays (`testing` feature). Joins, leaves, states
+    /// and ready toggles also update `players()` and the ready count; a
+    /// `GameStart` sets the status to `Playing`. Up to 100 events wait for
+    /// `recv()`, after which this waits too.
+    #[cfg(any(test, feature = "testing"))]
+    pub async fn inject_event(&self, event: ArenaEvent<T>) {
+        match &event {
+            ArenaEvent::PlayerJoin(presence) => {
+                self.players
+                    .insert(presence.pubkey.clone(), presence.clone());
+            }
+            ArenaEvent::PlayerLeave(pubkey) => {
+                self.players.remove(pubkey);
+                self.player_states.remove(pubkey);
+            }
+            ArenaEvent::PlayerState { pubkey, state } => {
+                self.player_states.insert(pubkey.clone(), state.clone());
+            }
+            ArenaEvent::PlayerReady { pubkey, ready } => {
+                self.players.update(pubkey, |p| p.ready = *ready);
+            }
+            ArenaEvent::GameStart { .. } => {
+                self.room_state.write().await.status = RoomStatus::Playing;
+            }
+            _ => {}
+        }
+        let _ = self.event_tx.send(event).await;
+    }
+
+    /// Enter a playing room with `peers` without touching the relays, as a
+    /// non-host player (`bench` feature)
+    #[cfg(feature = "bench")]
+    pub async fn bench_enter(&self, room_id: &str, peers: &[String]) {
+        {
+            let mut state = self.room_state.write().await;
+            state.room_id = Some(room_id.to_string());
+            state.status = RoomStatus::Playing;
+            state.host_pubkey = peers.first().cloned();
+            state.is_host = false;
+        }
+        for pubkey in std::iter::once(&self.public_key()).chain(peers) {
+            add_player(
+                &self.client,
+                &self.players,
+                &self.event_tx,
+                pubkey,
+                None,
+                None,
+            )
+            .await;
+        }
+    }
+
+    /// Enter a playing room as its host, with `peers` seated, without
+    /// touching the relays (`bench` feature)
+    #[cfg(feature = "bench")]
+    pub async fn bench_host(&self, room_id: &str, peers: &[String]) {
+        let me = self.public_key();
+        {
+            let mut state = self.room_state.write().await;
+            state.room_id = Some(room_id.to_string());
+            state.status = RoomStatus::Playing;
+            state.host_pubkey = Some(me.clone());
+            state.is_host = true;
+            state.seats = std::iter::once(&me).chain(peers).cloned().collect();
+        }
+        for pubkey in std::iter::once(&me).chain(peers) {
+            add_player(
+                &self.client,
+                &self.players,
+                &self.event_tx,
+                pubkey,
+                None,
+                None,
+            )
+            .await;
+        }
+    }
+
+    /// Run the heartbeat and digest loops of a room entered with
+    /// `bench_enter()` or `bench_host()`, in the lobby unless `playing`
+    /// (`bench` feature)
+    #[cfg(feature = "bench")]
+    pub async fn bench_timers(&self, playing: bool) {
+        {
+            let mut state = self.room_state.write().await;
+            state.status = if playing {
+                RoomStatus::Playing
+            } else {
+                RoomStatus::Waiting
+            };
+            state.sync_mode = self.config.sync_mode;
+        }
+        self.start_heartbeat().await;
+        self.start_digest().await;
+    }
+
+    /// Sign the `state` message `send_state()` would publish, without
+    /// publishing it (`bench` feature)
+    #[cfg(feature = "bench")]
+    pub async fn bench_state_event(&self, state: &T) -> Result<nostr_sdk::Event> {
+        let (room_tag, content) = self.state_message(state).await?;
+        self.client
+            .sign_room_message(&room_tag, &content, None)
+            .await
+    }
+
+    /// Handle `events` as if the room subscription delivered them (`bench`
+    /// feature). The resulting `ArenaEvent`s queue up for `recv()`, which
+    /// holds 100; drain it between batches.
+    #[cfg(feature = "bench")]
+    pub async fn bench_receive(&self, events: impl IntoIterator<Item = nostr_sdk::Event>) {
+        let room_id = self.room_state.read().await.room_id.clone();
+        let room_tag = create_room_tag(&self.config.game_id, &room_id.unwrap_or_default());
+        let dispatcher = self.dispatcher(room_tag);
+        for event in events {
+            dispatcher.handle(event).await;
+        }
+    }
+
+    /// Handler of the events of the room tagged `room_tag`
+    fn dispatcher(&self, room_tag: String) -> Dispatcher<T> {
+        Dispatcher {
+            my_pubkey: self.public_key(),
+            my_session: self.session.clone(),
+            room_tag,
+            config: self.config.clone(),
+            client: self.client.clone(),
+            room_state: self.room_state.clone(),
+            players: self.players.clone(),
+            player_states: self.player_states.clone(),
+            last_state: self.last_state.clone(),
+            event_tx: self.event_tx.clone(),
+            seat_reply: self.seat_reply.clone(),
+            snapshot_from: self.snapshot_from.clone(),
+            muted: self.muted.clone(),
+            channels: self.channels.clone(),
+            blocked: self.blocked.clone(),
+            chat_guards: self.chat_guards.clone(),
+            slow_guards: self.slow_guards.clone(),
+            peer_latency: self.peer_latency.clone(),
+            digest_states: self.digest_states.clone(),
+            input_chains: self.input_chains.clone(),
+            shadow: self.shadow.clone(),
+            #[cfg(feature = "raw-events")]
+            raw_tx: self.raw_tx.clone(),
+        }
+    }
+
+    async fn start_heartbeat(&self) {
+        let client = self.client.clone();
+        let room_state = self.room_state.clone();
+        let config = self.config.clone();
+        let peer_latency = self.peer_latency.clone();
+        let away = self.away.clone();
+
+        spawn(async move {
+            let mut ticker = interval(Duration::from_millis(config.heartbeat_interval));
+            // Ticks since the last heartbeat; idle rooms skip ticks
+            let mut ticks = u64::MAX;
+
+            loop {
+                ticker.tick().await;
+
+                let state = room_state.read().await;
+                ticks = ticks.saturating_add(1);
+                if state.room_id.is_none() {
+                    break;
+                }
+                if ticks < idle_ticks(&config, &state) {
+                    continue;
+                }
+                ticks = 0;
+                drop(state);
+
+                let away = away.load(Ordering::Relaxed);
+                if let Err(e) =
+                    send_heartbeat(&config, &client, &room_state, &peer_latency, away).await
+                {
+                    warn!("Failed to send heartbeat: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Let the host know we're still watching, every heartbeat interval
+    async fn start_spectate(&self) {
+        let client = self.client.clone();
+        let room_state = self.room_state.clone();
+        let config = self.config.clone();
+
+        spawn(async move {
+            let mut ticker = interval(Duration::from_millis(config.heartbeat_interval));
+
+            loop {
+                ticker.tick().await;
+
+                let room_tag = {
+                    let state = room_state.read().await;
+                    match &state.room_id {
+                        Some(room_id) if state.spectating => {
+                            create_room_tag(&config.game_id, room_id)
+                        }
+                        _ => break,
+                    }
+                };
+                let Ok(content) = encode_event(&EventContent::Spectate(SpectateEventContent {}))
+                else {
+                    break;
+                };
+                if let Err(e) = client.publish_ephemeral(&room_tag, &content).await {
+                    warn!("Failed to send spectate: {}", e);
+                }
+            }
+        });
+    }
+
+    /// With `time_control`: warn when a player's time runs low and, on the
+    /// host, end the game when the running player's time runs out
+    async fn start_clock(&self) {
+        if self.config.time_control.is_none() {
+            return;
+        }
+
+        let client = self.client.clone();
+        let room_state = self.room_state.clone();
+        let config = self.config.clone();
+        let event_tx = self.event_tx.clone();
+
+        spawn(async move {
+            let mut ticker = interval(Duration::from_millis(CLOCK_TICK));
+            let mut warned: HashSet<String> = HashSet::new();
+
+            loop {
+                ticker.tick().await;
+
+                let now = monotonic_ms();
+                let (low, flag_fall) = {
+                    let mut state = room_state.write().await;
+                    if state.room_id.is_none() {
+                        break;
+                    }
+                    let is_host = state.is_host;
+                    let Some(clock) = &mut state.clock else {
+                        warned.clear();
+                        continue;
+                    };
+                    let low = clock.low_players(now);
+                    let flag_fall = if is_host { clock.check_flag(now) } else { None };
+                    if let Some(pubkey) = &flag_fall {
+                        state.status = RoomStatus::Finished;
+                        let end = MatchEnd::FlagFall {
+                            pubkey: pubkey.clone(),
+                        };
+                        report_match_end(&config, &state, end);
+                    }
+                    (low, flag_fall)
+                };
+
+                warned.retain(|pubkey| low.contains(pubkey));
+                for pubkey in low {
+                    if warned.insert(pubkey.clone()) {
+                        let _ = event_tx.send(ArenaEvent::TimeLow { pubkey }).await;
+                    }
+                }
+                if let Some(pubkey) = flag_fall {
+                    publish_clock(&config, &client, &room_state).await;
+                    let _ = event_tx.send(ArenaEvent::FlagFall { pubkey }).await;
+                }
+            }
+        });
+    }
+
+    /// With `turn_timeout` (host only): count a strike each time the turn
+    /// timer runs out on the same turn, and open an AFK vote on the player
+    /// after `turn_strikes` of them
+    async fn start_turn_watch(&self) {
+        if self.config.turn_timeout == 0 {
+            return;
+        }
+
+        let client = self.client.clone();
+        let room_state = self.room_state.clone();
+        let config = self.config.clone();
+        let event_tx = self.event_tx.clone();
+
+        spawn(async move {
+            let tick = (config.turn_timeout / 4).clamp(CLOCK_TICK, 1000);
+            let mut ticker = interval(Duration::from_millis(tick));
+            // Player whose turn it is, since when (ms) and their strikes
+            let mut watched: Option<(String, u64, u32)> = None;
+
+            loop {
+                ticker.tick().await;
+
+                let now = monotonic_ms();
+                let turn = {
+                    let state = room_state.read().await;
+                    if state.room_id.is_none() {
+                        break;
+                    }
+                    state
+                        .turn
+                        .clone()
+                        .filter(|_| state.is_host && state.status == RoomStatus::Playing)
+                };
+                let Some(turn) = turn else {
+                    watched = None;
+                    continue;
+                };
+                let (pubkey, since, strikes) = match &mut watched {
+                    Some(w) if w.0 == turn => w,
+                    _ => watched.insert((turn, now, 0)),
+                };
+                if now.saturating_sub(*since) < config.turn_timeout {
+                    continue;
+                }
+                *since = now;
+                *strikes += 1;
+                let (pubkey, strikes) = (pubkey.clone(), *strikes);
+                let _ = event_tx
+                    .send(ArenaEvent::TurnTimeout {
+                        pubkey: pubkey.clone(),
+                        strikes,
+                    })
+                    .await;
+
+                let topic = afk_vote_topic(&pubkey);
+                let open = room_state
+                    .read()
+                    .await
+                    .votes
+                    .get(&topic)
+                    .is_some_and(|vote| vote.winner.is_none());
+                if strikes < config.turn_strikes || open {
+                    continue;
+                }
+                let options: Vec<String> = AFK_VOTE_OPTIONS.iter().map(|o| o.to_string()).collect();
+                match open_vote(&config, &client, &room_state, &topic, options.clone()).await {
+                    Ok(()) => {
+                        info!("Opened AFK vote on {}", pubkey);
+                        let _ = event_tx
+                            .send(ArenaEvent::VoteProposed { topic, options })
+                            .await;
+                    }
+                    Err(e) => warn!("Failed to open AFK vote: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Host of a `Hosted` room: publish the states collected from players
+    /// every `digest_interval`, and everyone's presence every heartbeat
+    /// interval
+    async fn start_digest(&self) {
+        {
+            let state = self.room_state.read().await;
+            if !state.is_host || state.sync_mode != SyncMode::Hosted {
+                return;
+            }
+        }
+
+        let client = self.client.clone();
+        let room_state = self.room_state.clone();
+        let players = self.players.clone();
+        let digest_states = self.digest_states.clone();
+        let config = self.config.clone();
+
+        spawn(async move {
+            let mut ticker = interval(Duration::from_millis(config.digest_interval.max(1)));
+            let mut last_presence = 0;
+
+            loop {
+                ticker.tick().await;
+
+                let (room_tag, idle) = {
+                    let state = room_state.read().await;
+                    match &state.room_id {
+                        Some(room_id) if state.is_host => {
+                            (create_room_tag(&config.game_id, room_id), state.is_idle())
+                        }
+                        _ => break,
+                    }
+                };
+
+                let now = monotonic_ms();
+                let mut digest = DigestEventContent {
+                    states: digest_states.drain(),
+                    presence: HashMap::new(),
+                };
+                if now.saturating_sub(last_presence) >= config.heartbeat_interval_for(idle) {
+                    digest.presence = players
+                        .to_map()
+                        .into_iter()
+                        .map(|(pubkey, p)| (pubkey, p.last_seen))
+                        .collect();
+                    last_presence = now;
+                }
+                if digest.states.is_empty() && digest.presence.is_empty() {
+                    continue;
+                }
+
+                let Ok(content) = encode_event(&EventContent::Digest(digest)) else {
+                    continue;
+                };
+                if let Err(e) = client.publish_ephemeral(&room_tag, &content).await {
+                    warn!("Failed to send digest: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Detect matches where every remote player has gone silent
+    async fn start_watchdog(&self) {
+        if self.config.watchdog_interval == 0 {
+            return;
+        }
+
+        let client = self.client.clone();
+        let room_state = self.room_state.clone();
+        let players = self.players.clone();
+        let config = self.config.clone();
+        let event_tx = self.event_tx.clone();
+        let my_pubkey = self.public_key();
+
+        spawn(async move {
+            let mut ticker = interval(Duration::from_millis(config.watchdog_interval));
+
+            loop {
+                ticker.tick().await;
+
+                let room_id = {
+                    let state = room_state.read().await;
+                    let Some(room_id) = state.room_id.clone() else {
+                        break;
+                    };
+                    if state.status != RoomStatus::Playing
+                        || !match_abandoned(&config, &players.values(), &my_pubkey)
+                    {
+                        continue;
+                    }
+                    room_id
+                };
+
+                // Settle it under the write lock, but publish after it is
+                // released; the dispatcher needs the lock to make progress
+                let content = {
+                    let mut state = room_state.write().await;
+                    if state.room_id.as_deref() != Some(room_id.as_str())
+                        || state.status != RoomStatus::Playing
+                    {
+                        continue;
+                    }
+                    state.status = RoomStatus::Finished;
+                    report_match_end(&config, &state, MatchEnd::Abandoned);
+                    state.is_host.then(|| {
+                        room_content(
+                            &config,
+                            my_pubkey.clone(),
+                            RoomStatus::Finished,
+                            &state,
+                            players.values(),
+                        )
+                    })
+                };
+
+                warn!("Match abandoned in room: {}", room_id);
+                let _ = event_tx.send(ArenaEvent::MatchAbandoned).await;
+
+                if let Some(json) = content.and_then(|c| encode_room(&c).ok()) {
+                    let room_tag = create_room_tag(&config.game_id, &room_id);
+                    let _ = client
+                        .publish_room(&room_tag, config.room_hashtag(), &json)
+                        .await;
+                }
+            }
+        });
+    }
+
+    /// Bring our roster in line with the host's room event now and then,
+    /// in case we missed a join or a departure (non-host peers)
+    async fn start_roster_reconcile(&self) {
+        if self.config.roster_reconcile_interval == 0 {
+            return;
+        }
+        let Some(room_id) = self.room_state.read().await.room_id.clone() else {
+            return;
+        };
+
+        let arena = self.clone();
+        spawn(async move {
+            let mut ticker = interval(Duration::from_millis(
+                arena.config.roster_reconcile_interval,
+            ));
+            // The first tick fires at once; we just got the roster
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                let is_host = {
+                    let state = arena.room_state.read().await;
+                    if state.room_id.as_deref() != Some(room_id.as_str()) {
+                        break;
+                    }
+                    state.is_host
+                };
+                if is_host {
+                    continue;
+                }
+                if let Err(e) = arena.sync_roster(&room_id).await {
+                    warn!("Failed to reconcile roster: {}", e);
+                }
+            }
+        });
+    }
+
+    async fn sync_roster(&self, room_id: &str) -> Result<()> {
+        let room_tag = create_room_tag(&self.config.game_id, room_id);
+        let hints = self.client.room_relays().await;
+        let host = self.room_state.read().await.host_pubkey.clone();
+        let Some((event, content)) = self
+            .fetch_latest_room(&room_tag, &hints, host.as_deref())
+            .await?
+        else {
+            return Ok(());
+        };
+        // Large rooms leave the roster out; `roster` requests cover them
+        if content.is_roster_compressed() {
+            return Ok(());
+        }
+
+        let published_at = local_ms(
+            event.created_at.as_u64().saturating_mul(1000),
+            self.client.clock_skew().await,
+        );
+        let diff = reconcile_roster(
+            &self.players.values(),
+            &content.players,
+            published_at,
+            &self.public_key(),
+        );
+        if diff.is_empty() {
+            return Ok(());
+        }
+        debug!(
+            "Reconciling roster: {} added, {} removed",
+            diff.added.len(),
+            diff.removed.len()
+        );
+
+        {
+            let mut state = self.room_state.write().await;
+            if state.room_id.as_deref() != Some(room_id) {
+                return Ok(());
+            }
+            state.seats = content.players.iter().map(|p| p.pubkey.clone()).collect();
+        }
+        let now = now_ms();
+        for player in diff.added {
+            if self.blocked.read().await.contains(&player.pubkey) {
+                continue;
+            }
+            let presence = PlayerPresence {
+                last_seen: player.last_seen.max(now),
+                seen_at: monotonic_ms(),
+                ..player
+            };
+            if self
+                .players
+                .insert_if_absent(presence.pubkey.clone(), presence.clone())
+            {
+                let _ = self.event_tx.send(ArenaEvent::PlayerJoin(presence)).await;
+            }
+        }
+        for pubkey in diff.removed {
+            if self.players.remove(&pubkey).is_some() {
+                self.player_states.remove(&pubkey);
+                let _ = self.event_tx.send(ArenaEvent::PlayerLeave(pubkey)).await;
+            }
+        }
+        self.client.set_participants(&self.players.keys()).await;
+        Ok(())
+    }
+
+    /// Reconnect the relays as soon as the network route changes (e.g.
+    /// Wi-Fi to cellular) instead of waiting for the sockets to time out
+    async fn start_network_watch(&self) {
+        #[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+        {
+            if self.config.network_watch_interval == 0 {
+                return;
+            }
+            let Some(room_id) = self.room_state.read().await.room_id.clone() else {
+                return;
+            };
+
+            let arena = self.clone();
+            spawn(async move {
+                let mut ticker =
+                    interval(Duration::from_millis(arena.config.network_watch_interval));
+                let mut route = NetworkRoute::current();
+
+                loop {
+                    ticker.tick().await;
+                    if arena.room_state.read().await.room_id.as_deref() != Some(room_id.as_str()) {
+                        break;
+                    }
+
+                    let current = NetworkRoute::current();
+                    if current == route {
+                        continue;
+                    }
+                    route = current;
+                    // Offline: wait for the next network to come up
+                    if !current.is_online() {
+                        continue;
+                    }
+
+                    info!("Network changed, reconnecting relays");
+                    if let Err(e) = arena.client.reconnect_relays().await {
+                        warn!("Failed to reconnect relays: {}", e);
+                        continue;
+                    }
+                    if let Err(e) = arena.catch_up().await {
+                        warn!("Failed to resync after network change: {}", e);
+                    }
+                    let _ = arena.event_tx.send(ArenaEvent::NetworkChanged).await;
+                }
+            });
+        }
+    }
+
+    /// Pick up a countdown advertised in the room event
+    async fn resume_countdown(&self, starts_at: Option<u64>) {
+        if let Some(starts_at) = starts_at {
+            start_countdown(
+                &self.config,
+                &self.client,
+                &self.room_state,
+                &self.players,
+                &self.event_tx,
+                starts_at,
+            )
+            .await;
+        }
+    }
+
+    /// Tear the room down locally once it passes `expires_at`. The host
+    /// publishes a final `finished` room state first.
+    async fn start_expiry_timer(&self) {
+        let room_id = {
+            let state = self.room_state.read().await;
+            match (&state.room_id, state.expires_at) {
+                (Some(room_id), Some(_)) => room_id.clone(),
+                _ => return,
+            }
+        };
+
+        let arena = self.clone();
+        spawn(async move {
+            // With `idle_expiry`, `expires_at` moves on while the room is active
+            loop {
+                let (expires_at, is_host) = {
+                    let state = arena.room_state.read().await;
+                    if state.room_id.as_deref() != Some(room_id.as_str()) {
+                        return;
+                    }
+                    let Some(expires_at) = state.expires_at else {
+                        return;
+                    };
+                    (expires_at, state.is_host)
+                };
+                // Other hosts' expiry comes from the room event's clock
+                let expires_at = if is_host {
+                    expires_at
+                } else {
+                    local_ms(expires_at, arena.client.clock_skew().await)
+                };
+                let now = now_ms();
+                if now >= expires_at {
+                    break;
+                }
+                sleep(Duration::from_millis(expires_at - now)).await;
+            }
+
+            let state = arena.room_state.read().await;
+            if state.room_id.as_deref() != Some(room_id.as_str()) {
+                return;
+            }
+            if state.is_host {
+                if state.status == RoomStatus::Playing {
+                    report_match_end(&arena.config, &state, MatchEnd::Expired);
+                }
+                let room_tag = create_room_tag(&arena.config.game_id, &room_id);
+                let content = room_content(
+                    &arena.config,
+                    arena.public_key(),
+                    RoomStatus::Finished,
+                    &state,
+                    arena.players.values(),
+                );
+                if let Ok(json) = encode_room(&content) {
+                    let _ = arena
+                        .client
+                        .publish_room(&room_tag, arena.config.room_hashtag(), &json)
+                        .await;
+                }
+            }
+            drop(state);
+
+            info!("Room expired: {}", room_id);
+            let _ = arena.leave().await;
+            arena.room_state.write().await.status = RoomStatus::Finished;
+            let _ = arena.event_tx.send(ArenaEvent::RoomExpired(room_id)).await;
+        });
+    }
+
+    /// Drop silent players every `presence_interval` on every peer, so
+    /// disconnects show up without waiting for the host. Whoever is host at
+    /// the time of a sweep republishes the room.
+    async fn start_presence_update(&self) {
+        let client = self.client.clone();
+        let room_state = self.room_state.clone();
+        let players = self.players.clone();
+        let config = self.config.clone();
+        let event_tx = self.event_tx.clone();
+        let my_pubkey = self.public_key();
+
+        spawn(async move {
+            let mut ticker = interval(Duration::from_millis(config.presence_interval));
+            let mut ticks = 0u64;
+
+            loop {
+                ticker.tick().await;
+
+                let state = room_state.read().await;
+                let Some(room_id) = state.room_id.clone() else {
+                    break;
+                };
+                let idle = state.is_idle();
+                ticks += 1;
+                let every = config
+                    .presence_interval_for(idle)
+                    .div_ceil(config.presence_interval.max(1));
+                if ticks < every {
+                    continue;
+                }
+                ticks = 0;
+                let room_tag = create_room_tag(&config.game_id, &room_id);
+
+                // Check for disconnected players
+                let threshold = config.disconnect_threshold_for(idle);
+                let to_remove = players.keys_where(|p| {
+                    p.pubkey != my_pubkey && p.silent_ms() > away_threshold(&config, p, threshold)
+                });
+
+                // Remove disconnected players and free their seats
+                drop(state);
+                for pubkey in to_remove {
+                    players.remove(&pubkey);
+                    room_state.write().await.seats.retain(|p| p != &pubkey);
+                    let _ = event_tx.send(ArenaEvent::PlayerLeave(pubkey)).await;
+                }
+                client.set_participants(&players.keys()).await;
+                let content = {
+                    let mut state = room_state.write().await;
+                    if !state.is_host {
+                        continue;
+                    }
+                    let seen_at = monotonic_ms();
+                    state
+                        .spectators
+                        .retain(|_, seen| seen_at.saturating_sub(*seen) <= threshold);
+                    // The room is active as long as its host sweeps it
+                    if config.idle_expiry > 0 {
+                        let now = now_ms();
+                        let created_at = state.created_at.unwrap_or(now);
+                        state.expires_at = expiry_deadline(&config, created_at, now);
+                    }
+                    room_content(
+                        &config,
+                        client.public_key(),
+                        state.status,
+                        &state,
+                        players.values(),
+                    )
+                };
+
+                // Publish updated room state, without holding the lock
+                if let Ok(json) = encode_room(&content) {
+                    let _ = client
+                        .publish_room(&room_tag, config.room_hashtag(), &json)
+                        .await;
+                }
+            }
+        });
+    }
+
+    /// Enforce the throttle for a message type, recording the send time.
+    ///
+    /// Returns the time (ms) at which sending is allowed again if throttled.
+    async fn check_throttle(&self, message_type: &str) -> Option<u64> {
+        let throttle = self.config.throttle_for(message_type);
+        if throttle == 0 {
+            return None;
+        }
+
+        let now = monotonic_ms();
+        let mut last_sent = self.last_sent.write().await;
+        if let Some(&last) = last_sent.get(message_type)
+            && now.saturating_sub(last) < throttle
+        {
+            return Some(last + throttle);
+        }
+        last_sent.insert(message_type.to_string(), now);
+        None
+    }
+
+    /// Deliver a locally published event to the app if `echo_self` is on
+    async fn echo(&self, event: ArenaEvent<T>) {
+        if self.config.echo_self {
+            let _ = self.event_tx.send(event).await;
+        }
+    }
+
+    async fn check_auto_start(&self) {
+        check_seats_full(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.players,
+            &self.event_tx,
+        )
+        .await;
+    }
+
+    /// Wait for the host to confirm or deny our seat
+    async fn wait_for_seat(&self) -> Result<()> {
+        let deadline = monotonic_ms() + self.config.join_timeout;
+        loop {
+            match *self.seat_reply.read().await {
+                SeatReply::Granted => return Ok(()),
+                SeatReply::Denied(ReasonCode::Blocked) => {
+                    self.leave().await?;
+                    return Err(ArenaError::NotAuthorized("blocked by the host".to_string()));
+                }
+                SeatReply::Denied(ReasonCode::NotAuthorized) => {
+                    self.leave().await?;
+                    return Err(ArenaError::NotAuthorized("wrong room password".to_string()));
+                }
+                SeatReply::Denied(_) => {
+                    self.leave().await?;
+                    return Err(ArenaError::RoomFull);
+                }
+                SeatReply::Pending => {}
+            }
+            if monotonic_ms() >= deadline {
+                self.leave().await?;
+                return Err(ArenaError::Timeout);
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    async fn check_all_ready(&self) {
+        let all_ready = self.players.all(|p| p.ready);
+        if !all_ready {
+            return;
+        }
+
+        let _ = self.event_tx.send(ArenaEvent::AllReady).await;
+
+        let start_mode = self.room_state.read().await.start_mode(&self.config);
+        match start_mode {
+            StartMode::Ready => {
+                begin_play(
+                    &self.config,
+                    &self.client,
+                    &self.room_state,
+                    &self.players,
+                    &self.event_tx,
+                )
+                .await;
+            }
+            StartMode::Countdown => {
+                announce_countdown(
+                    &self.config,
+                    &self.client,
+                    &self.room_state,
+                    &self.players,
+                    &self.event_tx,
+                )
+                .await;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Maximum length of a channel name in bytes
+const MAX_CHANNEL_NAME: usize = 64;
+
+/// Messages buffered per channel for slow subscribers
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Interval in ms of the time control check
+const CLOCK_TICK: u64 = 100;
+
+/// Messages unpacked from one batch at most
+const MAX_BATCH_MESSAGES: usize = 64;
+
+/// Capacity of the per-room inbound event queue
+const DISPATCH_QUEUE: usize = 256;
+
+/// Handles inbound room events for one room, in order
+struct Dispatcher<T> {
+    my_pubkey: String,
+    my_session: String,
+    room_tag: String,
+    config: ArenaConfig,
+    client: Arc<NostrClient>,
+    room_state: Arc<RwLock<RoomState>>,
+    players: Arc<PlayerMap<PlayerPresence>>,
+    player_states: Arc<PlayerMap<T>>,
+    last_state: Arc<RwLock<Option<T>>>,
+    event_tx: mpsc::Sender<ArenaEvent<T>>,
+    seat_reply: Arc<RwLock<SeatReply>>,
+    snapshot_from: Arc<RwLock<Option<String>>>,
+    muted: Arc<RwLock<HashSet<String>>>,
+    channels: Arc<RwLock<HashMap<String, broadcast::Sender<ChannelMessage>>>>,
+    blocked: Arc<RwLock<HashSet<String>>>,
+    chat_guards: Arc<PlayerMap<FloodGuard>>,
+    slow_guards: Arc<PlayerMap<FloodGuard>>,
+    peer_latency: Arc<PlayerMap<PeerLatency>>,
+    digest_states: Arc<PlayerMap<serde_json::Value>>,
+    input_chains: Arc<PlayerMap<InputChain>>,
+    shadow: Arc<RwLock<EventShadow>>,
+    #[cfg(feature = "raw-events")]
+    raw_tx: broadcast::Sender<nostr_sdk::Event>,
+}
+
+impl<T> Dispatcher<T>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    async fn handle(&self, event: nostr_sdk::Event) {
+        #[cfg(feature = "raw-events")]
+        let _ = self.raw_tx.send(event.clone());
+
+        for (relay, skew_ms) in self.client.take_skew_alerts().await {
+            warn!("Relay {} timestamps are {} ms off", relay, skew_ms);
+            let _ = self
+                .event_tx
+                .send(ArenaEvent::RelayClockSkew { relay, skew_ms })
+                .await;
+        }
+
+        let pubkey = event.pubkey.to_hex();
+
+        // Skip own events, keeping our control messages for resending
+        if pubkey == self.my_pubkey {
+            if self.config.resend_window > 0
+                && let Ok(content) = decode_event(&event.content)
+            {
+                self.retain(&event, &content).await;
+            }
+            return;
+        }
+
+        // Open sealed messages first, so the middleware sees them plain
+        let seal = self.client.room_seal().await;
+        let Some((message, sealed)) = seal.unseal(Cow::Borrowed(&event.content)) else {
+            return;
+        };
+        let Some(message) = self.config.middleware.inbound(&pubkey, &message) else {
+            return;
+        };
+        if !seal.admits(&message, sealed) {
+            return;
+        }
+
+        // Drop blocked and kicked players' traffic; a host still answers
+        // their join with a denied seat so they don't wait for the join
+        // timeout
+        let kicked = self.room_state.read().await.kicked.contains(&pubkey);
+        if kicked || self.blocked.read().await.contains(&pubkey) {
+            if let Ok(EventContent::Join(join)) = decode_event(&message)
+                && join.player_pubkey == pubkey
+                && self.room_state.read().await.is_host
+            {
+                self.on_join(join).await;
+            }
+            return;
+        }
+
+        if let Some(batch) = self.handle_message(&event, &pubkey, &message, false).await {
+            for message in batch.messages.iter().take(MAX_BATCH_MESSAGES) {
+                self.handle_message(&event, &pubkey, message, true).await;
+            }
+        }
+    }
+
+    /// Handle one message of `event`'s content. A batch is handed back to
+    /// unpack; `batched` messages can't be batches or control messages.
+    async fn handle_message(
+        &self,
+        event: &nostr_sdk::Event,
+        pubkey: &str,
+        message: &str,
+        batched: bool,
+    ) -> Option<BatchEventContent> {
+        let pubkey = pubkey.to_string();
+
+        // Hot path: state messages decode straight into T
+        if let Some(state) = decode_state::<T>(message) {
+            if let Ok(state) = state {
+                let collect = {
+                    let room = self.room_state.read().await;
+                    room.is_host && room.sync_mode == SyncMode::Hosted
+                };
+                // Forwarded to the room in the next digest
+                if collect && let Ok(value) = serde_json::to_value(&state) {
+                    self.digest_states.insert(pubkey.clone(), value);
+                }
+                if self.config.input_audit {
+                    record_input(&self.input_chains, pubkey.clone(), message);
+                }
+                receive_state(
+                    &self.players,
+                    &self.player_states,
+                    &self.event_tx,
+                    pubkey,
+                    state,
+                )
+                .await;
+            }
+            return None;
+        }
+
+        let Ok(content) = decode_event(message) else {
+            return None;
+        };
+        if batched && (content.is_control() || matches!(content, EventContent::Batch(_))) {
+            debug!("Dropped a batched control message from {}", pubkey);
+            return None;
+        }
+        // A copy resent for someone else
+        if !self.retain(event, &content).await {
+            return None;
+        }
+
+        match content {
+            EventContent::Batch(batch) => return Some(batch),
+            EventContent::Join(join) => {
+                // Only a key can join (and take over sessions) for itself
+                if join.player_pubkey != pubkey {
+                    return None;
+                }
+                self.room_state
+                    .write()
+                    .await
+                    .peer_versions
+                    .insert(join.player_pubkey.clone(), message_version(message));
+                self.on_join(join).await
+            }
+            EventContent::Seat(seat) => self.on_seat(pubkey, seat).await,
+
+            EventContent::State(_) => {
+                // Decoded on the hot path above
+            }
+
+            EventContent::Heartbeat(hb) => self.on_heartbeat(pubkey, hb).await,
+
+            EventContent::GameOver(go) => {
+                // Only seated players finish the match
+                if !self.players.contains_key(&pubkey) {
+                    return None;
+                }
+                self.on_game_over(&pubkey, go.final_score).await;
+                self.on_finish(&pubkey, &go).await;
+                let _ = self
+                    .event_tx
+                    .send(ArenaEvent::PlayerGameOver {
+                        pubkey,
+                        reason: go.reason(),
+                        final_score: go.final_score,
+                    })
+                    .await;
+            }
+
+            EventContent::Rematch(rm) => match rm.action {
+                RematchAction::Request => {
+                    let _ = self
+                        .event_tx
+                        .send(ArenaEvent::RematchRequested(pubkey))
+                        .await;
+                }
+                RematchAction::Accept => {
+                    if let Some(new_seed) = rm.new_seed
+                        && self.room_state.read().await.is_authority(&pubkey)
+                    {
+                        self.reset_for_rematch(new_seed).await;
+                    }
+                }
+                RematchAction::Round => {
+                    if let (Some(seed), Some(round)) = (rm.new_seed, rm.round) {
+                        self.on_round(pubkey, round, seed).await;
+                    }
+                }
+            },
+
+            EventContent::Ready(r) => self.on_ready(pubkey, r.ready).await,
+
+            EventContent::Chat(chat) => self.on_chat(pubkey, chat.text).await,
+
+            EventContent::Turn(turn) => self.on_turn(pubkey, turn).await,
+
+            EventContent::Invite(_) | EventContent::Party(_) => {
+                // Sent to inboxes and party channels, not rooms
+            }
+
+            EventContent::GameStart(start) => {
+                {
+                    let mut state = self.room_state.write().await;
+                    // A second start (host and co-host both starting) would
+                    // reset the turns, clock and seed mid-match
+                    if !state.is_authority(&pubkey) || state.status == RoomStatus::Playing {
+                        return None;
+                    }
+                    if let Some(config) = start.config {
+                        state.start_config = Some(config);
+                    }
+                    if let Some(seed) = start.seed {
+                        state.seed = seed;
+                    }
+                    if !start.turn_order.is_empty() {
+                        state.turn_order = start.turn_order;
+                    }
+                }
+                self.begin_play().await;
+            }
+
+            EventContent::Countdown(countdown) => {
+                if self.room_state.read().await.is_authority(&pubkey) {
+                    start_countdown(
+                        &self.config,
+                        &self.client,
+                        &self.room_state,
+                        &self.players,
+                        &self.event_tx,
+                        countdown.starts_at,
+                    )
+                    .await;
+                }
+            }
+
+            EventContent::Roster(roster) => self.on_roster(pubkey, roster).await,
+            EventContent::Digest(digest) => self.on_digest(pubkey, digest).await,
+            EventContent::Phase(phase) => self.on_phase(pubkey, phase).await,
+            EventContent::Clock(clock) => self.on_clock(pubkey, clock).await,
+            EventContent::Audit(audit) => self.on_audit(pubkey, audit).await,
+            EventContent::Vote(vote) => self.on_vote(pubkey, vote).await,
+            EventContent::Claim(claim) => self.on_claim(pubkey, claim).await,
+            EventContent::GameEnded(ended) => self.on_game_ended(pubkey, ended).await,
+            EventContent::Moderation(content) => self.on_moderation(pubkey, content).await,
+            EventContent::Kick(kick) => self.on_kick(pubkey, kick.player_pubkey).await,
+            EventContent::Leave(_) => self.on_leave(pubkey).await,
+            EventContent::Rekey(rekey) => self.on_rekey(pubkey, rekey).await,
+            EventContent::Spectate(_) => self.on_spectate(pubkey).await,
+            EventContent::Sealed(_) => {
+                // Opened before handling; left sealed only outside encrypted rooms
+            }
+
+            EventContent::Summary(summary) => {
+                let _ = self
+                    .event_tx
+                    .send(ArenaEvent::PlayerSummary {
+                        pubkey,
+                        summary: summary.summary,
+                    })
+                    .await;
+            }
+            EventContent::ResendRequest(request) => self.on_resend_request(pubkey, request).await,
+            EventContent::SnapshotRequest(request) => {
+                self.on_snapshot_request(pubkey, request).await
+            }
+            EventContent::Snapshot(snapshot) => self.on_snapshot(pubkey, snapshot).await,
+            EventContent::Message(message) => {
+                let message = ChannelMessage {
+                    pubkey,
+                    channel: message.channel,
+                    payload: message.payload,
+                };
+                deliver_message(&self.channels, &self.event_tx, message).await;
+            }
+
+            EventContent::Room(room) => {
+                // Only the host changes who the co-hosts are
+                let mut state = self.room_state.write().await;
+                if state.host_pubkey.as_deref() == Some(pubkey.as_str()) {
+                    state.expires_at = room.expires_at;
+                    state.co_hosts = room.co_hosts;
+                    state.chat_slow_mode = room.chat_slow_mode;
+                    state.chat_muted = room.chat_muted;
+                    state.start_config = room.start_config;
+                    state.turn_order = room.turn_order;
+                    // Join metadata of players who joined before we did
+                    for p in room.players {
+                        self.players.update(&p.pubkey, |known| {
+                            if known.capabilities.is_none() {
+                                known.capabilities = p.capabilities;
+                            }
+                        });
+                    }
+                }
+            }
+
+            EventContent::Unknown {
+                message_type,
+                payload,
+            } => {
+                let _ = self
+                    .event_tx
+                    .send(ArenaEvent::UnknownMessage {
+                        pubkey,
+                        message_type,
+                        payload,
+                    })
+                    .await;
+            }
+        }
+        None
+    }
+
+    async fn on_join(&self, join: JoinEventContent) {
+        let (is_host, seated) = {
+            let state = self.room_state.read().await;
+            (state.is_host, !state.seats.is_empty())
+        };
+
+        let mut granted = None;
+        let mut identity = None;
+        if is_host {
+            let kicked = self
+                .room_state
+                .read()
+                .await
+                .kicked
+                .contains(&join.player_pubkey);
+            let (seat, denial) = if self.blocked.read().await.contains(&join.player_pubkey) {
+                (None, ReasonCode::Blocked)
+            } else if kicked {
+                (None, ReasonCode::Kicked)
+            } else if !self.check_proof(&join).await {
+                (None, ReasonCode::NotAuthorized)
+            } else {
+                (
+                    assign_seat(&self.config, &self.room_state, &join.player_pubkey).await,
+                    ReasonCode::RoomFull,
+                )
+            };
+            let seats = self.room_state.read().await.seats.clone();
+            if let Some(payload) = &join.identity {
+                identity = self
+                    .open_identity::<IdentityLink>(&join.player_pubkey, payload)
+                    .await;
+            }
+            let (identity_for, identities) = match seat {
+                Some(_) => {
+                    self.share_identities(&join.player_pubkey, &seats, identity.as_ref())
+                        .await
+                }
+                None => (HashMap::new(), None),
+            };
+            let room_key = match (seat, self.client.room_seal().await) {
+                (Some(_), RoomSeal::Key(key)) => self
+                    .client
+                    .nip44_encrypt(&join.player_pubkey, &key.to_hex())
+                    .await
+                    .ok(),
+                _ => None,
+            };
+            if let Ok(json) = encode_event(&EventContent::Seat(SeatEventContent {
+                player_pubkey: join.player_pubkey.clone(),
+                seat,
+                seats,
+                capabilities: join.capabilities.clone(),
+                identity: identity_for,
+                identities,
+                reason: seat.is_none().then(|| denial.into()),
+                session: join.session.clone(),
+                room_key,
+            })) {
+                let _ = self.client.publish_ephemeral(&self.room_tag, &json).await;
+            }
+            if seat.is_none() {
+                return;
+            }
+            self.room_state
+                .write()
+                .await
+                .spectators
+                .remove(&join.player_pubkey);
+            granted = seat.filter(|_| !self.players.contains_key(&join.player_pubkey));
+        } else if seated {
+            // Wait for the host to confirm the seat
+            return;
+        }
+
+        add_player(
+            &self.client,
+            &self.players,
+            &self.event_tx,
+            &join.player_pubkey,
+            join.capabilities,
+            identity,
+        )
+        .await;
+        if let Some(seat) = granted {
+            let state = self.room_state.read().await;
+            if let Some(room_id) = &state.room_id {
+                self.config.hooks.player_joined(&PlayerJoined {
+                    room_id: room_id.clone(),
+                    game_id: self.config.game_id.clone(),
+                    pubkey: join.player_pubkey.clone(),
+                    seat,
+                    players: state.seats.len(),
+                    max_players: state.max_players(&self.config),
+                    joined_at: now_ms(),
+                });
+            }
+        }
+        self.track_session(&join.player_pubkey, join.session).await;
+        self.check_seats_full().await;
+    }
+
+    /// Whether a join proves the room password, if we set one
+    async fn check_proof(&self, join: &JoinEventContent) -> bool {
+        let Some(password) = &self.config.room_password else {
+            return true;
+        };
+        let expected = {
+            let state = self.room_state.read().await;
+            let Some(room_id) = &state.room_id else {
+                return false;
+            };
+            // Players we already seated keep their seat when they rejoin
+            if state.seats.contains(&join.player_pubkey) {
+                return true;
+            }
+            room_password_proof(room_id, password, &join.player_pubkey)
+        };
+        let Some(payload) = &join.proof else {
+            return false;
+        };
+        self.client
+            .nip44_decrypt(&join.player_pubkey, payload)
+            .await
+            .is_ok_and(|proof| proof == expected)
+    }
+
+    /// Record the session a player joined with. A different session than
+    /// the one we knew is the player rejoining (a new tab, a restarted
+    /// client): the newest session takes over with a fresh ready flag and
+    /// state.
+    async fn track_session(&self, pubkey: &str, session: Option<String>) {
+        let Some(session) = session else {
+            return;
+        };
+        let was_ready = self.players.update(pubkey, |p| {
+            let superseded = p.session.as_ref().is_some_and(|s| *s != session);
+            p.session = Some(session);
+            let was_ready = superseded && p.ready;
+            if superseded {
+                p.ready = false;
+            }
+            superseded.then_some(was_ready)
+        });
+        let Some(Some(was_ready)) = was_ready else {
+            return;
+        };
+        debug!("New session for {}", pubkey);
+        self.player_states.remove(pubkey);
+        if was_ready {
+            let _ = self
+                .event_tx
+                .send(ArenaEvent::PlayerReady {
+                    pubkey: pubkey.to_string(),
+                    ready: false,
+                })
+                .await;
+        }
+    }
+
+    async fn on_turn(&self, pubkey: String, turn: TurnEventContent) {
+        let next = turn.player_pubkey;
+        let is_host = {
+            let mut state = self.room_state.write().await;
+            if !self.players.contains_key(&pubkey) && !state.is_authority(&pubkey) {
+                return;
+            }
+            // Only the player to move passes the turn on; the host and
+            // co-hosts may still skip a player, but not move for them
+            if self.config.enforce_turns
+                && state.turn.as_deref() != Some(pubkey.as_str())
+                && (turn.player_move.is_some() || !state.is_authority(&pubkey))
+            {
+                debug!("Dropped out-of-turn move from {}", pubkey);
+                return;
+            }
+            state.turn = Some(next.clone());
+            if let Some(clock) = &mut state.clock {
+                clock.switch(&next, monotonic_ms());
+            }
+            state.is_host
+        };
+
+        if is_host {
+            publish_clock(&self.config, &self.client, &self.room_state).await;
+        }
+        // Keep the turn in the room event for players who are offline
+        if is_host
+            && let Err(e) =
+                republish_room(&self.config, &self.client, &self.room_state, &self.players).await
+        {
+            warn!("Failed to record turn: {}", e);
+        }
+        if let Some(payload) = turn.player_move {
+            let _ = self
+                .event_tx
+                .send(ArenaEvent::TurnReceived { pubkey, payload })
+                .await;
+        }
+        let _ = self.event_tx.send(turn_event(&self.config, &next)).await;
+    }
+
+    /// Keep a control message in the shadow; false if it was already kept
+    async fn retain(&self, event: &nostr_sdk::Event, content: &EventContent) -> bool {
+        if self.config.resend_window == 0 || !content.is_control() {
+            return true;
+        }
+        self.shadow
+            .write()
+            .await
+            .record(event, monotonic_ms(), self.config.resend_window)
+    }
+
+    async fn on_resend_request(&self, pubkey: String, request: ResendRequestEventContent) {
+        if self.config.resend_window == 0
+            || !self.room_state.read().await.is_host
+            || !self.players.contains_key(&pubkey)
+        {
+            return;
+        }
+        let events = self.shadow.read().await.since(request.since);
+        if events.is_empty() {
+            return;
+        }
+        debug!("Resending {} control messages for {}", events.len(), pubkey);
+        if let Err(e) = self.client.rebroadcast(events).await {
+            warn!("Failed to resend control messages: {}", e);
+        }
+    }
+
+    async fn on_snapshot_request(&self, pubkey: String, request: SnapshotRequestEventContent) {
+        let (room_id, turn, round, phase) = {
+            let state = self.room_state.read().await;
+            let asked = match &request.peer {
+                Some(peer) => *peer == self.my_pubkey,
+                None => state.is_host,
+            };
+            let known =
+                self.players.contains_key(&pubkey) || state.spectators.contains_key(&pubkey);
+            let Some(room_id) = state.room_id.clone().filter(|_| asked && known) else {
+                return;
+            };
+            (
+                room_id,
+                state.turn.clone(),
+                state.round,
+                state.phase.clone(),
+            )
+        };
+
+        let mut states: HashMap<String, serde_json::Value> = self
+            .player_states
+            .to_map()
+            .into_iter()
+            .filter_map(|(player, state)| Some((player, serde_json::to_value(state).ok()?)))
+            .collect();
+        if let Some(state) = self.last_state.read().await.as_ref()
+            && let Ok(state) = serde_json::to_value(state)
+        {
+            states.insert(self.my_pubkey.clone(), state);
+        }
+
+        let room_tag = create_room_tag(&self.config.game_id, &room_id);
+        let content = encode_event(&EventContent::Snapshot(SnapshotEventContent {
+            player_pubkey: pubkey,
+            states,
+            turn,
+            round,
+            phase,
+        }));
+        match content {
+            Ok(json) => {
+                if let Err(e) = self.client.publish_ephemeral(&room_tag, &json).await {
+                    warn!("Failed to send snapshot: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to encode snapshot: {}", e),
+        }
+    }
+
+    async fn on_snapshot(&self, pubkey: String, snapshot: SnapshotEventContent) {
+        if snapshot.player_pubkey != self.my_pubkey {
+            return;
+        }
+        {
+            // Only from the peer we asked, once
+            let mut from = self.snapshot_from.write().await;
+            if from.as_deref() != Some(pubkey.as_str()) {
+                return;
+            }
+            *from = None;
+        }
+        {
+            let mut state = self.room_state.write().await;
+            if snapshot.turn.is_some() {
+                state.turn = snapshot.turn;
+            }
+            state.round = state.round.max(snapshot.round);
+            if snapshot.phase.is_some() {
+                state.phase = snapshot.phase;
+            }
+        }
+
+        let states: HashMap<String, T> = snapshot
+            .states
+            .into_iter()
+            .filter(|(player, _)| *player != self.my_pubkey)
+            .filter_map(|(player, state)| Some((player, serde_json::from_value(state).ok()?)))
+            .collect();
+        for (player, state) in &states {
+            self.player_states.insert(player.clone(), state.clone());
+        }
+        let _ = self
+            .event_tx
+            .send(ArenaEvent::SnapshotReceived {
+                from: pubkey,
+                states,
+            })
+            .await;
+    }
+
+    async fn on_leave(&self, pubkey: String) {
+        if self.players.remove(&pubkey).is_none() {
+            self.room_state.write().await.spectators.remove(&pubkey);
+            return;
+        }
+        self.player_states.remove(&pubkey);
+        let is_host = {
+            let mut state = self.room_state.write().await;
+            state.seats.retain(|p| p != &pubkey);
+            state.is_host
+        };
+        self.client.set_participants(&self.players.keys()).await;
+        if is_host {
+            if let Err(e) = rotate_room_key(
+                &self.client,
+                &self.room_state,
+                &self.players,
+                &self.room_tag,
+            )
+            .await
+            {
+                warn!("Failed to rotate the room key: {}", e);
+            }
+            if let Err(e) =
+                republish_room(&self.config, &self.client, &self.room_state, &self.players).await
+            {
+                warn!("Failed to republish room: {}", e);
+            }
+        }
+        let _ = self.event_tx.send(ArenaEvent::PlayerLeave(pubkey)).await;
+    }
+
+    /// Take the host's next room key. A player left out of it lost their
+    /// seat and can't read the room's game state from now on.
+    async fn on_rekey(&self, pubkey: String, rekey: RekeyEventContent) {
+        if self.room_state.read().await.host_pubkey.as_deref() != Some(pubkey.as_str()) {
+            return;
+        }
+        if let Some(payload) = rekey.keys.get(&self.my_pubkey) {
+            self.take_room_key(&pubkey, payload).await;
+        }
+    }
+
+    /// Start sealing and opening with the room key `payload`, encrypted to
+    /// us by `from`
+    async fn take_room_key(&self, from: &str, payload: &str) {
+        match self.client.nip44_decrypt(from, payload).await {
+            Ok(hex) => match RoomKey::from_hex(&hex) {
+                Ok(key) => self.client.set_room_seal(RoomSeal::Key(key)).await,
+                Err(e) => warn!("Invalid room key from the host: {}", e),
+            },
+            Err(e) => warn!("Could not decrypt the room key: {}", e),
+        }
+    }
+
+    async fn on_kick(&self, pubkey: String, target: String) {
+        if self.room_state.read().await.host_pubkey.as_deref() != Some(pubkey.as_str()) {
+            return;
+        }
+        kick_player(&self.room_state, &self.players, &target).await;
+        self.player_states.remove(&target);
+        self.client.set_participants(&self.players.keys()).await;
+        lock_room_authors(&self.config, &self.client, &self.room_state, &self.players).await;
+        let _ = self.event_tx.send(ArenaEvent::PlayerKicked(target)).await;
+    }
+
+    /// Count a spectator (host only); newcomers are announced in the room
+    /// event right away
+    async fn on_spectate(&self, pubkey: String) {
+        if self.players.contains_key(&pubkey) || self.blocked.read().await.contains(&pubkey) {
+            return;
+        }
+        let is_new = {
+            let mut state = self.room_state.write().await;
+            if !state.is_host {
+                return;
+            }
+            state.spectators.insert(pubkey, monotonic_ms()).is_none()
+        };
+        if is_new
+            && let Err(e) =
+                republish_room(&self.config, &self.client, &self.room_state, &self.players).await
+        {
+            warn!("Failed to republish room: {}", e);
+        }
+    }
+
+    async fn on_claim(&self, pubkey: String, content: ClaimEventContent) {
+        let is_host = self.room_state.read().await.is_host;
+        match content.action {
+            ClaimAction::Claim => {
+                let Some(at) = content.at else {
+                    return;
+                };
+                if !is_host || !self.players.contains_key(&pubkey) {
+                    return;
+                }
+                let offset = self
+                    .peer_latency
+                    .get(&pubkey)
+                    .map_or(0, |latency| latency.clock_offset_ms);
+                let at = claim_time(at, offset, now_ms(), self.config.claim_window);
+                record_claim(
+                    &self.config,
+                    &self.client,
+                    &self.room_state,
+                    &self.event_tx,
+                    &content.claim_id,
+                    &pubkey,
+                    at,
+                )
+                .await;
+            }
+            ClaimAction::Resolved => {
+                let Some(winner) = content.winner else {
+                    return;
+                };
+                {
+                    let mut state = self.room_state.write().await;
+                    if state.host_pubkey.as_deref() != Some(pubkey.as_str()) {
+                        return;
+                    }
+                    let claim = state.claims.entry(content.claim_id.clone()).or_default();
+                    if claim.winner.is_some() {
+                        return;
+                    }
+                    claim.winner = Some(winner.clone());
+                }
+                let _ = self
+                    .event_tx
+                    .send(ArenaEvent::ClaimResolved {
+                        winner,
+                        claim_id: content.claim_id,
+                    })
+                    .await;
+            }
+        }
+    }
+
+    async fn on_vote(&self, pubkey: String, content: VoteEventContent) {
+        let topic = content.topic;
+        let event = {
+            let mut state = self.room_state.write().await;
+            let authority = state.is_authority(&pubkey);
+            match content.action {
+                VoteAction::Propose => {
+                    if !authority || content.options.is_empty() {
+                        return;
+                    }
+                    let vote = Vote::new(topic.clone(), content.options.clone());
+                    state.votes.insert(topic.clone(), vote);
+                    ArenaEvent::VoteProposed {
+                        topic: topic.clone(),
+                        options: content.options,
+                    }
+                }
+                VoteAction::Cast => {
+                    let Some(choice) = content.choice else {
+                        return;
+                    };
+                    if !self.players.contains_key(&pubkey)
+                        || afk_vote_target(&topic) == Some(pubkey.as_str())
+                    {
+                        return;
+                    }
+                    let Some(vote) = state.votes.get_mut(&topic) else {
+                        return;
+                    };
+                    if vote.winner.is_some() || !vote.options.contains(&choice) {
+                        return;
+                    }
+                    vote.ballots.insert(pubkey.clone(), choice.clone());
+                    ArenaEvent::VoteCast {
+                        pubkey,
+                        topic: topic.clone(),
+                        choice,
+                    }
+                }
+                VoteAction::Result => {
+                    // Only the host tallies
+                    if state.host_pubkey.as_deref() != Some(pubkey.as_str()) {
+                        return;
+                    }
+                    let Some(winner) = content.choice else {
+                        return;
+                    };
+                    let vote = state
+                        .votes
+                        .entry(topic.clone())
+                        .or_insert_with(|| Vote::new(topic.clone(), Vec::new()));
+                    if vote.options.is_empty() {
+                        vote.options = content.tally.keys().cloned().collect();
+                        vote.options.sort();
+                    }
+                    vote.winner = Some(winner.clone());
+                    let tally = vote
+                        .options
+                        .iter()
+                        .map(|o| (o.clone(), content.tally.get(o).copied().unwrap_or(0)))
+                        .collect();
+                    ArenaEvent::VoteResult {
+                        topic: topic.clone(),
+                        winner,
+                        tally,
+                    }
+                }
+            }
+        };
+
+        let cast = matches!(event, ArenaEvent::VoteCast { .. });
+        let _ = self.event_tx.send(event).await;
+        if cast && self.room_state.read().await.is_host {
+            close_vote_if_complete(
+                &self.config,
+                &self.client,
+                &self.room_state,
+                &self.players,
+                &self.event_tx,
+                &topic,
+            )
+            .await;
+        }
+    }
+
+    async fn on_audit(&self, pubkey: String, content: AuditEventContent) {
+        if !self.config.input_audit {
+            return;
+        }
+        let chain = self.input_chains.remove(&pubkey).unwrap_or_default();
+        if chain.count < content.inputs {
+            // We missed some of their messages; nothing to compare against
+            debug!(
+                "Can't audit {}: received {} of {} inputs",
+                pubkey, chain.count, content.inputs
+            );
+            return;
+        }
+        if chain.count == content.inputs && chain.hash == content.chain {
+            return;
+        }
+
+        warn!("Input audit failed for {}", pubkey);
+        let _ = self.event_tx.send(ArenaEvent::AuditFailed(pubkey)).await;
+    }
+
+    async fn on_clock(&self, pubkey: String, content: ClockEventContent) {
+        let Some(control) = self.config.time_control else {
+            return;
+        };
+        let flag_fall = {
+            let mut state = self.room_state.write().await;
+            if state.host_pubkey.as_deref() != Some(pubkey.as_str()) {
+                return;
+            }
+            // Count down from receipt, so the host's clock skew doesn't matter
+            let clock = state
+                .clock
+                .get_or_insert_with(|| ChessClock::new(control, []));
+            let was_flagged = clock.flagged.is_some();
+            clock.remaining = content.remaining;
+            clock.running = content.running;
+            clock.since = monotonic_ms();
+            clock.flagged = content.flagged;
+            let flag_fall = clock.flagged.clone().filter(|_| !was_flagged);
+            if flag_fall.is_some() {
+                state.status = RoomStatus::Finished;
+            }
+            flag_fall
+        };
+
+        if let Some(pubkey) = flag_fall {
+            let _ = self.event_tx.send(ArenaEvent::FlagFall { pubkey }).await;
+        }
+    }
+
+    async fn on_phase(&self, pubkey: String, phase: PhaseEventContent) {
+        let is_host = {
+            let mut state = self.room_state.write().await;
+            // Ignore late messages about earlier rounds
+            if !state.is_authority(&pubkey) || phase.round < state.round {
+                return;
+            }
+            state.round = phase.round;
+            state.phase = phase.phase.clone();
+            state.is_host
+        };
+
+        if is_host
+            && let Err(e) =
+                republish_room(&self.config, &self.client, &self.room_state, &self.players).await
+        {
+            warn!("Failed to record phase: {}", e);
+        }
+        let _ = self
+            .event_tx
+            .send(ArenaEvent::PhaseChanged {
+                round: phase.round,
+                phase: phase.phase,
+            })
+            .await;
+    }
+
+    /// Back to the lobby for a rematch on `new_seed`
+    async fn reset_for_rematch(&self, new_seed: u64) {
+        let mut state = self.room_state.write().await;
+        state.seed = new_seed;
+        state.status = RoomStatus::Ready;
+        state.rematch_requested = false;
+        state.countdown_at = None;
+        state.round = 0;
+        state.phase = None;
+        state.turn_order.clear();
+        state.claims.clear();
+        state.finishes.clear();
+        state.game_ended = false;
+        state.clock = None;
+        let d_tags = room_channels(&self.config, &state);
+        drop(state);
+
+        // Back in the lobby: hear joiners and spectators again
+        if let Err(e) = self.client.open_room_authors(&d_tags).await {
+            warn!("Failed to unlock room subscription: {}", e);
+        }
+
+        // Reset player ready states
+        self.players.update_all(|p| p.ready = false);
+
+        // Clear game states
+        self.player_states.clear();
+        self.input_chains.clear();
+
+        let _ = self.event_tx.send(ArenaEvent::RematchStart(new_seed)).await;
+    }
+
+    async fn on_game_over(&self, pubkey: &str, final_score: Option<i64>) {
+        if !self.players.contains_key(pubkey) {
+            return;
+        }
+        let is_host = {
+            let mut state = self.room_state.write().await;
+            let was_finished = state.status == RoomStatus::Finished;
+            state.status = RoomStatus::Finished;
+            if !state.record_score(pubkey, final_score) {
+                return;
+            }
+            if !was_finished {
+                let end = MatchEnd::GameOver {
+                    pubkey: pubkey.to_string(),
+                    final_score,
+                };
+                report_match_end(&self.config, &state, end);
+            }
+            state.is_host
+        };
+
+        // Keep the standings in the room event for late joiners
+        if is_host
+            && let Err(e) =
+                republish_room(&self.config, &self.client, &self.room_state, &self.players).await
+        {
+            warn!("Failed to record score: {}", e);
+        }
+    }
+
+    /// Host: record a player's game over for deciding the match
+    async fn on_finish(&self, pubkey: &str, game_over: &GameOverEventContent) {
+        if !self.room_state.read().await.is_host || !self.players.contains_key(pubkey) {
+            return;
+        }
+        let now = now_ms();
+        let offset = self
+            .peer_latency
+            .get(pubkey)
+            .map_or(0, |latency| latency.clock_offset_ms);
+        let at = claim_time(
+            game_over.at.unwrap_or(now),
+            offset,
+            now,
+            self.config.game_over_window,
+        );
+        record_finish(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.event_tx,
+            pubkey,
+            at,
+            game_over.reason().code,
+        )
+        .await;
+    }
+
+    async fn on_game_ended(&self, pubkey: String, ended: GameEndedEventContent) {
+        {
+            let mut state = self.room_state.write().await;
+            if state.host_pubkey.as_deref() != Some(pubkey.as_str()) || state.game_ended {
+                return;
+            }
+            state.game_ended = true;
+        }
+        let _ = self
+            .event_tx
+            .send(ArenaEvent::GameEnded {
+                winner: ended.winner,
+                order: ended.order,
+            })
+            .await;
+    }
+
+    async fn on_round(&self, pubkey: String, round: u32, seed: u64) {
+        let is_host = {
+            let mut state = self.room_state.write().await;
+            if !state.is_authority(&pubkey) || round <= state.round {
+                return;
+            }
+            state.round = round;
+            state.seed = seed;
+            state.is_host
+        };
+
+        if is_host
+            && let Err(e) =
+                republish_room(&self.config, &self.client, &self.room_state, &self.players).await
+        {
+            warn!("Failed to record round: {}", e);
+        }
+        let _ = self
+            .event_tx
+            .send(ArenaEvent::RoundStart { round, seed })
+            .await;
+    }
+
+    async fn on_seat(&self, pubkey: String, seat: SeatEventContent) {
+        {
+            let mut state = self.room_state.write().await;
+            if state.host_pubkey.as_deref() != Some(pubkey.as_str()) {
+                return;
+            }
+            state.seats = seat.seats;
+        }
+
+        if seat.player_pubkey == self.my_pubkey {
+            if seat.session.as_ref().is_some_and(|s| *s != self.my_session) {
+                // Another session of our key joined; once seated, we're
+                // the stale one
+                if *self.seat_reply.read().await == SeatReply::Granted {
+                    self.supersede().await;
+                }
+                return;
+            }
+            if let Some(payload) = &seat.identities
+                && let Some(links) = self
+                    .open_identity::<HashMap<String, IdentityLink>>(&pubkey, payload)
+                    .await
+            {
+                for (player, link) in links {
+                    self.players
+                        .update(&player, |p| p.identity = Some(Box::new(link)));
+                }
+            }
+            // Take the room key before anything we send needs it
+            if let Some(room_key) = &seat.room_key {
+                self.take_room_key(&pubkey, room_key).await;
+            }
+            *self.seat_reply.write().await = if seat.seat.is_some() {
+                SeatReply::Granted
+            } else {
+                SeatReply::Denied(seat.reason.map_or(ReasonCode::RoomFull, |r| r.code))
+            };
+            return;
+        }
+
+        if seat.seat.is_some() && !self.blocked.read().await.contains(&seat.player_pubkey) {
+            let identity = match seat.identity.get(&self.my_pubkey) {
+                Some(payload) => self.open_identity::<IdentityLink>(&pubkey, payload).await,
+                None => None,
+            };
+            add_player(
+                &self.client,
+                &self.players,
+                &self.event_tx,
+                &seat.player_pubkey,
+                seat.capabilities,
+                identity,
+            )
+            .await;
+            self.track_session(&seat.player_pubkey, seat.session).await;
+            self.check_seats_full().await;
+        }
+    }
+
+    /// Decrypt identity links `from` sent to us
+    async fn open_identity<D: DeserializeOwned>(&self, from: &str, payload: &str) -> Option<D> {
+        let json = self
+            .client
+            .nip44_decrypt(from, payload)
+            .await
+            .map_err(|e| warn!("Could not decrypt identity links: {}", e))
+            .ok()?;
+        serde_json::from_str(&json)
+            .map_err(|e| warn!("Invalid identity links: {}", e))
+            .ok()
+    }
+
+    /// As host, encrypt the joiner's link to every other seated player, and
+    /// the links we know to the joiner
+    async fn share_identities(
+        &self,
+        joiner: &str,
+        seats: &[String],
+        link: Option<&IdentityLink>,
+    ) -> (HashMap<String, String>, Option<String>) {
+        let mut identity_for = HashMap::new();
+        if let Some(json) = link.and_then(|link| serde_json::to_string(link).ok()) {
+            for player in seats {
+                if *player == joiner || *player == self.my_pubkey {
+                    continue;
+                }
+                if let Ok(payload) = self.client.nip44_encrypt(player, &json).await {
+                    identity_for.insert(player.clone(), payload);
+                }
+            }
+        }
+
+        let known: HashMap<String, IdentityLink> = self
+            .players
+            .values()
+            .into_iter()
+            .filter(|p| p.pubkey != joiner)
+            .filter_map(|p| Some((p.pubkey, *p.identity?)))
+            .collect();
+        let identities = match serde_json::to_string(&known) {
+            Ok(json) if !known.is_empty() => self.client.nip44_encrypt(joiner, &json).await.ok(),
+            _ => None,
+        };
+        (identity_for, identities)
+    }
+
+    /// Stop acting in the room after a newer session of our key took our
+    /// seat: background tasks end and sends fail with `NotInRoom`
+    async fn supersede(&self) {
+        let room_id = {
+            let mut state = self.room_state.write().await;
+            state.status = RoomStatus::Idle;
+            state.room_id.take()
+        };
+        if let Some(room_id) = room_id {
+            warn!("Session superseded in room: {}", room_id);
+            let _ = self
+                .event_tx
+                .send(ArenaEvent::SessionSuperseded(room_id))
+                .await;
+        }
+    }
+
+    async fn on_roster(&self, pubkey: String, roster: RosterEventContent) {
+        let (is_host, from_host) = {
+            let state = self.room_state.read().await;
+            (
+                state.is_host,
+                state.host_pubkey.as_deref() == Some(pubkey.as_str()),
+            )
+        };
+
+        match roster.action {
+            RosterAction::Request if is_host => {
+                // In seat order, like the room event's player list
+                let seats = self.room_state.read().await.seats.clone();
+                let mut players = self.players.values();
+                players.sort_by_key(|p| {
+                    seats
+                        .iter()
+                        .position(|s| *s == p.pubkey)
+                        .unwrap_or(usize::MAX)
+                });
+                if let Ok(json) = encode_event(&EventContent::Roster(RosterEventContent {
+                    action: RosterAction::Response,
+                    players,
+                })) {
+                    let _ = self.client.publish_ephemeral(&self.room_tag, &json).await;
+                }
+            }
+            RosterAction::Response if from_host => {
+                {
+                    let mut state = self.room_state.write().await;
+                    if state.seats.is_empty() {
+                        state.seats = roster.players.iter().map(|p| p.pubkey.clone()).collect();
+                    }
+                }
+                for player in roster.players {
+                    if player.pubkey != self.my_pubkey
+                        && !self.blocked.read().await.contains(&player.pubkey)
+                    {
+                        let player = PlayerPresence {
+                            seen_at: monotonic_at(player.last_seen),
+                            ..player
+                        };
+                        self.players.insert_if_absent(player.pubkey.clone(), player);
+                    }
+                }
+                self.client.set_participants(&self.players.keys()).await;
+            }
+            _ => {}
+        }
+    }
+
+    async fn on_digest(&self, pubkey: String, digest: DigestEventContent) {
+        if self.room_state.read().await.host_pubkey.as_deref() != Some(pubkey.as_str()) {
+            return;
+        }
+
+        for (player, last_seen) in digest.presence {
+            self.players.update(&player, |p| {
+                p.last_seen = p.last_seen.max(last_seen);
+                p.seen_at = p.seen_at.max(monotonic_at(last_seen));
+            });
+        }
+        for (player, value) in digest.states {
+            if player == self.my_pubkey || self.blocked.read().await.contains(&player) {
+                continue;
+            }
+            if let Ok(state) = serde_json::from_value::<T>(value) {
+                receive_state(
+                    &self.players,
+                    &self.player_states,
+                    &self.event_tx,
+                    player,
+                    state,
+                )
+                .await;
+            }
+        }
+    }
+
+    async fn on_heartbeat(&self, pubkey: String, hb: HeartbeatEventContent) {
+        if !self.players.contains_key(&pubkey) {
+            return;
+        }
+        let now = now_ms();
+        let delay = (now as i64).saturating_sub(hb.timestamp.min(i64::MAX as u64) as i64);
+        let echoed = hb.seen.get(&self.my_pubkey).copied();
+        let latency = self
+            .peer_latency
+            .update(&pubkey, |l| {
+                l.record(delay, echoed);
+                *l
+            })
+            .unwrap_or_else(|| {
+                let mut latency = PeerLatency::default();
+                latency.record(delay, echoed);
+                self.peer_latency.insert(pubkey.clone(), latency);
+                latency
+            });
+
+        self.players.update(&pubkey, |p| {
+            // A clock far ahead must not keep the player alive forever
+            p.last_seen = hb.timestamp.min(now);
+            p.seen_at = monotonic_ms();
+            p.latency_ms = Some(latency.one_way_ms);
+            p.away = hb.away;
+        });
+    }
+
+    async fn on_ready(&self, pubkey: String, ready: bool) {
+        set_ready(&self.players, &self.event_tx, &pubkey, ready).await;
+        let _ = self
+            .event_tx
+            .send(ArenaEvent::PlayerReady { pubkey, ready })
+            .await;
+
+        // Check if all ready
+        let all_ready = self.players.all(|p| p.ready);
+        if !all_ready {
+            return;
+        }
+        let _ = self.event_tx.send(ArenaEvent::AllReady).await;
+
+        let start_mode = self.room_state.read().await.start_mode(&self.config);
+        match start_mode {
+            StartMode::Ready => self.begin_play().await,
+            StartMode::Countdown => {
+                announce_countdown(
+                    &self.config,
+                    &self.client,
+                    &self.room_state,
+                    &self.players,
+                    &self.event_tx,
+                )
+                .await;
+            }
+            _ => {}
+        }
+    }
+
+    async fn on_chat(&self, pubkey: String, text: String) {
+        if self.muted.read().await.contains(&pubkey) {
+            return;
+        }
+
+        // The host's rules apply to everyone but the host
+        let now = monotonic_ms();
+        let slow_window = {
+            let state = self.room_state.read().await;
+            if state.host_pubkey.as_deref() == Some(pubkey.as_str()) {
+                0
+            } else if state.chat_muted.contains(&pubkey) {
+                return;
+            } else {
+                u64::from(state.chat_slow_mode) * 1000
+            }
+        };
+        if slow_window > 0 {
+            let allowed = self
+                .slow_guards
+                .update(&pubkey, |guard| guard.allow(now, 1, slow_window))
+                .unwrap_or_else(|| {
+                    let mut guard = FloodGuard::default();
+                    guard.allow(now, 1, slow_window);
+                    self.slow_guards.insert(pubkey.clone(), guard);
+                    true
+                });
+            if !allowed {
+                debug!("Dropped chat from {} in slow mode", pubkey);
+                return;
+            }
+        }
+
+        let (limit, window) = (self.config.chat_flood_limit, self.config.chat_flood_window);
+        let allowed = self
+            .chat_guards
+            .update(&pubkey, |guard| guard.allow(now, limit, window))
+            .unwrap_or_else(|| {
+                let mut guard = FloodGuard::default();
+                let allowed = guard.allow(now, limit, window);
+                self.chat_guards.insert(pubkey.clone(), guard);
+                allowed
+            });
+        if !allowed {
+            debug!("Dropped chat flood from {}", pubkey);
+            return;
+        }
+
+        let text = sanitize_chat(&text, self.config.chat_max_length);
+        if text.is_empty() {
+            return;
+        }
+        let _ = self.event_tx.send(ArenaEvent::Chat { pubkey, text }).await;
+    }
+
+    async fn on_moderation(&self, pubkey: String, content: ModerationEventContent) {
+        {
+            let mut state = self.room_state.write().await;
+            if state.host_pubkey.as_deref() != Some(pubkey.as_str())
+                || !state.apply_moderation(&content)
+            {
+                return;
+            }
+        }
+        if let Some(event) = moderation_event(&content) {
+            let _ = self.event_tx.send(event).await;
+        }
+    }
+
+    async fn check_seats_full(&self) {
+        check_seats_full(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.players,
+            &self.event_tx,
+        )
+        .await;
+    }
+
+    async fn begin_play(&self) {
+        begin_play(
+            &self.config,
+            &self.client,
+            &self.room_state,
+            &self.players,
+            &self.event_tx,
+        )
+        .await;
+    }
+}
+
+/// Hand a channel message to the channel's subscribers, or to the event
+/// stream if it has none
+async fn deliver_message<T>(
+    channels: &RwLock<HashMap<String, broadcast::Sender<ChannelMessage>>>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    message: ChannelMessage,
+) {
+    let message = {
+        let mut channels = channels.write().await;
+        match channels.get(&message.channel) {
+            Some(tx) => match tx.send(message) {
+                Ok(_) => return,
+                Err(broadcast::error::SendError(message)) => {
+                    // Every subscriber is gone
+                    channels.remove(&message.channel);
+                    message
+                }
+            },
+            None => message,
+        }
+    };
+    let _ = event_tx
+        .send(ArenaEvent::Message {
+            pubkey: message.pubkey,
+            channel: message.channel,
+            payload: message.payload,
+        })
+        .await;
+}
+
+/// Add a `state` message to the sender's input chain (`input_audit`)
+fn record_input(chains: &PlayerMap<InputChain>, pubkey: String, content: &str) {
+    if chains
+        .update(&pubkey, |chain| chain.push(content))
+        .is_none()
+    {
+        let mut chain = InputChain::default();
+        chain.push(content);
+        chains.insert(pubkey, chain);
+    }
+}
+
+/// Host: add a game over at `at` (host clock, ms) and decide the match
+/// `game_over_window` after the first one
+async fn record_finish<T>(
+    config: &ArenaConfig,
+    client: &Arc<NostrClient>,
+    room_state: &Arc<RwLock<RoomState>>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    pubkey: &str,
+    at: u64,
+    code: ReasonCode,
+) where
+    T: Send + 'static,
+{
+    {
+        let mut state = room_state.write().await;
+        if state.game_ended || state.finishes.iter().any(|f| f.pubkey == pubkey) {
+            return;
+        }
+        // Already decided: the window is open and takes this one in too
+        let deciding = !state.finishes.is_empty() && match_decided(&state.finishes, &state.seats);
+        state.finishes.push(Finish {
+            pubkey: pubkey.to_string(),
+            at,
+            code,
+        });
+        if deciding || !match_decided(&state.finishes, &state.seats) {
+            return;
+        }
+    }
+
+    let config = config.clone();
+    let client = client.clone();
+    let room_state = room_state.clone();
+    let event_tx = event_tx.clone();
+    spawn(async move {
+        sleep(Duration::from_millis(config.game_over_window)).await;
+
+        let (room_id, ended) = {
+            let mut state = room_state.write().await;
+            if state.game_ended || state.finishes.is_empty() {
+                return;
+            }
+            let Some(room_id) = state.room_id.clone() else {
+                return;
+            };
+            let (order, winner) = resolve_finishes(&state.finishes, &state.seats, state.seed);
+            state.game_ended = true;
+            (room_id, GameEndedEventContent { order, winner })
+        };
+
+        let room_tag = create_room_tag(&config.game_id, &room_id);
+        match encode_event(&EventContent::GameEnded(ended.clone())) {
+            Ok(json) => {
+                if let Err(e) = client.publish_result(&room_tag, &json).await {
+                    warn!("Failed to send game result: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to encode game result: {}", e),
+        }
+        let _ = event_tx
+            .send(ArenaEvent::GameEnded {
+                winner: ended.winner,
+                order: ended.order,
+            })
+            .await;
+    });
+}
 
-        Ok(())
+/// Host: add a claim at `at` (host clock, ms) and resolve the claim
+/// `claim_window` after its first entry
+async fn record_claim<T>(
+    config: &ArenaConfig,
+    client: &Arc<NostrClient>,
+    room_state: &Arc<RwLock<RoomState>>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    claim_id: &str,
+    pubkey: &str,
+    at: u64,
+) where
+    T: Send + 'static,
+{
+    {
+        let mut state = room_state.write().await;
+        let claim = state.claims.entry(claim_id.to_string()).or_default();
+        let first = claim.entries.is_empty();
+        if claim.winner.is_some() {
+            return;
+        }
+        claim.add(pubkey, at);
+        if !first {
+            return;
+        }
     }
 
-    // =========================================================================
-    // Start Mode
-    // =========================================================================
-
-    /// Send ready signal (for Ready/Countdown modes)
-    pub async fn send_ready(&self, ready: bool) -> Result<()> {
-        let room_state = self.room_state.read().await;
-        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
-        let room_tag = create_room_tag(&self.config.game_id, room_id);
-
-        let content = serde_json::to_string(&EventContent::Ready(ReadyEventContent { ready }))?;
+    let config = config.clone();
+    let client = client.clone();
+    let room_state = room_state.clone();
+    let event_tx = event_tx.clone();
+    let claim_id = claim_id.to_string();
+    spawn(async move {
+        sleep(Duration::from_millis(config.claim_window)).await;
+
+        let winner = {
+            let mut state = room_state.write().await;
+            let Some(claim) = state.claims.get_mut(&claim_id) else {
+                return;
+            };
+            let Some(winner) = claim.earliest() else {
+                return;
+            };
+            claim.winner = Some(winner.clone());
+            state.room_id.clone().map(|room_id| (room_id, winner))
+        };
+        let Some((room_id, winner)) = winner else {
+            return;
+        };
 
-        self.client.publish_ephemeral(&room_tag, &content).await?;
+        let room_tag = create_room_tag(&config.game_id, &room_id);
+        match encode_event(&EventContent::Claim(ClaimEventContent {
+            action: ClaimAction::Resolved,
+            claim_id: claim_id.clone(),
+            at: None,
+            winner: Some(winner.clone()),
+        })) {
+            Ok(json) => {
+                if let Err(e) = client.publish_ephemeral(&room_tag, &json).await {
+                    warn!("Failed to send claim result: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to encode claim result: {}", e),
+        }
+        let _ = event_tx
+            .send(ArenaEvent::ClaimResolved { winner, claim_id })
+            .await;
+    });
+}
 
-        // Update self ready status
-        let mut players = self.players.write().await;
-        if let Some(p) = players.get_mut(&self.public_key()) {
-            p.ready = ready;
+/// Pass the turn to `next` and tell the room; the host also sends the clock
+/// and records the turn in the room event. Returns the room id.
+async fn publish_turn(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+    next: &str,
+    player_move: Option<serde_json::Value>,
+) -> Result<String> {
+    let (room_id, is_host) = {
+        let mut state = room_state.write().await;
+        let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+        state.turn = Some(next.to_string());
+        if let Some(clock) = &mut state.clock {
+            clock.switch(next, monotonic_ms());
         }
+        (room_id, state.is_host)
+    };
+
+    let room_tag = create_room_tag(&config.game_id, &room_id);
+    let content = encode_event(&EventContent::Turn(TurnEventContent {
+        player_pubkey: next.to_string(),
+        player_move,
+    }))?;
+    client.publish_ephemeral(&room_tag, &content).await?;
+    if is_host {
+        publish_clock(config, client, room_state).await;
+        republish_room(config, client, room_state, players).await?;
+    }
+    Ok(room_id)
+}
 
-        drop(room_state);
-        drop(players);
-        self.check_all_ready().await;
+/// Open a vote on `topic` and tell the room, replacing an earlier vote on
+/// the same topic
+async fn open_vote(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    topic: &str,
+    options: Vec<String>,
+) -> Result<()> {
+    let room_id = {
+        let mut state = room_state.write().await;
+        let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+        state
+            .votes
+            .insert(topic.to_string(), Vote::new(topic, options.clone()));
+        room_id
+    };
+
+    let room_tag = create_room_tag(&config.game_id, &room_id);
+    let content = encode_event(&EventContent::Vote(VoteEventContent {
+        action: VoteAction::Propose,
+        topic: topic.to_string(),
+        options,
+        choice: None,
+        tally: HashMap::new(),
+    }))?;
+    client.publish_ephemeral(&room_tag, &content).await?;
+    Ok(())
+}
 
-        Ok(())
+/// Carry out the result of an AFK vote on `target` (host only): `skip`
+/// passes their turn on, `kick` removes them from the match
+async fn settle_afk_vote<T>(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    target: &str,
+    winner: &str,
+) -> Result<()> {
+    if winner != "skip" && winner != "kick" {
+        return Ok(());
     }
+    let (room_id, next) = {
+        let state = room_state.read().await;
+        let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+        let next = (state.turn.as_deref() == Some(target))
+            .then(|| next_in_turn(&resolve_turn_order(&state, players), target))
+            .flatten();
+        (room_id, next)
+    };
+
+    if winner == "kick" {
+        let room_tag = create_room_tag(&config.game_id, &room_id);
+        let content = encode_event(&EventContent::Kick(KickEventContent {
+            player_pubkey: target.to_string(),
+        }))?;
+        client.publish_ephemeral(&room_tag, &content).await?;
+        kick_player(room_state, players, target).await;
+        client.set_participants(&players.keys()).await;
+        lock_room_authors(config, client, room_state, players).await;
+        rotate_room_key(client, room_state, players, &room_tag).await?;
+        let _ = event_tx
+            .send(ArenaEvent::PlayerKicked(target.to_string()))
+            .await;
+    }
+    if let Some(next) = next {
+        publish_turn(config, client, room_state, players, &next, None).await?;
+        let _ = event_tx.send(turn_event(config, &next)).await;
+    } else if winner == "kick" {
+        republish_room(config, client, room_state, players).await?;
+    }
+    Ok(())
+}
 
-    /// Start the game (for Host mode, host only)
-    pub async fn start_game(&self) -> Result<()> {
-        let room_state = self.room_state.read().await;
-        if !room_state.is_host {
-            return Err(ArenaError::NotAuthorized(
-                "Only host can start game".to_string(),
-            ));
+/// Event announcing that the turn passed to `next`
+fn turn_event<T>(config: &ArenaConfig, next: &str) -> ArenaEvent<T> {
+    if config.enforce_turns {
+        ArenaEvent::TurnStart {
+            pubkey: next.to_string(),
         }
+    } else {
+        ArenaEvent::TurnChanged(next.to_string())
+    }
+}
 
-        let room_id = room_state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
-        let room_tag = create_room_tag(&self.config.game_id, room_id);
-
-        let content = serde_json::to_string(&EventContent::GameStart(GameStartEventContent {}))?;
-
-        self.client.publish_ephemeral(&room_tag, &content).await?;
+/// Player after `current` in `order`, skipping `current`; the first player
+/// if `current` isn't in it
+pub(crate) fn next_in_turn(order: &[String], current: &str) -> Option<String> {
+    let start = order.iter().position(|p| p == current).map_or(0, |i| i + 1);
+    order
+        .iter()
+        .cycle()
+        .skip(start)
+        .take(order.len())
+        .find(|p| *p != current)
+        .cloned()
+}
 
-        drop(room_state);
-        let mut state = self.room_state.write().await;
-        state.status = RoomStatus::Playing;
+/// Forget a removed player: their presence, seat and place in the turn
+/// order
+async fn drop_player(
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+    pubkey: &str,
+) {
+    players.remove(pubkey);
+    let mut state = room_state.write().await;
+    state.seats.retain(|p| p != pubkey);
+    state.turn_order.retain(|p| p != pubkey);
+}
 
-        let _ = self.event_tx.send(ArenaEvent::GameStart).await;
+/// Who a locked room subscription still hears: the players, the host and
+/// co-hosts even when not seated, and the spectators the host counts, whose
+/// heartbeats keep them counted. Kicked players are left out.
+pub(crate) fn room_authors(state: &RoomState, players: &[String]) -> Vec<String> {
+    let mut authors: Vec<String> = players
+        .iter()
+        .chain(&state.host_pubkey)
+        .chain(&state.co_hosts)
+        .chain(state.spectators.keys())
+        .filter(|p| !state.kicked.contains(p))
+        .cloned()
+        .collect();
+    authors.sort();
+    authors.dedup();
+    authors
+}
 
-        Ok(())
+/// Host of an encrypted room: switch to a new room key and send it to the
+/// players still in the room, so a player who left or was kicked can't read
+/// the game state from now on. State sealed under the old key while the new
+/// one travels is dropped; the next state gets through.
+async fn rotate_room_key(
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+    room_tag: &str,
+) -> Result<()> {
+    if !room_state.read().await.is_host || !matches!(client.room_seal().await, RoomSeal::Key(_)) {
+        return Ok(());
+    }
+    let key = RoomKey::generate();
+    let mut keys = HashMap::new();
+    for pubkey in players.keys() {
+        if pubkey != client.public_key() {
+            let payload = client.nip44_encrypt(&pubkey, &key.to_hex()).await?;
+            keys.insert(pubkey, payload);
+        }
     }
+    // Nothing more goes out under the old key
+    client.set_room_seal(RoomSeal::Key(key)).await;
+    let content = encode_event(&EventContent::Rekey(RekeyEventContent { keys }))?;
+    client.publish_ephemeral(room_tag, &content).await?;
+    debug!("Rotated the room key");
+    Ok(())
+}
 
-    // =========================================================================
-    // QR Code / URL
-    // =========================================================================
+/// Remove a kicked player for good: their traffic is dropped from now on
+/// and the host denies their joins
+async fn kick_player(
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+    pubkey: &str,
+) {
+    drop_player(room_state, players, pubkey).await;
+    let mut state = room_state.write().await;
+    if !state.kicked.iter().any(|p| p == pubkey) {
+        state.kicked.push(pubkey.to_string());
+    }
+}
 
-    /// Get room URL
-    pub async fn get_room_url(&self) -> Option<String> {
-        let state = self.room_state.read().await;
-        let room_id = state.room_id.as_ref()?;
+/// Close the vote on `topic` with its leading option, send the result to
+/// the room and emit `VoteResult` (host only)
+async fn close_vote<T>(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    topic: &str,
+) -> Result<String> {
+    let (room_id, winner, tally) = {
+        let mut state = room_state.write().await;
+        let room_id = state.room_id.clone().ok_or(ArenaError::NotInRoom)?;
+        let vote = state
+            .votes
+            .get_mut(topic)
+            .filter(|vote| vote.winner.is_none())
+            .ok_or_else(|| ArenaError::InvalidValue(format!("no open vote on {topic}")))?;
+        let winner = vote.leader().unwrap_or_default();
+        vote.winner = Some(winner.clone());
+        (room_id, winner, vote.tally())
+    };
+
+    let room_tag = create_room_tag(&config.game_id, &room_id);
+    let content = encode_event(&EventContent::Vote(VoteEventContent {
+        action: VoteAction::Result,
+        topic: topic.to_string(),
+        options: Vec::new(),
+        choice: Some(winner.clone()),
+        tally: tally.iter().cloned().collect(),
+    }))?;
+    client.publish_ephemeral(&room_tag, &content).await?;
+    let _ = event_tx
+        .send(ArenaEvent::VoteResult {
+            topic: topic.to_string(),
+            winner: winner.clone(),
+            tally,
+        })
+        .await;
+    Ok(winner)
+}
 
-        if let Some(base) = &self.config.base_url {
-            Some(format!("{base}/battle/{room_id}"))
-        } else {
-            Some(format!("/battle/{room_id}"))
+/// Host: close the vote on `topic` once every player in the room voted
+async fn close_vote_if_complete<T>(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    topic: &str,
+) {
+    let complete = room_state
+        .read()
+        .await
+        .votes
+        .get(topic)
+        .is_some_and(|vote| {
+            // The player an AFK vote is about doesn't vote
+            vote.winner.is_none()
+                && players.all(|p| {
+                    vote.ballots.contains_key(&p.pubkey)
+                        || afk_vote_target(topic) == Some(p.pubkey.as_str())
+                })
+        });
+    if !complete {
+        return;
+    }
+    let settled = async {
+        let winner = close_vote(config, client, room_state, event_tx, topic).await?;
+        if let Some(target) = afk_vote_target(topic) {
+            settle_afk_vote(
+                config, client, room_state, players, event_tx, target, &winner,
+            )
+            .await?;
         }
+        Ok::<_, ArenaError>(())
+    };
+    if let Err(e) = settled.await {
+        warn!("Failed to close vote {}: {}", topic, e);
     }
+}
 
-    /// Get room QR code as SVG
-    pub async fn get_room_qr_svg(&self, options: Option<crate::qr::QrOptions>) -> Option<String> {
-        let url = self.get_room_url().await?;
-        crate::qr::generate_qr_svg(&url, &options.unwrap_or_default()).ok()
+/// The app event for a host chat rule
+fn moderation_event<T>(content: &ModerationEventContent) -> Option<ArenaEvent<T>> {
+    match content.action {
+        ModerationAction::SlowMode => content.seconds.map(ArenaEvent::ChatSlowMode),
+        ModerationAction::Mute => content.player_pubkey.clone().map(ArenaEvent::ChatMuted),
+        ModerationAction::Unmute => content.player_pubkey.clone().map(ArenaEvent::ChatUnmuted),
+        ModerationAction::Clear => Some(ArenaEvent::ChatCleared),
     }
+}
 
-    /// Get room QR code as data URL
-    pub async fn get_room_qr_data_url(
-        &self,
-        options: Option<crate::qr::QrOptions>,
-    ) -> Option<String> {
-        let url = self.get_room_url().await?;
-        crate::qr::generate_qr_data_url(&url, &options.unwrap_or_default()).ok()
+/// The host's turn order, or the one derived from the seed and the seated
+/// players (all players if the room has no seats)
+fn resolve_turn_order(state: &RoomState, players: &PlayerMap<PlayerPresence>) -> Vec<String> {
+    if !state.turn_order.is_empty() {
+        return state.turn_order.clone();
     }
+    let seated = if state.seats.is_empty() {
+        players.keys()
+    } else {
+        state.seats.clone()
+    };
+    turn_order(state.seed, &seated)
+}
 
-    // =========================================================================
-    // Private: Event Handling
-    // =========================================================================
-
-    async fn start_room_subscription(&self, room_id: &str) -> Result<()> {
-        let room_tag = create_room_tag(&self.config.game_id, room_id);
-        let my_pubkey = self.public_key();
-        let players = self.players.clone();
-        let player_states = self.player_states.clone();
-        let room_state = self.room_state.clone();
-        let event_tx = self.event_tx.clone();
-        let config = self.config.clone();
-
-        self.client
-            .subscribe_room(&room_tag, move |event| {
-                // Skip own events
-                if event.pubkey.to_hex() == my_pubkey {
-                    return;
-                }
-
-                let pubkey = event.pubkey.to_hex();
+/// Send the clock to the room (host only, with `time_control`)
+async fn publish_clock(config: &ArenaConfig, client: &NostrClient, room_state: &RwLock<RoomState>) {
+    let (room_tag, content) = {
+        let state = room_state.read().await;
+        let (Some(room_id), Some(clock)) = (&state.room_id, &state.clock) else {
+            return;
+        };
+        let now = monotonic_ms();
+        let content = ClockEventContent {
+            remaining: clock
+                .remaining
+                .keys()
+                .filter_map(|pubkey| Some((pubkey.clone(), clock.remaining(pubkey, now)?)))
+                .collect(),
+            running: clock.running.clone(),
+            flagged: clock.flagged.clone(),
+        };
+        (create_room_tag(&config.game_id, room_id), content)
+    };
+
+    let Ok(json) = encode_event(&EventContent::Clock(content)) else {
+        return;
+    };
+    if let Err(e) = client.publish_ephemeral(&room_tag, &json).await {
+        warn!("Failed to send clock: {}", e);
+    }
+}
 
-                // Parse content
-                if let Ok(content) = serde_json::from_str::<EventContent>(&event.content) {
-                    let players = players.clone();
-                    let player_states = player_states.clone();
-                    let room_state = room_state.clone();
-                    let event_tx = event_tx.clone();
-                    let config = config.clone();
-
-                    spawn(async move {
-                        match content {
-                            EventContent::Join(join) => {
-                                let now = now_ms();
-                                let presence = PlayerPresence {
-                                    pubkey: join.player_pubkey.clone(),
-                                    joined_at: now,
-                                    last_seen: now,
-                                    ready: false,
-                                };
-
-                                players
-                                    .write()
-                                    .await
-                                    .insert(join.player_pubkey.clone(), presence.clone());
-
-                                let _ = event_tx.send(ArenaEvent::PlayerJoin(presence)).await;
-
-                                // Check auto-start
-                                if config.start_mode == StartMode::Auto {
-                                    let player_count = players.read().await.len();
-                                    if player_count >= config.max_players {
-                                        let mut state = room_state.write().await;
-                                        state.status = RoomStatus::Playing;
-                                        let _ = event_tx.send(ArenaEvent::GameStart).await;
-                                    }
-                                }
-                            }
+fn check_channel_name(channel: &str) -> Result<()> {
+    if channel.is_empty() || channel.len() > MAX_CHANNEL_NAME {
+        return Err(ArenaError::InvalidValue(format!(
+            "channel name must be 1-{MAX_CHANNEL_NAME} bytes"
+        )));
+    }
+    Ok(())
+}
 
-                            EventContent::State(state_event) => {
-                                // Update last_seen
-                                if let Some(p) = players.write().await.get_mut(&pubkey) {
-                                    p.last_seen = now_ms();
-                                }
-
-                                if let Ok(state) =
-                                    serde_json::from_value::<T>(state_event.game_state)
-                                {
-                                    player_states
-                                        .write()
-                                        .await
-                                        .insert(pubkey.clone(), state.clone());
-                                    let _ = event_tx
-                                        .send(ArenaEvent::PlayerState { pubkey, state })
-                                        .await;
-                                }
-                            }
+/// Publish a heartbeat to the room now, echoing measured peer delays
+async fn send_heartbeat(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    peer_latency: &PlayerMap<PeerLatency>,
+    away: bool,
+) -> Result<()> {
+    let room_tag = {
+        let state = room_state.read().await;
+        let room_id = state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+        if state.sends_to_host() {
+            create_host_tag(&config.game_id, room_id)
+        } else {
+            create_room_tag(&config.game_id, room_id)
+        }
+    };
+    let content = encode_event(&heartbeat_content(peer_latency, away))?;
+    client.publish_ephemeral(&room_tag, &content).await?;
+    Ok(())
+}
 
-                            EventContent::Heartbeat(hb) => {
-                                if let Some(p) = players.write().await.get_mut(&pubkey) {
-                                    p.last_seen = hb.timestamp;
-                                }
-                            }
+fn heartbeat_content(peer_latency: &PlayerMap<PeerLatency>, away: bool) -> EventContent {
+    let seen = peer_latency
+        .to_map()
+        .into_iter()
+        .map(|(pubkey, latency)| (pubkey, latency.last_delay_ms))
+        .collect();
+    EventContent::Heartbeat(HeartbeatEventContent {
+        timestamp: now_ms(),
+        seen,
+        away,
+    })
+}
 
-                            EventContent::GameOver(go) => {
-                                let _ = event_tx
-                                    .send(ArenaEvent::PlayerGameOver {
-                                        pubkey,
-                                        reason: go.reason,
-                                        final_score: go.final_score,
-                                    })
-                                    .await;
+/// Silence (ms) after which `player` counts as disconnected: `threshold`,
+/// or at least `away_timeout` while their app is in the background
+fn away_threshold(config: &ArenaConfig, player: &PlayerPresence, threshold: u64) -> u64 {
+    if player.away {
+        threshold.max(config.away_timeout)
+    } else {
+        threshold
+    }
+}
 
-                                room_state.write().await.status = RoomStatus::Finished;
-                            }
+/// Whether every other player has gone quiet past the disconnect threshold.
+/// A room with nobody else in it is not abandoned; there is no one to lose.
+pub(crate) fn match_abandoned(
+    config: &ArenaConfig,
+    players: &[PlayerPresence],
+    my_pubkey: &str,
+) -> bool {
+    let mut others = players.iter().filter(|p| p.pubkey != my_pubkey).peekable();
+    others.peek().is_some()
+        && others.all(|p| p.silent_ms() > away_threshold(config, p, config.disconnect_threshold))
+}
 
-                            EventContent::Rematch(rm) => match rm.action {
-                                RematchAction::Request => {
-                                    let _ =
-                                        event_tx.send(ArenaEvent::RematchRequested(pubkey)).await;
-                                }
-                                RematchAction::Accept => {
-                                    if let Some(new_seed) = rm.new_seed {
-                                        let mut state = room_state.write().await;
-                                        state.seed = new_seed;
-                                        state.status = RoomStatus::Ready;
-                                        state.rematch_requested = false;
-                                        let _ =
-                                            event_tx.send(ArenaEvent::RematchStart(new_seed)).await;
-                                    }
-                                }
-                            },
-
-                            EventContent::Ready(r) => {
-                                if let Some(p) = players.write().await.get_mut(&pubkey) {
-                                    p.ready = r.ready;
-                                }
-
-                                // Check if all ready
-                                let all_ready = players.read().await.values().all(|p| p.ready);
-                                if all_ready {
-                                    let _ = event_tx.send(ArenaEvent::AllReady).await;
-
-                                    match config.start_mode {
-                                        StartMode::Ready => {
-                                            room_state.write().await.status = RoomStatus::Playing;
-                                            let _ = event_tx.send(ArenaEvent::GameStart).await;
-                                        }
-                                        StartMode::Countdown => {
-                                            let secs = config.countdown_seconds;
-                                            let _ = event_tx
-                                                .send(ArenaEvent::CountdownStart(secs))
-                                                .await;
-
-                                            // Spawn countdown task
-                                            let event_tx_clone = event_tx.clone();
-                                            let room_state_clone = room_state.clone();
-                                            spawn(async move {
-                                                for remaining in (1..=secs).rev() {
-                                                    sleep(Duration::from_secs(1)).await;
-                                                    let _ = event_tx_clone
-                                                        .send(ArenaEvent::CountdownTick(
-                                                            remaining - 1,
-                                                        ))
-                                                        .await;
-                                                }
-                                                room_state_clone.write().await.status =
-                                                    RoomStatus::Playing;
-                                                let _ = event_tx_clone
-                                                    .send(ArenaEvent::GameStart)
-                                                    .await;
-                                            });
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                            }
+/// Heartbeat ticks per heartbeat: 1 while active, more while idle
+fn idle_ticks(config: &ArenaConfig, state: &RoomState) -> u64 {
+    config
+        .heartbeat_interval_for(state.is_idle())
+        .div_ceil(config.heartbeat_interval.max(1))
+}
 
-                            EventContent::GameStart(_) => {
-                                room_state.write().await.status = RoomStatus::Playing;
-                                let _ = event_tx.send(ArenaEvent::GameStart).await;
-                            }
+/// Treat every player as just seen, so backed-off idle heartbeats don't get
+/// them dropped the moment the room switches to the playing cadence
+fn wake_presence(players: &PlayerMap<PlayerPresence>) {
+    let (now, seen_at) = (now_ms(), monotonic_ms());
+    players.update_all(|p| {
+        p.last_seen = p.last_seen.max(now);
+        p.seen_at = p.seen_at.max(seen_at);
+    });
+}
 
-                            EventContent::Room(_) => {
-                                // Room metadata update - usually ignored in ephemeral subscription
-                            }
-                        }
-                    });
-                }
-            })
-            .await?;
+/// Tags of the ephemeral channels we listen to: the room, plus the host
+/// channel when we host a `Hosted` room
+fn room_channels(config: &ArenaConfig, state: &RoomState) -> Vec<String> {
+    let Some(room_id) = &state.room_id else {
+        return Vec::new();
+    };
+    // Spectators of such rooms follow the public summaries only
+    if state.spectating && state.summaries {
+        return vec![create_summary_tag(&config.game_id, room_id)];
+    }
+    let mut d_tags = vec![create_room_tag(&config.game_id, room_id)];
+    if state.is_host && state.sync_mode == SyncMode::Hosted {
+        d_tags.push(create_host_tag(&config.game_id, room_id));
+    }
+    d_tags
+}
 
-        Ok(())
+/// First protocol version that reads rooms without a `players` list
+const COMPRESSED_ROSTER_VERSION: u32 = 3;
+
+/// Build the room event content published by the host
+pub(crate) fn room_content(
+    config: &ArenaConfig,
+    host_pubkey: String,
+    status: RoomStatus,
+    state: &RoomState,
+    players: Vec<PlayerPresence>,
+) -> RoomEventContent {
+    // Large rooms publish only the count and a hash; peers ask the host.
+    // Until every player is known to read that, they get the full list.
+    let compress = config.roster_threshold > 0
+        && players.len() > config.roster_threshold
+        && players.iter().all(|p| {
+            p.pubkey == host_pubkey
+                || state
+                    .peer_versions
+                    .get(&p.pubkey)
+                    .is_some_and(|v| *v >= COMPRESSED_ROSTER_VERSION)
+        });
+    let (players, player_count, roster_hash) = if compress {
+        let hash = roster_hash(players.iter().map(|p| p.pubkey.as_str()));
+        (Vec::new(), Some(players.len()), Some(hash))
+    } else {
+        (players, None, None)
+    };
+    RoomEventContent {
+        version: PROTOCOL_VERSION,
+        status,
+        seed: state.seed,
+        host_pubkey,
+        max_players: state.max_players(config),
+        expires_at: state.expires_at,
+        players,
+        relays: state.relays(config).to_vec(),
+        preferred_relay: config.preferred_relay.clone(),
+        co_hosts: state.co_hosts.clone(),
+        turn: state.turn.clone(),
+        countdown_at: state.countdown_at,
+        player_count,
+        roster_hash,
+        sync_mode: state.sync_mode,
+        round: state.round,
+        phase: state.phase.clone(),
+        scoreboard: state.scoreboard.clone(),
+        start_config: state.start_config.clone(),
+        turn_order: state.turn_order.clone(),
+        chat_slow_mode: state.chat_slow_mode,
+        chat_muted: state.chat_muted.clone(),
+        spectator_count: state.spectators.len(),
+        summaries: config.spectator_summaries,
+        visibility: config.visibility,
+        encrypted: state.encrypted,
+        late_join: state.late_join,
+        password: config.room_password.is_some(),
     }
+}
 
-    async fn start_heartbeat(&self) {
-        let client = self.client.clone();
-        let room_state = self.room_state.clone();
-        let config = self.config.clone();
+/// Rooms that can still be joined or watched: not deleted, not expired
+/// and matching `status_filter`
+#[cfg(feature = "discovery")]
+fn listed_rooms(
+    game_id: &str,
+    events: Vec<nostr_sdk::Event>,
+    status_filter: Option<RoomStatus>,
+    skew: i64,
+) -> Vec<RoomInfo> {
+    let now = now_ms();
+    let mut rooms = Vec::new();
+    for event in events {
+        if let Ok(content) = decode_room(&event.content) {
+            // Skip deleted rooms
+            if content.status == RoomStatus::Deleted {
+                continue;
+            }
 
-        spawn(async move {
-            let mut ticker = interval(Duration::from_millis(config.heartbeat_interval));
+            // Skip expired rooms
+            if let Some(expires_at) = content.expires_at
+                && now > local_ms(expires_at, skew)
+            {
+                continue;
+            }
 
-            loop {
-                ticker.tick().await;
+            // Apply status filter
+            if let Some(filter) = status_filter
+                && content.status != filter
+            {
+                continue;
+            }
 
-                let state = room_state.read().await;
-                if let Some(room_id) = &state.room_id {
-                    let room_tag = create_room_tag(&config.game_id, room_id);
-                    let content =
-                        serde_json::to_string(&EventContent::Heartbeat(HeartbeatEventContent {
-                            timestamp: now_ms(),
-                        }))
-                        .unwrap();
+            rooms.push(room_info(game_id, &event, content, skew));
+        }
+    }
+    rooms
+}
 
-                    if let Err(e) = client.publish_ephemeral(&room_tag, &content).await {
-                        warn!("Failed to send heartbeat: {}", e);
-                    }
-                } else {
-                    break;
-                }
+/// Room listing entry for a room event
+fn room_info(
+    game_id: &str,
+    event: &nostr_sdk::Event,
+    content: RoomEventContent,
+    skew: i64,
+) -> RoomInfo {
+    // Extract room_id from d tag
+    let room_id = event
+        .tags
+        .iter()
+        .find_map(|tag| {
+            if tag.kind()
+                == nostr_sdk::TagKind::SingleLetter(nostr_sdk::SingleLetterTag::lowercase(
+                    nostr_sdk::Alphabet::D,
+                ))
+            {
+                tag.content().map(|s| {
+                    s.strip_prefix(&format!("{game_id}-"))
+                        .unwrap_or(s)
+                        .to_string()
+                })
+            } else {
+                None
             }
-        });
+        })
+        .unwrap_or_default();
+
+    RoomInfo {
+        room_id,
+        game_id: game_id.to_string(),
+        status: content.status,
+        player_count: content.player_count(),
+        host_pubkey: content.host_pubkey,
+        max_players: content.max_players,
+        created_at: local_ms(event.created_at.as_u64().saturating_mul(1000), skew),
+        expires_at: content.expires_at.map(|t| local_ms(t, skew)),
+        seed: content.seed,
+        round: content.round,
+        phase: content.phase,
+        spectator_count: content.spectator_count,
+        visibility: content.visibility,
+        locked: content.password,
+        client: event
+            .tags
+            .iter()
+            .find(|tag| tag.as_slice().first().map(String::as_str) == Some("client"))
+            .and_then(|tag| tag.as_slice().get(1).cloned()),
     }
+}
 
-    async fn start_presence_update(&self) {
-        let client = self.client.clone();
-        let room_state = self.room_state.clone();
-        let players = self.players.clone();
-        let config = self.config.clone();
-        let event_tx = self.event_tx.clone();
-
-        spawn(async move {
-            let mut ticker = interval(Duration::from_secs(30));
-
-            loop {
-                ticker.tick().await;
+/// `expires_at` of a room created at `created_at` and last active at `now`:
+/// `idle_expiry` after `now`, capped by `room_expiry` after creation
+fn expiry_deadline(config: &ArenaConfig, created_at: u64, now: u64) -> Option<u64> {
+    let idle = (config.idle_expiry > 0).then(|| now + config.idle_expiry);
+    let absolute = (config.room_expiry > 0).then(|| created_at + config.room_expiry);
+    match (idle, absolute) {
+        (Some(idle), Some(absolute)) => Some(idle.min(absolute)),
+        (idle, absolute) => idle.or(absolute),
+    }
+}
 
-                let state = room_state.read().await;
-                if state.room_id.is_none() || !state.is_host {
-                    continue;
-                }
+/// Local time of a timestamp taken from relay traffic, `skew` ms off
+fn local_ms(timestamp: u64, skew: i64) -> u64 {
+    timestamp.saturating_add_signed(-skew)
+}
 
-                let room_id = state.room_id.clone().unwrap();
-                let room_tag = create_room_tag(&config.game_id, &room_id);
+/// Republish the room event from the current room state (host only)
+async fn republish_room(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+) -> Result<RoomEventContent> {
+    let state = room_state.read().await;
+    let room_id = state.room_id.as_ref().ok_or(ArenaError::NotInRoom)?;
+    let room_tag = create_room_tag(&config.game_id, room_id);
+    let content = room_content(
+        config,
+        client.public_key(),
+        state.status,
+        &state,
+        players.values(),
+    );
+    drop(state);
+
+    client
+        .publish_room(&room_tag, config.room_hashtag(), &encode_room(&content)?)
+        .await?;
+    Ok(content)
+}
 
-                // Check for disconnected players
-                let now = now_ms();
-                let mut to_remove = Vec::new();
+/// Pick the authoritative version of a room from every author's room event.
+///
+/// The newest event authored by the room's host is trusted; a newer event
+/// from one of the co-hosts it lists supersedes it. Anyone can publish an
+/// event with the room's `d` tag naming themselves host, so the host is
+/// `host` when known. Otherwise the room must have a single self-declared
+/// host; with several there is no telling which is real and `None` is
+/// returned.
+pub(crate) fn latest_room(
+    events: Vec<nostr_sdk::Event>,
+    host: Option<&str>,
+) -> Option<(nostr_sdk::Event, RoomEventContent)> {
+    let mut rooms: Vec<_> = events
+        .into_iter()
+        .filter_map(|event| {
+            let content = decode_room(&event.content).ok()?;
+            Some((event, content))
+        })
+        .collect();
+    rooms.sort_by_key(|(event, _)| std::cmp::Reverse(event.created_at));
+
+    let self_hosted = |(event, content): &&(nostr_sdk::Event, RoomEventContent)| {
+        event.pubkey.to_hex() == content.host_pubkey
+            && host.is_none_or(|host| content.host_pubkey == host)
+    };
+    let (_, host_room) = rooms.iter().find(self_hosted)?;
+    if host.is_none()
+        && rooms
+            .iter()
+            .filter(self_hosted)
+            .any(|(_, content)| content.host_pubkey != host_room.host_pubkey)
+    {
+        warn!("Room events from several self-declared hosts, ignoring the room");
+        return None;
+    }
+    let host = host_room.host_pubkey.clone();
+    let co_hosts = host_room.co_hosts.clone();
 
-                {
-                    let players_read = players.read().await;
-                    for (pubkey, presence) in players_read.iter() {
-                        if now - presence.last_seen > config.disconnect_threshold {
-                            to_remove.push(pubkey.clone());
-                        }
-                    }
-                }
+    rooms.into_iter().find(|(event, content)| {
+        let author = event.pubkey.to_hex();
+        content.host_pubkey == host && (author == host || co_hosts.contains(&author))
+    })
+}
 
-                // Remove disconnected players
-                for pubkey in to_remove {
-                    players.write().await.remove(&pubkey);
-                    let _ = event_tx.send(ArenaEvent::PlayerLeave(pubkey)).await;
-                }
+/// Tell the lifecycle hooks that the match in our room ended (host only)
+fn report_match_end(config: &ArenaConfig, state: &RoomState, end: MatchEnd) {
+    if !state.is_host || config.hooks.is_empty() {
+        return;
+    }
+    if let Some(finish) = MatchFinished::from_state(config, state, end) {
+        config.hooks.match_finished(&finish);
+    }
+}
 
-                // Publish updated room state
-                let content = RoomEventContent {
-                    status: state.status,
-                    seed: state.seed,
-                    host_pubkey: client.public_key(),
-                    max_players: config.max_players,
-                    expires_at: state.expires_at,
-                    players: players.read().await.values().cloned().collect(),
-                };
+/// Seat a joining player (host only), returning the seat or `None` if full.
+///
+/// Already seated players get their existing seat back, so repeated join
+/// messages are harmless.
+async fn assign_seat(
+    config: &ArenaConfig,
+    room_state: &RwLock<RoomState>,
+    pubkey: &str,
+) -> Option<usize> {
+    let mut state = room_state.write().await;
+    if let Some(seat) = state.seat_of(pubkey) {
+        return Some(seat);
+    }
+    let closed = state.status == RoomStatus::Playing && !state.late_join;
+    if state.seats.len() >= state.max_players(config) || closed {
+        return None;
+    }
+    state.seats.push(pubkey.to_string());
+    Some(state.seats.len() - 1)
+}
 
-                if let Ok(json) = serde_json::to_string(&content) {
-                    let _ = client.publish_room(&room_tag, &config.game_id, &json).await;
-                }
+/// Track a newly seated player, announcing them once
+async fn add_player<T>(
+    client: &NostrClient,
+    players: &PlayerMap<PlayerPresence>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    pubkey: &str,
+    capabilities: Option<Capabilities>,
+    identity: Option<IdentityLink>,
+) {
+    let identity = identity.map(Box::new);
+    let now = now_ms();
+    let presence = PlayerPresence {
+        pubkey: pubkey.to_string(),
+        joined_at: now,
+        last_seen: now,
+        seen_at: monotonic_ms(),
+        ready: false,
+        latency_ms: None,
+        away: false,
+        session: None,
+        capabilities: capabilities.clone(),
+        identity: identity.clone(),
+    };
+
+    if !players.insert_if_absent(pubkey.to_string(), presence.clone()) {
+        // Join resends may carry the metadata we missed
+        players.update(pubkey, |p| {
+            if capabilities.is_some() {
+                p.capabilities = capabilities;
+            }
+            if identity.is_some() {
+                p.identity = identity;
             }
         });
+        return;
     }
+    client.set_participants(&players.keys()).await;
 
-    async fn check_auto_start(&self) {
-        if self.config.start_mode != StartMode::Auto {
-            return;
-        }
+    let _ = event_tx.send(ArenaEvent::PlayerJoin(presence)).await;
+}
 
-        let player_count = self.players.read().await.len();
-        if player_count >= self.config.max_players {
-            let mut state = self.room_state.write().await;
-            state.status = RoomStatus::Playing;
-            let _ = self.event_tx.send(ArenaEvent::GameStart).await;
-        }
+/// Record a player's latest game state and notify the app
+async fn receive_state<T>(
+    players: &PlayerMap<PlayerPresence>,
+    player_states: &PlayerMap<T>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    pubkey: String,
+    state: T,
+) where
+    T: Clone,
+{
+    players.update(&pubkey, PlayerPresence::touch);
+    player_states.insert(pubkey.clone(), state.clone());
+    let _ = event_tx
+        .send(ArenaEvent::PlayerState { pubkey, state })
+        .await;
+}
+
+/// Update a player's ready flag, announcing the new tally if it changed
+async fn set_ready<T>(
+    players: &PlayerMap<PlayerPresence>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    pubkey: &str,
+    ready: bool,
+) {
+    let changed = players.update(pubkey, |p| std::mem::replace(&mut p.ready, ready) != ready);
+    if changed != Some(true) {
+        return;
     }
+    let (ready_count, total) = (players.count(|p| p.ready), players.len());
+    let _ = event_tx
+        .send(ArenaEvent::ReadyChanged { ready_count, total })
+        .await;
+}
 
-    async fn check_all_ready(&self) {
-        let all_ready = self.players.read().await.values().all(|p| p.ready);
-        if !all_ready {
+/// Auto mode: start once the room is full. Confirmed seats are counted when
+/// the host assigns them, otherwise the known players.
+async fn check_seats_full<T>(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+) {
+    let (confirmed, max_players) = {
+        let state = room_state.read().await;
+        if state.start_mode(config) != StartMode::Auto || state.status == RoomStatus::Playing {
             return;
         }
+        let confirmed = if state.seats.is_empty() {
+            players.len()
+        } else {
+            state.seats.len()
+        };
+        (confirmed, state.max_players(config))
+    };
+    if confirmed >= max_players {
+        begin_play(config, client, room_state, players, event_tx).await;
+    }
+}
 
-        let _ = self.event_tx.send(ArenaEvent::AllReady).await;
+/// Start the countdown once everyone is ready. The host picks the start time
+/// and sends it to the room (and puts it in the room event for late
+/// arrivals); other peers wait for it so everyone counts down together.
+async fn announce_countdown<T>(
+    config: &ArenaConfig,
+    client: &Arc<NostrClient>,
+    room_state: &Arc<RwLock<RoomState>>,
+    players: &Arc<PlayerMap<PlayerPresence>>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+) where
+    T: Send + 'static,
+{
+    let (room_id, is_host, hosted, countdown_seconds) = {
+        let state = room_state.read().await;
+        (
+            state.room_id.clone(),
+            state.is_host,
+            state.host_pubkey.is_some(),
+            state.countdown_seconds(config),
+        )
+    };
+    // Pre-v2 rooms have no known host; count down locally
+    if !is_host && hosted {
+        return;
+    }
 
-        match self.config.start_mode {
-            StartMode::Ready => {
-                self.room_state.write().await.status = RoomStatus::Playing;
-                let _ = self.event_tx.send(ArenaEvent::GameStart).await;
+    let starts_at = now_ms() + countdown_seconds as u64 * 1000;
+    start_countdown(config, client, room_state, players, event_tx, starts_at).await;
+
+    if is_host && let Some(room_id) = room_id {
+        let room_tag = create_room_tag(&config.game_id, &room_id);
+        let content = encode_event(&EventContent::Countdown(CountdownEventContent {
+            starts_at,
+        }));
+        match content {
+            Ok(json) => {
+                if let Err(e) = client.publish_ephemeral(&room_tag, &json).await {
+                    warn!("Failed to send countdown: {}", e);
+                }
             }
-            StartMode::Countdown => {
-                let secs = self.config.countdown_seconds;
-                let _ = self.event_tx.send(ArenaEvent::CountdownStart(secs)).await;
+            Err(e) => warn!("Failed to encode countdown: {}", e),
+        }
+        let _ = republish_room(config, client, room_state, players).await;
+    }
+}
 
-                // Simple countdown
-                let event_tx = self.event_tx.clone();
-                let room_state = self.room_state.clone();
+/// Count down to `starts_at` on shared time, emitting `CountdownStart` with
+/// the seconds left and a `CountdownTick` per second, then start the game.
+/// Repeated calls for the same start time are ignored.
+async fn start_countdown<T>(
+    config: &ArenaConfig,
+    client: &Arc<NostrClient>,
+    room_state: &Arc<RwLock<RoomState>>,
+    players: &Arc<PlayerMap<PlayerPresence>>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+    starts_at: u64,
+) where
+    T: Send + 'static,
+{
+    {
+        let mut state = room_state.write().await;
+        if state.status == RoomStatus::Playing || state.countdown_at == Some(starts_at) {
+            return;
+        }
+        state.countdown_at = Some(starts_at);
+    }
+    wake_presence(players);
+
+    let remaining = starts_at.saturating_sub(now_ms()).div_ceil(1000) as u32;
+    let _ = event_tx.send(ArenaEvent::CountdownStart(remaining)).await;
+
+    let config = config.clone();
+    let client = client.clone();
+    let room_state = room_state.clone();
+    let players = players.clone();
+    let event_tx = event_tx.clone();
+    spawn(async move {
+        for tick in (0..remaining).rev() {
+            let at = starts_at - tick as u64 * 1000;
+            sleep(Duration::from_millis(at.saturating_sub(now_ms()))).await;
+            if room_state.read().await.countdown_at != Some(starts_at) {
+                return;
+            }
+            let _ = event_tx.send(ArenaEvent::CountdownTick(tick)).await;
+        }
+        if room_state.read().await.countdown_at == Some(starts_at) {
+            begin_play(&config, &client, &room_state, &players, &event_tx).await;
+        }
+    });
+}
 
-                spawn(async move {
-                    for i in (1..=secs).rev() {
-                        let _ = event_tx.send(ArenaEvent::CountdownTick(i)).await;
-                        sleep(Duration::from_secs(1)).await;
-                    }
-                    room_state.write().await.status = RoomStatus::Playing;
-                    let _ = event_tx.send(ArenaEvent::GameStart).await;
-                });
+/// Mark the match as started, notify the app and, if configured, narrow the
+/// room subscription to the current players
+async fn begin_play<T>(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+    event_tx: &mpsc::Sender<ArenaEvent<T>>,
+) {
+    let (in_room, is_host, start_config, first) = {
+        let mut state = room_state.write().await;
+        state.status = RoomStatus::Playing;
+        state.turn_order = resolve_turn_order(&state, players);
+        if config.enforce_turns {
+            state.turn = state.turn_order.first().cloned();
+        }
+        if let Some(control) = config.time_control {
+            // Every peer starts the same clock; the host's corrects it
+            let order = state.turn_order.clone();
+            let mut clock = ChessClock::new(control, order.iter().cloned());
+            if let Some(first) = state.turn.as_ref().or(order.first()) {
+                clock.start(first, monotonic_ms());
             }
-            _ => {}
+            state.clock = Some(clock);
         }
+        (
+            state.room_id.is_some(),
+            state.is_host,
+            state.start_config.clone(),
+            state.turn.clone().filter(|_| config.enforce_turns),
+        )
+    };
+    wake_presence(players);
+    if is_host {
+        publish_clock(config, client, room_state).await;
+    }
+    let _ = event_tx
+        .send(ArenaEvent::GameStart {
+            config: start_config,
+        })
+        .await;
+    if let Some(pubkey) = first {
+        let _ = event_tx.send(ArenaEvent::TurnStart { pubkey }).await;
     }
 
-    async fn reset_for_rematch(&self, new_seed: u64) {
-        let mut state = self.room_state.write().await;
-        state.seed = new_seed;
-        state.status = RoomStatus::Ready;
-        state.rematch_requested = false;
+    if in_room {
+        lock_room_authors(config, client, room_state, players).await;
+    }
+}
 
-        // Reset player ready states
-        let mut players = self.players.write().await;
-        for p in players.values_mut() {
-            p.ready = false;
+/// With `lock_on_start`, narrow the room subscription of a running game to
+/// the players in it, so kicked players and strangers aren't heard. Lifted
+/// again when a rematch takes the room back to the lobby.
+async fn lock_room_authors(
+    config: &ArenaConfig,
+    client: &NostrClient,
+    room_state: &RwLock<RoomState>,
+    players: &PlayerMap<PlayerPresence>,
+) {
+    let (d_tags, authors) = {
+        let state = room_state.read().await;
+        // Late joiners have to be heard, so their rooms stay open
+        if !config.lock_on_start
+            || state.room_id.is_none()
+            || state.status != RoomStatus::Playing
+            || state.late_join
+        {
+            return;
         }
-
-        // Clear game states
-        self.player_states.write().await.clear();
-
-        let _ = self.event_tx.send(ArenaEvent::RematchStart(new_seed)).await;
+        (
+            room_channels(config, &state),
+            room_authors(&state, &players.keys()),
+        )
+    };
+    if let Err(e) = client.restrict_room_authors(&d_tags, &authors).await {
+        warn!("Failed to lock room subscription: {}", e);
     }
 }
@@ -0,0 +1,57 @@
+//! Chat safety helpers
+//!
+//! Text from peers is untrusted. Every chat message, sent or received, is
+//! sanitized (control and bidirectional-override characters stripped, length
+//! capped), inbound messages are flood-limited per player, and muted players
+//! are filtered locally.
+
+use std::collections::VecDeque;
+
+/// Strip control characters (except newlines) and bidi overrides, trim, and
+/// cap the message at `max_chars` characters
+pub fn sanitize_chat(text: &str, max_chars: usize) -> String {
+    text.chars()
+        .filter(|&c| c == '\n' || !(c.is_control() || is_bidi_control(c)))
+        .take(max_chars)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Characters that can reorder surrounding text (e.g. to spoof names)
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Sliding-window message counter for one player
+#[derive(Debug, Clone, Default)]
+pub struct FloodGuard {
+    sent: VecDeque<u64>,
+}
+
+impl FloodGuard {
+    /// Record a message at `now`, returning false if it exceeds `limit`
+    /// messages within `window_ms` (the message is then not recorded)
+    pub fn allow(&mut self, now: u64, limit: u32, window_ms: u64) -> bool {
+        if limit == 0 {
+            return true;
+        }
+        while self
+            .sent
+            .front()
+            .is_some_and(|&t| now.saturating_sub(t) >= window_ms)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= limit as usize {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+
+    /// When the next message will be allowed, given the same window
+    pub fn retry_at(&self, window_ms: u64) -> u64 {
+        self.sent.front().map_or(0, |&t| t + window_ms)
+    }
+}
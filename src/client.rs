@@ -1,48 +1,361 @@
 //! Nostr client wrapper
 
 use crate::error::{ArenaError, Result};
+use crate::latency::{LatencyReport, RelayClocks};
+use crate::middleware::Middleware;
+use crate::pool::RelayPool;
+use crate::seal::RoomSeal;
 use crate::spawn::spawn;
-use crate::types::kinds;
+use crate::types::{kinds, now_ms};
+use nostr_sdk::nips::nip49;
 use nostr_sdk::prelude::*;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{RwLock, mpsc};
 use tracing::{debug, warn};
 
+/// Scrypt cost of exported keys (2^16 rounds, about 64 MiB of memory)
+const NIP49_LOG_N: u8 = 16;
+
 /// Nostr client for arena operations
 pub struct NostrClient {
     client: Client,
     relays: Vec<String>,
+    read_relays: Vec<String>,
+    write_relays: Vec<String>,
+    room_relays: Arc<RwLock<Vec<String>>>,
+    added_relays: Arc<RwLock<Vec<String>>>,
+    extra_tags: Vec<Tag>,
+    /// NIP-89 `client` tag for room and result events
+    client_tag: Option<Tag>,
+    thread_tags: bool,
+    /// How long fetches wait for relays to answer
+    fetch_timeout: Duration,
+    /// Hooks that see room messages before they are published
+    middleware: Middleware,
+    /// Room key sealing our game state, below the middleware
+    seal: Arc<RwLock<RoomSeal>>,
+    /// Address (`30078:<host>:<d>`) of the room, for `a` tags
+    room_address: Arc<RwLock<Option<String>>>,
+    participants: Arc<RwLock<Vec<PublicKey>>>,
+    inbox_subscription: Arc<RwLock<Option<SubscriptionId>>>,
+    room_subscription: Arc<RwLock<Option<SubscriptionId>>>,
+    /// Authors the room subscription is narrowed to, if any
+    room_authors: Arc<RwLock<Option<Vec<PublicKey>>>>,
+    latency: Arc<RwLock<LatencyReport>>,
+    relay_clocks: Arc<RwLock<RelayClocks>>,
     connected: Arc<RwLock<bool>>,
     public_key: String,
+    /// Our keys, unless signing goes through an external signer or a pool
+    keys: Option<Keys>,
+    /// Shared connections, if created with [`Self::with_pool`]
+    pool: Option<RelayPool>,
 }
 
 impl NostrClient {
     /// Create a new NostrClient with generated keys
     pub async fn new(relays: Vec<String>) -> Result<Self> {
-        let keys = Keys::generate();
-        let public_key = keys.public_key().to_hex();
-        let client = Client::new(keys);
-
-        Ok(Self {
-            client,
-            relays,
-            connected: Arc::new(RwLock::new(false)),
-            public_key,
-        })
+        Ok(Self::with_keys(Keys::generate(), relays))
     }
 
     /// Create a new NostrClient with provided secret key
     pub async fn with_secret_key(secret_key: &str, relays: Vec<String>) -> Result<Self> {
         let keys = Keys::parse(secret_key).map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        Ok(Self::with_keys(keys, relays))
+    }
+
+    /// Create a new NostrClient from a NIP-49 `ncryptsec` key backup (see
+    /// [`Self::export_encrypted_key`]). Decryption runs scrypt on the
+    /// calling task, like the export.
+    pub async fn import_encrypted_key(
+        ncryptsec: &str,
+        passphrase: &str,
+        relays: Vec<String>,
+    ) -> Result<Self> {
+        let secret_key = nip49::EncryptedSecretKey::from_bech32(ncryptsec)
+            .map_err(|e| ArenaError::InvalidValue(e.to_string()))?
+            .to_secret_key(passphrase)
+            .map_err(|e| ArenaError::NotAuthorized(e.to_string()))?;
+        Ok(Self::with_keys(Keys::new(secret_key), relays))
+    }
+
+    fn with_keys(keys: Keys, relays: Vec<String>) -> Self {
         let public_key = keys.public_key().to_hex();
-        let client = Client::new(keys);
+        let mut client = Self::from_client(Client::new(keys.clone()), public_key, relays);
+        client.keys = Some(keys);
+        client
+    }
 
-        Ok(Self {
+    /// Create a new NostrClient that signs and encrypts through an external
+    /// signer instead of local keys
+    pub async fn with_signer<S>(signer: S, relays: Vec<String>) -> Result<Self>
+    where
+        S: IntoNostrSigner,
+    {
+        let signer = signer.into_nostr_signer();
+        let public_key = signer
+            .get_public_key()
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?
+            .to_hex();
+        Ok(Self::from_client(Client::new(signer), public_key, relays))
+    }
+
+    /// Create a new NostrClient on a shared [`RelayPool`], using the pool's
+    /// identity and connections
+    pub fn with_pool(pool: &RelayPool, relays: Vec<String>) -> Self {
+        let mut client = Self::from_client(pool.client(), pool.public_key(), relays);
+        client.pool = Some(pool.clone());
+        client
+    }
+
+    fn from_client(client: Client, public_key: String, relays: Vec<String>) -> Self {
+        Self {
             client,
+            read_relays: relays.clone(),
+            write_relays: relays.clone(),
             relays,
+            room_relays: Arc::new(RwLock::new(Vec::new())),
+            added_relays: Arc::new(RwLock::new(Vec::new())),
+            extra_tags: Vec::new(),
+            client_tag: None,
+            thread_tags: false,
+            fetch_timeout: Duration::from_secs(5),
+            middleware: Middleware::new(),
+            seal: Arc::new(RwLock::new(RoomSeal::Off)),
+            room_address: Arc::new(RwLock::new(None)),
+            participants: Arc::new(RwLock::new(Vec::new())),
+            inbox_subscription: Arc::new(RwLock::new(None)),
+            room_subscription: Arc::new(RwLock::new(None)),
+            room_authors: Arc::new(RwLock::new(None)),
+            latency: Arc::new(RwLock::new(LatencyReport::default())),
+            relay_clocks: Arc::new(RwLock::new(RelayClocks::default())),
             connected: Arc::new(RwLock::new(false)),
             public_key,
-        })
+            keys: None,
+            pool: None,
+        }
+    }
+
+    /// Export our secret key as a NIP-49 `ncryptsec` string protected by
+    /// `passphrase`, for backing up the identity or moving it to another
+    /// device. Fails when signing goes through an external signer or a
+    /// shared pool, whose keys this client doesn't hold.
+    ///
+    /// This blocks: scrypt with `log_n` 16 takes about 64 MiB and a good
+    /// fraction of a second. Call it from a blocking thread
+    /// (`tokio::task::spawn_blocking`) rather than a busy runtime.
+    pub fn export_encrypted_key(&self, passphrase: &str) -> Result<String> {
+        let keys = self.keys.as_ref().ok_or_else(|| {
+            ArenaError::NotAuthorized("the secret key is held by the signer".to_string())
+        })?;
+        let encrypted = nip49::EncryptedSecretKey::new(
+            keys.secret_key(),
+            passphrase,
+            NIP49_LOG_N,
+            nip49::KeySecurity::Medium,
+        )
+        .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        encrypted
+            .to_bech32()
+            .map_err(|e| ArenaError::Nostr(e.to_string()))
+    }
+
+    /// Use separate relay sets for reading (fetch/subscribe) and writing
+    /// (room metadata). Empty sets fall back to all relays.
+    pub fn with_relay_sets(mut self, read: Vec<String>, write: Vec<String>) -> Self {
+        if !read.is_empty() {
+            self.read_relays = read;
+        }
+        if !write.is_empty() {
+            self.write_relays = write;
+        }
+        self
+    }
+
+    /// Add custom tags to every published event. Invalid tags are skipped.
+    pub fn with_extra_tags(mut self, tags: &[Vec<String>]) -> Self {
+        for tag in tags {
+            match Tag::parse(tag) {
+                Ok(tag) => self.extra_tags.push(tag),
+                Err(e) => warn!("Skipping invalid extra tag {:?}: {}", tag, e),
+            }
+        }
+        self
+    }
+
+    /// Tag room and result events with a NIP-89 `client` tag
+    pub fn with_client_tag(mut self, client: Option<String>) -> Self {
+        self.client_tag = client.and_then(|client| Tag::parse(["client", client.as_str()]).ok());
+        self
+    }
+
+    /// Thread ephemeral events with `p` tags for the other participants and
+    /// an `e` tag referencing the room event
+    pub fn with_thread_tags(mut self, enabled: bool) -> Self {
+        self.thread_tags = enabled;
+        self
+    }
+
+    /// How long fetches wait for relays to answer, in ms (default: 5000)
+    pub fn with_fetch_timeout(mut self, ms: u64) -> Self {
+        self.fetch_timeout = Duration::from_millis(ms);
+        self
+    }
+
+    /// Run room messages through `middleware` before publishing them
+    pub fn with_middleware(mut self, middleware: Middleware) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Set the room referenced by ephemeral events, as an address (see
+    /// [`crate::types::room_address`]) that stays valid while the host
+    /// republishes the room
+    pub async fn set_room_address(&self, address: Option<String>) {
+        *self.room_address.write().await = address;
+    }
+
+    /// Set the encryption state of the current room
+    pub async fn set_room_seal(&self, seal: RoomSeal) {
+        *self.seal.write().await = seal;
+    }
+
+    pub async fn room_seal(&self) -> RoomSeal {
+        self.seal.read().await.clone()
+    }
+
+    /// NIP-44 encrypt `content` to `pubkey` (hex) through our signer
+    pub async fn nip44_encrypt(&self, pubkey: &str, content: &str) -> Result<String> {
+        let receiver =
+            PublicKey::from_hex(pubkey).map_err(|e| ArenaError::InvalidValue(e.to_string()))?;
+        let signer = self
+            .client
+            .signer()
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        signer
+            .nip44_encrypt(&receiver, content)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))
+    }
+
+    /// NIP-44 decrypt a `payload` from `pubkey` (hex) through our signer
+    pub async fn nip44_decrypt(&self, pubkey: &str, payload: &str) -> Result<String> {
+        let sender =
+            PublicKey::from_hex(pubkey).map_err(|e| ArenaError::InvalidValue(e.to_string()))?;
+        let signer = self
+            .client
+            .signer()
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        signer
+            .nip44_decrypt(&sender, payload)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))
+    }
+
+    /// Set the participants tagged on ephemeral events (own key is skipped)
+    pub async fn set_participants<'a>(&self, pubkeys: impl IntoIterator<Item = &'a String>) {
+        *self.participants.write().await = pubkeys
+            .into_iter()
+            .filter(|pk| **pk != self.public_key)
+            .filter_map(|pk| PublicKey::from_hex(pk).ok())
+            .collect();
+    }
+
+    /// Room-scoped tags added to ephemeral events
+    pub(crate) async fn room_tags(&self) -> Vec<Tag> {
+        if !self.thread_tags {
+            return Vec::new();
+        }
+        let mut tags: Vec<Tag> = self
+            .participants
+            .read()
+            .await
+            .iter()
+            .map(|pk| Tag::public_key(*pk))
+            .collect();
+        if let Some(address) = &*self.room_address.read().await {
+            tags.extend(Tag::parse(["a", address.as_str()]).ok());
+        }
+        tags
+    }
+
+    /// Relays used for fetching and subscribing
+    pub fn read_relays(&self) -> &[String] {
+        &self.read_relays
+    }
+
+    /// Relays used for publishing room metadata
+    pub fn write_relays(&self) -> &[String] {
+        &self.write_relays
+    }
+
+    /// Relays room events go to: the write relays, plus the read relays
+    /// that peers configured like us look for rooms on
+    pub fn metadata_relays(&self) -> Vec<String> {
+        let mut relays = self.write_relays.clone();
+        relays.extend(
+            self.read_relays
+                .iter()
+                .filter(|r| !self.write_relays.contains(r))
+                .cloned(),
+        );
+        relays
+    }
+
+    /// Set the relays agreed upon in the room event for ephemeral traffic.
+    ///
+    /// Relays not yet in the pool are added and connected for the duration of
+    /// the room; relays added for a previous room are removed again.
+    pub async fn set_room_relays(&self, relays: Vec<String>) {
+        let base = self.all_relays();
+        let mut added = self.added_relays.write().await;
+        let previous = std::mem::take(&mut *added);
+
+        for relay in &relays {
+            if base.contains(relay) {
+                continue;
+            }
+            if !previous.contains(relay) {
+                if let Err(e) = self.open_relay(relay).await {
+                    warn!("Failed to add relay {}: {}", relay, e);
+                    continue;
+                }
+                debug!("Added room relay: {}", relay);
+            }
+            added.push(relay.clone());
+        }
+
+        for relay in previous {
+            if !relays.contains(&relay) {
+                self.close_relay(&relay).await;
+                debug!("Removed room relay: {}", relay);
+            }
+        }
+
+        *self.room_relays.write().await = relays;
+    }
+
+    /// Relays used for ephemeral room traffic
+    pub async fn room_relays(&self) -> Vec<String> {
+        let room_relays = self.room_relays.read().await;
+        if room_relays.is_empty() {
+            self.read_relays.clone()
+        } else {
+            room_relays.clone()
+        }
+    }
+
+    fn all_relays(&self) -> Vec<String> {
+        let mut all = self.relays.clone();
+        for relay in self.read_relays.iter().chain(&self.write_relays) {
+            if !all.contains(relay) {
+                all.push(relay.clone());
+            }
+        }
+        all
     }
 
     /// Get the public key
@@ -55,9 +368,47 @@ impl NostrClient {
         *self.connected.read().await
     }
 
+    /// Add and connect a relay, through the pool if there is one
+    async fn open_relay(&self, url: &str) -> Result<()> {
+        if let Some(pool) = &self.pool {
+            return pool.acquire(url).await;
+        }
+        self.client
+            .add_relay(url)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        if let Err(e) = self.client.connect_relay(url).await {
+            warn!("Failed to connect relay {}: {}", url, e);
+        }
+        Ok(())
+    }
+
+    /// Remove a relay, or hand it back to the pool
+    async fn close_relay(&self, url: &str) {
+        if let Some(pool) = &self.pool {
+            pool.release(url).await;
+        } else if let Err(e) = self.client.remove_relay(url).await {
+            warn!("Failed to remove relay {}: {}", url, e);
+        }
+    }
+
     /// Connect to relays
     pub async fn connect(&self) -> Result<()> {
-        for relay in &self.relays {
+        if let Some(pool) = &self.pool {
+            let mut connected = self.connected.write().await;
+            if !*connected {
+                for relay in &self.all_relays() {
+                    if let Err(e) = pool.acquire(relay).await {
+                        warn!("Failed to add relay {}: {}", relay, e);
+                    }
+                }
+                *connected = true;
+                debug!("Connected to pooled relays");
+            }
+            return Ok(());
+        }
+
+        for relay in &self.all_relays() {
             if let Err(e) = self.client.add_relay(relay).await {
                 warn!("Failed to add relay {}: {}", relay, e);
             }
@@ -69,14 +420,61 @@ impl NostrClient {
         Ok(())
     }
 
-    /// Disconnect from relays
+    /// Drop and reopen every relay connection, e.g. after the network
+    /// changed under sockets that would take long to time out. Pooled
+    /// clients leave their connections to the pool.
+    pub async fn reconnect_relays(&self) -> Result<()> {
+        if self.pool.is_some() {
+            return Ok(());
+        }
+        self.client
+            .disconnect()
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        self.client.connect().await;
+        debug!("Reconnected to relays");
+        Ok(())
+    }
+
+    /// Disconnect from relays. Pooled clients only release their relays, as
+    /// other arenas may still use them.
     pub async fn disconnect(&self) -> Result<()> {
+        if let Some(pool) = &self.pool {
+            let mut connected = self.connected.write().await;
+            if *connected {
+                for relay in &self.all_relays() {
+                    pool.release(relay).await;
+                }
+                for relay in self.added_relays.write().await.drain(..) {
+                    pool.release(&relay).await;
+                }
+                *connected = false;
+                debug!("Released pooled relays");
+            }
+            return Ok(());
+        }
+
         let _ = self.client.disconnect().await;
         *self.connected.write().await = false;
         debug!("Disconnected from relays");
         Ok(())
     }
 
+    /// Close every connection and subscription for good, ending the tasks
+    /// that forward relay events. Pooled clients only release their
+    /// relays, as other arenas may still use them.
+    pub async fn shutdown(&self) -> Result<()> {
+        if self.pool.is_some() {
+            return self.disconnect().await;
+        }
+        if let Err(e) = self.client.shutdown().await {
+            warn!("Failed to shut down the relay pool: {}", e);
+        }
+        *self.connected.write().await = false;
+        debug!("Shut down relay connections");
+        Ok(())
+    }
+
     /// Get relay connection status
     pub async fn relay_status(&self) -> Vec<(String, bool)> {
         let mut status = Vec::new();
@@ -96,14 +494,24 @@ impl NostrClient {
         false
     }
 
-    /// Publish a room event (kind 30078)
-    pub async fn publish_room(&self, d_tag: &str, game_id: &str, content: &str) -> Result<EventId> {
+    /// Publish a room event (kind 30078). Rooms without a `hashtag` can't
+    /// be found by [`Self::fetch_rooms`].
+    pub async fn publish_room(
+        &self,
+        d_tag: &str,
+        hashtag: Option<&str>,
+        content: &str,
+    ) -> Result<EventId> {
+        let mut tags = vec![Tag::identifier(d_tag)];
+        tags.extend(hashtag.map(Tag::hashtag));
+        tags.extend(self.client_tag.clone());
         let builder = EventBuilder::new(Kind::Custom(kinds::ROOM), content)
-            .tags(vec![Tag::identifier(d_tag), Tag::hashtag(game_id)]);
+            .tags(tags)
+            .tags(self.extra_tags.clone());
 
         let output = self
             .client
-            .send_event_builder(builder)
+            .send_event_builder_to(self.metadata_relays(), builder)
             .await
             .map_err(|e| ArenaError::Nostr(e.to_string()))?;
 
@@ -111,14 +519,267 @@ impl NostrClient {
         Ok(*output.id())
     }
 
+    /// Publish a NIP-56 report about `pubkey`, optionally referencing the
+    /// room event by its `30078:<host>:<d>` coordinate
+    pub async fn publish_report(
+        &self,
+        pubkey: &str,
+        report_type: &str,
+        room_coordinate: Option<&str>,
+    ) -> Result<EventId> {
+        let mut tags = vec![
+            Tag::parse(["p", pubkey, report_type]).map_err(|e| ArenaError::Nostr(e.to_string()))?,
+        ];
+        if let Some(coordinate) = room_coordinate {
+            tags.push(Tag::parse(["a", coordinate]).map_err(|e| ArenaError::Nostr(e.to_string()))?);
+        }
+        let builder = EventBuilder::new(Kind::Custom(kinds::REPORT), "")
+            .tags(tags)
+            .tags(self.extra_tags.clone());
+
+        let output = self
+            .client
+            .send_event_builder_to(self.write_relays.clone(), builder)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+
+        debug!("Published report: {}", output.id());
+        Ok(*output.id())
+    }
+
+    /// Publish a NIP-38 user status under `d_tag` (empty content clears it)
+    pub async fn publish_user_status(
+        &self,
+        d_tag: &str,
+        content: &str,
+        tags: &[Vec<String>],
+    ) -> Result<EventId> {
+        let mut status_tags = vec![Tag::identifier(d_tag)];
+        for tag in tags {
+            status_tags.push(Tag::parse(tag).map_err(|e| ArenaError::Nostr(e.to_string()))?);
+        }
+        let builder = EventBuilder::new(Kind::Custom(kinds::USER_STATUS), content)
+            .tags(status_tags)
+            .tags(self.extra_tags.clone());
+
+        let output = self
+            .client
+            .send_event_builder_to(self.write_relays.clone(), builder)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+
+        debug!("Published user status: {}", output.id());
+        Ok(*output.id())
+    }
+
+    /// Fetch the NIP-38 user statuses of `authors`
+    pub async fn fetch_user_statuses(&self, authors: &[String]) -> Result<Vec<Event>> {
+        let filter = Filter::new()
+            .kind(Kind::Custom(kinds::USER_STATUS))
+            .authors(authors.iter().filter_map(|pk| PublicKey::from_hex(pk).ok()));
+
+        let events = self
+            .client
+            .fetch_events_from(self.read_relays.clone(), vec![filter], self.fetch_timeout)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+
+        Ok(events.into_iter().collect())
+    }
+
+    /// Publish a match archive (kind 30079) under `d_tag`, tagged with the
+    /// game id
+    pub async fn publish_archive(
+        &self,
+        d_tag: &str,
+        game_id: &str,
+        content: &str,
+    ) -> Result<EventId> {
+        let id = self
+            .publish_addressable(kinds::MATCH_ARCHIVE, d_tag, game_id, content)
+            .await?;
+        debug!("Published match archive: {}", id);
+        Ok(id)
+    }
+
+    /// Fetch the match archives `author` published for `game_id`
+    pub async fn fetch_archives(&self, author: &str, game_id: &str) -> Result<Vec<Event>> {
+        self.fetch_addressable(kinds::MATCH_ARCHIVE, author, game_id)
+            .await
+    }
+
+    /// Publish a room template as an addressable event; saving a template
+    /// with the same `d_tag` replaces it
+    pub async fn publish_template(
+        &self,
+        d_tag: &str,
+        game_id: &str,
+        content: &str,
+    ) -> Result<EventId> {
+        let id = self
+            .publish_addressable(kinds::ROOM_TEMPLATE, d_tag, game_id, content)
+            .await?;
+        debug!("Published room template: {}", id);
+        Ok(id)
+    }
+
+    /// Fetch the room templates `author` saved for `game_id`
+    pub async fn fetch_templates(&self, author: &str, game_id: &str) -> Result<Vec<Event>> {
+        self.fetch_addressable(kinds::ROOM_TEMPLATE, author, game_id)
+            .await
+    }
+
+    async fn publish_addressable(
+        &self,
+        kind: u16,
+        d_tag: &str,
+        game_id: &str,
+        content: &str,
+    ) -> Result<EventId> {
+        let mut tags = vec![Tag::identifier(d_tag), Tag::hashtag(game_id)];
+        tags.extend(self.client_tag.clone());
+        let builder = EventBuilder::new(Kind::Custom(kind), content)
+            .tags(tags)
+            .tags(self.extra_tags.clone());
+
+        let output = self
+            .client
+            .send_event_builder_to(self.write_relays.clone(), builder)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        Ok(*output.id())
+    }
+
+    async fn fetch_addressable(
+        &self,
+        kind: u16,
+        author: &str,
+        game_id: &str,
+    ) -> Result<Vec<Event>> {
+        let author =
+            PublicKey::from_hex(author).map_err(|e| ArenaError::InvalidValue(e.to_string()))?;
+        let filter = Filter::new()
+            .kind(Kind::Custom(kind))
+            .author(author)
+            .hashtag(game_id);
+
+        let events = self
+            .client
+            .fetch_events_from(self.read_relays.clone(), vec![filter], self.fetch_timeout)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+
+        Ok(events.into_iter().collect())
+    }
+
+    /// Send a NIP-17 private direct message to `pubkey` via the write relays
+    pub async fn send_direct_message(&self, pubkey: &str, message: &str) -> Result<EventId> {
+        let receiver =
+            PublicKey::from_hex(pubkey).map_err(|e| ArenaError::InvalidValue(e.to_string()))?;
+        let output = self
+            .client
+            .send_private_msg_to(self.write_relays.clone(), receiver, message, [])
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+
+        debug!("Sent direct message: {}", output.id());
+        Ok(*output.id())
+    }
+
+    /// Publish an ephemeral event addressed to `pubkey`'s inbox for
+    /// `game_id` (invitations), on the write and room relays
+    pub async fn publish_to_inbox(
+        &self,
+        pubkey: &str,
+        game_id: &str,
+        content: &str,
+    ) -> Result<EventId> {
+        let receiver =
+            PublicKey::from_hex(pubkey).map_err(|e| ArenaError::InvalidValue(e.to_string()))?;
+        let builder = EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), content)
+            .tags(vec![Tag::public_key(receiver), Tag::hashtag(game_id)])
+            .tags(self.extra_tags.clone());
+
+        let mut relays = self.write_relays.clone();
+        for relay in self.room_relays().await {
+            if !relays.contains(&relay) {
+                relays.push(relay);
+            }
+        }
+
+        let output = self
+            .client
+            .send_event_builder_to(relays, builder)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+
+        debug!("Published inbox event: {}", output.id());
+        Ok(*output.id())
+    }
+
     /// Publish an ephemeral event (kind 25000)
     pub async fn publish_ephemeral(&self, d_tag: &str, content: &str) -> Result<EventId> {
-        let builder = EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), content)
-            .tags(vec![Tag::identifier(d_tag)]);
+        self.publish_room_message(d_tag, content, None).await
+    }
+
+    /// Publish a public summary (kind 25000) on `d_tag`, tagged with
+    /// `game_id` so match browsers can find it, on the write and room relays
+    pub async fn publish_summary(
+        &self,
+        d_tag: &str,
+        game_id: &str,
+        content: &str,
+    ) -> Result<EventId> {
+        let event = self
+            .sign_room_message(d_tag, content, Some(Tag::hashtag(game_id)))
+            .await?;
+
+        let mut relays = self.write_relays.clone();
+        for relay in self.room_relays().await {
+            if !relays.contains(&relay) {
+                relays.push(relay);
+            }
+        }
+
+        let output = self
+            .client
+            .send_event_to(relays, event)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+
+        debug!("Published summary");
+        Ok(*output.id())
+    }
+
+    /// Publish events again, as signed by their authors, to the room relays
+    pub async fn rebroadcast(&self, events: Vec<Event>) -> Result<()> {
+        let relays = self.room_relays().await;
+        for event in events {
+            self.client
+                .send_event_to(relays.clone(), event)
+                .await
+                .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        }
+        debug!("Rebroadcast room events");
+        Ok(())
+    }
+
+    /// Publish a game result (`gameover`) to the room, with the `client` tag
+    pub async fn publish_result(&self, d_tag: &str, content: &str) -> Result<EventId> {
+        self.publish_room_message(d_tag, content, self.client_tag.clone())
+            .await
+    }
 
+    async fn publish_room_message(
+        &self,
+        d_tag: &str,
+        content: &str,
+        tag: Option<Tag>,
+    ) -> Result<EventId> {
+        let event = self.sign_room_message(d_tag, content, tag).await?;
         let output = self
             .client
-            .send_event_builder(builder)
+            .send_event_to(self.room_relays().await, event)
             .await
             .map_err(|e| ArenaError::Nostr(e.to_string()))?;
 
@@ -126,7 +787,36 @@ impl NostrClient {
         Ok(*output.id())
     }
 
+    /// Build and sign an ephemeral room message without publishing it
+    pub(crate) async fn sign_room_message(
+        &self,
+        d_tag: &str,
+        content: &str,
+        tag: Option<Tag>,
+    ) -> Result<Event> {
+        let content = {
+            let seal = self.seal.read().await;
+            let sealed = seal.seals(content)?;
+            let content = self
+                .middleware
+                .outbound(content)
+                .ok_or(ArenaError::Dropped)?;
+            if sealed { seal.seal(content)? } else { content }
+        };
+        let builder = EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), content)
+            .tags(vec![Tag::identifier(d_tag)])
+            .tags(tag)
+            .tags(self.room_tags().await)
+            .tags(self.extra_tags.clone());
+
+        self.client
+            .sign_event_builder(builder)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))
+    }
+
     /// Fetch room events
+    #[cfg(feature = "discovery")]
     pub async fn fetch_rooms(&self, game_id: &str, limit: usize) -> Result<Vec<Event>> {
         let filter = Filter::new()
             .kind(Kind::Custom(kinds::ROOM))
@@ -135,66 +825,440 @@ impl NostrClient {
 
         let events = self
             .client
-            .fetch_events(vec![filter], std::time::Duration::from_secs(5))
+            .fetch_events_from(self.read_relays.clone(), vec![filter], self.fetch_timeout)
             .await
             .map_err(|e| ArenaError::Nostr(e.to_string()))?;
 
         Ok(events.into_iter().collect())
     }
 
-    /// Fetch a specific room by room tag
-    pub async fn fetch_room(&self, d_tag: &str) -> Result<Option<Event>> {
+    /// Subscribe to room events of `game_id` published from now on (new
+    /// rooms and updates of existing ones), forwarding them to `tx`. The
+    /// subscription closes once `tx`'s receiver is dropped.
+    #[cfg(feature = "discovery")]
+    pub async fn watch_rooms(
+        &self,
+        game_id: &str,
+        tx: mpsc::Sender<Event>,
+    ) -> Result<SubscriptionId> {
         let filter = Filter::new()
             .kind(Kind::Custom(kinds::ROOM))
-            .identifier(d_tag)
-            .limit(1);
+            .hashtag(game_id)
+            .since(Timestamp::now());
 
-        let events = self
+        let output = self
             .client
-            .fetch_events(vec![filter], std::time::Duration::from_secs(5))
+            .subscribe_to(self.read_relays.clone(), vec![filter.clone()], None)
             .await
             .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        let sub_id = output.id().clone();
+
+        let client = self.client.clone();
+        let watch_id = sub_id.clone();
+        spawn(async move {
+            let mut notifications = client.notifications();
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Event { event, .. } = notification
+                    && filter.match_event(&event)
+                    && tx.send(*event).await.is_err()
+                {
+                    break;
+                }
+            }
+            client.unsubscribe(watch_id).await;
+        });
 
-        Ok(events.into_iter().next())
+        debug!("Watching rooms of {}", game_id);
+        Ok(sub_id)
     }
 
-    /// Subscribe to room events
-    pub async fn subscribe_room<F>(&self, d_tag: &str, callback: F) -> Result<SubscriptionId>
-    where
-        F: Fn(Event) + Send + Sync + 'static,
-    {
+    /// Subscribe to public summaries of `game_id` published from now on,
+    /// forwarding them to `tx`. The subscription closes once `tx`'s receiver
+    /// is dropped.
+    #[cfg(feature = "discovery")]
+    pub async fn watch_summaries(
+        &self,
+        game_id: &str,
+        tx: mpsc::Sender<Event>,
+    ) -> Result<SubscriptionId> {
         let filter = Filter::new()
             .kind(Kind::Custom(kinds::EPHEMERAL))
+            .hashtag(game_id)
+            .since(Timestamp::now());
+
+        let output = self
+            .client
+            .subscribe_to(self.read_relays.clone(), vec![filter.clone()], None)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        let sub_id = output.id().clone();
+
+        let client = self.client.clone();
+        let watch_id = sub_id.clone();
+        spawn(async move {
+            let mut notifications = client.notifications();
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Event { event, .. } = notification
+                    && filter.match_event(&event)
+                    && tx.send(*event).await.is_err()
+                {
+                    break;
+                }
+            }
+            client.unsubscribe(watch_id).await;
+        });
+
+        debug!("Watching summaries of {}", game_id);
+        Ok(sub_id)
+    }
+
+    /// Fetch a specific room by room tag, also asking the `hints` relays
+    /// (which must already be in the pool, see [`Self::set_room_relays`]).
+    ///
+    /// Returns every author's version of the room event (the host's and its
+    /// co-hosts'); the caller decides which one is authoritative.
+    pub async fn fetch_room(&self, d_tag: &str, hints: &[String]) -> Result<Vec<Event>> {
+        Ok(self.fetch_room_copies(d_tag, hints).await?.events())
+    }
+
+    /// Like [`Self::fetch_room`], but asks each relay separately and keeps
+    /// track of which revisions each one holds, for read-repair.
+    ///
+    /// Fails only if no relay answered.
+    pub async fn fetch_room_copies(&self, d_tag: &str, hints: &[String]) -> Result<RoomCopies> {
+        let filter = Filter::new()
+            .kind(Kind::Custom(kinds::ROOM))
             .identifier(d_tag);
 
+        let mut relays = self.read_relays.clone();
+        relays.extend(
+            hints
+                .iter()
+                .filter(|r| !self.read_relays.contains(r))
+                .cloned(),
+        );
+
+        let (tx, mut rx) = mpsc::channel(relays.len().max(1));
+        for relay in relays {
+            let client = self.client.clone();
+            let filter = filter.clone();
+            let timeout = self.fetch_timeout;
+            let tx = tx.clone();
+            spawn(async move {
+                let events = client
+                    .fetch_events_from(vec![relay.clone()], vec![filter], timeout)
+                    .await;
+                let _ = tx.send((relay, events)).await;
+            });
+        }
+        drop(tx);
+
+        let mut copies = RoomCopies::default();
+        let mut error = None;
+        while let Some((relay, events)) = rx.recv().await {
+            match events {
+                Ok(events) => copies.insert(relay, events.into_iter().collect()),
+                Err(e) => {
+                    debug!("Room fetch from {} failed: {}", relay, e);
+                    error = Some(e);
+                }
+            }
+        }
+        match error {
+            Some(e) if copies.is_empty() => Err(ArenaError::Nostr(e.to_string())),
+            _ => Ok(copies),
+        }
+    }
+
+    /// Republish a room event, as signed by its author, to `relays` in the
+    /// background (read-repair of relays holding an older revision)
+    pub fn repair_room(&self, event: Event, relays: Vec<String>) {
+        let client = self.client.clone();
+        spawn(async move {
+            match client.send_event_to(relays, event).await {
+                Ok(output) => debug!("Repaired room event {}", output.id()),
+                Err(e) => warn!("Failed to repair room event: {}", e),
+            }
+        });
+    }
+
+    /// Subscribe to room events tagged with any of `d_tags`, forwarding them
+    /// to `tx` in arrival order.
+    ///
+    /// Forwarding waits while `tx` is full and stops once its receiver is
+    /// dropped.
+    pub async fn subscribe_room(
+        &self,
+        d_tags: &[String],
+        tx: mpsc::Sender<Event>,
+    ) -> Result<SubscriptionId> {
+        let filter = Filter::new()
+            .kind(Kind::Custom(kinds::EPHEMERAL))
+            .identifiers(d_tags.iter().cloned());
+
         let output = self
             .client
-            .subscribe(vec![filter], None)
+            .subscribe_to(self.room_relays().await, vec![filter.clone()], None)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+
+        let sub_id = output.id().clone();
+        *self.room_subscription.write().await = Some(sub_id.clone());
+        *self.room_authors.write().await = None;
+
+        // Forward events in background
+        let client = self.client.clone();
+        let latency = self.latency.clone();
+        let relay_clocks = self.relay_clocks.clone();
+
+        spawn(async move {
+            let mut notifications = client.notifications();
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Event {
+                    relay_url, event, ..
+                } = notification
+                    && filter.match_event(&event)
+                {
+                    let relay = relay_url.to_string();
+                    let created_at = event.created_at.as_u64() * 1000;
+                    let now = now_ms();
+                    latency
+                        .write()
+                        .await
+                        .record(&event.pubkey.to_hex(), &relay, created_at, now);
+                    relay_clocks.write().await.record(&relay, created_at, now);
+                    if tx.send(*event).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        debug!("Subscribed to room: {}", d_tags.join(", "));
+        Ok(sub_id)
+    }
+
+    /// Renew the room subscription on the room relays under the same id,
+    /// e.g. after relays dropped it while the app was in the background.
+    /// Events keep flowing to the `tx` given to [`Self::subscribe_room`],
+    /// and a narrowing by [`Self::restrict_room_authors`] is kept.
+    pub async fn resubscribe_room(&self, d_tags: &[String]) -> Result<()> {
+        let Some(sub_id) = self.room_subscription.read().await.clone() else {
+            return Ok(());
+        };
+        let mut filter = Filter::new()
+            .kind(Kind::Custom(kinds::EPHEMERAL))
+            .identifiers(d_tags.iter().cloned());
+        if let Some(authors) = self.room_authors.read().await.clone() {
+            filter = filter.authors(authors);
+        }
+
+        self.client
+            .subscribe_with_id_to(self.room_relays().await, sub_id, vec![filter], None)
             .await
             .map_err(|e| ArenaError::Nostr(e.to_string()))?;
 
+        debug!("Resubscribed to room: {}", d_tags.join(", "));
+        Ok(())
+    }
+
+    /// Subscribe to ephemeral events addressed to us for `game_id`
+    /// (invitations), forwarding them to `tx`. Only one inbox subscription
+    /// is kept; later calls return the existing one.
+    pub async fn subscribe_inbox(
+        &self,
+        game_id: &str,
+        tx: mpsc::Sender<Event>,
+    ) -> Result<SubscriptionId> {
+        let mut inbox = self.inbox_subscription.write().await;
+        if let Some(sub_id) = inbox.clone() {
+            return Ok(sub_id);
+        }
+
+        let me = PublicKey::from_hex(&self.public_key)
+            .map_err(|e| ArenaError::InvalidValue(e.to_string()))?;
+        let filter = Filter::new()
+            .kind(Kind::Custom(kinds::EPHEMERAL))
+            .pubkey(me)
+            .hashtag(game_id);
+
+        let output = self
+            .client
+            .subscribe_to(self.read_relays.clone(), vec![filter.clone()], None)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
         let sub_id = output.id().clone();
+        *inbox = Some(sub_id.clone());
 
-        // Handle events in background
         let client = self.client.clone();
-        let callback = Arc::new(callback);
+        spawn(async move {
+            let mut notifications = client.notifications();
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Event { event, .. } = notification
+                    && filter.match_event(&event)
+                    && tx.send(*event).await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        debug!("Subscribed to inbox for {}", game_id);
+        Ok(sub_id)
+    }
+
+    /// Open an independent subscription to the room's traffic.
+    ///
+    /// Unlike [`subscribe_room`](Self::subscribe_room), it is never narrowed
+    /// to the players and the callback also sees our own events, along with
+    /// the relay each event arrived from. Return `false` from the callback
+    /// to close the subscription.
+    pub async fn watch_room<F>(&self, d_tag: &str, callback: F) -> Result<SubscriptionId>
+    where
+        F: Fn(&RelayUrl, Event) -> bool + Send + Sync + 'static,
+    {
+        let filter = Filter::new()
+            .kind(Kind::Custom(kinds::EPHEMERAL))
+            .identifier(d_tag);
+
+        let output = self
+            .client
+            .subscribe_to(self.room_relays().await, vec![filter.clone()], None)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        let sub_id = output.id().clone();
 
+        let client = self.client.clone();
+        let watch_id = sub_id.clone();
         spawn(async move {
             let mut notifications = client.notifications();
             while let Ok(notification) = notifications.recv().await {
-                if let RelayPoolNotification::Event { event, .. } = notification {
-                    callback(*event);
+                if let RelayPoolNotification::Event {
+                    relay_url, event, ..
+                } = notification
+                    && filter.match_event(&event)
+                    && !callback(&relay_url, *event)
+                {
+                    break;
                 }
             }
+            client.unsubscribe(watch_id).await;
         });
 
-        debug!("Subscribed to room: {}", d_tag);
+        debug!("Watching room: {}", d_tag);
         Ok(sub_id)
     }
 
+    /// Narrow the room subscription to events authored by `authors`.
+    ///
+    /// Re-sends the subscription under the same id, so relays replace the
+    /// filter and the existing event handler keeps working.
+    pub async fn restrict_room_authors(&self, d_tags: &[String], authors: &[String]) -> Result<()> {
+        if self.room_subscription.read().await.is_none() {
+            return Err(ArenaError::NotInRoom);
+        }
+        *self.room_authors.write().await = Some(
+            authors
+                .iter()
+                .filter_map(|pk| PublicKey::from_hex(pk).ok())
+                .collect(),
+        );
+        self.resubscribe_room(d_tags).await?;
+
+        debug!(
+            "Restricted room {} to {} authors",
+            d_tags.join(", "),
+            authors.len()
+        );
+        Ok(())
+    }
+
+    /// Authors the room subscription is narrowed to, or `None` while it
+    /// takes anyone's traffic
+    pub async fn room_authors(&self) -> Option<Vec<String>> {
+        self.room_authors
+            .read()
+            .await
+            .as_ref()
+            .map(|authors| authors.iter().map(|pk| pk.to_hex()).collect())
+    }
+
+    /// Undo [`NostrClient::restrict_room_authors`]: take room traffic from
+    /// anyone again
+    pub async fn open_room_authors(&self, d_tags: &[String]) -> Result<()> {
+        if self.room_authors.write().await.take().is_none() {
+            return Ok(());
+        }
+        self.resubscribe_room(d_tags).await?;
+
+        debug!("Opened room {} to every author", d_tags.join(", "));
+        Ok(())
+    }
+
+    /// Latency histograms of inbound room events so far
+    pub async fn latency_report(&self) -> LatencyReport {
+        self.latency.read().await.clone()
+    }
+
+    /// Timestamp skew of every relay room events arrived from
+    pub async fn relay_clocks(&self) -> RelayClocks {
+        self.relay_clocks.read().await.clone()
+    }
+
+    /// Correction (ms) for `created_at`-derived times; see
+    /// [`RelayClocks::skew_ms`]
+    pub async fn clock_skew(&self) -> i64 {
+        self.relay_clocks.read().await.skew_ms()
+    }
+
+    /// Relays that became skewed since the last call, with their estimate
+    /// (ms)
+    pub async fn take_skew_alerts(&self) -> Vec<(String, i64)> {
+        self.relay_clocks.write().await.take_alerts()
+    }
+
     /// Unsubscribe from a subscription
     pub async fn unsubscribe(&self, sub_id: SubscriptionId) -> Result<()> {
         self.client.unsubscribe(sub_id).await;
         Ok(())
     }
 }
+
+/// Revisions of a room event, per relay that answered a fetch
+#[derive(Debug, Clone, Default)]
+pub struct RoomCopies {
+    copies: Vec<(String, Vec<Event>)>,
+}
+
+impl RoomCopies {
+    /// Record what `relay` answered
+    pub fn insert(&mut self, relay: String, events: Vec<Event>) {
+        self.copies.push((relay, events));
+    }
+
+    /// Whether no relay answered
+    pub fn is_empty(&self) -> bool {
+        self.copies.is_empty()
+    }
+
+    /// Every revision any relay holds, once
+    pub fn events(&self) -> Vec<Event> {
+        let mut events: Vec<Event> = Vec::new();
+        for event in self.copies.iter().flat_map(|(_, events)| events) {
+            if !events.iter().any(|e| e.id == event.id) {
+                events.push(event.clone());
+            }
+        }
+        events
+    }
+
+    /// Relays that answered without `event`: they hold an older revision or
+    /// none at all
+    pub fn lagging(&self, event: &Event) -> Vec<String> {
+        self.copies
+            .iter()
+            .filter(|(_, events)| !events.iter().any(|e| e.id == event.id))
+            .map(|(relay, _)| relay.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,141 @@
+//! Time control
+//!
+//! Per-player time banks for turn-based games, like a chess clock: every
+//! player starts with the main time, only the player whose turn it is
+//! loses time, and passing the turn adds the increment (Fischer). The host
+//! keeps the authoritative clock; after every turn change it sends the banks
+//! in a `clock` message, and peers count the running player down from the
+//! moment it arrived, so their own clock skew doesn't matter.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Main time and increment of a time control
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeControl {
+    /// Time bank each player starts with, in ms
+    pub main_ms: u64,
+    /// Time added after each turn, in ms
+    pub increment_ms: u64,
+    /// Remaining time below which `ArenaEvent::TimeLow` fires, in ms
+    /// (default: a tenth of the main time)
+    pub low_time_ms: u64,
+}
+
+impl TimeControl {
+    pub fn new(main_ms: u64, increment_ms: u64) -> Self {
+        Self {
+            main_ms,
+            increment_ms,
+            low_time_ms: main_ms / 10,
+        }
+    }
+
+    pub fn low_time(mut self, ms: u64) -> Self {
+        self.low_time_ms = ms;
+        self
+    }
+}
+
+/// Time banks of every player
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChessClock {
+    pub control: TimeControl,
+    /// Time left per player in ms; the running player's as of `since`
+    pub remaining: HashMap<String, u64>,
+    /// Player whose time is running
+    pub running: Option<String>,
+    /// Monotonic time (ms, `time::monotonic_ms`) the running player's bank
+    /// was last charged
+    pub since: u64,
+    /// Player who ran out of time, ending the game
+    pub flagged: Option<String>,
+}
+
+impl ChessClock {
+    /// Stopped clock with the main time for each player
+    pub fn new(control: TimeControl, players: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            control,
+            remaining: players
+                .into_iter()
+                .map(|pubkey| (pubkey, control.main_ms))
+                .collect(),
+            running: None,
+            since: 0,
+            flagged: None,
+        }
+    }
+
+    /// Time left for a player at `now`
+    pub fn remaining(&self, pubkey: &str, now: u64) -> Option<u64> {
+        let left = *self.remaining.get(pubkey)?;
+        Some(if self.running.as_deref() == Some(pubkey) {
+            left.saturating_sub(now.saturating_sub(self.since))
+        } else {
+            left
+        })
+    }
+
+    /// Start `pubkey`'s time without charging anyone
+    pub fn start(&mut self, pubkey: &str, now: u64) {
+        self.remaining
+            .entry(pubkey.to_string())
+            .or_insert(self.control.main_ms);
+        self.running = Some(pubkey.to_string());
+        self.since = now;
+    }
+
+    /// End the running player's turn: charge the elapsed time, add the
+    /// increment and start `next`'s time
+    pub fn switch(&mut self, next: &str, now: u64) {
+        self.charge(now);
+        if let Some(running) = self.running.take()
+            && let Some(left) = self.remaining.get_mut(&running)
+        {
+            *left = left.saturating_add(self.control.increment_ms);
+        }
+        self.start(next, now);
+    }
+
+    /// Stop the running player's time
+    pub fn pause(&mut self, now: u64) {
+        self.charge(now);
+        self.running = None;
+    }
+
+    /// Flag the running player if their time ran out, returning them once
+    pub fn check_flag(&mut self, now: u64) -> Option<String> {
+        if self.flagged.is_some() {
+            return None;
+        }
+        let running = self.running.clone()?;
+        if self.remaining(&running, now)? > 0 {
+            return None;
+        }
+        self.pause(now);
+        self.flagged = Some(running.clone());
+        Some(running)
+    }
+
+    /// Players at or below the low time mark at `now`
+    pub fn low_players(&self, now: u64) -> Vec<String> {
+        self.remaining
+            .keys()
+            .filter(|pubkey| {
+                self.remaining(pubkey, now)
+                    .is_some_and(|left| left <= self.control.low_time_ms)
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn charge(&mut self, now: u64) {
+        if let Some(running) = &self.running
+            && let Some(left) = self.remaining.get_mut(running)
+        {
+            *left = left.saturating_sub(now.saturating_sub(self.since));
+        }
+        self.since = now;
+    }
+}
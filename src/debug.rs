@@ -0,0 +1,123 @@
+//! Debug timeline of room traffic
+//!
+//! Attaches a second, read-only subscription to the current room and turns
+//! every event seen on it - including our own - into a [`TimelineEntry`]
+//! with the sender, message type, relay and latency from `created_at` to
+//! local receipt. Useful for checking exactly what peers are receiving.
+//!
+//! ```rust,ignore
+//! let mut viewer = arena.debug_viewer().await?;
+//! while let Some(entry) = viewer.next().await {
+//!     println!("{entry}");
+//! }
+//!
+//! // Or just send everything to the log
+//! arena.debug_viewer().await?.log();
+//! ```
+
+use crate::client::NostrClient;
+use crate::error::Result;
+use crate::spawn::spawn;
+use crate::types::now_ms;
+use serde_json::Value;
+use std::fmt;
+use tokio::sync::mpsc;
+use tracing::info;
+
+/// One event observed on the room subscription
+#[derive(Debug, Clone)]
+pub struct TimelineEntry {
+    /// Local receipt time (ms)
+    pub received_at: u64,
+    /// Event `created_at` (ms, second resolution)
+    pub created_at: u64,
+    /// `received_at - created_at`; negative values indicate clock skew
+    pub latency_ms: i64,
+    pub kind: u16,
+    pub sender: String,
+    /// Content `type` field, or `"?"` if the content isn't arena JSON
+    pub message_type: String,
+    pub relay: String,
+    /// Whether the event was published by this arena
+    pub own: bool,
+}
+
+impl fmt::Display for TimelineEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sender = self.sender.get(..8).unwrap_or(&self.sender);
+        write!(
+            f,
+            "{} kind={} {:<10} from {}{} via {} ({:+}ms)",
+            self.received_at,
+            self.kind,
+            self.message_type,
+            sender,
+            if self.own { " (own)" } else { "" },
+            self.relay,
+            self.latency_ms,
+        )
+    }
+}
+
+/// Receiver of [`TimelineEntry`] items for a room
+pub struct DebugViewer {
+    rx: mpsc::Receiver<TimelineEntry>,
+}
+
+impl DebugViewer {
+    /// Subscribe to a room's traffic
+    pub(crate) async fn attach(client: &NostrClient, d_tag: &str) -> Result<Self> {
+        let (tx, rx) = mpsc::channel(1024);
+        let my_pubkey = client.public_key();
+
+        client
+            .watch_room(d_tag, move |relay_url, event| {
+                let received_at = now_ms();
+                let created_at = event.created_at.as_u64() * 1000;
+                let sender = event.pubkey.to_hex();
+                let message_type = serde_json::from_str::<Value>(&event.content)
+                    .ok()
+                    .and_then(|v| v.get("type").and_then(Value::as_str).map(str::to_string))
+                    .unwrap_or_else(|| "?".to_string());
+
+                let entry = TimelineEntry {
+                    received_at,
+                    created_at,
+                    latency_ms: received_at as i64 - created_at as i64,
+                    kind: event.kind.as_u16(),
+                    own: sender == my_pubkey,
+                    sender,
+                    message_type,
+                    relay: relay_url.to_string(),
+                };
+                // Drop entries if the reader falls behind; stop once it is gone
+                !matches!(
+                    tx.try_send(entry),
+                    Err(mpsc::error::TrySendError::Closed(_))
+                )
+            })
+            .await?;
+
+        Ok(Self { rx })
+    }
+
+    /// Wait for the next entry
+    pub async fn next(&mut self) -> Option<TimelineEntry> {
+        self.rx.recv().await
+    }
+
+    /// Iterate over the entries received so far without waiting
+    pub fn try_iter(&mut self) -> impl Iterator<Item = TimelineEntry> + '_ {
+        std::iter::from_fn(|| self.rx.try_recv().ok())
+    }
+
+    /// Write every entry to the log (`nostr_arena::debug` target) in the
+    /// background
+    pub fn log(mut self) {
+        spawn(async move {
+            while let Some(entry) = self.rx.recv().await {
+                info!(target: "nostr_arena::debug", "{}", entry);
+            }
+        });
+    }
+}
@@ -0,0 +1,248 @@
+//! In-terminal debug console
+//!
+//! A reference client and debugger for any game using the crate: it drives
+//! an `Arena` from typed commands while showing the relay status, the player
+//! table and a live pane of the latest events. Input is read asynchronously,
+//! so events keep flowing while the console waits for a command.
+//!
+//! ```rust,ignore
+//! let arena: Arena<GameState> = Arena::new(config).await?;
+//! arena.connect().await?;
+//! DebugConsole::new(arena).run().await?;
+//! ```
+
+use crate::arena::{Arena, ArenaEvent, SendResult};
+use crate::error::Result;
+use crate::lobby::LobbyStateMachine;
+use crate::types::short_key;
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::VecDeque;
+use std::fmt::{Debug, Write};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::time::interval;
+
+/// Events shown in the event pane by default
+pub const DEFAULT_EVENT_LINES: usize = 12;
+
+/// Longest event line shown; longer lines are cut
+const MAX_LINE: usize = 120;
+
+/// How often the relay status is refreshed without other activity
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+const HELP: &str = "c create | j <room> join | r ready | s <json> state | m <text> chat | \
+                    l list rooms | x leave | q quit";
+
+/// Terminal front end for an `Arena`
+pub struct DebugConsole<T> {
+    arena: Arena<T>,
+    lobby: LobbyStateMachine,
+    events: VecDeque<String>,
+    event_lines: usize,
+    /// Outcome of the last command
+    notice: String,
+    ready: bool,
+}
+
+impl<T> DebugConsole<T>
+where
+    T: Clone + Debug + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    pub fn new(arena: Arena<T>) -> Self {
+        Self {
+            arena,
+            lobby: LobbyStateMachine::new(),
+            events: VecDeque::new(),
+            event_lines: DEFAULT_EVENT_LINES,
+            notice: HELP.to_string(),
+            ready: false,
+        }
+    }
+
+    /// Number of events kept in the event pane
+    pub fn event_lines(mut self, lines: usize) -> Self {
+        self.event_lines = lines.max(1);
+        self
+    }
+
+    /// Run until `q` or the end of input, then leave the room
+    pub async fn run(mut self) -> Result<()> {
+        let mut input = BufReader::new(tokio::io::stdin()).lines();
+        let mut refresh = interval(REFRESH_INTERVAL);
+        self.render().await;
+
+        loop {
+            tokio::select! {
+                line = input.next_line() => {
+                    let Ok(Some(line)) = line else {
+                        break;
+                    };
+                    if !self.command(line.trim()).await {
+                        break;
+                    }
+                }
+                Some(event) = self.arena.recv() => self.record(&event),
+                _ = refresh.tick() => {}
+            }
+            self.render().await;
+        }
+
+        if self.arena.room_state().await.room_id.is_some() {
+            self.arena.leave().await?;
+        }
+        Ok(())
+    }
+
+    /// Run one command; returns false to quit
+    async fn command(&mut self, line: &str) -> bool {
+        let (cmd, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        self.notice = match cmd {
+            "" => return true,
+            "q" => return false,
+            "c" => match self.arena.create().await {
+                Ok(url) => {
+                    self.enter_room().await;
+                    format!("Room created: {url}")
+                }
+                Err(e) => format!("Create failed: {e}"),
+            },
+            "j" if !arg.is_empty() => match self.arena.join(arg).await {
+                Ok(()) => {
+                    self.enter_room().await;
+                    format!("Joined {arg}")
+                }
+                Err(e) => format!("Join failed: {e}"),
+            },
+            "r" => match self.arena.send_ready(!self.ready).await {
+                Ok(()) => {
+                    self.ready = !self.ready;
+                    self.lobby.set_ready(&self.arena.public_key(), self.ready);
+                    format!("Ready: {}", self.ready)
+                }
+                Err(e) => format!("Ready failed: {e}"),
+            },
+            "s" => match serde_json::from_str::<T>(arg) {
+                Ok(state) => match self.arena.send_state(&state).await {
+                    Ok(SendResult::Sent(_)) => "State sent".to_string(),
+                    Ok(SendResult::Throttled { .. }) => "State throttled".to_string(),
+                    Err(e) => format!("State failed: {e}"),
+                },
+                Err(e) => format!("Invalid state: {e}"),
+            },
+            "m" => match self.arena.send_chat(arg).await {
+                Ok(_) => "Message sent".to_string(),
+                Err(e) => format!("Chat failed: {e}"),
+            },
+            "l" => self.list_rooms().await,
+            "x" => match self.arena.leave().await {
+                Ok(()) => {
+                    self.lobby = LobbyStateMachine::new();
+                    self.ready = false;
+                    "Left the room".to_string()
+                }
+                Err(e) => format!("Leave failed: {e}"),
+            },
+            _ => HELP.to_string(),
+        };
+        true
+    }
+
+    #[cfg(feature = "discovery")]
+    async fn list_rooms(&self) -> String {
+        match self.arena.find_rooms(None, 10).await {
+            Ok(rooms) if rooms.is_empty() => "No rooms found".to_string(),
+            Ok(rooms) => rooms
+                .iter()
+                .map(|r| {
+                    format!(
+                        "{} {:?} {}/{}",
+                        r.room_id, r.status, r.player_count, r.max_players
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" | "),
+            Err(e) => format!("Listing failed: {e}"),
+        }
+    }
+
+    #[cfg(not(feature = "discovery"))]
+    async fn list_rooms(&self) -> String {
+        "Room listing needs the discovery feature".to_string()
+    }
+
+    async fn enter_room(&mut self) {
+        self.lobby = LobbyStateMachine::new();
+        self.lobby.sync(&self.arena.players().await);
+        self.ready = false;
+    }
+
+    fn record(&mut self, event: &ArenaEvent<T>) {
+        self.lobby.apply(event);
+        let mut line = format!("{event:?}");
+        if let Some((cut, _)) = line.char_indices().nth(MAX_LINE) {
+            line.truncate(cut);
+            line.push('…');
+        }
+        self.events.push_back(line);
+        while self.events.len() > self.event_lines {
+            self.events.pop_front();
+        }
+    }
+
+    async fn render(&self) {
+        let room = self.arena.room_state().await;
+        let view = self.lobby.view();
+        let me = self.arena.public_key();
+        let players = self.arena.players().await;
+
+        // Clear the screen and move home
+        let mut out = String::from("\x1b[2J\x1b[H");
+        let _ = writeln!(out, "nostr-arena debug console - {}", short_key(&me));
+        let _ = writeln!(
+            out,
+            "Room: {}  Status: {:?}  {}",
+            room.room_id.as_deref().unwrap_or("-"),
+            room.status,
+            view.status
+        );
+
+        out.push_str("\nRelays\n");
+        for (url, connected) in self.arena.relay_status().await {
+            let mark = if connected { "up  " } else { "down" };
+            let _ = writeln!(out, "  {mark} {url}");
+        }
+
+        out.push_str("\nPlayers\n");
+        for player in &view.players {
+            let latency = players
+                .iter()
+                .find(|p| p.pubkey == player.pubkey)
+                .and_then(|p| p.latency_ms)
+                .map(|ms| format!("{ms} ms"))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "  {} {:<5} {:<9} {}{}",
+                short_key(&player.pubkey),
+                if player.ready { "ready" } else { "" },
+                if player.connected { "" } else { "offline" },
+                latency,
+                if player.pubkey == me { " (you)" } else { "" },
+            );
+        }
+        for banner in &view.banners {
+            let _ = writeln!(out, "  ! {banner}");
+        }
+
+        out.push_str("\nEvents\n");
+        for line in &self.events {
+            let _ = writeln!(out, "  {line}");
+        }
+
+        let _ = write!(out, "\n{}\n> ", self.notice);
+        print!("{out}");
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+    }
+}
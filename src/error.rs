@@ -22,6 +22,12 @@ pub enum ArenaError {
     #[error("Invalid room data: {0}")]
     InvalidRoomData(String),
 
+    #[error("Invalid event data: {0}")]
+    InvalidEventData(String),
+
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+
     #[error("Operation timed out")]
     Timeout,
 
@@ -34,6 +40,15 @@ pub enum ArenaError {
     #[error("Not in room")]
     NotInRoom,
 
+    #[error("Message dropped by middleware")]
+    Dropped,
+
+    #[error("Relay error: {0}")]
+    Relay(String),
+
+    #[error("I/O error: {0}")]
+    Io(String),
+
     #[error("Nostr error: {0}")]
     Nostr(String),
 
@@ -0,0 +1,162 @@
+//! Host lifecycle hooks
+//!
+//! A [`LifecycleHook`] is told about the lifecycle of the rooms an arena
+//! hosts: the room was created, a player was seated, the match finished.
+//! Each call gets a structured context, so host-side bots can feed
+//! analytics or chat webhooks without re-deriving these transitions from
+//! the event stream. Add hooks with `ArenaConfig::with_hook()`.
+//!
+//! Hooks only fire on the host, once per transition, and run on the
+//! arena's own tasks: keep them quick and hand slow work (HTTP requests) to
+//! a task of your own. The contexts serialize to JSON for that purpose.
+
+use crate::types::{ArenaConfig, RoomState, RoomVisibility, Standing, now_ms};
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+/// Observer of the rooms we host
+pub trait LifecycleHook: Send + Sync {
+    /// We published a new room
+    fn on_room_created(&self, room: &RoomCreated) {
+        let _ = room;
+    }
+
+    /// We seated a new player
+    fn on_player_joined(&self, join: &PlayerJoined) {
+        let _ = join;
+    }
+
+    /// The match in our room ended
+    fn on_match_finished(&self, finish: &MatchFinished) {
+        let _ = finish;
+    }
+}
+
+/// A room we created
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RoomCreated {
+    pub room_id: String,
+    pub game_id: String,
+    pub host_pubkey: String,
+    pub seed: u64,
+    pub max_players: usize,
+    pub visibility: RoomVisibility,
+    /// ms since the epoch
+    pub created_at: u64,
+}
+
+/// A player we seated
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerJoined {
+    pub room_id: String,
+    pub game_id: String,
+    pub pubkey: String,
+    pub seat: usize,
+    /// Seated players, including the new one
+    pub players: usize,
+    pub max_players: usize,
+    /// ms since the epoch
+    pub joined_at: u64,
+}
+
+/// How a match ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MatchEnd {
+    /// A player (possibly us) sent game over
+    GameOver {
+        pubkey: String,
+        final_score: Option<i64>,
+    },
+    /// A player ran out of time
+    FlagFall { pubkey: String },
+    /// Every remote player went silent
+    Abandoned,
+    /// The room expired during play
+    Expired,
+}
+
+/// A match that ended in our room
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatchFinished {
+    pub room_id: String,
+    pub game_id: String,
+    pub end: MatchEnd,
+    /// Seated players, in seat order
+    pub players: Vec<String>,
+    pub round: u32,
+    /// Standings of the session, best first
+    pub scoreboard: Vec<Standing>,
+    /// ms since the epoch
+    pub finished_at: u64,
+}
+
+impl MatchFinished {
+    /// Context of the match in `state`, if it is a room
+    pub(crate) fn from_state(
+        config: &ArenaConfig,
+        state: &RoomState,
+        end: MatchEnd,
+    ) -> Option<Self> {
+        Some(Self {
+            room_id: state.room_id.clone()?,
+            game_id: config.game_id.clone(),
+            end,
+            players: state.seats.clone(),
+            round: state.round,
+            scoreboard: state.scoreboard.clone(),
+            finished_at: now_ms(),
+        })
+    }
+}
+
+/// The lifecycle hooks of an arena, in the order they were added
+#[derive(Clone, Default)]
+pub struct Hooks {
+    hooks: Vec<Arc<dyn LifecycleHook>>,
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+impl Hooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, hook: Box<dyn LifecycleHook>) {
+        self.hooks.push(Arc::from(hook));
+    }
+
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub fn room_created(&self, room: &RoomCreated) {
+        for hook in &self.hooks {
+            hook.on_room_created(room);
+        }
+    }
+
+    pub fn player_joined(&self, join: &PlayerJoined) {
+        for hook in &self.hooks {
+            hook.on_player_joined(join);
+        }
+    }
+
+    pub fn match_finished(&self, finish: &MatchFinished) {
+        for hook in &self.hooks {
+            hook.on_match_finished(finish);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Per-match keys linked to a long-term identity
+//!
+//! Playing every match under the same key lets anyone watching relays follow
+//! a player from match to match. With `Arena::with_linked_identity`, a match
+//! is played under a fresh throwaway key instead, and the player's long-term
+//! key signs an [`IdentityLink`] naming it. The link travels NIP-44
+//! encrypted in the join handshake: to the host in `join`, and from the host
+//! to each seated player in `seat`. Opponents and leaderboards can check who
+//! they played, while the relays only see unrelated throwaway keys.
+//!
+//! The link is an event signed by the long-term key that is never published
+//! on its own; its content is `{game_id}:{match_pubkey}`.
+
+use crate::error::{ArenaError, Result};
+use crate::types::kinds;
+use nostr_sdk::{Event, EventBuilder, Keys, Kind};
+use serde::{Deserialize, Serialize};
+
+/// Proof that a match key belongs to a long-term identity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct IdentityLink(pub Event);
+
+impl IdentityLink {
+    /// Sign a link from `identity` to the match key `match_pubkey` (hex)
+    pub fn sign(identity: &Keys, game_id: &str, match_pubkey: &str) -> Result<Self> {
+        EventBuilder::new(
+            Kind::Custom(kinds::IDENTITY_LINK),
+            link_content(game_id, match_pubkey),
+        )
+        .sign_with_keys(identity)
+        .map(IdentityLink)
+        .map_err(|e| ArenaError::Nostr(e.to_string()))
+    }
+
+    /// The long-term public key (hex) if this is a valid link to
+    /// `match_pubkey` for `game_id`
+    pub fn verify(&self, game_id: &str, match_pubkey: &str) -> Option<String> {
+        let event = &self.0;
+        (event.kind == Kind::Custom(kinds::IDENTITY_LINK)
+            && event.content == link_content(game_id, match_pubkey)
+            && event.verify().is_ok())
+        .then(|| event.pubkey.to_hex())
+    }
+}
+
+fn link_content(game_id: &str, match_pubkey: &str) -> String {
+    format!("{game_id}:{match_pubkey}")
+}
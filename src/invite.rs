@@ -0,0 +1,350 @@
+//! Shareable room links
+//!
+//! Room URLs carry relay hints as `relay` query parameters, and
+//! [`parse_room_link`] splits them back into a room id and relays.
+//!
+//! For a compact, self-contained alternative, an [`Invite`] packs the game id, room id, relay hints and optionally the seed
+//! into a single bech32 token with the `arena` prefix (`arena1...`). Unlike a
+//! room URL it needs no `base_url` on the joiner's side and tells the joiner
+//! which relays to look on, so it works across differently configured apps.
+//!
+//! The payload is a TLV list like NIP-19:
+//!
+//! | Type | Value |
+//! |------|-------|
+//! | 0 | room id (UTF-8) |
+//! | 1 | game id (UTF-8) |
+//! | 2 | relay URL (UTF-8, repeatable) |
+//! | 3 | seed (8 bytes, big endian) |
+//! | 4 | room password (UTF-8) |
+//! | 5 | host public key (32 bytes) |
+
+use crate::error::{ArenaError, Result};
+use nostr_sdk::PublicKey;
+use std::fmt;
+use std::str::FromStr;
+
+/// Human-readable prefix of invite tokens
+pub const INVITE_HRP: &str = "arena";
+
+const TLV_ROOM_ID: u8 = 0;
+const TLV_GAME_ID: u8 = 1;
+const TLV_RELAY: u8 = 2;
+const TLV_SEED: u8 = 3;
+const TLV_SECRET: u8 = 4;
+const TLV_HOST: u8 = 5;
+
+/// Room invite decoded from or encoded to an `arena1...` token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invite {
+    pub game_id: String,
+    pub room_id: String,
+    /// Relays the room can be found on
+    pub relays: Vec<String>,
+    pub seed: Option<u64>,
+    /// Password of the room, so the token alone lets friends in
+    pub secret: Option<String>,
+    /// Hex public key of the host; only its room events are trusted
+    pub host: Option<String>,
+}
+
+impl Invite {
+    pub fn new(game_id: impl Into<String>, room_id: impl Into<String>) -> Self {
+        Self {
+            game_id: game_id.into(),
+            room_id: room_id.into(),
+            relays: Vec::new(),
+            seed: None,
+            secret: None,
+            host: None,
+        }
+    }
+
+    pub fn relays(mut self, relays: Vec<String>) -> Self {
+        self.relays = relays;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    pub fn host(mut self, pubkey: impl Into<String>) -> Self {
+        self.host = Some(pubkey.into());
+        self
+    }
+
+    /// Encode as an `arena1...` token
+    pub fn encode(&self) -> String {
+        let mut payload = Vec::new();
+        push_tlv(&mut payload, TLV_ROOM_ID, self.room_id.as_bytes());
+        push_tlv(&mut payload, TLV_GAME_ID, self.game_id.as_bytes());
+        for relay in &self.relays {
+            push_tlv(&mut payload, TLV_RELAY, relay.as_bytes());
+        }
+        if let Some(seed) = self.seed {
+            push_tlv(&mut payload, TLV_SEED, &seed.to_be_bytes());
+        }
+        if let Some(secret) = &self.secret {
+            push_tlv(&mut payload, TLV_SECRET, secret.as_bytes());
+        }
+        if let Some(host) = self
+            .host
+            .as_deref()
+            .and_then(|h| PublicKey::from_hex(h).ok())
+        {
+            push_tlv(&mut payload, TLV_HOST, &host.to_bytes());
+        }
+        bech32::encode(INVITE_HRP, &payload)
+    }
+
+    /// Decode an `arena1...` token
+    pub fn decode(token: &str) -> Result<Self> {
+        let invalid = |msg: &str| ArenaError::InvalidValue(format!("invite: {msg}"));
+
+        let (hrp, payload) = bech32::decode(token.trim()).ok_or_else(|| invalid("bad encoding"))?;
+        if hrp != INVITE_HRP {
+            return Err(invalid("wrong prefix"));
+        }
+
+        let mut room_id = None;
+        let mut game_id = None;
+        let mut relays = Vec::new();
+        let mut seed = None;
+        let mut secret = None;
+        let mut host = None;
+
+        let mut rest = payload.as_slice();
+        while let [kind, len, tail @ ..] = rest {
+            let len = *len as usize;
+            if tail.len() < len {
+                return Err(invalid("truncated"));
+            }
+            let (value, tail) = tail.split_at(len);
+            let text = || String::from_utf8(value.to_vec()).map_err(|_| invalid("bad text"));
+            match *kind {
+                TLV_ROOM_ID => room_id = Some(text()?),
+                TLV_GAME_ID => game_id = Some(text()?),
+                TLV_RELAY => relays.push(text()?),
+                TLV_SEED => {
+                    let bytes = value.try_into().map_err(|_| invalid("bad seed"))?;
+                    seed = Some(u64::from_be_bytes(bytes));
+                }
+                TLV_SECRET => secret = Some(text()?),
+                TLV_HOST => {
+                    let key = PublicKey::from_slice(value).map_err(|_| invalid("bad host"))?;
+                    host = Some(key.to_hex());
+                }
+                // Unknown entries from newer versions are skipped
+                _ => {}
+            }
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(invalid("truncated"));
+        }
+
+        Ok(Self {
+            game_id: game_id.ok_or_else(|| invalid("missing game id"))?,
+            room_id: room_id.ok_or_else(|| invalid("missing room id"))?,
+            relays,
+            seed,
+            secret,
+            host,
+        })
+    }
+}
+
+impl fmt::Display for Invite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl FromStr for Invite {
+    type Err = ArenaError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::decode(s)
+    }
+}
+
+/// Build a shareable room URL, adding each relay hint as a `relay` query
+/// parameter: `{base}/battle/{room_id}?relay=wss%3A%2F%2F...`
+pub fn room_url(base_url: Option<&str>, room_id: &str, relays: &[String]) -> String {
+    let mut url = format!("{}/battle/{room_id}", base_url.unwrap_or_default());
+    for (i, relay) in relays.iter().enumerate() {
+        url.push(if i == 0 { '?' } else { '&' });
+        url.push_str("relay=");
+        url.push_str(&percent_encode(relay));
+    }
+    url
+}
+
+/// Split a room reference into the room id and relay hints.
+///
+/// Accepts a bare room id or a room URL as produced by [`room_url`] (any
+/// path ending in the room id, with optional `relay` query parameters).
+pub fn parse_room_link(link: &str) -> (String, Vec<String>) {
+    let link = link.trim();
+    let (path, query) = link.split_once('?').unwrap_or((link, ""));
+    let path = path.split('#').next().unwrap_or_default();
+    let room_id = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+
+    let relays = query
+        .split('#')
+        .next()
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("relay="))
+        .filter_map(percent_decode)
+        .filter(|relay| !relay.is_empty())
+        .collect();
+
+    (room_id.to_string(), relays)
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'%' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                out.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b'+' => out.push(b' '),
+            _ => out.push(b),
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn push_tlv(out: &mut Vec<u8>, kind: u8, value: &[u8]) {
+    // Values longer than 255 bytes can't be represented; truncate like NIP-19
+    let value = &value[..value.len().min(u8::MAX as usize)];
+    out.push(kind);
+    out.push(value.len() as u8);
+    out.extend_from_slice(value);
+}
+
+/// Minimal bech32 (BIP-173) without the 90 character limit, as in NIP-19
+mod bech32 {
+    const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+        let mut chk: u32 = 1;
+        for v in values {
+            let top = chk >> 25;
+            chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(v);
+            for (i, g) in GENERATOR.iter().enumerate() {
+                if (top >> i) & 1 == 1 {
+                    chk ^= g;
+                }
+            }
+        }
+        chk
+    }
+
+    fn hrp_expand(hrp: &str) -> Vec<u8> {
+        let bytes = hrp.as_bytes();
+        bytes
+            .iter()
+            .map(|b| b >> 5)
+            .chain([0])
+            .chain(bytes.iter().map(|b| b & 31))
+            .collect()
+    }
+
+    /// Regroup bits, e.g. 8-bit bytes into 5-bit groups
+    fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+        let mut acc: u32 = 0;
+        let mut bits: u32 = 0;
+        let max = (1 << to) - 1;
+        let mut out = Vec::new();
+        for &value in data {
+            if u32::from(value) >> from != 0 {
+                return None;
+            }
+            acc = (acc << from) | u32::from(value);
+            bits += from;
+            while bits >= to {
+                bits -= to;
+                out.push(((acc >> bits) & max) as u8);
+            }
+        }
+        if pad {
+            if bits > 0 {
+                out.push(((acc << (to - bits)) & max) as u8);
+            }
+        } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+            return None;
+        }
+        Some(out)
+    }
+
+    pub fn encode(hrp: &str, data: &[u8]) -> String {
+        let values = convert_bits(data, 8, 5, true).unwrap_or_default();
+        let checksum = {
+            let poly = polymod(
+                hrp_expand(hrp)
+                    .into_iter()
+                    .chain(values.iter().copied())
+                    .chain([0; 6]),
+            ) ^ 1;
+            (0..6).map(move |i| ((poly >> (5 * (5 - i))) & 31) as u8)
+        };
+        let mut out = format!("{hrp}1");
+        out.extend(
+            values
+                .iter()
+                .copied()
+                .chain(checksum)
+                .map(|v| CHARSET[v as usize] as char),
+        );
+        out
+    }
+
+    pub fn decode(s: &str) -> Option<(String, Vec<u8>)> {
+        if s.chars().any(|c| c.is_ascii_uppercase()) && s.chars().any(|c| c.is_ascii_lowercase()) {
+            return None;
+        }
+        let s = s.to_ascii_lowercase();
+        let sep = s.rfind('1')?;
+        let (hrp, data) = (&s[..sep], &s[sep + 1..]);
+        if hrp.is_empty() || data.len() < 6 {
+            return None;
+        }
+        let values: Vec<u8> = data
+            .bytes()
+            .map(|c| CHARSET.iter().position(|&x| x == c).map(|p| p as u8))
+            .collect::<Option<_>>()?;
+        if polymod(hrp_expand(hrp).into_iter().chain(values.iter().copied())) != 1 {
+            return None;
+        }
+        let payload = convert_bits(&values[..values.len() - 6], 5, 8, false)?;
+        Some((hrp.to_string(), payload))
+    }
+}
@@ -0,0 +1,279 @@
+//! Event latency tracking
+//!
+//! For every inbound room event the client records the delay between the
+//! event's `created_at` and local receipt, bucketed per peer and per relay.
+//! Nostr timestamps have second resolution, so individual samples can be
+//! off by up to a second; the distribution is what matters.
+//!
+//! Heartbeats carry a millisecond `timestamp` from the sender's clock, which
+//! gives a finer, continuous baseline: [`PeerLatency`] turns the delay of
+//! each heartbeat into a one-way latency estimate. Each heartbeat also
+//! echoes the delays the sender last measured from every peer, so the two
+//! directions can be combined NTP-style to cancel the clock offset.
+//!
+//! Some relays rewrite or clamp `created_at`. [`RelayClocks`] keeps a
+//! per-relay estimate of how far event timestamps are from local time, so
+//! values derived from `created_at` can be corrected.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Upper bounds (ms) of the histogram buckets; a final bucket holds the rest
+pub const BUCKET_BOUNDS_MS: [u64; 8] = [25, 50, 100, 200, 400, 800, 1600, 3200];
+
+/// Latency distribution for one peer, relay, or all traffic
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// Sample counts per bucket, aligned with [`BUCKET_BOUNDS_MS`] plus an
+    /// overflow bucket
+    pub buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    pub count: u64,
+    pub sum_ms: u64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyHistogram {
+    /// Add a sample
+    pub fn record(&mut self, latency_ms: u64) {
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| latency_ms <= bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[bucket] += 1;
+        self.min_ms = if self.count == 0 {
+            latency_ms
+        } else {
+            self.min_ms.min(latency_ms)
+        };
+        self.max_ms = self.max_ms.max(latency_ms);
+        self.count += 1;
+        self.sum_ms += latency_ms;
+    }
+
+    /// Mean latency (ms), 0 if empty
+    pub fn mean_ms(&self) -> u64 {
+        self.sum_ms.checked_div(self.count).unwrap_or(0)
+    }
+
+    /// Approximate percentile (0-100) as the upper bound of the bucket it
+    /// falls into; the overflow bucket reports `max_ms`
+    pub fn percentile_ms(&self, percentile: u8) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let target = (self.count * u64::from(percentile.min(100)))
+            .div_ceil(100)
+            .max(1);
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= target {
+                return BUCKET_BOUNDS_MS.get(i).copied().unwrap_or(self.max_ms);
+            }
+        }
+        self.max_ms
+    }
+}
+
+/// Latency histograms for all inbound room events
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyReport {
+    pub overall: LatencyHistogram,
+    /// Keyed by sender public key (hex)
+    pub by_peer: HashMap<String, LatencyHistogram>,
+    /// Keyed by relay URL
+    pub by_relay: HashMap<String, LatencyHistogram>,
+}
+
+impl LatencyReport {
+    /// Record one event; negative latencies (clock skew) count as 0
+    pub fn record(&mut self, peer: &str, relay: &str, created_at_ms: u64, received_at_ms: u64) {
+        let latency = received_at_ms.saturating_sub(created_at_ms);
+        self.overall.record(latency);
+        self.by_peer
+            .entry(peer.to_string())
+            .or_default()
+            .record(latency);
+        self.by_relay
+            .entry(relay.to_string())
+            .or_default()
+            .record(latency);
+    }
+
+    /// Relay with the highest mean latency, if any
+    pub fn slowest_relay(&self) -> Option<(&str, &LatencyHistogram)> {
+        self.by_relay
+            .iter()
+            .max_by_key(|(_, h)| h.mean_ms())
+            .map(|(url, h)| (url.as_str(), h))
+    }
+
+    /// Peer with the highest mean latency, if any
+    pub fn slowest_peer(&self) -> Option<(&str, &LatencyHistogram)> {
+        self.by_peer
+            .iter()
+            .max_by_key(|(_, h)| h.mean_ms())
+            .map(|(pk, h)| (pk.as_str(), h))
+    }
+}
+
+/// Offset (ms) of a relay's timestamps beyond which it counts as skewed
+pub const SKEW_THRESHOLD_MS: i64 = 5000;
+
+/// Samples before a relay's skew estimate is trusted
+const SKEW_MIN_SAMPLES: u64 = 5;
+
+/// How far one relay's event timestamps are from local time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelaySkew {
+    /// Smoothed `created_at` minus receipt time (ms, positive = ahead);
+    /// includes the latency and the second resolution of `created_at`
+    pub skew_ms: i64,
+    /// Events measured
+    pub samples: u64,
+    /// Whether the estimate is past [`SKEW_THRESHOLD_MS`]
+    pub skewed: bool,
+}
+
+impl RelaySkew {
+    /// Record an event; returns true when the relay just became skewed
+    pub fn record(&mut self, created_at_ms: u64, received_at_ms: u64) -> bool {
+        let sample = (created_at_ms.min(i64::MAX as u64) as i64)
+            .saturating_sub(received_at_ms.min(i64::MAX as u64) as i64);
+        if self.samples == 0 {
+            self.skew_ms = sample;
+        } else {
+            self.skew_ms = self
+                .skew_ms
+                .saturating_add(smooth(sample.saturating_sub(self.skew_ms)));
+        }
+        self.samples += 1;
+
+        let skewed = self.samples >= SKEW_MIN_SAMPLES
+            && self.skew_ms.unsigned_abs() > SKEW_THRESHOLD_MS as u64;
+        let newly_skewed = skewed && !self.skewed;
+        self.skewed = skewed;
+        newly_skewed
+    }
+}
+
+/// Timestamp skew of every relay room events arrived from
+#[derive(Debug, Clone, Default)]
+pub struct RelayClocks {
+    /// Keyed by relay URL
+    pub relays: HashMap<String, RelaySkew>,
+    /// Relays that became skewed since the last `take_alerts()`, with
+    /// their estimate (ms)
+    alerts: Vec<(String, i64)>,
+}
+
+impl RelayClocks {
+    /// Record one event received from `relay`
+    pub fn record(&mut self, relay: &str, created_at_ms: u64, received_at_ms: u64) {
+        let skew = self.relays.entry(relay.to_string()).or_default();
+        if skew.record(created_at_ms, received_at_ms) {
+            self.alerts.push((relay.to_string(), skew.skew_ms));
+        }
+    }
+
+    /// Correction (ms) for timestamps whose relay is unknown: the mean
+    /// estimate of the skewed relays, 0 when none is skewed
+    pub fn skew_ms(&self) -> i64 {
+        let skewed: Vec<i64> = self
+            .relays
+            .values()
+            .filter(|r| r.skewed)
+            .map(|r| r.skew_ms)
+            .collect();
+        skewed
+            .iter()
+            .sum::<i64>()
+            .checked_div(skewed.len() as i64)
+            .unwrap_or(0)
+    }
+
+    /// Relays that became skewed since the last call
+    pub fn take_alerts(&mut self) -> Vec<(String, i64)> {
+        std::mem::take(&mut self.alerts)
+    }
+}
+
+/// Smoothing factor of the estimates, as in TCP's RTT estimator (1/8)
+const SMOOTHING: i64 = 8;
+
+/// One-way latency and clock offset of a peer, estimated from heartbeats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerLatency {
+    /// Smoothed one-way latency (ms)
+    pub one_way_ms: u64,
+    /// Smoothed offset of the peer's clock from ours (ms, positive = ahead);
+    /// stays 0 until the peer echoes our heartbeat delays
+    pub clock_offset_ms: i64,
+    /// Heartbeats measured
+    pub samples: u64,
+    /// Latest raw delay of the peer's heartbeats (receipt minus `timestamp`),
+    /// echoed back to the peer in our heartbeats
+    pub last_delay_ms: i64,
+}
+
+impl PeerLatency {
+    /// Record a heartbeat that arrived `delay_ms` after its `timestamp`.
+    /// `echoed_ms` is the delay the peer last measured for our heartbeats.
+    pub fn record(&mut self, delay_ms: i64, echoed_ms: Option<i64>) {
+        // delay = latency - offset, echoed = latency + offset
+        let (latency, offset) = match echoed_ms {
+            Some(echoed) => (
+                delay_ms.saturating_add(echoed) / 2,
+                echoed.saturating_sub(delay_ms) / 2,
+            ),
+            None => (
+                delay_ms.saturating_add(self.clock_offset_ms),
+                self.clock_offset_ms,
+            ),
+        };
+        let latency = latency.max(0);
+
+        if self.samples == 0 {
+            self.one_way_ms = latency as u64;
+            self.clock_offset_ms = offset;
+        } else {
+            let one_way = self.one_way_ms as i64;
+            self.one_way_ms = one_way
+                .saturating_add(smooth(latency.saturating_sub(one_way)))
+                .max(0) as u64;
+            self.clock_offset_ms = self
+                .clock_offset_ms
+                .saturating_add(smooth(offset.saturating_sub(self.clock_offset_ms)));
+        }
+        self.samples += 1;
+        self.last_delay_ms = delay_ms;
+    }
+}
+
+/// Step of the moving average towards a sample `diff` away, rounded
+fn smooth(diff: i64) -> i64 {
+    diff.saturating_add(diff.signum() * SMOOTHING / 2) / SMOOTHING
+}
+
+/// Connection quality of the current room, from `Arena::stats()`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArenaStats {
+    /// Heartbeat-based estimates keyed by peer public key (hex)
+    pub peers: HashMap<String, PeerLatency>,
+}
+
+impl ArenaStats {
+    /// Mean one-way latency over all peers (ms), 0 without samples
+    pub fn mean_one_way_ms(&self) -> u64 {
+        let measured: Vec<u64> = self
+            .peers
+            .values()
+            .filter(|p| p.samples > 0)
+            .map(|p| p.one_way_ms)
+            .collect();
+        (measured.iter().sum::<u64>())
+            .checked_div(measured.len() as u64)
+            .unwrap_or(0)
+    }
+}
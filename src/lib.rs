@@ -4,11 +4,16 @@
 //!
 //! ## Features
 //!
-//! - **Room Discovery**: Find available game rooms
+//! - **Room Discovery**: Find available game rooms (`discovery` feature, default)
 //! - **Presence Tracking**: Track multiple players in a room
 //! - **Start Modes**: Auto, Ready, Countdown, or Host-controlled
 //! - **Game State Sync**: Real-time state synchronization
-//! - **QR Code**: Generate QR codes for room sharing
+//! - **QR Code**: Generate QR codes for room sharing (`qr` feature, default)
+//! - **Embedded Relay**: Serve LAN matches from a native host (`relay` feature)
+//! - **Debug Viewer**: Timeline of room traffic with latencies (`debug-viewer` feature)
+//! - **Debug Console**: Terminal client showing relays, players and live events (`debug-console` feature)
+//! - **Metrics**: Export room and latency metrics via the `metrics` crate or Prometheus text (`metrics` feature)
+//! - **Notifications**: Alert unfocused players of invites, matches and turns (`desktop-notify` feature for native notifications)
 //!
 //! ## Example
 //!
@@ -39,7 +44,7 @@
 //!             ArenaEvent::PlayerJoin(player) => {
 //!                 println!("Player joined: {}", player.pubkey);
 //!             }
-//!             ArenaEvent::GameStart => {
+//!             ArenaEvent::GameStart { .. } => {
 //!                 println!("Game started!");
 //!             }
 //!             _ => {}
@@ -50,19 +55,93 @@
 //! }
 //! ```
 
+pub mod activity;
 pub mod arena;
+pub mod chat;
 pub mod client;
+pub mod clock;
+#[cfg(feature = "debug-viewer")]
+pub mod debug;
+#[cfg(all(feature = "debug-console", not(target_arch = "wasm32")))]
+pub mod debug_console;
 pub mod error;
+pub mod hooks;
+pub mod identity;
+pub mod invite;
+pub mod latency;
+pub mod lobby;
+pub mod manifest;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
+pub mod migrate;
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub mod network;
+pub mod notify;
+pub mod offload;
+pub mod party;
+pub mod pool;
+#[cfg(feature = "qr")]
 pub mod qr;
+pub mod record;
+#[cfg(all(feature = "relay", not(target_arch = "wasm32")))]
+pub mod relay;
+pub mod rng;
+pub mod seal;
+pub mod shadow;
+pub mod shard;
 pub mod spawn;
 pub mod time;
+pub mod turns;
 pub mod types;
 
 #[cfg(test)]
 mod tests;
 
-pub use arena::{Arena, ArenaEvent};
-pub use client::NostrClient;
+pub use activity::{Activity, ActivityStatus};
+pub use arena::{
+    Arena, ArenaEvent, BatchResult, EventClass, MessageBatch, SendResult, ShutdownReport,
+};
+pub use client::{NostrClient, RoomCopies};
+pub use clock::{ChessClock, TimeControl};
+#[cfg(feature = "debug-viewer")]
+pub use debug::{DebugViewer, TimelineEntry};
+#[cfg(all(feature = "debug-console", not(target_arch = "wasm32")))]
+pub use debug_console::DebugConsole;
 pub use error::{ArenaError, Result};
+pub use hooks::{Hooks, LifecycleHook, MatchEnd, MatchFinished, PlayerJoined, RoomCreated};
+pub use identity::IdentityLink;
+pub use invite::{Invite, parse_room_link, room_url};
+pub use latency::{
+    ArenaStats, LatencyHistogram, LatencyReport, PeerLatency, RelayClocks, RelaySkew,
+};
+pub use lobby::{LobbyPhase, LobbyPlayer, LobbyStateMachine, LobbyView};
+pub use manifest::{ProtocolManifest, TrafficEstimate, estimate_traffic, protocol_manifest};
+#[cfg(all(feature = "metrics", feature = "native", not(target_arch = "wasm32")))]
+pub use metrics::MetricsEndpoint;
+#[cfg(feature = "metrics")]
+pub use metrics::{ArenaMetrics, MetricKind, MetricSample, render_prometheus};
+pub use middleware::{EventMiddleware, Middleware};
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub use network::NetworkRoute;
+#[cfg(all(feature = "desktop-notify", not(target_arch = "wasm32")))]
+pub use notify::DesktopNotifier;
+pub use notify::{Notice, Notifier};
+#[cfg(feature = "wasm")]
+pub use offload::JsOffload;
+pub use offload::{Offload, OffloadSigner};
+pub use party::Party;
+pub use pool::RelayPool;
+#[cfg(feature = "qr")]
 pub use qr::{QrOptions, generate_qr_data_url, generate_qr_svg};
+pub use record::{
+    FrameKind, MatchRecord, MatchResult, PlayerOutcome, RecordFrame, RulesEngine, Verdict,
+    VerifiedResult, verify_replay,
+};
+#[cfg(all(feature = "relay", not(target_arch = "wasm32")))]
+pub use relay::EmbeddedRelay;
+pub use rng::{SeededRng, derive_seed, turn_order};
+pub use seal::{RoomKey, RoomSeal};
+pub use shadow::EventShadow;
+pub use turns::TurnManager;
 pub use types::*;
@@ -0,0 +1,195 @@
+//! Render-ready lobby view
+//!
+//! Every frontend needs the same picture of the lobby: who is in the room,
+//! who is ready, how long the countdown has left and what went wrong.
+//! [`LobbyStateMachine`] consumes the `ArenaEvent`s an app receives and keeps
+//! that picture; [`LobbyStateMachine::view`] returns it ready to render.
+//!
+//! Seed it with `Arena::players()` after creating or joining a room, then
+//! pass it every event from `recv()`. Our own ready toggles only arrive as
+//! events with `echo_self`; otherwise call [`LobbyStateMachine::set_ready`].
+
+use crate::arena::ArenaEvent;
+use crate::types::PlayerPresence;
+
+/// Error banners kept at most; older ones are dropped
+pub const MAX_BANNERS: usize = 3;
+
+/// Where the lobby is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LobbyPhase {
+    /// Waiting for players or for everyone to be ready
+    #[default]
+    Waiting,
+    /// The start countdown is running
+    Countdown,
+    /// The game started
+    Playing,
+    /// The match was abandoned or the room expired
+    Finished,
+}
+
+/// One row of the player list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LobbyPlayer {
+    pub pubkey: String,
+    pub ready: bool,
+    /// False after a heartbeat timeout until the player is heard from again
+    pub connected: bool,
+}
+
+/// Snapshot of the lobby for rendering
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LobbyView {
+    /// Players in join order
+    pub players: Vec<LobbyPlayer>,
+    pub ready_count: usize,
+    pub phase: LobbyPhase,
+    /// Seconds left while the countdown runs
+    pub countdown: Option<u32>,
+    /// One-line status, e.g. "2/3 ready"
+    pub status: String,
+    /// Errors to show until dismissed, oldest first
+    pub banners: Vec<String>,
+}
+
+/// Lobby state derived from `ArenaEvent`s
+#[derive(Debug, Clone, Default)]
+pub struct LobbyStateMachine {
+    players: Vec<LobbyPlayer>,
+    phase: LobbyPhase,
+    countdown: Option<u32>,
+    /// Why the lobby finished, if it did
+    ended: Option<&'static str>,
+    banners: Vec<String>,
+}
+
+impl LobbyStateMachine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the player list, e.g. with `Arena::players()` after joining
+    pub fn sync(&mut self, players: &[PlayerPresence]) {
+        let mut players: Vec<&PlayerPresence> = players.iter().collect();
+        players.sort_by_key(|p| p.joined_at);
+        self.players = players
+            .into_iter()
+            .map(|p| LobbyPlayer {
+                pubkey: p.pubkey.clone(),
+                ready: p.ready,
+                connected: true,
+            })
+            .collect();
+    }
+
+    /// Set a player's ready flag, e.g. ours after `send_ready()`
+    pub fn set_ready(&mut self, pubkey: &str, ready: bool) {
+        if let Some(player) = self.player_mut(pubkey) {
+            player.ready = ready;
+        }
+    }
+
+    /// Update the lobby with an event from the arena
+    pub fn apply<T>(&mut self, event: &ArenaEvent<T>) {
+        match event {
+            ArenaEvent::PlayerJoin(presence) => match self.player_mut(&presence.pubkey) {
+                Some(player) => player.connected = true,
+                None => self.players.push(LobbyPlayer {
+                    pubkey: presence.pubkey.clone(),
+                    ready: presence.ready,
+                    connected: true,
+                }),
+            },
+            ArenaEvent::PlayerLeave(pubkey) => self.players.retain(|p| &p.pubkey != pubkey),
+            ArenaEvent::PlayerDisconnect(pubkey) => {
+                if let Some(player) = self.player_mut(pubkey) {
+                    player.connected = false;
+                }
+            }
+            ArenaEvent::PlayerReady { pubkey, ready } => {
+                if let Some(player) = self.player_mut(pubkey) {
+                    player.ready = *ready;
+                    player.connected = true;
+                }
+            }
+            // Late ticks don't take a started game back to the countdown
+            ArenaEvent::CountdownStart(seconds) | ArenaEvent::CountdownTick(seconds)
+                if matches!(self.phase, LobbyPhase::Waiting | LobbyPhase::Countdown) =>
+            {
+                self.phase = LobbyPhase::Countdown;
+                self.countdown = Some(*seconds);
+            }
+            ArenaEvent::GameStart { .. } => {
+                self.phase = LobbyPhase::Playing;
+                self.countdown = None;
+            }
+            ArenaEvent::RematchStart(_) => {
+                self.phase = LobbyPhase::Waiting;
+                self.countdown = None;
+                self.ended = None;
+                for player in &mut self.players {
+                    player.ready = false;
+                }
+            }
+            ArenaEvent::MatchAbandoned => self.finish("Match abandoned"),
+            ArenaEvent::RoomExpired(_) => self.finish("Room expired"),
+            ArenaEvent::Error(reason) => self.banner(reason.to_string()),
+            _ => {}
+        }
+    }
+
+    /// Drop every error banner
+    pub fn dismiss_banners(&mut self) {
+        self.banners.clear();
+    }
+
+    pub fn phase(&self) -> LobbyPhase {
+        self.phase
+    }
+
+    /// The lobby as it should be rendered now
+    pub fn view(&self) -> LobbyView {
+        let ready_count = self.players.iter().filter(|p| p.ready).count();
+        let total = self.players.len();
+        let status = match self.phase {
+            LobbyPhase::Waiting if total > 0 && ready_count == total => {
+                "Everyone is ready".to_string()
+            }
+            LobbyPhase::Waiting => format!("{ready_count}/{total} ready"),
+            LobbyPhase::Countdown => match self.countdown {
+                Some(0) | None => "Starting".to_string(),
+                Some(seconds) => format!("Starting in {seconds}"),
+            },
+            LobbyPhase::Playing => "Playing".to_string(),
+            LobbyPhase::Finished => self.ended.unwrap_or("Finished").to_string(),
+        };
+
+        LobbyView {
+            players: self.players.clone(),
+            ready_count,
+            phase: self.phase,
+            countdown: self.countdown,
+            status,
+            banners: self.banners.clone(),
+        }
+    }
+
+    fn player_mut(&mut self, pubkey: &str) -> Option<&mut LobbyPlayer> {
+        self.players.iter_mut().find(|p| p.pubkey == pubkey)
+    }
+
+    fn finish(&mut self, reason: &'static str) {
+        self.phase = LobbyPhase::Finished;
+        self.countdown = None;
+        self.ended = Some(reason);
+        self.banner(reason.to_string());
+    }
+
+    fn banner(&mut self, message: String) {
+        self.banners.push(message);
+        if self.banners.len() > MAX_BANNERS {
+            self.banners.remove(0);
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! Machine-readable description of the wire protocol
+//!
+//! Lists the event kinds, tags and message types this crate publishes, with
+//! expected rates and payload sizes under the default configuration, so relay
+//! operators can whitelist and size-limit game traffic and bindings can
+//! document the protocol they speak.
+
+use crate::types::{ArenaConfig, PROTOCOL_VERSION, SyncMode, kinds};
+use serde::Serialize;
+
+/// Approximate size of a serialized player presence entry in room content
+const PLAYER_ENTRY_BYTES: usize = 140;
+
+/// Room size assumed for payload size estimates
+const MAX_PLAYERS: usize = 8;
+
+/// Everything this crate puts on the wire
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolManifest {
+    /// Protocol version carried in every payload's `version` field
+    pub version: u32,
+    pub kinds: Vec<KindManifest>,
+}
+
+/// One event kind
+#[derive(Debug, Clone, Serialize)]
+pub struct KindManifest {
+    pub kind: u16,
+    pub name: &'static str,
+    /// NIP-01 storage class: `replaceable`, `ephemeral` or `regular`
+    pub storage: &'static str,
+    pub description: &'static str,
+    pub tags: Vec<TagManifest>,
+    /// Message types carried in the content's `type` field (empty if the
+    /// content is not typed)
+    pub messages: Vec<MessageManifest>,
+    /// Expected events per minute from one publisher, if bounded
+    pub max_per_minute: Option<u32>,
+    /// Upper bound on the content size in bytes, if known
+    pub max_content_bytes: Option<usize>,
+}
+
+/// One tag an event kind may carry
+#[derive(Debug, Clone, Serialize)]
+pub struct TagManifest {
+    pub name: &'static str,
+    pub required: bool,
+    pub description: &'static str,
+}
+
+/// One message `type` sent as an ephemeral event
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageManifest {
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    pub description: &'static str,
+    /// Expected messages per minute from one player, if bounded
+    pub max_per_minute: Option<u32>,
+    /// Upper bound on the content size in bytes, if known (`None` for
+    /// game-defined payloads)
+    pub max_content_bytes: Option<usize>,
+}
+
+impl ProtocolManifest {
+    /// Serialize the manifest as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Look up a kind
+    pub fn kind(&self, kind: u16) -> Option<&KindManifest> {
+        self.kinds.iter().find(|k| k.kind == kind)
+    }
+}
+
+/// Describe the event kinds, tags, rates and payload sizes this crate
+/// produces with the default [`ArenaConfig`]
+pub fn protocol_manifest() -> ProtocolManifest {
+    let config = ArenaConfig::default();
+
+    ProtocolManifest {
+        version: PROTOCOL_VERSION,
+        kinds: vec![
+            KindManifest {
+                kind: kinds::ROOM,
+                name: "room",
+                storage: "replaceable",
+                description: "Room metadata for discovery and joining (NIP-78)",
+                tags: vec![
+                    tag("d", true, "Room identifier `{gameId}-{roomId}`"),
+                    tag(
+                        "t",
+                        false,
+                        "Game id, for discovery (omitted for unlisted rooms)",
+                    ),
+                    tag(
+                        "client",
+                        false,
+                        "NIP-89 app `{name}/{version}` (`client_name`)",
+                    ),
+                ],
+                messages: Vec::new(),
+                // Republished by the host every presence interval, plus on
+                // changes
+                max_per_minute: per_minute(config.presence_interval).map(|n| n + 2),
+                max_content_bytes: Some(512 + MAX_PLAYERS * PLAYER_ENTRY_BYTES),
+            },
+            KindManifest {
+                kind: kinds::MATCH_ARCHIVE,
+                name: "archive",
+                storage: "replaceable",
+                description: "Result and keyframes of a finished match (`archive_matches`)",
+                tags: vec![
+                    tag("d", true, "Match identifier `{gameId}-{roomId}-{seed}`"),
+                    tag("t", true, "Game id, for match history"),
+                    tag(
+                        "client",
+                        false,
+                        "NIP-89 app `{name}/{version}` (`client_name`)",
+                    ),
+                ],
+                messages: Vec::new(),
+                // One per game over
+                max_per_minute: None,
+                max_content_bytes: None,
+            },
+            KindManifest {
+                kind: kinds::ROOM_TEMPLATE,
+                name: "template",
+                storage: "replaceable",
+                description: "Room settings saved by a host (`save_template`)",
+                tags: vec![
+                    tag("d", true, "Template identifier `{gameId}-template-{id}`"),
+                    tag("t", true, "Game id"),
+                    tag(
+                        "client",
+                        false,
+                        "NIP-89 app `{name}/{version}` (`client_name`)",
+                    ),
+                ],
+                messages: Vec::new(),
+                max_per_minute: None,
+                max_content_bytes: None,
+            },
+            KindManifest {
+                kind: kinds::EPHEMERAL,
+                name: "game",
+                storage: "ephemeral",
+                description: "Game traffic between the players of a room",
+                tags: vec![
+                    tag(
+                        "d",
+                        false,
+                        "Room identifier `{gameId}-{roomId}`, on room traffic \
+                         (`{gameId}-{roomId}-host` for players of `Hosted` rooms, \
+                         `{gameId}-{roomId}-summary` for summaries)",
+                    ),
+                    tag("p", false, "Participants (`thread_tags`), or the invitee"),
+                    tag("e", false, "Room event (`thread_tags`)"),
+                    tag("t", false, "Game id, on `invite` and `summary` messages"),
+                    tag(
+                        "client",
+                        false,
+                        "App `{name}/{version}`, on `gameover` messages",
+                    ),
+                ],
+                messages: vec![
+                    message(
+                        "join",
+                        "Player asks to join; resent twice",
+                        Some(3),
+                        Some(128),
+                    ),
+                    message(
+                        "seat",
+                        "Host assigns or denies a seat",
+                        None,
+                        Some(256 + MAX_PLAYERS * 70),
+                    ),
+                    message(
+                        "heartbeat",
+                        "Presence keep-alive, echoing measured peer delays",
+                        per_minute(config.heartbeat_interval),
+                        Some(96 + MAX_PLAYERS * 90),
+                    ),
+                    message(
+                        "spectate",
+                        "Spectator keep-alive, counted by the host",
+                        per_minute(config.heartbeat_interval),
+                        Some(32),
+                    ),
+                    message(
+                        "state",
+                        "Game state, defined by the game",
+                        per_minute(config.state_throttle),
+                        None,
+                    ),
+                    message("ready", "Ready toggle", None, Some(64)),
+                    message("turn", "Turn passed to the next player", None, Some(128)),
+                    message(
+                        "phase",
+                        "Round and phase set by the host or a co-host",
+                        None,
+                        Some(128),
+                    ),
+                    message(
+                        "clock",
+                        "Host's chess clock after each turn, with time_control",
+                        None,
+                        Some(64 + config.max_players * 80),
+                    ),
+                    message(
+                        "countdown",
+                        "Host's countdown start time, once everyone is ready",
+                        None,
+                        Some(64),
+                    ),
+                    message(
+                        "gamestart",
+                        "Host or co-host starts the game",
+                        None,
+                        Some(64),
+                    ),
+                    message("gameover", "Player's game ended", None, Some(256)),
+                    message(
+                        "audit",
+                        "Player's input chain after game over, with input_audit",
+                        None,
+                        Some(160),
+                    ),
+                    message("rematch", "Rematch request or acceptance", None, Some(128)),
+                    message("claim", "Claim and the host's resolution", None, Some(160)),
+                    message(
+                        "gameended",
+                        "Host's decision on a match: finish order and winner",
+                        None,
+                        Some(256),
+                    ),
+                    message(
+                        "moderation",
+                        "Host chat rule: slow mode, mute or clear",
+                        None,
+                        Some(128),
+                    ),
+                    message(
+                        "kick",
+                        "Host removes a player after a vote",
+                        None,
+                        Some(128),
+                    ),
+                    message("leave", "Player leaving for good", None, Some(64)),
+                    message(
+                        "rekey",
+                        "Host's next room key after a leave or kick (encrypt_state)",
+                        None,
+                        Some(64 + config.max_players * 240),
+                    ),
+                    message(
+                        "resendrequest",
+                        "Ask the host to resend recent control messages",
+                        None,
+                        Some(64),
+                    ),
+                    message(
+                        "snapshotrequest",
+                        "Late joiner asking for the game state",
+                        None,
+                        Some(64),
+                    ),
+                    message(
+                        "snapshot",
+                        "Player states and turn for a late joiner",
+                        None,
+                        None,
+                    ),
+                    message("vote", "Lobby vote proposal, ballot or result", None, None),
+                    message(
+                        "message",
+                        "App-defined message on a named channel",
+                        None,
+                        None,
+                    ),
+                    message(
+                        "sealed",
+                        "State, digest or batch sealed with the room key (encrypt_state)",
+                        None,
+                        None,
+                    ),
+                    message(
+                        "summary",
+                        "Player's public summary for spectators and match browsers",
+                        per_minute(config.summary_throttle),
+                        None,
+                    ),
+                    message(
+                        "batch",
+                        "State, chat, channel messages and heartbeat of one tick in one event",
+                        None,
+                        None,
+                    ),
+                    message(
+                        "chat",
+                        "Chat message",
+                        per_window(config.chat_flood_limit, config.chat_flood_window),
+                        Some(64 + config.chat_max_length * 4),
+                    ),
+                    message(
+                        "invite",
+                        "Invitation to a room, or its answer",
+                        None,
+                        Some(512),
+                    ),
+                    message(
+                        "party",
+                        "Party presence and matchmaking on the party channel",
+                        per_minute(config.heartbeat_interval),
+                        Some(512),
+                    ),
+                    message(
+                        "digest",
+                        "Host's batch of player states and presence (`Hosted` rooms)",
+                        per_minute(config.digest_interval),
+                        None,
+                    ),
+                    message(
+                        "roster",
+                        "Player list request, or the host's answer, for large rooms",
+                        None,
+                        None,
+                    ),
+                    message(
+                        "room",
+                        "Room content pushed to peers when co-hosts change",
+                        None,
+                        Some(512 + MAX_PLAYERS * PLAYER_ENTRY_BYTES),
+                    ),
+                ],
+                max_per_minute: None,
+                max_content_bytes: None,
+            },
+            KindManifest {
+                kind: kinds::USER_STATUS,
+                name: "activity",
+                storage: "replaceable",
+                description: "Player's activity in a game (NIP-38 user status)",
+                tags: vec![
+                    tag("d", true, "`arena-{gameId}`"),
+                    tag("t", true, "Game id"),
+                    tag("status", true, "`lobby` or `playing`"),
+                    tag("room", false, "Room id"),
+                    tag("r", false, "Room URL"),
+                ],
+                messages: Vec::new(),
+                max_per_minute: None,
+                max_content_bytes: Some(256),
+            },
+            KindManifest {
+                kind: kinds::REPORT,
+                name: "report",
+                storage: "regular",
+                description: "Player report (NIP-56)",
+                tags: vec![
+                    tag("p", true, "Reported player and report type"),
+                    tag("a", false, "Room event coordinate"),
+                ],
+                messages: Vec::new(),
+                max_per_minute: None,
+                max_content_bytes: Some(0),
+            },
+        ],
+    }
+}
+
+/// Expected ephemeral traffic of one room, in events per minute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TrafficEstimate {
+    /// Events published by all peers together
+    pub published_per_minute: u32,
+    /// Events relays deliver to subscribers (the fan-out)
+    pub delivered_per_minute: u32,
+    /// Events published by the busiest peer, the figure relay rate limits
+    /// apply to
+    pub max_per_publisher: u32,
+}
+
+/// Estimate the heartbeat, state and digest traffic of a room with
+/// `players` peers, in the idle lobby or while `playing` (every player
+/// sending state at the throttle). Unthrottled state is not counted.
+pub fn estimate_traffic(config: &ArenaConfig, players: usize, playing: bool) -> TrafficEstimate {
+    let others = players.saturating_sub(1) as u32;
+    let heartbeat = per_minute(config.heartbeat_interval_for(!playing)).unwrap_or(0);
+    let state = if playing {
+        per_minute(config.state_throttle).unwrap_or(0)
+    } else {
+        0
+    };
+    let peer = heartbeat + state;
+
+    match config.sync_mode {
+        // Everyone hears everyone
+        SyncMode::Mesh => TrafficEstimate {
+            published_per_minute: peer * players as u32,
+            delivered_per_minute: peer * players as u32 * others,
+            max_per_publisher: peer,
+        },
+        // Players reach the host only; the host's messages reach everyone.
+        // Digests go out at the digest rate while states flow, otherwise
+        // once per heartbeat interval for presence.
+        SyncMode::Hosted => {
+            let digest = if playing {
+                per_minute(config.digest_interval).unwrap_or(0)
+            } else {
+                heartbeat
+            };
+            let host = peer + digest;
+            TrafficEstimate {
+                published_per_minute: host + peer * others,
+                delivered_per_minute: host * others + peer * others,
+                max_per_publisher: host.max(peer),
+            }
+        }
+    }
+}
+
+fn tag(name: &'static str, required: bool, description: &'static str) -> TagManifest {
+    TagManifest {
+        name,
+        required,
+        description,
+    }
+}
+
+fn message(
+    message_type: &'static str,
+    description: &'static str,
+    max_per_minute: Option<u32>,
+    max_content_bytes: Option<usize>,
+) -> MessageManifest {
+    MessageManifest {
+        message_type,
+        description,
+        max_per_minute,
+        max_content_bytes,
+    }
+}
+
+/// Messages per minute for a minimum interval in ms (`None` if unthrottled)
+fn per_minute(interval_ms: u64) -> Option<u32> {
+    (interval_ms > 0).then(|| (60_000 / interval_ms) as u32)
+}
+
+/// Messages per minute for `limit` messages per `window_ms`
+fn per_window(limit: u32, window_ms: u64) -> Option<u32> {
+    (limit > 0 && window_ms > 0).then(|| (limit as u64 * 60_000 / window_ms) as u32)
+}
@@ -0,0 +1,251 @@
+//! Monitoring export
+//!
+//! Long-running hosts (bots, tournament servers running many arenas on a
+//! [`RelayPool`]) can take an [`ArenaMetrics`] snapshot from
+//! `Arena::metrics()` now and then and export it in two ways:
+//!
+//! - [`ArenaMetrics::record`] sets gauges and counters through the
+//!   [`metrics`](https://docs.rs/metrics) facade, for whichever recorder the
+//!   app installed (Prometheus, StatsD, ...).
+//! - [`render_prometheus`] formats snapshots in the Prometheus text format,
+//!   which [`MetricsEndpoint`] serves over plain HTTP on native hosts.
+//!
+//! ```rust,ignore
+//! let endpoint = MetricsEndpoint::bind("0.0.0.0:9464").await?;
+//! loop {
+//!     let snapshots = vec![arena_a.metrics().await, arena_b.metrics().await];
+//!     endpoint.set(render_prometheus(&snapshots));
+//!     tokio::time::sleep(Duration::from_secs(15)).await;
+//! }
+//! ```
+//!
+//! Every sample is labeled with `game` and `room` (empty outside a room).
+//!
+//! [`RelayPool`]: crate::pool::RelayPool
+
+use std::fmt::Write;
+
+/// How a sample behaves over time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Gauge,
+    /// Only ever grows while the arena stays in the same room
+    Counter,
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gauge => "gauge",
+            Self::Counter => "counter",
+        }
+    }
+}
+
+/// One value of one metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSample {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+    pub labels: Vec<(&'static str, String)>,
+    pub value: f64,
+}
+
+/// Point-in-time metrics of one arena, from `Arena::metrics()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArenaMetrics {
+    pub samples: Vec<MetricSample>,
+}
+
+impl ArenaMetrics {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        kind: MetricKind,
+        labels: Vec<(&'static str, String)>,
+        value: f64,
+    ) {
+        self.samples.push(MetricSample {
+            name,
+            help,
+            kind,
+            labels,
+            value,
+        });
+    }
+
+    /// Samples of the metric `name`
+    pub fn get(&self, name: &str) -> impl Iterator<Item = &MetricSample> {
+        self.samples.iter().filter(move |s| s.name == name)
+    }
+
+    /// Hand every sample to the installed `metrics` recorder
+    pub fn record(&self) {
+        for sample in &self.samples {
+            let labels: Vec<metrics::Label> = sample
+                .labels
+                .iter()
+                .map(|(k, v)| metrics::Label::new(*k, v.clone()))
+                .collect();
+            match sample.kind {
+                MetricKind::Gauge => metrics::gauge!(sample.name, labels).set(sample.value),
+                MetricKind::Counter => {
+                    metrics::counter!(sample.name, labels).absolute(sample.value as u64)
+                }
+            }
+        }
+    }
+
+    /// This snapshot in the Prometheus text format
+    pub fn to_prometheus(&self) -> String {
+        render_prometheus(std::slice::from_ref(self))
+    }
+}
+
+/// Snapshots of several arenas in the Prometheus text format, each metric
+/// described once
+pub fn render_prometheus(snapshots: &[ArenaMetrics]) -> String {
+    let mut names: Vec<&MetricSample> = Vec::new();
+    for sample in snapshots.iter().flat_map(|m| &m.samples) {
+        if !names.iter().any(|s| s.name == sample.name) {
+            names.push(sample);
+        }
+    }
+
+    let mut out = String::new();
+    for first in names {
+        let _ = writeln!(out, "# HELP {} {}", first.name, first.help);
+        let _ = writeln!(out, "# TYPE {} {}", first.name, first.kind.as_str());
+        for sample in snapshots
+            .iter()
+            .flat_map(|m| &m.samples)
+            .filter(|s| s.name == first.name)
+        {
+            out.push_str(sample.name);
+            if !sample.labels.is_empty() {
+                let labels: Vec<String> = sample
+                    .labels
+                    .iter()
+                    .map(|(k, v)| format!("{k}=\"{}\"", escape_label(v)))
+                    .collect();
+                let _ = write!(out, "{{{}}}", labels.join(","));
+            }
+            let _ = writeln!(out, " {}", sample.value);
+        }
+    }
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub use endpoint::MetricsEndpoint;
+
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+mod endpoint {
+    use crate::error::{ArenaError, Result};
+    use std::net::SocketAddr;
+    use std::sync::{Arc, RwLock};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+    use tokio::sync::watch;
+    use tracing::{debug, warn};
+
+    /// Minimal HTTP server answering every request with the latest text
+    /// given to [`MetricsEndpoint::set`]
+    pub struct MetricsEndpoint {
+        local_addr: SocketAddr,
+        body: Arc<RwLock<String>>,
+        shutdown: watch::Sender<bool>,
+    }
+
+    impl MetricsEndpoint {
+        /// Bind a listener and start serving in the background
+        pub async fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+            let listener = TcpListener::bind(addr)
+                .await
+                .map_err(|e| ArenaError::Io(e.to_string()))?;
+            let local_addr = listener
+                .local_addr()
+                .map_err(|e| ArenaError::Io(e.to_string()))?;
+
+            let body = Arc::new(RwLock::new(String::new()));
+            let (shutdown, shutdown_rx) = watch::channel(false);
+            tokio::spawn(accept_loop(listener, body.clone(), shutdown_rx));
+
+            debug!("Metrics endpoint listening on {}", local_addr);
+            Ok(Self {
+                local_addr,
+                body,
+                shutdown,
+            })
+        }
+
+        /// Address the endpoint is bound to
+        pub fn local_addr(&self) -> SocketAddr {
+            self.local_addr
+        }
+
+        /// Replace the text served to scrapers
+        pub fn set(&self, text: String) {
+            if let Ok(mut body) = self.body.write() {
+                *body = text;
+            }
+        }
+
+        /// Stop accepting connections
+        pub fn shutdown(&self) {
+            let _ = self.shutdown.send(true);
+        }
+    }
+
+    impl Drop for MetricsEndpoint {
+        fn drop(&mut self) {
+            self.shutdown();
+        }
+    }
+
+    async fn accept_loop(
+        listener: TcpListener,
+        body: Arc<RwLock<String>>,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        let text = body.read().map(|b| b.clone()).unwrap_or_default();
+                        tokio::spawn(serve_scrape(stream, text));
+                    }
+                    Err(e) => warn!("Metrics accept failed: {}", e),
+                },
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+
+    async fn serve_scrape(mut stream: TcpStream, text: String) {
+        // The request itself does not matter; read what arrived and answer
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request).await;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            text.len(),
+            text
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+}
@@ -0,0 +1,90 @@
+//! Hooks on room traffic
+//!
+//! An [`EventMiddleware`] sees every message an arena publishes to its room
+//! and every message a peer publishes there, as the JSON content of the
+//! ephemeral event. It can pass the message on, change it (compression,
+//! encryption) or drop it (anti-cheat filters), or just look (logging,
+//! metrics). Add layers with `ArenaConfig::with_middleware()`.
+//!
+//! Layers wrap each other like an onion: outgoing messages go through them
+//! in the order they were added, incoming ones in reverse order, so the
+//! last layer added is the one closest to the wire. Room events (kind
+//! 30078), inbox and party messages, and our own messages echoed back by
+//! relays do not go through the hooks.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+/// A layer that observes, transforms or drops room messages
+pub trait EventMiddleware: Send + Sync {
+    /// A message we are about to publish; `None` drops it
+    fn outbound(&self, content: String) -> Option<String> {
+        Some(content)
+    }
+
+    /// A message from `pubkey` (hex) before the arena handles it; `None`
+    /// drops it
+    fn inbound(&self, pubkey: &str, content: String) -> Option<String> {
+        let _ = pubkey;
+        Some(content)
+    }
+}
+
+/// The layers of an arena, in the order they were added
+#[derive(Clone, Default)]
+pub struct Middleware {
+    layers: Vec<Arc<dyn EventMiddleware>>,
+}
+
+impl fmt::Debug for Middleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Middleware")
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+impl Middleware {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a layer closest to the wire
+    pub fn push(&mut self, layer: Box<dyn EventMiddleware>) {
+        self.layers.push(Arc::from(layer));
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Run an outgoing message through every layer; `None` if one dropped it
+    pub fn outbound<'a>(&self, content: &'a str) -> Option<Cow<'a, str>> {
+        if self.layers.is_empty() {
+            return Some(Cow::Borrowed(content));
+        }
+        let mut content = content.to_string();
+        for layer in &self.layers {
+            content = layer.outbound(content)?;
+        }
+        Some(Cow::Owned(content))
+    }
+
+    /// Run an incoming message through every layer, last added first;
+    /// `None` if one dropped it
+    pub fn inbound<'a>(&self, pubkey: &str, content: &'a str) -> Option<Cow<'a, str>> {
+        if self.layers.is_empty() {
+            return Some(Cow::Borrowed(content));
+        }
+        let mut content = content.to_string();
+        for layer in self.layers.iter().rev() {
+            content = layer.inbound(pubkey, content)?;
+        }
+        Some(Cow::Owned(content))
+    }
+}
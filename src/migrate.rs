@@ -0,0 +1,203 @@
+//! Wire format versioning and migration shims
+//!
+//! Every payload published by the library carries a `version` field. When a
+//! payload from an older peer is received, it is upgraded step by step to
+//! the layout of [`PROTOCOL_VERSION`] before being deserialized, so that
+//! layout changes do not silently break matches between different app
+//! versions. The `version` itself is kept, so callers can tell what the
+//! sender speaks.
+//!
+//! Content comes from anyone who can publish to a relay, so decoding never
+//! panics: oversized or malformed payloads (deep nesting, out-of-range
+//! numbers, wrong `type` values) come back as `InvalidEventData` or
+//! `InvalidRoomData`.
+
+use crate::error::{ArenaError, Result};
+use crate::types::{EventContent, PROTOCOL_VERSION, RoomEventContent};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Map, Value, value::RawValue};
+use std::borrow::Cow;
+
+/// Largest content decoded, in bytes; relays rarely accept bigger events
+pub const MAX_CONTENT_BYTES: usize = 256 * 1024;
+
+/// Upgrade step from version `n` to `n + 1`, indexed by `n`
+type Migration = fn(&mut Map<String, Value>);
+
+/// Room content migrations
+const ROOM_MIGRATIONS: &[Migration] = &[unchanged, unchanged, room_v2_to_v3];
+
+/// Ephemeral content migrations
+const EVENT_MIGRATIONS: &[Migration] = &[unchanged, unchanged, unchanged];
+
+/// Serialize ephemeral content with the current protocol version
+pub fn encode_event(content: &EventContent) -> Result<String> {
+    let mut value = match content {
+        EventContent::Unknown {
+            message_type,
+            payload,
+        } => {
+            let mut map = match payload {
+                Value::Object(map) => map.clone(),
+                Value::Null => Map::new(),
+                other => Map::from_iter([("payload".to_string(), other.clone())]),
+            };
+            map.insert("type".to_string(), Value::from(message_type.as_str()));
+            Value::Object(map)
+        }
+        _ => serde_json::to_value(content)?,
+    };
+    if let Value::Object(map) = &mut value {
+        map.insert("version".to_string(), Value::from(PROTOCOL_VERSION));
+    }
+    Ok(serde_json::to_string(&value)?)
+}
+
+/// Deserialize ephemeral content, upgrading older layouts.
+///
+/// Content with an unrecognized `type` is returned as
+/// [`EventContent::Unknown`] instead of being rejected.
+pub fn decode_event(json: &str) -> Result<EventContent> {
+    check_size(json).map_err(ArenaError::InvalidEventData)?;
+    let value = serde_json::from_str(json).map_err(invalid_event)?;
+    let mut value = upgrade(value, EVENT_MIGRATIONS);
+    let message_type = match value.get("type").and_then(Value::as_str) {
+        Some(t) if !EventContent::TYPES.contains(&t) => t.to_string(),
+        // Known types (and content without one) must decode
+        _ => return serde_json::from_value(value).map_err(invalid_event),
+    };
+    if let Value::Object(map) = &mut value {
+        map.remove("type");
+        map.remove("version");
+    }
+    Ok(EventContent::Unknown {
+        message_type,
+        payload: value,
+    })
+}
+
+/// Envelope of any message, for its `type` alone
+#[derive(Deserialize)]
+struct TypeEnvelope<'a> {
+    #[serde(rename = "type", borrow)]
+    message_type: Cow<'a, str>,
+}
+
+/// The `type` of a message, without decoding the rest
+pub fn message_type(json: &str) -> Option<Cow<'_, str>> {
+    let envelope: TypeEnvelope = serde_json::from_str(json).ok()?;
+    Some(envelope.message_type)
+}
+
+/// Envelope of any message, for its `version` alone
+#[derive(Deserialize)]
+struct VersionEnvelope {
+    #[serde(default)]
+    version: u64,
+}
+
+/// The `version` of a message (missing = 0), without decoding the rest
+pub fn message_version(json: &str) -> u32 {
+    serde_json::from_str::<VersionEnvelope>(json).map_or(0, |envelope| {
+        u32::try_from(envelope.version).unwrap_or(u32::MAX)
+    })
+}
+
+/// Envelope of a `state` message with the game state left unparsed
+#[derive(Deserialize)]
+struct StateEnvelope<'a> {
+    #[serde(rename = "type", borrow)]
+    message_type: Cow<'a, str>,
+    #[serde(borrow)]
+    game_state: Option<&'a RawValue>,
+}
+
+/// Decode a `state` message straight into `T`.
+///
+/// This is the hot path for game traffic: the game state is parsed directly
+/// from the event content without going through [`serde_json::Value`].
+/// Returns `None` for any other message type, which should then go through
+/// [`decode_event`]. The `state` layout has not changed between protocol
+/// versions, so no migration is needed here.
+pub fn decode_state<T: DeserializeOwned>(json: &str) -> Option<Result<T>> {
+    if let Err(e) = check_size(json) {
+        return Some(Err(ArenaError::InvalidEventData(e)));
+    }
+    let envelope: StateEnvelope = serde_json::from_str(json).ok()?;
+    if envelope.message_type != "state" {
+        return None;
+    }
+    let game_state = envelope.game_state?;
+    Some(serde_json::from_str(game_state.get()).map_err(invalid_event))
+}
+
+/// Serialize room content with the current protocol version
+pub fn encode_room(content: &RoomEventContent) -> Result<String> {
+    let mut content = content.clone();
+    content.version = PROTOCOL_VERSION;
+    Ok(serde_json::to_string(&content)?)
+}
+
+/// Deserialize room content, upgrading older layouts
+pub fn decode_room(json: &str) -> Result<RoomEventContent> {
+    check_size(json).map_err(ArenaError::InvalidRoomData)?;
+    let value =
+        serde_json::from_str(json).map_err(|e| ArenaError::InvalidRoomData(e.to_string()))?;
+    let value = upgrade(value, ROOM_MIGRATIONS);
+    serde_json::from_value(value).map_err(|e| ArenaError::InvalidRoomData(e.to_string()))
+}
+
+/// Read the version of a raw payload (missing = 0)
+pub fn payload_version(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map(|v| u32::try_from(v).unwrap_or(u32::MAX))
+        .unwrap_or(0)
+}
+
+fn check_size(json: &str) -> std::result::Result<(), String> {
+    if json.len() > MAX_CONTENT_BYTES {
+        return Err(format!(
+            "{} bytes, more than {MAX_CONTENT_BYTES}",
+            json.len()
+        ));
+    }
+    Ok(())
+}
+
+fn invalid_event(e: serde_json::Error) -> ArenaError {
+    ArenaError::InvalidEventData(e.to_string())
+}
+
+/// Apply migrations until the payload has the current layout, keeping its
+/// `version`.
+///
+/// Payloads from newer peers are passed through untouched and parsed on a
+/// best-effort basis, since unknown fields are ignored by serde.
+fn upgrade(mut value: Value, migrations: &[Migration]) -> Value {
+    let from = payload_version(&value);
+    let Value::Object(map) = &mut value else {
+        return value;
+    };
+
+    for migration in migrations.iter().skip(from as usize) {
+        migration(map);
+    }
+    value
+}
+
+/// Version bump without layout changes: v0 -> v1 started stamping
+/// `version`, v1 -> v2 added the `seat` message, v2 -> v3 new messages only
+fn unchanged(_map: &mut Map<String, Value>) {}
+
+/// v2 rooms published a `password_hash`; v3 rooms only say a password is
+/// needed
+fn room_v2_to_v3(map: &mut Map<String, Value>) {
+    if map
+        .remove("password_hash")
+        .is_some_and(|hash| hash.is_string())
+    {
+        map.insert("password".to_string(), Value::Bool(true));
+    }
+}
@@ -0,0 +1,46 @@
+//! Network change detection on native targets
+//!
+//! When a phone moves from Wi-Fi to cellular, relay websockets stay open on
+//! the dead interface until they time out, which takes 30 seconds or more.
+//! [`NetworkRoute`] notices the change much sooner: it records the local
+//! address the OS would send from to reach the internet, found by
+//! connecting a UDP socket (which sends nothing), and the arena compares it
+//! every `network_watch_interval`.
+
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+
+/// Public resolvers whose routes stand for "the internet"; never contacted
+const PROBE_V4: &str = "1.1.1.1:53";
+const PROBE_V6: &str = "[2606:4700:4700::1111]:53";
+
+/// Local addresses of the current IPv4 and IPv6 default routes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkRoute {
+    pub v4: Option<IpAddr>,
+    pub v6: Option<IpAddr>,
+}
+
+impl NetworkRoute {
+    /// Look up the current routes; `None` where there is no route
+    pub fn current() -> Self {
+        Self {
+            v4: local_addr("0.0.0.0:0", PROBE_V4),
+            v6: local_addr("[::]:0", PROBE_V6),
+        }
+    }
+
+    /// Whether any route is up
+    pub fn is_online(&self) -> bool {
+        self.v4.is_some() || self.v6.is_some()
+    }
+}
+
+fn local_addr(bind: &str, probe: &str) -> Option<IpAddr> {
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(probe).ok()?;
+    socket
+        .local_addr()
+        .ok()
+        .map(|addr: SocketAddr| addr.ip())
+        .filter(|ip| !ip.is_unspecified())
+}
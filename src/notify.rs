@@ -0,0 +1,80 @@
+//! Player notifications
+//!
+//! Turn-based and asynchronous games are often in the background when
+//! something needs the player: an invitation, a match that starts, their
+//! turn. An `Arena` with a [`Notifier`] raises a [`Notice`] for these events
+//! while the app reports itself unfocused (`Arena::set_focused(false)`).
+//!
+//! Notices are raised as the app receives the event from `recv()` or
+//! `try_recv()`. With the `desktop-notify` feature, [`DesktopNotifier`]
+//! shows them as native desktop notifications.
+
+use crate::types::short_key;
+
+/// Something that needs the player's attention
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notice {
+    /// A player invited us to their room
+    Invite { from: String, room_id: String },
+    /// Our game started, or our party was seated in a room
+    MatchFound { room_id: String },
+    /// The turn passed to us
+    TurnStart { room_id: String },
+}
+
+impl Notice {
+    /// Short title, e.g. for a notification's summary
+    pub fn title(&self) -> &'static str {
+        match self {
+            Notice::Invite { .. } => "Game invitation",
+            Notice::MatchFound { .. } => "Match found",
+            Notice::TurnStart { .. } => "Your turn",
+        }
+    }
+
+    /// One-line description
+    pub fn body(&self) -> String {
+        match self {
+            Notice::Invite { from, room_id } => {
+                format!("{} invited you to room {}", short_key(from), room_id)
+            }
+            Notice::MatchFound { room_id } => format!("Room {room_id} is starting"),
+            Notice::TurnStart { room_id } => format!("It's your move in room {room_id}"),
+        }
+    }
+}
+
+/// Shows notices to the player
+pub trait Notifier: Send + Sync {
+    fn notify(&self, notice: &Notice);
+}
+
+/// Notifier showing native desktop notifications
+#[cfg(all(feature = "desktop-notify", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone)]
+pub struct DesktopNotifier {
+    app_name: String,
+}
+
+#[cfg(all(feature = "desktop-notify", not(target_arch = "wasm32")))]
+impl DesktopNotifier {
+    pub fn new(app_name: impl Into<String>) -> Self {
+        Self {
+            app_name: app_name.into(),
+        }
+    }
+}
+
+#[cfg(all(feature = "desktop-notify", not(target_arch = "wasm32")))]
+impl Notifier for DesktopNotifier {
+    fn notify(&self, notice: &Notice) {
+        if let Err(e) = notify_rust::Notification::new()
+            .appname(&self.app_name)
+            .summary(notice.title())
+            .body(&notice.body())
+            .show()
+        {
+            tracing::warn!("Failed to show notification: {}", e);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Signing and encryption off the caller's thread
+//!
+//! In the browser the arena runs on the main thread, where signing every
+//! state message (with the canonical event serialization and hashing behind
+//! the id) and NIP-44 cause frame hitches at 10-20 Hz state rates. An
+//! [`OffloadSigner`] hands that work to another thread, typically a web
+//! worker running the same wasm module, through an [`Offload`] transport.
+//! The keys stay on the other side, which answers with [`handle_job`]:
+//!
+//! ```rust,ignore
+//! // Worker: answer every request buffer with the player's keys
+//! let reply = nostr_arena::offload::handle_job(&keys, &request).await;
+//!
+//! // Main thread (`wasm` feature): `post` transfers the buffer to the
+//! // worker and resolves to its reply
+//! let signer = OffloadSigner::new(JsOffload::new(post)).await?;
+//! let arena = Arena::<State>::with_signer(config, signer).await?;
+//! ```
+//!
+//! Requests and replies are JSON in byte buffers, so a binding can move
+//! them to the worker as transferables without copying. The game state is
+//! still encoded to JSON on the caller's thread, where `T` lives, and room
+//! seals (`RoomKey`) are applied there too.
+
+use crate::error::{ArenaError, Result};
+use async_trait::async_trait;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Reply of an [`Offload`] transport
+#[cfg(not(target_arch = "wasm32"))]
+pub type OffloadFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>>;
+
+/// Reply of an [`Offload`] transport
+#[cfg(target_arch = "wasm32")]
+pub type OffloadFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<u8>>> + 'a>>;
+
+/// Transport to the thread holding the keys
+pub trait Offload: Send + Sync {
+    /// Send a request buffer and wait for the reply buffer
+    fn run(&self, request: Vec<u8>) -> OffloadFuture<'_>;
+}
+
+/// Work an [`OffloadSigner`] asks for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum OffloadJob {
+    PublicKey,
+    Sign {
+        event: UnsignedEvent,
+    },
+    Nip04Encrypt {
+        public_key: PublicKey,
+        content: String,
+    },
+    Nip04Decrypt {
+        public_key: PublicKey,
+        content: String,
+    },
+    Nip44Encrypt {
+        public_key: PublicKey,
+        content: String,
+    },
+    Nip44Decrypt {
+        public_key: PublicKey,
+        content: String,
+    },
+}
+
+/// Answer to an [`OffloadJob`]: the public key (hex), the signed event
+/// (JSON) or the encrypted or decrypted text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffloadReply {
+    Ok(String),
+    Error(String),
+}
+
+/// Answer an [`OffloadSigner`] request with `keys` (the worker side)
+pub async fn handle_job(keys: &Keys, request: &[u8]) -> Vec<u8> {
+    let reply = match serde_json::from_slice::<OffloadJob>(request) {
+        Ok(job) => match run_job(keys, job).await {
+            Ok(value) => OffloadReply::Ok(value),
+            Err(e) => OffloadReply::Error(e.to_string()),
+        },
+        Err(e) => OffloadReply::Error(format!("bad request: {e}")),
+    };
+    serde_json::to_vec(&reply).unwrap_or_default()
+}
+
+async fn run_job(keys: &Keys, job: OffloadJob) -> std::result::Result<String, SignerError> {
+    match job {
+        OffloadJob::PublicKey => Ok(keys.public_key().to_hex()),
+        OffloadJob::Sign { event } => Ok(keys.sign_event(event).await?.as_json()),
+        OffloadJob::Nip04Encrypt {
+            public_key,
+            content,
+        } => keys.nip04_encrypt(&public_key, &content).await,
+        OffloadJob::Nip04Decrypt {
+            public_key,
+            content,
+        } => keys.nip04_decrypt(&public_key, &content).await,
+        OffloadJob::Nip44Encrypt {
+            public_key,
+            content,
+        } => keys.nip44_encrypt(&public_key, &content).await,
+        OffloadJob::Nip44Decrypt {
+            public_key,
+            content,
+        } => keys.nip44_decrypt(&public_key, &content).await,
+    }
+}
+
+/// `NostrSigner` that signs and encrypts through an [`Offload`] transport
+#[derive(Clone)]
+pub struct OffloadSigner {
+    public_key: PublicKey,
+    offload: Arc<dyn Offload>,
+}
+
+impl fmt::Debug for OffloadSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OffloadSigner")
+            .field("public_key", &self.public_key)
+            .finish()
+    }
+}
+
+impl OffloadSigner {
+    /// Ask the other side for its public key and sign through it
+    pub async fn new(offload: impl Offload + 'static) -> Result<Self> {
+        let offload: Arc<dyn Offload> = Arc::new(offload);
+        let hex = call(offload.as_ref(), OffloadJob::PublicKey).await?;
+        let public_key = PublicKey::from_hex(&hex).map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        Ok(Self {
+            public_key,
+            offload,
+        })
+    }
+
+    async fn call(&self, job: OffloadJob) -> std::result::Result<String, SignerError> {
+        call(self.offload.as_ref(), job)
+            .await
+            .map_err(SignerError::backend)
+    }
+}
+
+async fn call(offload: &dyn Offload, job: OffloadJob) -> Result<String> {
+    let reply = offload.run(serde_json::to_vec(&job)?).await?;
+    match serde_json::from_slice(&reply)? {
+        OffloadReply::Ok(value) => Ok(value),
+        OffloadReply::Error(e) => Err(ArenaError::Nostr(e)),
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl NostrSigner for OffloadSigner {
+    fn backend(&self) -> SignerBackend<'_> {
+        SignerBackend::Custom(Cow::Borrowed("offload"))
+    }
+
+    async fn get_public_key(&self) -> std::result::Result<PublicKey, SignerError> {
+        Ok(self.public_key)
+    }
+
+    async fn sign_event(&self, event: UnsignedEvent) -> std::result::Result<Event, SignerError> {
+        let json = self.call(OffloadJob::Sign { event }).await?;
+        let event = Event::from_json(json).map_err(SignerError::backend)?;
+        // Don't trust the other side with what we publish
+        if event.pubkey != self.public_key {
+            return Err(SignerError::backend(ArenaError::Nostr(
+                "offload signed with another key".to_string(),
+            )));
+        }
+        event.verify().map_err(SignerError::backend)?;
+        Ok(event)
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> std::result::Result<String, SignerError> {
+        self.call(OffloadJob::Nip04Encrypt {
+            public_key: *public_key,
+            content: content.to_string(),
+        })
+        .await
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> std::result::Result<String, SignerError> {
+        self.call(OffloadJob::Nip04Decrypt {
+            public_key: *public_key,
+            content: content.to_string(),
+        })
+        .await
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> std::result::Result<String, SignerError> {
+        self.call(OffloadJob::Nip44Encrypt {
+            public_key: *public_key,
+            content: content.to_string(),
+        })
+        .await
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> std::result::Result<String, SignerError> {
+        self.call(OffloadJob::Nip44Decrypt {
+            public_key: *public_key,
+            content: content.to_string(),
+        })
+        .await
+    }
+}
+
+/// [`Offload`] over a JS function that takes the request as a `Uint8Array`
+/// and returns the reply as one (or a promise of it), e.g. one that posts
+/// the buffer to a web worker with `postMessage(bytes, [bytes.buffer])`
+#[cfg(feature = "wasm")]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub struct JsOffload(js_sys::Function);
+
+#[cfg(feature = "wasm")]
+impl JsOffload {
+    pub fn new(post: js_sys::Function) -> Self {
+        Self(post)
+    }
+}
+
+// wasm32 without threads: the function never leaves the main thread
+#[cfg(feature = "wasm")]
+unsafe impl Send for JsOffload {}
+#[cfg(feature = "wasm")]
+unsafe impl Sync for JsOffload {}
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+impl Offload for JsOffload {
+    fn run(&self, request: Vec<u8>) -> OffloadFuture<'_> {
+        Box::pin(post_js(&self.0, request))
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+async fn post_js(post: &js_sys::Function, request: Vec<u8>) -> Result<Vec<u8>> {
+    use js_sys::{Promise, Uint8Array};
+    use wasm_bindgen_futures::JsFuture;
+    use wasm_bindgen_futures::wasm_bindgen::JsValue;
+
+    let js_error = |e: JsValue| ArenaError::Nostr(format!("offload: {e:?}"));
+    let request = Uint8Array::from(request.as_slice());
+    let reply = post.call1(&JsValue::NULL, &request).map_err(js_error)?;
+    let reply = JsFuture::from(Promise::resolve(&reply))
+        .await
+        .map_err(js_error)?;
+    Ok(Uint8Array::new(&reply).to_vec())
+}
@@ -0,0 +1,332 @@
+//! Party - a pre-made group that matches into rooms together
+//!
+//! Party members talk over their own ephemeral channel (d tag
+//! `{gameId}-party-{partyId}`): everyone sends presence at the heartbeat
+//! interval, and the leader tells members which room to join. A match only
+//! counts once every member reports a seat; otherwise the leader aborts and
+//! everyone leaves the room again.
+
+use crate::arena::{Arena, ArenaEvent};
+#[cfg(feature = "discovery")]
+use crate::error::ArenaError;
+use crate::error::Result;
+use crate::migrate::{decode_event, encode_event};
+use crate::shard::PlayerMap;
+use crate::spawn::spawn;
+#[cfg(feature = "discovery")]
+use crate::time::sleep;
+use crate::time::{Duration, interval, monotonic_ms};
+use crate::types::*;
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{RwLock, mpsc};
+#[cfg(feature = "discovery")]
+use tracing::info;
+use tracing::warn;
+
+/// Rooms considered by `quick_match` before creating a new one
+#[cfg(feature = "discovery")]
+const QUICK_MATCH_CANDIDATES: usize = 20;
+
+/// A group of players that joins matches together
+#[derive(Clone)]
+pub struct Party<T> {
+    arena: Arena<T>,
+    party_id: String,
+    leader: String,
+    /// Members by public key, with the time they were last seen
+    members: Arc<PlayerMap<u64>>,
+    /// Members' answers to the current match, `true` = seated
+    answers: Arc<RwLock<HashMap<String, bool>>>,
+    /// Rooms the leader gave up on; members still joining leave them again
+    aborted: Arc<RwLock<HashSet<String>>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl<T> Party<T>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    /// Start a new party led by this player
+    pub async fn create(arena: &Arena<T>) -> Result<Self> {
+        let leader = arena.public_key();
+        Self::start(arena, &generate_room_id(), &leader).await
+    }
+
+    /// Join `leader`'s party
+    pub async fn join(arena: &Arena<T>, leader: &str, party_id: &str) -> Result<Self> {
+        Self::start(arena, party_id, leader).await
+    }
+
+    async fn start(arena: &Arena<T>, party_id: &str, leader: &str) -> Result<Self> {
+        let client = arena.client();
+        if !client.is_connected().await {
+            client.connect().await?;
+        }
+
+        let party = Self {
+            arena: arena.clone(),
+            party_id: party_id.to_string(),
+            leader: leader.to_string(),
+            members: Arc::new(PlayerMap::new()),
+            answers: Arc::new(RwLock::new(HashMap::new())),
+            aborted: Arc::new(RwLock::new(HashSet::new())),
+            closed: Arc::new(AtomicBool::new(false)),
+        };
+        party.members.insert(arena.public_key(), monotonic_ms());
+
+        let (tx, mut rx) = mpsc::channel(64);
+        client
+            .watch_room(&party.tag(), move |_, event| {
+                let _ = tx.try_send(event);
+                !tx.is_closed()
+            })
+            .await?;
+
+        let handler = party.clone();
+        spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if handler.closed.load(Ordering::SeqCst) {
+                    break;
+                }
+                handler.handle(event).await;
+            }
+        });
+
+        party.start_presence().await;
+        Ok(party)
+    }
+
+    pub fn party_id(&self) -> &str {
+        &self.party_id
+    }
+
+    pub fn leader(&self) -> &str {
+        &self.leader
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader == self.arena.public_key()
+    }
+
+    /// Members seen within the disconnect threshold, leader included
+    pub fn members(&self) -> Vec<String> {
+        let now = monotonic_ms();
+        let threshold = self.arena.config().disconnect_threshold;
+        self.members
+            .to_map()
+            .into_iter()
+            .filter(|(_, last_seen)| now.saturating_sub(*last_seen) <= threshold)
+            .map(|(pubkey, _)| pubkey)
+            .collect()
+    }
+
+    /// Leave the party (does not leave a room already joined)
+    pub async fn leave(&self) -> Result<()> {
+        self.closed.store(true, Ordering::SeqCst);
+        self.publish(PartyAction::Leave, None, Vec::new()).await
+    }
+
+    /// Find a waiting room with a free seat for every member, or create one,
+    /// and move the whole party into it (leader only).
+    ///
+    /// Fails for the whole group: if any member can't get a seat within the
+    /// join timeout, everyone leaves the room again and `RoomFull` or
+    /// `Timeout` is returned. Returns the room id on success.
+    #[cfg(feature = "discovery")]
+    pub async fn quick_match(&self) -> Result<String> {
+        if !self.is_leader() {
+            return Err(ArenaError::NotAuthorized(
+                "Only the party leader can start matchmaking".to_string(),
+            ));
+        }
+
+        let config = self.arena.config();
+        let members = self.members();
+        if members.len() > config.max_players {
+            return Err(ArenaError::RoomFull);
+        }
+
+        // Seat the leader first; nobody else has moved yet if this fails
+        let rooms = self
+            .arena
+            .find_rooms(Some(RoomStatus::Waiting), QUICK_MATCH_CANDIDATES)
+            .await?;
+        let created = match rooms.iter().find(|r| r.free_slots() >= members.len()) {
+            Some(room) => {
+                self.arena.join(&room.room_id).await?;
+                false
+            }
+            None => {
+                self.arena.create().await?;
+                true
+            }
+        };
+        let room_id = self
+            .arena
+            .room_state()
+            .await
+            .room_id
+            .ok_or(ArenaError::NotInRoom)?;
+
+        // Send the others and wait for every answer
+        let others: Vec<String> = members
+            .into_iter()
+            .filter(|pk| *pk != self.leader)
+            .collect();
+        self.answers.write().await.clear();
+        let relays = self.arena.client().room_relays().await;
+        self.publish(PartyAction::Match, Some(room_id.clone()), relays)
+            .await?;
+
+        let deadline = monotonic_ms() + config.join_timeout;
+        let outcome = loop {
+            {
+                let answers = self.answers.read().await;
+                if others.iter().any(|pk| answers.get(pk) == Some(&false)) {
+                    break Err(ArenaError::RoomFull);
+                }
+                if others.iter().all(|pk| answers.get(pk) == Some(&true)) {
+                    break Ok(());
+                }
+            }
+            if monotonic_ms() >= deadline {
+                break Err(ArenaError::Timeout);
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+
+        match outcome {
+            Ok(()) => {
+                info!("Party {} matched into room {}", self.party_id, room_id);
+                self.publish(PartyAction::Matched, Some(room_id.clone()), Vec::new())
+                    .await?;
+                self.arena
+                    .emit(ArenaEvent::PartyMatched(room_id.clone()))
+                    .await;
+                Ok(room_id)
+            }
+            Err(e) => {
+                warn!("Party {} failed to match: {}", self.party_id, e);
+                let _ = self
+                    .publish(PartyAction::Abort, Some(room_id), Vec::new())
+                    .await;
+                if created {
+                    self.arena.delete_room().await?;
+                } else {
+                    self.arena.leave().await?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn tag(&self) -> String {
+        format!("{}-party-{}", self.arena.config().game_id, self.party_id)
+    }
+
+    async fn publish(
+        &self,
+        action: PartyAction,
+        room_id: Option<String>,
+        relays: Vec<String>,
+    ) -> Result<()> {
+        let content = encode_event(&EventContent::Party(PartyEventContent {
+            action,
+            room_id,
+            relays,
+        }))?;
+        self.arena
+            .client()
+            .publish_ephemeral(&self.tag(), &content)
+            .await?;
+        Ok(())
+    }
+
+    /// Announce ourselves at the heartbeat interval until the party is left
+    async fn start_presence(&self) {
+        let party = self.clone();
+        spawn(async move {
+            let mut ticker = interval(Duration::from_millis(
+                party.arena.config().heartbeat_interval,
+            ));
+            while !party.closed.load(Ordering::SeqCst) {
+                ticker.tick().await;
+                let _ = party.publish(PartyAction::Presence, None, Vec::new()).await;
+            }
+        });
+    }
+
+    async fn handle(&self, event: nostr_sdk::Event) {
+        let pubkey = event.pubkey.to_hex();
+        let Ok(EventContent::Party(party)) = decode_event(&event.content) else {
+            return;
+        };
+
+        match party.action {
+            PartyAction::Presence => {
+                self.members.insert(pubkey, monotonic_ms());
+            }
+            PartyAction::Leave => {
+                self.members.remove(&pubkey);
+            }
+            PartyAction::Joined | PartyAction::Failed => {
+                if self.is_leader() {
+                    let seated = party.action == PartyAction::Joined;
+                    self.answers.write().await.insert(pubkey, seated);
+                }
+            }
+            PartyAction::Match => {
+                if pubkey != self.leader || self.is_leader() {
+                    return;
+                }
+                let Some(room_id) = party.room_id else {
+                    return;
+                };
+                // Join off the handler so an abort can still arrive
+                let member = self.clone();
+                spawn(async move {
+                    let action = match member
+                        .arena
+                        .join_with_hints(&room_id, party.relays, None, None)
+                        .await
+                    {
+                        Ok(()) if member.aborted.read().await.contains(&room_id) => {
+                            let _ = member.arena.leave().await;
+                            return;
+                        }
+                        Ok(()) => PartyAction::Joined,
+                        Err(e) => {
+                            warn!("Party member failed to join {}: {}", room_id, e);
+                            PartyAction::Failed
+                        }
+                    };
+                    let _ = member.publish(action, Some(room_id), Vec::new()).await;
+                });
+            }
+            PartyAction::Matched => {
+                if pubkey == self.leader
+                    && !self.is_leader()
+                    && let Some(room_id) = party.room_id
+                {
+                    self.arena.emit(ArenaEvent::PartyMatched(room_id)).await;
+                }
+            }
+            PartyAction::Abort => {
+                if pubkey != self.leader || self.is_leader() {
+                    return;
+                }
+                let Some(room_id) = party.room_id else {
+                    return;
+                };
+                self.aborted.write().await.insert(room_id.clone());
+                if self.arena.room_state().await.room_id.as_deref() == Some(room_id.as_str()) {
+                    let _ = self.arena.leave().await;
+                }
+                self.arena.emit(ArenaEvent::PartyMatchFailed(room_id)).await;
+            }
+        }
+    }
+}
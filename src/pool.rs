@@ -0,0 +1,152 @@
+//! Shared relay connections
+//!
+//! Every `Arena` normally owns a Nostr client and with it one websocket per
+//! relay. Apps running several arenas at once (room discovery, the active
+//! room, a background lobby) can create one `RelayPool` and build each arena
+//! with `Arena::with_pool`, so they share a single connection per relay and
+//! one identity.
+//!
+//! The pool counts how many clients use each relay. Relays nobody uses stay
+//! open for reuse; once `max_relays` is reached, the least recently used of
+//! them is closed to make room. If every open relay is in use, the new relay
+//! is refused.
+
+use crate::error::{ArenaError, Result};
+use crate::time::monotonic_ms;
+use nostr_sdk::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+/// Users of one open relay
+#[derive(Debug, Clone, Copy)]
+struct RelayUse {
+    users: usize,
+    last_used: u64,
+}
+
+/// Relay connections shared by several arenas
+#[derive(Clone)]
+pub struct RelayPool {
+    client: Client,
+    public_key: String,
+    max_relays: usize,
+    relays: Arc<RwLock<HashMap<String, RelayUse>>>,
+}
+
+impl RelayPool {
+    /// Create a pool with generated keys
+    pub async fn new() -> Result<Self> {
+        let keys = Keys::generate();
+        let public_key = keys.public_key().to_hex();
+        Ok(Self::from_client(Client::new(keys), public_key))
+    }
+
+    /// Create a pool with a persistent identity (hex or nsec secret key)
+    pub async fn with_secret_key(secret_key: &str) -> Result<Self> {
+        let keys = Keys::parse(secret_key).map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        let public_key = keys.public_key().to_hex();
+        Ok(Self::from_client(Client::new(keys), public_key))
+    }
+
+    /// Create a pool that signs through an external signer
+    pub async fn with_signer<S>(signer: S) -> Result<Self>
+    where
+        S: IntoNostrSigner,
+    {
+        let signer = signer.into_nostr_signer();
+        let public_key = signer
+            .get_public_key()
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?
+            .to_hex();
+        Ok(Self::from_client(Client::new(signer), public_key))
+    }
+
+    fn from_client(client: Client, public_key: String) -> Self {
+        Self {
+            client,
+            public_key,
+            max_relays: 0,
+            relays: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Maximum number of relays kept open (0 = unlimited, the default)
+    pub fn max_relays(mut self, n: usize) -> Self {
+        self.max_relays = n;
+        self
+    }
+
+    /// Public key of the pool's identity
+    pub fn public_key(&self) -> String {
+        self.public_key.clone()
+    }
+
+    /// Open relays with the number of clients using each
+    pub async fn relays(&self) -> Vec<(String, usize)> {
+        self.relays
+            .read()
+            .await
+            .iter()
+            .map(|(url, relay)| (url.clone(), relay.users))
+            .collect()
+    }
+
+    pub(crate) fn client(&self) -> Client {
+        self.client.clone()
+    }
+
+    /// Start using `url`, opening it if needed
+    pub(crate) async fn acquire(&self, url: &str) -> Result<()> {
+        let mut relays = self.relays.write().await;
+        let now = monotonic_ms();
+        if let Some(relay) = relays.get_mut(url) {
+            relay.users += 1;
+            relay.last_used = now;
+            return Ok(());
+        }
+
+        if self.max_relays > 0 && relays.len() >= self.max_relays {
+            let idle = relays
+                .iter()
+                .filter(|(_, relay)| relay.users == 0)
+                .min_by_key(|(_, relay)| relay.last_used)
+                .map(|(url, _)| url.clone())
+                .ok_or_else(|| {
+                    ArenaError::Nostr(format!("All {} pooled relays are in use", self.max_relays))
+                })?;
+            relays.remove(&idle);
+            if let Err(e) = self.client.remove_relay(&idle).await {
+                warn!("Failed to remove relay {}: {}", idle, e);
+            }
+            debug!("Evicted idle relay: {}", idle);
+        }
+
+        self.client
+            .add_relay(url)
+            .await
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        if let Err(e) = self.client.connect_relay(url).await {
+            warn!("Failed to connect relay {}: {}", url, e);
+        }
+        relays.insert(
+            url.to_string(),
+            RelayUse {
+                users: 1,
+                last_used: now,
+            },
+        );
+        debug!("Opened pooled relay: {}", url);
+        Ok(())
+    }
+
+    /// Stop using `url`; it stays open for reuse until evicted
+    pub(crate) async fn release(&self, url: &str) {
+        if let Some(relay) = self.relays.write().await.get_mut(url) {
+            relay.users = relay.users.saturating_sub(1);
+            relay.last_used = monotonic_ms();
+        }
+    }
+}
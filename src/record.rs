@@ -0,0 +1,398 @@
+//! Match records
+//!
+//! A `MatchRecord` captures everything needed to archive a match off Nostr
+//! or re-verify it by deterministic replay: the players, the seed, the
+//! inputs and/or state keyframes, the result and timestamps. It has a stable
+//! JSON schema (`to_json` / `from_json`) and a line-based, PGN-like text form
+//! (`to_text` / `from_text`):
+//!
+//! ```text
+//! [Schema "nostr-arena.match/1"]
+//! [Game "tetris"]
+//! [Room "abc123"]
+//! [Seed "42"]
+//! [StartedAt "1704000000000"]
+//! [Player "abc..."]
+//! [Result "{\"winner\":\"abc...\",\"outcomes\":[]}"]
+//!
+//! 1704000000100 abc... i {"dx":1}
+//! 1704000000200 def... k {"score":10}
+//! ```
+//!
+//! Header values are JSON string literals; each frame line holds the time,
+//! the player, `i` (input) or `k` (keyframe) and the JSON payload.
+//!
+//! A game that implements [`RulesEngine`] lets anyone check a submitted
+//! record with [`verify_replay`]: the inputs are replayed from the seed and
+//! the outcome compared with the result the record claims.
+
+use crate::error::{ArenaError, Result};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// Schema identifier written into every record
+pub const MATCH_RECORD_SCHEMA: &str = "nostr-arena.match/1";
+
+/// Archived match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchRecord<T> {
+    pub schema: String,
+    pub game_id: String,
+    pub room_id: String,
+    pub seed: u64,
+    /// Players in seat order
+    pub players: Vec<String>,
+    pub started_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<u64>,
+    /// Inputs and keyframes in time order
+    #[serde(default = "Vec::new")]
+    pub frames: Vec<RecordFrame<T>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<MatchResult>,
+}
+
+/// One recorded input or state keyframe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordFrame<T> {
+    /// Time in ms
+    pub at: u64,
+    pub pubkey: String,
+    pub kind: FrameKind,
+    pub data: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameKind {
+    /// Player input, replayed to reproduce the match
+    Input,
+    /// Full state snapshot, for seeking and verification
+    Keyframe,
+}
+
+/// How a match ended
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub winner: Option<String>,
+    #[serde(default)]
+    pub outcomes: Vec<PlayerOutcome>,
+}
+
+/// A player's game over report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerOutcome {
+    pub pubkey: String,
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_score: Option<i64>,
+}
+
+impl<T> MatchRecord<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub fn new(
+        game_id: impl Into<String>,
+        room_id: impl Into<String>,
+        seed: u64,
+        started_at: u64,
+    ) -> Self {
+        Self {
+            schema: MATCH_RECORD_SCHEMA.to_string(),
+            game_id: game_id.into(),
+            room_id: room_id.into(),
+            seed,
+            players: Vec::new(),
+            started_at,
+            ended_at: None,
+            frames: Vec::new(),
+            result: None,
+        }
+    }
+
+    pub fn player(mut self, pubkey: impl Into<String>) -> Self {
+        self.players.push(pubkey.into());
+        self
+    }
+
+    pub fn push_input(&mut self, at: u64, pubkey: impl Into<String>, input: T) {
+        self.push(at, pubkey.into(), FrameKind::Input, input);
+    }
+
+    pub fn push_keyframe(&mut self, at: u64, pubkey: impl Into<String>, state: T) {
+        self.push(at, pubkey.into(), FrameKind::Keyframe, state);
+    }
+
+    fn push(&mut self, at: u64, pubkey: String, kind: FrameKind, data: T) {
+        // Keep time order even if frames arrive slightly out of order
+        let index = self.frames.partition_point(|f| f.at <= at);
+        self.frames.insert(
+            index,
+            RecordFrame {
+                at,
+                pubkey,
+                kind,
+                data,
+            },
+        );
+    }
+
+    /// Record the result and end time
+    pub fn finish(&mut self, ended_at: u64, result: MatchResult) {
+        self.ended_at = Some(ended_at);
+        self.result = Some(result);
+    }
+
+    /// Copy without the inputs: the result and keyframes only, small
+    /// enough for `Arena::archive_match()`
+    pub fn compact(&self) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            frames: self
+                .frames
+                .iter()
+                .filter(|f| f.kind == FrameKind::Keyframe)
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Inputs in time order
+    pub fn inputs(&self) -> impl Iterator<Item = &RecordFrame<T>> {
+        self.frames.iter().filter(|f| f.kind == FrameKind::Input)
+    }
+
+    /// Replay the inputs onto `state`, e.g. a game state seeded with
+    /// `self.seed`, and return the final state
+    pub fn replay<S>(&self, mut state: S, mut apply: impl FnMut(&mut S, &RecordFrame<T>)) -> S {
+        for frame in self.inputs() {
+            apply(&mut state, frame);
+        }
+        state
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let record: Self = serde_json::from_str(json)?;
+        check_schema(&record.schema)?;
+        Ok(record)
+    }
+
+    /// Render the PGN-like text form
+    pub fn to_text(&self) -> Result<String> {
+        let mut out = String::new();
+        let mut header = |key: &str, value: &str| -> Result<()> {
+            out.push_str(&format!("[{key} {}]\n", serde_json::to_string(value)?));
+            Ok(())
+        };
+        header("Schema", &self.schema)?;
+        header("Game", &self.game_id)?;
+        header("Room", &self.room_id)?;
+        header("Seed", &self.seed.to_string())?;
+        header("StartedAt", &self.started_at.to_string())?;
+        if let Some(ended_at) = self.ended_at {
+            header("EndedAt", &ended_at.to_string())?;
+        }
+        for player in &self.players {
+            header("Player", player)?;
+        }
+        if let Some(result) = &self.result {
+            header("Result", &serde_json::to_string(result)?)?;
+        }
+
+        out.push('\n');
+        for frame in &self.frames {
+            let kind = match frame.kind {
+                FrameKind::Input => 'i',
+                FrameKind::Keyframe => 'k',
+            };
+            out.push_str(&format!(
+                "{} {} {} {}\n",
+                frame.at,
+                frame.pubkey,
+                kind,
+                serde_json::to_string(&frame.data)?
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Parse the PGN-like text form
+    pub fn from_text(text: &str) -> Result<Self> {
+        let mut record = Self::new("", "", 0, 0);
+        record.schema = String::new();
+
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let (key, value) = header
+                    .split_once(' ')
+                    .ok_or_else(|| invalid(format!("bad header: {line}")))?;
+                let value: String = serde_json::from_str(value)?;
+                match key {
+                    "Schema" => record.schema = value,
+                    "Game" => record.game_id = value,
+                    "Room" => record.room_id = value,
+                    "Seed" => record.seed = parse_number(&value)?,
+                    "StartedAt" => record.started_at = parse_number(&value)?,
+                    "EndedAt" => record.ended_at = Some(parse_number(&value)?),
+                    "Player" => record.players.push(value),
+                    "Result" => record.result = Some(serde_json::from_str(&value)?),
+                    // Headers from newer versions
+                    _ => {}
+                }
+                continue;
+            }
+
+            let mut parts = line.splitn(4, ' ');
+            let (Some(at), Some(pubkey), Some(kind), Some(data)) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(invalid(format!("bad frame: {line}")));
+            };
+            let kind = match kind {
+                "i" => FrameKind::Input,
+                "k" => FrameKind::Keyframe,
+                _ => return Err(invalid(format!("bad frame kind: {kind}"))),
+            };
+            record.frames.push(RecordFrame {
+                at: parse_number(at)?,
+                pubkey: pubkey.to_string(),
+                kind,
+                data: serde_json::from_str(data)?,
+            });
+        }
+
+        check_schema(&record.schema)?;
+        Ok(record)
+    }
+}
+
+/// Deterministic game rules, for re-simulating recorded matches
+pub trait RulesEngine<T> {
+    type State;
+
+    /// State at the start of a match with this seed and players (seat order)
+    fn initial(&self, seed: u64, players: &[String]) -> Self::State;
+
+    /// Apply one input; `Err` with a reason if the input is illegal
+    fn apply(
+        &self,
+        state: &mut Self::State,
+        frame: &RecordFrame<T>,
+    ) -> std::result::Result<(), String>;
+
+    /// Result of the match in this state
+    fn result(&self, state: &Self::State) -> MatchResult;
+}
+
+/// Outcome of [`verify_replay`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifiedResult {
+    pub verdict: Verdict,
+    /// Result the rules reached, `None` if an input was illegal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub computed: Option<MatchResult>,
+    /// Inputs replayed
+    pub inputs: usize,
+}
+
+impl VerifiedResult {
+    /// Whether the record's claimed result is what its inputs produce
+    pub fn is_verified(&self) -> bool {
+        self.verdict == Verdict::Verified
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "verdict", rename_all = "lowercase")]
+pub enum Verdict {
+    /// The replay yields the claimed result
+    Verified,
+    /// The replay yields a different result than claimed
+    Mismatch,
+    /// The record claims no result
+    Unclaimed,
+    /// An input was rejected by the rules or sent by someone not playing;
+    /// `frame` indexes `MatchRecord::frames`
+    Illegal { frame: usize, reason: String },
+}
+
+/// Re-simulate `replay` from its seed and inputs with `rules` and check it
+/// yields the result the record claims.
+///
+/// Outcomes are compared regardless of order. Keyframes are not trusted and
+/// are skipped.
+pub fn verify_replay<T, R>(replay: &MatchRecord<T>, rules: &R) -> VerifiedResult
+where
+    R: RulesEngine<T>,
+{
+    let mut state = rules.initial(replay.seed, &replay.players);
+    let mut inputs = 0;
+    for (index, frame) in replay.frames.iter().enumerate() {
+        if frame.kind != FrameKind::Input {
+            continue;
+        }
+        let legal = if replay.players.contains(&frame.pubkey) {
+            rules.apply(&mut state, frame)
+        } else {
+            Err(format!("{} is not playing", frame.pubkey))
+        };
+        if let Err(reason) = legal {
+            return VerifiedResult {
+                verdict: Verdict::Illegal {
+                    frame: index,
+                    reason,
+                },
+                computed: None,
+                inputs,
+            };
+        }
+        inputs += 1;
+    }
+
+    let computed = rules.result(&state);
+    let verdict = match &replay.result {
+        None => Verdict::Unclaimed,
+        Some(claimed) if same_result(claimed, &computed) => Verdict::Verified,
+        Some(_) => Verdict::Mismatch,
+    };
+    VerifiedResult {
+        verdict,
+        computed: Some(computed),
+        inputs,
+    }
+}
+
+fn same_result(a: &MatchResult, b: &MatchResult) -> bool {
+    let sorted = |result: &MatchResult| {
+        let mut outcomes = result.outcomes.clone();
+        outcomes.sort_by(|x, y| x.pubkey.cmp(&y.pubkey));
+        outcomes
+    };
+    a.winner == b.winner && sorted(a) == sorted(b)
+}
+
+fn check_schema(schema: &str) -> Result<()> {
+    if schema.split('/').next() == MATCH_RECORD_SCHEMA.split('/').next() {
+        Ok(())
+    } else {
+        Err(invalid(format!("unknown match record schema: {schema}")))
+    }
+}
+
+fn parse_number(value: &str) -> Result<u64> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("not a number: {value}")))
+}
+
+fn invalid(message: String) -> ArenaError {
+    ArenaError::InvalidValue(message)
+}
@@ -0,0 +1,330 @@
+//! Embedded ephemeral relay for native hosts
+//!
+//! A tiny in-memory Nostr relay that lets a native host serve a LAN/party
+//! match without any external infrastructure. It speaks just enough of NIP-01
+//! (`EVENT`, `REQ`, `CLOSE`) for the arena:
+//!
+//! - Ephemeral events (kinds 20000-29999) are broadcast to matching
+//!   subscriptions and never stored.
+//! - Room events (kind 30078) keep only the latest version per author and
+//!   `d` tag, so joiners can fetch the room from the host directly. At most
+//!   [`MAX_ROOM_AUTHORS`] authors are stored per `d` tag and
+//!   [`MAX_ROOM_EVENTS`] events in all; past either limit the oldest event
+//!   goes, with events signed by the host their content names kept over
+//!   others on the same `d` tag.
+//! - Every other kind is rejected.
+//!
+//! Advertise the relay through [`ArenaConfig::preferred_relay`]:
+//!
+//! ```rust,ignore
+//! let relay = EmbeddedRelay::bind("0.0.0.0:7777").await?;
+//! let config = ArenaConfig::new("my-game")
+//!     .preferred_relay(relay.url_for_host("192.168.1.10"))
+//!     .write_relays(vec![relay.url_for_host("192.168.1.10")]);
+//! ```
+//!
+//! [`ArenaConfig::preferred_relay`]: crate::types::ArenaConfig::preferred_relay
+
+use crate::error::{ArenaError, Result};
+use crate::migrate::decode_room;
+use crate::types::kinds;
+use futures_util::{SinkExt, StreamExt};
+use nostr_sdk::prelude::*;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{RwLock, broadcast, watch};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tracing::{debug, warn};
+
+/// Most authors whose room event is stored for one `d` tag (the host and
+/// its co-hosts)
+pub const MAX_ROOM_AUTHORS: usize = 16;
+
+/// Most room events stored in all, unless bound with
+/// [`EmbeddedRelay::bind_with_capacity`]
+pub const MAX_ROOM_EVENTS: usize = 4096;
+
+/// Shared relay state
+struct RelayState {
+    events: broadcast::Sender<Arc<Event>>,
+    /// Latest room event per `d` tag and author
+    rooms: RwLock<HashMap<String, HashMap<PublicKey, Event>>>,
+    /// Most room events stored in all
+    capacity: usize,
+}
+
+/// In-memory relay serving ephemeral and room events
+pub struct EmbeddedRelay {
+    local_addr: SocketAddr,
+    shutdown: watch::Sender<bool>,
+}
+
+impl EmbeddedRelay {
+    /// Bind a websocket listener and start serving in the background
+    pub async fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        Self::bind_with_capacity(addr, MAX_ROOM_EVENTS).await
+    }
+
+    /// Like [`Self::bind`], storing at most `max_room_events` room events
+    pub async fn bind_with_capacity(
+        addr: impl ToSocketAddrs,
+        max_room_events: usize,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| ArenaError::Relay(e.to_string()))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| ArenaError::Relay(e.to_string()))?;
+
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let state = Arc::new(RelayState {
+            events: broadcast::channel(1024).0,
+            rooms: RwLock::new(HashMap::new()),
+            capacity: max_room_events.max(1),
+        });
+
+        tokio::spawn(accept_loop(listener, state, shutdown_rx));
+
+        debug!("Embedded relay listening on {}", local_addr);
+        Ok(Self {
+            local_addr,
+            shutdown,
+        })
+    }
+
+    /// Address the relay is bound to
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Relay URL using the bound address
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.local_addr)
+    }
+
+    /// Relay URL reachable by peers through `host` (e.g. the LAN IP)
+    pub fn url_for_host(&self, host: &str) -> String {
+        format!("ws://{host}:{}", self.local_addr.port())
+    }
+
+    /// Stop accepting connections and close existing ones
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+impl Drop for EmbeddedRelay {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    state: Arc<RelayState>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    debug!("Relay connection from {}", peer);
+                    tokio::spawn(serve_connection(stream, state.clone(), shutdown.clone()));
+                }
+                Err(e) => warn!("Relay accept failed: {}", e),
+            },
+            _ = shutdown.changed() => break,
+        }
+    }
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    state: Arc<RelayState>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let ws = match accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            warn!("Relay handshake failed: {}", e);
+            return;
+        }
+    };
+    let (mut sink, mut stream) = ws.split();
+    let mut events = state.events.subscribe();
+    let mut subscriptions: HashMap<String, Vec<Filter>> = HashMap::new();
+
+    loop {
+        let outgoing = tokio::select! {
+            msg = stream.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    handle_message(&text, &state, &mut subscriptions).await
+                }
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => continue,
+            },
+            event = events.recv() => match event {
+                Ok(event) => subscriptions
+                    .iter()
+                    .filter(|(_, filters)| filters.iter().any(|f| f.match_event(&event)))
+                    .map(|(sub_id, _)| json!(["EVENT", sub_id, event.as_ref()]))
+                    .collect(),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Relay connection lagged by {} events", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = shutdown.changed() => break,
+        };
+
+        for reply in outgoing {
+            if sink.send(Message::Text(reply.to_string())).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    let _ = sink.close().await;
+}
+
+/// Handle one client message, returning the replies to send
+async fn handle_message(
+    text: &str,
+    state: &RelayState,
+    subscriptions: &mut HashMap<String, Vec<Filter>>,
+) -> Vec<Value> {
+    let Ok(Value::Array(parts)) = serde_json::from_str::<Value>(text) else {
+        return vec![json!(["NOTICE", "invalid: expected JSON array"])];
+    };
+
+    match parts.first().and_then(Value::as_str) {
+        Some("EVENT") => {
+            let Some(Ok(event)) = parts
+                .get(1)
+                .map(|v| serde_json::from_value::<Event>(v.clone()))
+            else {
+                return vec![json!(["NOTICE", "invalid: malformed event"])];
+            };
+            let (accepted, message) = accept_event(event.clone(), state).await;
+            vec![json!(["OK", event.id.to_hex(), accepted, message])]
+        }
+        Some("REQ") => {
+            let Some(sub_id) = parts.get(1).and_then(Value::as_str) else {
+                return vec![json!(["NOTICE", "invalid: missing subscription id"])];
+            };
+            let filters: Vec<Filter> = parts[2..]
+                .iter()
+                .filter_map(|v| serde_json::from_value(v.clone()).ok())
+                .collect();
+
+            let mut replies: Vec<Value> = state
+                .rooms
+                .read()
+                .await
+                .values()
+                .flat_map(HashMap::values)
+                .filter(|event| filters.iter().any(|f| f.match_event(event)))
+                .map(|event| json!(["EVENT", sub_id, event]))
+                .collect();
+            replies.push(json!(["EOSE", sub_id]));
+
+            subscriptions.insert(sub_id.to_string(), filters);
+            replies
+        }
+        Some("CLOSE") => {
+            if let Some(sub_id) = parts.get(1).and_then(Value::as_str) {
+                subscriptions.remove(sub_id);
+                return vec![json!(["CLOSED", sub_id, ""])];
+            }
+            Vec::new()
+        }
+        _ => vec![json!(["NOTICE", "unsupported: message type"])],
+    }
+}
+
+/// Validate and route an event, returning the `OK` status and message
+async fn accept_event(event: Event, state: &RelayState) -> (bool, &'static str) {
+    if event.verify().is_err() {
+        return (false, "invalid: bad signature");
+    }
+
+    let kind = event.kind.as_u16();
+    if kind == kinds::ROOM {
+        let Some(d_tag) = event.tags.identifier().map(str::to_string) else {
+            return (false, "invalid: missing d tag");
+        };
+        let mut rooms = state.rooms.write().await;
+        if rooms
+            .get(&d_tag)
+            .and_then(|authors| authors.get(&event.pubkey))
+            .is_some_and(|existing| existing.created_at > event.created_at)
+        {
+            return (true, "duplicate: have a newer event");
+        }
+        if !store_room(&mut rooms, d_tag, event.clone(), state.capacity) {
+            return (false, "rate-limited: too many events for this room");
+        }
+    } else if !(20000..30000).contains(&kind) {
+        return (
+            false,
+            "blocked: only ephemeral and room events are accepted",
+        );
+    }
+
+    let _ = state.events.send(Arc::new(event));
+    (true, "")
+}
+
+/// Store a room event, replacing the author's older one and making room by
+/// dropping others; false if it ranks below every event it could displace
+fn store_room(
+    rooms: &mut HashMap<String, HashMap<PublicKey, Event>>,
+    d_tag: String,
+    event: Event,
+    capacity: usize,
+) -> bool {
+    let authors = rooms.entry(d_tag).or_default();
+    if !authors.contains_key(&event.pubkey) && authors.len() >= MAX_ROOM_AUTHORS {
+        let Some(victim) = authors
+            .values()
+            .min_by_key(|e| room_rank(e))
+            .filter(|victim| room_rank(victim) < room_rank(&event))
+            .map(|victim| victim.pubkey)
+        else {
+            return false;
+        };
+        authors.remove(&victim);
+    }
+    authors.insert(event.pubkey, event);
+
+    let stored: usize = rooms.values().map(HashMap::len).sum();
+    if stored > capacity {
+        let oldest = rooms
+            .iter()
+            .flat_map(|(d_tag, authors)| authors.values().map(move |e| (d_tag, e)))
+            .min_by_key(|(_, e)| e.created_at)
+            .map(|(d_tag, e)| (d_tag.clone(), e.pubkey));
+        if let Some((d_tag, pubkey)) = oldest
+            && let Some(authors) = rooms.get_mut(&d_tag)
+        {
+            authors.remove(&pubkey);
+            if authors.is_empty() {
+                rooms.remove(&d_tag);
+            }
+        }
+    }
+    true
+}
+
+/// Keeping order of room events: ones signed by the host their content
+/// names first, then newer ones
+fn room_rank(event: &Event) -> (bool, Timestamp) {
+    let self_hosted =
+        decode_room(&event.content).is_ok_and(|room| room.host_pubkey == event.pubkey.to_hex());
+    (self_hosted, event.created_at)
+}
@@ -0,0 +1,151 @@
+//! Deterministic random numbers from the room seed
+//!
+//! Every peer of a room knows the same seed, so games that derive all
+//! randomness from it stay in lockstep without sending dice rolls. Games
+//! usually need several independent streams (each player's deck, each
+//! round's layout); `SeededRng` derives them from the seed with a fixed,
+//! documented scheme so every peer and every binding gets the same numbers:
+//!
+//! - sub-seed of a stream: 64-bit FNV-1a over the parent seed (8 bytes,
+//!   little endian) followed by a label, passed through the SplitMix64
+//!   finalizer. Labels are `player:{pubkey}`, `round:{n}`, `turn-order`
+//!   or custom.
+//! - numbers: the SplitMix64 sequence of the stream's seed.
+//!
+//! ```
+//! use nostr_arena::SeededRng;
+//!
+//! let seed = 42;
+//! let mut deck = SeededRng::new(seed).round(1).player("abc...");
+//! let card = deck.below(52);
+//! # assert!(card < 52);
+//! ```
+
+use rand::RngCore;
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Deterministic SplitMix64 generator with labeled sub-streams
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededRng {
+    seed: u64,
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    /// Seed of this stream
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Independent stream for `label`, derived from this stream's seed
+    /// (not from the numbers drawn so far)
+    pub fn derive(&self, label: &str) -> Self {
+        Self::new(derive_seed(self.seed, label))
+    }
+
+    /// Independent stream for a player
+    pub fn player(&self, pubkey: &str) -> Self {
+        self.derive(&format!("player:{pubkey}"))
+    }
+
+    /// Independent stream for a round
+    pub fn round(&self, round: u64) -> Self {
+        self.derive(&format!("round:{round}"))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+
+    /// Uniform number in `0..n` (0 if `n` is 0), without modulo bias
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+
+    /// Uniform float in `[0, 1)`
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u64) as usize)
+    }
+}
+
+/// Lets `rand`'s distributions and slice helpers draw from the stream
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        (SeededRng::next_u64(self) >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        SeededRng::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = SeededRng::next_u64(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Turn order for `players` under `seed`: the players sorted by public key,
+/// then shuffled with the `turn-order` stream, so every peer agrees on who
+/// goes first
+pub fn turn_order(seed: u64, players: &[String]) -> Vec<String> {
+    let mut order = players.to_vec();
+    order.sort();
+    SeededRng::new(seed)
+        .derive("turn-order")
+        .shuffle(&mut order);
+    order
+}
+
+/// Sub-seed for `label` under `seed`
+pub fn derive_seed(seed: u64, label: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in seed.to_le_bytes().into_iter().chain(label.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    mix(hash)
+}
+
+/// SplitMix64 finalizer
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
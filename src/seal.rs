@@ -0,0 +1,172 @@
+//! End-to-end encrypted game state
+//!
+//! With `encrypt_state`, a host generates a [`RoomKey`] for each room it
+//! creates and hands it to every player it seats, NIP-44 encrypted to the
+//! player's key in the `seat` reply. Game state (`state`, `digest`,
+//! `batch` and `snapshot` messages) is then published as `sealed`
+//! messages: NIP-44 v2 payloads under the room key, unreadable by relays
+//! and anyone without a seat. Other messages stay in the clear, so
+//! lobbies, chat and votes work as before. When a player leaves or is
+//! kicked, the host sends the others a new key in a `rekey` message.
+//!
+//! Sealing happens below the middleware, closest to the wire: layers see
+//! the plain messages in both directions. Whether a message is sealed is
+//! decided on the plain message, so transforming layers (compression) work
+//! in encrypted rooms too.
+
+use crate::error::{ArenaError, Result};
+use crate::migrate::{decode_event, encode_event, message_type};
+use crate::types::{EventContent, SealedEventContent};
+use nostr_sdk::base64::Engine;
+use nostr_sdk::base64::engine::general_purpose::STANDARD as BASE64;
+use nostr_sdk::nips::nip44::v2::{self, ConversationKey};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Message types sealed in encrypted rooms
+pub const SEALED_TYPES: [&str; 4] = ["state", "digest", "batch", "snapshot"];
+
+/// Whether messages of `message_type` are sealed in encrypted rooms
+pub fn is_sealed_type(message_type: &str) -> bool {
+    SEALED_TYPES.contains(&message_type)
+}
+
+/// Symmetric key of an encrypted room
+#[derive(Clone, PartialEq, Eq)]
+pub struct RoomKey([u8; 32]);
+
+impl fmt::Debug for RoomKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RoomKey(..)")
+    }
+}
+
+impl RoomKey {
+    pub fn generate() -> Self {
+        Self(rand::random())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let invalid = || ArenaError::InvalidValue("room key".to_string());
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(key))
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Encrypt a message's content, as base64
+    pub fn seal(&self, content: &str) -> Result<String> {
+        let payload = v2::encrypt_to_bytes(&self.conversation_key()?, content.as_bytes())
+            .map_err(|e| ArenaError::Nostr(e.to_string()))?;
+        Ok(BASE64.encode(payload))
+    }
+
+    /// Decrypt a payload made by [`Self::seal`]
+    pub fn open(&self, payload: &str) -> Result<String> {
+        let invalid = |msg: &str| ArenaError::InvalidEventData(format!("sealed: {msg}"));
+        let payload = BASE64
+            .decode(payload)
+            .map_err(|_| invalid("bad encoding"))?;
+        let content = v2::decrypt_to_bytes(&self.conversation_key()?, &payload)
+            .map_err(|_| invalid("wrong key or tampered"))?;
+        String::from_utf8(content).map_err(|_| invalid("bad text"))
+    }
+
+    fn conversation_key(&self) -> Result<ConversationKey> {
+        ConversationKey::from_slice(&self.0).map_err(|e| ArenaError::Nostr(e.to_string()))
+    }
+}
+
+/// Encryption state of the room we are in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RoomSeal {
+    /// The room is not encrypted
+    #[default]
+    Off,
+    /// The room is encrypted and the host has not sent us the key yet
+    Pending,
+    Key(RoomKey),
+}
+
+impl RoomSeal {
+    pub fn is_on(&self) -> bool {
+        *self != RoomSeal::Off
+    }
+
+    /// Content to publish for an outgoing message: sealed if its type is,
+    /// unchanged otherwise. Fails for game state while the key is pending.
+    pub fn seal_message<'a>(&self, content: Cow<'a, str>) -> Result<Cow<'a, str>> {
+        if self.seals(&content)? {
+            self.seal(content)
+        } else {
+            Ok(content)
+        }
+    }
+
+    /// Content of an incoming message, opened if sealed. `None` drops it:
+    /// sealed messages we can't open, and game state sent in the clear to
+    /// an encrypted room.
+    pub fn open_message<'a>(&self, content: Cow<'a, str>) -> Option<Cow<'a, str>> {
+        let (content, sealed) = self.unseal(content)?;
+        self.admits(&content, sealed).then_some(content)
+    }
+
+    /// Whether the plain message `content` goes out sealed. Fails for game
+    /// state while the key is pending.
+    pub fn seals(&self, content: &str) -> Result<bool> {
+        if !self.is_on() || !message_type(content).is_some_and(|t| is_sealed_type(&t)) {
+            return Ok(false);
+        }
+        match self {
+            RoomSeal::Key(_) => Ok(true),
+            _ => Err(ArenaError::NotAuthorized(
+                "room key not received yet".to_string(),
+            )),
+        }
+    }
+
+    /// Wrap `content` in a `sealed` message. The arena decides with
+    /// [`Self::seals`] on the plain message, then seals what the middleware
+    /// made of it.
+    pub fn seal<'a>(&self, content: Cow<'a, str>) -> Result<Cow<'a, str>> {
+        let RoomSeal::Key(key) = self else {
+            return Err(ArenaError::NotAuthorized(
+                "room key not received yet".to_string(),
+            ));
+        };
+        let sealed = encode_event(&EventContent::Sealed(SealedEventContent {
+            payload: key.seal(&content)?,
+        }))?;
+        Ok(Cow::Owned(sealed))
+    }
+
+    /// Open a `sealed` message, with whether it was sealed; other messages
+    /// pass unchanged. `None` for sealed messages we can't open.
+    pub fn unseal<'a>(&self, content: Cow<'a, str>) -> Option<(Cow<'a, str>, bool)> {
+        if !self.is_on() || message_type(&content).as_deref() != Some("sealed") {
+            return Some((content, false));
+        }
+        let RoomSeal::Key(key) = self else {
+            return None;
+        };
+        let Ok(EventContent::Sealed(sealed)) = decode_event(&content) else {
+            return None;
+        };
+        Some((Cow::Owned(key.open(&sealed.payload).ok()?), true))
+    }
+
+    /// Whether to handle the plain message `content`: in encrypted rooms,
+    /// game state must have come sealed, and sealed messages hold game state
+    /// only
+    pub fn admits(&self, content: &str, sealed: bool) -> bool {
+        !self.is_on() || message_type(content).is_some_and(|t| is_sealed_type(&t)) == sealed
+    }
+}
@@ -0,0 +1,68 @@
+//! Short-term memory of room control messages
+//!
+//! Ephemeral events are only delivered to subscriptions open when they are
+//! published, so a peer whose subscription drops for a moment misses a
+//! `ready` or `gameover` for good. With `resend_window` set, every peer keeps
+//! the control messages it saw for that long in an [`EventShadow`]. A peer
+//! that may have missed traffic sends `resendrequest`, and the host publishes
+//! the kept events again, signatures intact. The shadow also drops the
+//! copies a peer already handled.
+
+use nostr_sdk::{Event, EventId};
+use std::collections::VecDeque;
+
+/// Events kept at most, however short the window
+pub const MAX_SHADOW_EVENTS: usize = 256;
+
+/// Control messages seen in the last `window_ms`, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct EventShadow {
+    events: VecDeque<(u64, Event)>,
+}
+
+impl EventShadow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep `event`, seen at `now` (ms), dropping events older than
+    /// `window_ms`. Returns false if the event was already kept.
+    pub fn record(&mut self, event: &Event, now: u64, window_ms: u64) -> bool {
+        while self
+            .events
+            .front()
+            .is_some_and(|(t, _)| now.saturating_sub(*t) >= window_ms)
+        {
+            self.events.pop_front();
+        }
+        if self.contains(&event.id) {
+            return false;
+        }
+        if self.events.len() >= MAX_SHADOW_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back((now, event.clone()));
+        true
+    }
+
+    pub fn contains(&self, id: &EventId) -> bool {
+        self.events.iter().any(|(_, e)| e.id == *id)
+    }
+
+    /// Kept events created at or after `since` (ms), oldest first
+    pub fn since(&self, since: u64) -> Vec<Event> {
+        self.events
+            .iter()
+            .filter(|(_, e)| e.created_at.as_u64() >= since / 1000)
+            .map(|(_, e)| e.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
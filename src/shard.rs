@@ -0,0 +1,176 @@
+//! Sharded per-player map
+//!
+//! Presence and game state maps are touched from the dispatcher, the
+//! heartbeat, presence and watchdog tasks and every API accessor. A single
+//! lock around the whole map serializes all of them; [`PlayerMap`] instead
+//! splits entries across shards by public key, so updates for different
+//! players rarely contend. Locks are held only for the duration of one
+//! operation (never across an `.await`), and bulk reads return snapshots.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+const SHARDS: usize = 16;
+
+/// Concurrent map keyed by player public key
+#[derive(Debug)]
+pub struct PlayerMap<V> {
+    shards: [RwLock<HashMap<String, V>>; SHARDS],
+}
+
+impl<V> Default for PlayerMap<V> {
+    fn default() -> Self {
+        Self {
+            shards: std::array::from_fn(|_| RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+impl<V> PlayerMap<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn shard(&self, key: &str) -> &RwLock<HashMap<String, V>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    fn read(lock: &RwLock<HashMap<String, V>>) -> RwLockReadGuard<'_, HashMap<String, V>> {
+        lock.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(lock: &RwLock<HashMap<String, V>>) -> RwLockWriteGuard<'_, HashMap<String, V>> {
+        lock.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Insert or replace an entry, returning the previous value
+    pub fn insert(&self, key: String, value: V) -> Option<V> {
+        Self::write(self.shard(&key)).insert(key, value)
+    }
+
+    /// Insert only if the key is absent, returning whether it was inserted
+    pub fn insert_if_absent(&self, key: String, value: V) -> bool {
+        let mut shard = Self::write(self.shard(&key));
+        if shard.contains_key(&key) {
+            return false;
+        }
+        shard.insert(key, value);
+        true
+    }
+
+    pub fn remove(&self, key: &str) -> Option<V> {
+        Self::write(self.shard(key)).remove(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        Self::read(self.shard(key)).contains_key(key)
+    }
+
+    /// Mutate an entry in place, returning the closure's result if present
+    pub fn update<R>(&self, key: &str, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        Self::write(self.shard(key)).get_mut(key).map(f)
+    }
+
+    /// Mutate every entry, one shard at a time
+    pub fn update_all(&self, mut f: impl FnMut(&mut V)) {
+        for shard in &self.shards {
+            Self::write(shard).values_mut().for_each(&mut f);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| Self::read(s).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| Self::read(s).is_empty())
+    }
+
+    /// Number of entries matching a predicate
+    pub fn count(&self, f: impl Fn(&V) -> bool) -> usize {
+        self.shards
+            .iter()
+            .map(|s| Self::read(s).values().filter(|v| f(v)).count())
+            .sum()
+    }
+
+    /// Whether every entry matches a predicate (true if empty)
+    pub fn all(&self, f: impl Fn(&V) -> bool) -> bool {
+        self.shards.iter().all(|s| Self::read(s).values().all(&f))
+    }
+
+    /// Snapshot of the keys
+    pub fn keys(&self) -> Vec<String> {
+        self.shards
+            .iter()
+            .flat_map(|s| Self::read(s).keys().cloned().collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Keys of entries matching a predicate
+    pub fn keys_where(&self, f: impl Fn(&V) -> bool) -> Vec<String> {
+        self.shards
+            .iter()
+            .flat_map(|s| {
+                Self::read(s)
+                    .iter()
+                    .filter(|(_, v)| f(v))
+                    .map(|(k, _)| k.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            Self::write(shard).clear();
+        }
+    }
+
+    /// Remove and return all entries, one shard at a time
+    pub fn drain(&self) -> HashMap<String, V> {
+        self.shards
+            .iter()
+            .flat_map(|s| std::mem::take(&mut *Self::write(s)))
+            .collect()
+    }
+
+    /// Replace all entries
+    pub fn replace(&self, entries: impl IntoIterator<Item = (String, V)>) {
+        self.clear();
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<V: Clone> PlayerMap<V> {
+    pub fn get(&self, key: &str) -> Option<V> {
+        Self::read(self.shard(key)).get(key).cloned()
+    }
+
+    /// Snapshot of the values
+    pub fn values(&self) -> Vec<V> {
+        self.shards
+            .iter()
+            .flat_map(|s| Self::read(s).values().cloned().collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Snapshot of all entries
+    pub fn to_map(&self) -> HashMap<String, V> {
+        self.shards
+            .iter()
+            .flat_map(|s| {
+                Self::read(s)
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
@@ -10,6 +10,7 @@ mod tests {
         assert_eq!(config.game_id, "test-game");
         assert_eq!(config.max_players, 2);
         assert_eq!(config.room_expiry, 0);
+        assert_eq!(config.idle_expiry, 0);
         assert_eq!(config.countdown_seconds, 3);
         assert!(matches!(config.start_mode, StartMode::Auto));
     }
@@ -19,17 +20,121 @@ mod tests {
         let config = ArenaConfig::new("test-game")
             .max_players(4)
             .room_expiry(600000)
+            .idle_expiry(300000)
             .start_mode(StartMode::Ready)
             .countdown_seconds(5)
             .base_url("https://example.com");
 
         assert_eq!(config.max_players, 4);
         assert_eq!(config.room_expiry, 600000);
+        assert_eq!(config.idle_expiry, 300000);
         assert!(matches!(config.start_mode, StartMode::Ready));
         assert_eq!(config.countdown_seconds, 5);
         assert_eq!(config.base_url, Some("https://example.com".to_string()));
     }
 
+    #[test]
+    fn test_arena_config_blocklist() {
+        assert!(ArenaConfig::new("test-game").blocklist.is_empty());
+
+        let config = ArenaConfig::new("test-game").blocklist(vec!["abc".to_string()]);
+        assert_eq!(config.blocklist, vec!["abc".to_string()]);
+    }
+
+    #[test]
+    fn test_arena_config_unlisted() {
+        let config = ArenaConfig::new("test-game");
+        assert!(!config.unlisted);
+        assert_eq!(config.room_hashtag(), Some("test-game"));
+
+        let config = config.unlisted(true);
+        assert_eq!(config.room_hashtag(), None);
+    }
+
+    #[test]
+    fn test_arena_config_client_tag() {
+        let config = ArenaConfig::new("test-game");
+        assert_eq!(config.client_tag(), None);
+        assert!(config.is_compatible_client(None));
+
+        let config = config.client_name("tetris-app", "1.4.2");
+        assert_eq!(config.client_tag().as_deref(), Some("tetris-app/1.4.2"));
+        assert_eq!(
+            ArenaConfig::new("test-game")
+                .client_name("tetris-app", "")
+                .client_tag()
+                .as_deref(),
+            Some("tetris-app")
+        );
+
+        let config = config.compatible_versions(vec!["1.".to_string()]);
+        assert!(config.is_compatible_client(Some("tetris-app/1.0.0")));
+        assert!(!config.is_compatible_client(Some("tetris-app/2.0.0")));
+        assert!(!config.is_compatible_client(Some("other-app/1.0.0")));
+        assert!(!config.is_compatible_client(None));
+    }
+
+    #[test]
+    fn test_arena_config_presence_interval() {
+        assert_eq!(ArenaConfig::new("test-game").presence_interval, 30000);
+
+        let config = ArenaConfig::new("test-game").presence_interval(5000);
+        assert_eq!(config.presence_interval, 5000);
+    }
+
+    #[test]
+    fn test_arena_config_extra_tags() {
+        let config = ArenaConfig::new("test-game")
+            .extra_tag(["client", "my-game"])
+            .extra_tag(vec!["l".to_string(), "beta".to_string()]);
+        assert_eq!(config.extra_tags.len(), 2);
+        assert_eq!(config.extra_tags[0], vec!["client", "my-game"]);
+    }
+
+    #[test]
+    fn test_arena_config_presets() {
+        let config = ArenaConfig::new("test-game").preset(Preset::TurnBased);
+        assert_eq!(config.state_throttle, 0);
+        assert!(matches!(config.start_mode, StartMode::Ready));
+
+        let config = ArenaConfig::new("test-game").preset(Preset::Realtime);
+        assert!(config.disconnect_threshold < 10000);
+        assert!(matches!(config.start_mode, StartMode::Countdown));
+
+        // Later builder calls override the preset
+        let config = ArenaConfig::new("test-game")
+            .preset(Preset::Party)
+            .max_players(4)
+            .state_throttle(50);
+        assert_eq!(config.max_players, 4);
+        assert_eq!(config.state_throttle, 50);
+        assert!(matches!(config.start_mode, StartMode::Host));
+    }
+
+    #[test]
+    fn test_arena_config_throttles() {
+        let config = ArenaConfig::new("test-game");
+        assert_eq!(config.throttle_for("state"), 100);
+        assert_eq!(config.throttle_for("chat"), 0);
+
+        let config = config.throttle("chat", 1000).throttle("state", 30);
+        assert_eq!(config.throttle_for("chat"), 1000);
+        assert_eq!(config.throttle_for("state"), 30);
+    }
+
+    #[test]
+    fn test_arena_config_relay_sets() {
+        let config = ArenaConfig::new("test-game");
+        assert!(config.read_relays.is_empty());
+        assert!(config.write_relays.is_empty());
+
+        let config = config
+            .read_relays(vec!["wss://fast.example".to_string()])
+            .write_relays(vec!["wss://index.example".to_string()]);
+        assert_eq!(config.read_relays, vec!["wss://fast.example".to_string()]);
+        assert_eq!(config.write_relays, vec!["wss://index.example".to_string()]);
+    }
+
     #[test]
     fn test_room_state_default() {
         let state = RoomState::default();
@@ -38,13 +143,43 @@ mod tests {
         assert!(!state.is_host);
     }
 
+    #[test]
+    fn test_arena_snapshot_roundtrip() {
+        let snapshot = ArenaSnapshot {
+            public_key: "abc123".to_string(),
+            room: RoomState {
+                room_id: Some("room12".to_string()),
+                status: RoomStatus::Playing,
+                is_host: true,
+                seed: 42,
+                ..Default::default()
+            },
+            players: vec![],
+            player_states: [("def456".to_string(), 7u32)].into_iter().collect(),
+            taken_at: 1000,
+            room_key: None,
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: ArenaSnapshot<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.room.room_id.as_deref(), Some("room12"));
+        assert_eq!(restored.room.status, RoomStatus::Playing);
+        assert_eq!(restored.player_states["def456"], 7);
+    }
+
     #[test]
     fn test_player_presence() {
         let presence = PlayerPresence {
             pubkey: "abc123".to_string(),
             joined_at: 1000,
             last_seen: 2000,
+            seen_at: 0,
             ready: true,
+            latency_ms: None,
+            away: false,
+            session: None,
+            capabilities: None,
+            identity: None,
         };
         assert_eq!(presence.pubkey, "abc123");
         assert!(presence.ready);
@@ -65,6 +200,43 @@ mod tests {
         assert_eq!(statuses.len(), 8);
     }
 
+    #[test]
+    fn test_room_status_from_str() {
+        for status in [
+            RoomStatus::Idle,
+            RoomStatus::Waiting,
+            RoomStatus::Playing,
+            RoomStatus::Deleted,
+        ] {
+            assert_eq!(status.to_string().parse::<RoomStatus>().unwrap(), status);
+        }
+        assert_eq!(
+            "Waiting".parse::<RoomStatus>().unwrap(),
+            RoomStatus::Waiting
+        );
+        assert!("waitng".parse::<RoomStatus>().is_err());
+    }
+
+    #[test]
+    fn test_start_mode_from_str() {
+        assert_eq!(
+            "countdown".parse::<StartMode>().unwrap(),
+            StartMode::Countdown
+        );
+        assert_eq!(StartMode::Host.to_string(), "host");
+        assert!("manual".parse::<StartMode>().is_err());
+    }
+
+    #[test]
+    fn test_report_reason_from_str() {
+        assert_eq!(
+            " Spam ".parse::<ReportReason>().unwrap(),
+            ReportReason::Spam
+        );
+        assert_eq!(ReportReason::Impersonation.to_string(), "impersonation");
+        assert!("cheating".parse::<ReportReason>().is_err());
+    }
+
     #[test]
     fn test_start_mode_variants() {
         let modes = vec![
@@ -83,10 +255,15 @@ mod tests {
         // Test join event
         let join = EventContent::Join(JoinEventContent {
             player_pubkey: "abc123".to_string(),
+            capabilities: None,
+            identity: None,
+            session: Some("0123abcd".to_string()),
+            proof: None,
         });
         let json = serde_json::to_string(&join).unwrap();
         assert!(json.contains("join"));
         assert!(json.contains("abc123"));
+        assert!(json.contains(r#""session":"0123abcd""#));
 
         // Test state event
         let state = EventContent::State(StateEventContent {
@@ -103,20 +280,26 @@ mod tests {
         assert!(json.contains("true"));
 
         // Test heartbeat event
-        let heartbeat = EventContent::Heartbeat(HeartbeatEventContent { timestamp: 12345 });
+        let heartbeat = EventContent::Heartbeat(HeartbeatEventContent {
+            timestamp: 12345,
+            seen: Default::default(),
+            away: false,
+        });
         let json = serde_json::to_string(&heartbeat).unwrap();
         assert!(json.contains("heartbeat"));
         assert!(json.contains("12345"));
+        assert!(!json.contains("away"));
+        let away: HeartbeatEventContent =
+            serde_json::from_str(r#"{"timestamp":1,"away":true}"#).unwrap();
+        assert!(away.away);
 
         // Test game over event
-        let game_over = EventContent::GameOver(GameOverEventContent {
-            reason: "win".to_string(),
-            final_score: Some(100),
-            winner: None,
-        });
+        let game_over =
+            EventContent::GameOver(GameOverEventContent::new(ReasonCode::Won.into(), Some(100)));
         let json = serde_json::to_string(&game_over).unwrap();
         assert!(json.contains("gameover"));
-        assert!(json.contains("win"));
+        assert!(json.contains(r#""reason":"Won""#));
+        assert!(json.contains(r#""code":"won""#));
     }
 
     #[test]
@@ -131,9 +314,3397 @@ mod tests {
             created_at: 1000,
             expires_at: Some(2000),
             seed: 12345,
+            client: None,
+            round: 0,
+            phase: None,
+            spectator_count: 0,
+            visibility: RoomVisibility::Public,
+            locked: false,
         };
         assert_eq!(info.room_id, "room123");
         assert_eq!(info.player_count, 1);
         assert_eq!(info.max_players, 4);
+        assert_eq!(info.free_slots(), 3);
+        assert!(info.is_expired());
+        assert!(!info.is_joinable());
+        assert!(info.age() > 0);
+    }
+
+    #[test]
+    fn test_room_info_joinable() {
+        let mut info = RoomInfo {
+            room_id: "room123".to_string(),
+            game_id: "test-game".to_string(),
+            status: RoomStatus::Waiting,
+            host_pubkey: "host123".to_string(),
+            player_count: 1,
+            max_players: 2,
+            created_at: now_ms(),
+            expires_at: None,
+            seed: 12345,
+            client: None,
+            round: 0,
+            phase: None,
+            spectator_count: 0,
+            visibility: RoomVisibility::Public,
+            locked: false,
+        };
+        assert!(info.is_joinable());
+
+        info.player_count = 2;
+        assert_eq!(info.free_slots(), 0);
+        assert!(!info.is_joinable());
+
+        info.player_count = 1;
+        info.status = RoomStatus::Playing;
+        assert!(!info.is_joinable());
+    }
+
+    #[test]
+    fn test_event_content_version_roundtrip() {
+        use crate::migrate::{decode_event, encode_event, message_version};
+
+        let json = encode_event(&EventContent::Ready(ReadyEventContent { ready: true })).unwrap();
+        assert!(json.contains(&format!("\"version\":{PROTOCOL_VERSION}")));
+        assert_eq!(message_version(&json), PROTOCOL_VERSION);
+        assert!(matches!(
+            decode_event(&json).unwrap(),
+            EventContent::Ready(ReadyEventContent { ready: true })
+        ));
+
+        // Legacy payloads without a version still decode
+        let legacy = r#"{"type":"heartbeat","timestamp":42}"#;
+        assert!(matches!(
+            decode_event(legacy).unwrap(),
+            EventContent::Heartbeat(HeartbeatEventContent { timestamp: 42, .. })
+        ));
+    }
+
+    #[test]
+    fn test_room_content_migration() {
+        use crate::migrate::decode_room;
+
+        // The sender's version survives the upgrade
+        let legacy = r#"{"status":"waiting","seed":1,"host_pubkey":"abc","max_players":2}"#;
+        let content = decode_room(legacy).unwrap();
+        assert_eq!(content.version, 0);
+        assert!(content.players.is_empty());
+
+        // v2 rooms published a password hash
+        let v2 = r#"{"version":2,"status":"waiting","seed":1,"host_pubkey":"abc","max_players":2,"password_hash":"9f86"}"#;
+        let content = decode_room(v2).unwrap();
+        assert_eq!(content.version, 2);
+        assert!(content.password);
+        assert!(
+            !decode_room(&v2.replace(r#","password_hash":"9f86""#, ""))
+                .unwrap()
+                .password
+        );
+    }
+
+    #[test]
+    fn test_unknown_event_passthrough() {
+        use crate::migrate::{decode_event, encode_event};
+
+        let json = r#"{"type":"emote","version":2,"emoji":"wave"}"#;
+        let content = decode_event(json).unwrap();
+        let EventContent::Unknown {
+            message_type,
+            payload,
+        } = &content
+        else {
+            panic!("expected unknown content");
+        };
+        assert_eq!(message_type, "emote");
+        assert_eq!(payload["emoji"], "wave");
+
+        // Re-encoding keeps the original type
+        let encoded = encode_event(&content).unwrap();
+        assert!(encoded.contains("\"type\":\"emote\""));
+
+        // Malformed known types are still rejected, bad nested enums too
+        assert!(decode_event(r#"{"type":"ready","ready":"yes"}"#).is_err());
+        assert!(decode_event(r#"{"type":"rematch","action":"bogus"}"#).is_err());
+        assert!(decode_event(r#"{"version":2}"#).is_err());
+    }
+
+    #[test]
+    fn test_room_game_relays() {
+        let mut content = crate::migrate::decode_room(
+            r#"{"status":"waiting","seed":1,"host_pubkey":"abc","max_players":2}"#,
+        )
+        .unwrap();
+        let fallback = vec!["wss://a.example".to_string()];
+        assert_eq!(content.game_relays(&fallback), fallback);
+
+        content.preferred_relay = Some("ws://192.168.1.2:7777".to_string());
+        assert_eq!(
+            content.game_relays(&fallback),
+            vec![
+                "ws://192.168.1.2:7777".to_string(),
+                "wss://a.example".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seat_event_roundtrip() {
+        use crate::migrate::{decode_event, encode_event};
+
+        let content = EventContent::Seat(SeatEventContent {
+            player_pubkey: "def456".to_string(),
+            seat: None,
+            seats: vec!["abc123".to_string(), "ghi789".to_string()],
+            capabilities: None,
+            identity: Default::default(),
+            identities: None,
+            reason: Some(ReasonCode::RoomFull.into()),
+            session: None,
+            room_key: None,
+        });
+        let json = encode_event(&content).unwrap();
+        assert!(json.contains(r#""type":"seat""#));
+
+        match decode_event(&json).unwrap() {
+            EventContent::Seat(seat) => {
+                assert_eq!(seat.player_pubkey, "def456");
+                assert!(seat.seat.is_none());
+                assert_eq!(seat.seats.len(), 2);
+                assert_eq!(seat.reason.unwrap().code, ReasonCode::RoomFull);
+            }
+            other => panic!("unexpected content: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_room_state_seat_of() {
+        let state = RoomState {
+            seats: vec!["abc123".to_string(), "def456".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(state.seat_of("abc123"), Some(0));
+        assert_eq!(state.seat_of("def456"), Some(1));
+        assert_eq!(state.seat_of("ghi789"), None);
+    }
+
+    #[test]
+    fn test_room_state_is_authority() {
+        let state = RoomState {
+            host_pubkey: Some("host".to_string()),
+            co_hosts: vec!["organizer".to_string()],
+            ..Default::default()
+        };
+        assert!(state.is_authority("host"));
+        assert!(state.is_authority("organizer"));
+        assert!(!state.is_authority("player"));
+
+        // Legacy rooms without a known host
+        assert!(RoomState::default().is_authority("player"));
+    }
+
+    #[test]
+    fn test_latency_histogram() {
+        use crate::latency::LatencyHistogram;
+
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.mean_ms(), 0);
+        assert_eq!(histogram.percentile_ms(50), 0);
+
+        for latency in [10, 20, 30, 90, 5000] {
+            histogram.record(latency);
+        }
+        assert_eq!(histogram.count, 5);
+        assert_eq!(histogram.min_ms, 10);
+        assert_eq!(histogram.max_ms, 5000);
+        assert_eq!(histogram.mean_ms(), 1030);
+        assert_eq!(histogram.buckets[0], 2);
+        assert_eq!(histogram.percentile_ms(50), 50);
+        assert_eq!(histogram.percentile_ms(100), 5000);
+    }
+
+    #[test]
+    fn test_latency_report() {
+        use crate::latency::LatencyReport;
+
+        let mut report = LatencyReport::default();
+        report.record("abc", "wss://fast.example", 1000, 1040);
+        report.record("abc", "wss://slow.example", 1000, 1900);
+        // Clock skew counts as zero latency
+        report.record("def", "wss://fast.example", 2000, 1500);
+
+        assert_eq!(report.overall.count, 3);
+        assert_eq!(report.by_peer["abc"].count, 2);
+        assert_eq!(report.by_relay["wss://fast.example"].max_ms, 40);
+        assert_eq!(report.slowest_relay().unwrap().0, "wss://slow.example");
+        assert_eq!(report.slowest_peer().unwrap().0, "abc");
+    }
+
+    #[test]
+    fn test_decode_state_direct() {
+        use crate::migrate::decode_state;
+
+        let state: Vec<u32> = decode_state(r#"{"type":"state","version":2,"game_state":[1,2,3]}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(state, vec![1, 2, 3]);
+
+        // Other message types are left to decode_event
+        assert!(decode_state::<Vec<u32>>(r#"{"type":"ready","ready":true}"#).is_none());
+        // Game state that doesn't match T is an error
+        assert!(
+            decode_state::<Vec<u32>>(r#"{"type":"state","game_state":"oops"}"#)
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_player_map() {
+        use crate::shard::PlayerMap;
+
+        let map = PlayerMap::new();
+        assert!(map.is_empty());
+        for i in 0..20u32 {
+            map.insert(format!("player{i}"), i);
+        }
+        assert_eq!(map.len(), 20);
+        assert_eq!(map.get("player3"), Some(3));
+
+        assert!(!map.insert_if_absent("player3".to_string(), 99));
+        assert_eq!(map.update("player3", |v| *v += 100), Some(()));
+        assert_eq!(map.get("player3"), Some(103));
+        assert_eq!(map.update("nobody", |v| *v += 1), None);
+
+        assert_eq!(map.count(|v| v % 2 == 0), 10);
+        assert_eq!(map.keys_where(|v| *v > 100), vec!["player3".to_string()]);
+        assert_eq!(map.remove("player3"), Some(103));
+        assert_eq!(map.to_map().len(), 19);
+
+        map.update_all(|v| *v = 0);
+        assert!(map.all(|v| *v == 0));
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_invite_roundtrip() {
+        use crate::invite::Invite;
+
+        let invite = Invite::new("tetris", "abc123")
+            .relays(vec![
+                "wss://relay.damus.io".to_string(),
+                "ws://192.168.1.10:7777".to_string(),
+            ])
+            .seed(1234567890)
+            .host(nostr_sdk::Keys::generate().public_key().to_hex());
+        let token = invite.encode();
+        assert!(token.starts_with("arena1"));
+        assert_eq!(token.parse::<Invite>().unwrap(), invite);
+        // Tokens survive case changes (e.g. QR alphanumeric mode)
+        assert_eq!(Invite::decode(&token.to_uppercase()).unwrap(), invite);
+
+        let minimal = Invite::new("tetris", "abc123");
+        assert_eq!(Invite::decode(&minimal.encode()).unwrap(), minimal);
+    }
+
+    #[test]
+    fn test_invite_rejects_bad_tokens() {
+        use crate::invite::Invite;
+
+        let token = Invite::new("tetris", "abc123").encode();
+        let mut tampered = token.clone();
+        let last = tampered.pop().unwrap();
+        tampered.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(Invite::decode(&tampered).is_err());
+
+        assert!(
+            Invite::decode("npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg")
+                .is_err()
+        );
+        assert!(Invite::decode("not an invite").is_err());
+    }
+
+    #[test]
+    fn test_room_url_relay_hints() {
+        use crate::invite::{parse_room_link, room_url};
+
+        let relays = vec![
+            "wss://relay.damus.io".to_string(),
+            "ws://192.168.1.10:7777/path?x=1".to_string(),
+        ];
+        let url = room_url(Some("https://example.com"), "abc123", &relays);
+        assert!(url.starts_with("https://example.com/battle/abc123?relay=wss%3A%2F%2F"));
+        assert_eq!(parse_room_link(&url), ("abc123".to_string(), relays));
+
+        assert_eq!(room_url(None, "abc123", &[]), "/battle/abc123");
+        assert_eq!(
+            parse_room_link("abc123"),
+            ("abc123".to_string(), Vec::new())
+        );
+        assert_eq!(
+            parse_room_link("https://example.com/battle/abc123/#top").0,
+            "abc123"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_chat() {
+        use crate::chat::sanitize_chat;
+
+        assert_eq!(sanitize_chat("  hello\u{0007} world  ", 100), "hello world");
+        assert_eq!(sanitize_chat("line1\nline2\r", 100), "line1\nline2");
+        // Bidi overrides are stripped, other scripts are kept
+        assert_eq!(
+            sanitize_chat("\u{202E}こんにちは\u{2066}", 100),
+            "こんにちは"
+        );
+        // Length is counted in characters, not bytes
+        assert_eq!(sanitize_chat("日本語テキスト", 3), "日本語");
+        assert_eq!(sanitize_chat("\u{0000}\u{001B}", 100), "");
+    }
+
+    #[test]
+    fn test_chat_flood_guard() {
+        use crate::chat::FloodGuard;
+
+        let mut guard = FloodGuard::default();
+        assert!(guard.allow(0, 2, 1000));
+        assert!(guard.allow(100, 2, 1000));
+        assert!(!guard.allow(200, 2, 1000));
+        assert_eq!(guard.retry_at(1000), 1000);
+        assert!(guard.allow(1000, 2, 1000));
+
+        // 0 disables the limit
+        assert!((0..100).all(|t| guard.allow(t, 0, 1000)));
+    }
+
+    #[test]
+    fn test_protocol_manifest() {
+        use crate::manifest::protocol_manifest;
+
+        let manifest = protocol_manifest();
+        assert_eq!(manifest.version, PROTOCOL_VERSION);
+        for kind in [kinds::ROOM, kinds::EPHEMERAL, kinds::REPORT] {
+            assert!(manifest.kind(kind).is_some());
+        }
+
+        // Message types match the wire `type` names
+        let game = manifest.kind(kinds::EPHEMERAL).unwrap();
+        for content in [
+            EventContent::GameStart(GameStartEventContent::default()),
+            EventContent::Chat(ChatEventContent {
+                text: "hi".to_string(),
+            }),
+        ] {
+            let json = serde_json::to_value(&content).unwrap();
+            let message_type = json["type"].as_str().unwrap();
+            assert!(game.messages.iter().any(|m| m.message_type == message_type));
+        }
+
+        // Every known type is in the manifest, and nothing else
+        let mut known = EventContent::TYPES.to_vec();
+        let mut listed: Vec<&str> = game.messages.iter().map(|m| m.message_type).collect();
+        known.sort_unstable();
+        listed.sort_unstable();
+        assert_eq!(known, listed);
+
+        let chat = game.messages.iter().find(|m| m.message_type == "chat");
+        assert_eq!(chat.unwrap().max_per_minute, Some(30));
+
+        let json: serde_json::Value = serde_json::from_str(&manifest.to_json()).unwrap();
+        assert_eq!(json["kinds"][0]["kind"], 30078);
+    }
+
+    #[test]
+    fn test_turn_event_roundtrip() {
+        use crate::migrate::{decode_event, decode_room, encode_event};
+
+        let json = encode_event(&EventContent::Turn(TurnEventContent {
+            player_pubkey: "def456".to_string(),
+            player_move: None,
+        }))
+        .unwrap();
+        assert!(json.contains(r#""type":"turn""#));
+        match decode_event(&json).unwrap() {
+            EventContent::Turn(turn) => assert_eq!(turn.player_pubkey, "def456"),
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // Rooms without a turn still parse
+        let room = decode_room(
+            r#"{"version":2,"status":"playing","seed":1,"host_pubkey":"abc","max_players":2}"#,
+        )
+        .unwrap();
+        assert!(room.turn.is_none());
+    }
+
+    #[test]
+    fn test_countdown_event_roundtrip() {
+        use crate::migrate::{decode_event, decode_room, encode_event};
+
+        let json = encode_event(&EventContent::Countdown(CountdownEventContent {
+            starts_at: 1704000003000,
+        }))
+        .unwrap();
+        assert!(json.contains(r#""type":"countdown""#));
+        match decode_event(&json).unwrap() {
+            EventContent::Countdown(countdown) => assert_eq!(countdown.starts_at, 1704000003000),
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let room = decode_room(
+            r#"{"version":2,"status":"waiting","seed":1,"host_pubkey":"abc","max_players":2,"countdown_at":1704000003000}"#,
+        )
+        .unwrap();
+        assert_eq!(room.countdown_at, Some(1704000003000));
+    }
+
+    #[test]
+    fn test_invite_event_roundtrip() {
+        use crate::migrate::{decode_event, encode_event};
+
+        let json = encode_event(&EventContent::Invite(InviteEventContent {
+            action: InviteAction::Decline,
+            game_id: "test-game".to_string(),
+            room_id: "abc123".to_string(),
+            relays: Vec::new(),
+            proof: None,
+        }))
+        .unwrap();
+        assert!(json.contains(r#""action":"decline""#));
+        assert!(!json.contains("relays"));
+        match decode_event(&json).unwrap() {
+            EventContent::Invite(invite) => {
+                assert_eq!(invite.action, InviteAction::Decline);
+                assert_eq!(invite.room_id, "abc123");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_party_event_roundtrip() {
+        use crate::migrate::{decode_event, encode_event};
+
+        let json = encode_event(&EventContent::Party(PartyEventContent {
+            action: PartyAction::Presence,
+            room_id: None,
+            relays: Vec::new(),
+        }))
+        .unwrap();
+        assert!(json.contains(r#""type":"party""#));
+        assert!(!json.contains("room_id"));
+
+        let json = r#"{"version":2,"type":"party","action":"match","room_id":"abc123","relays":["wss://a"]}"#;
+        match decode_event(json).unwrap() {
+            EventContent::Party(party) => {
+                assert_eq!(party.action, PartyAction::Match);
+                assert_eq!(party.room_id.as_deref(), Some("abc123"));
+                assert_eq!(party.relays, vec!["wss://a".to_string()]);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_activity_status_roundtrip() {
+        use crate::activity::{Activity, ActivityStatus};
+
+        let activity = Activity {
+            pubkey: "abc".to_string(),
+            game_id: "tetris".to_string(),
+            status: ActivityStatus::Playing,
+            room_id: Some("room42".to_string()),
+            room_url: None,
+            updated_at: 1000,
+        };
+        assert_eq!(activity.to_string(), "playing tetris");
+
+        let mut tags = activity.tags();
+        tags.push(vec!["d".to_string(), Activity::status_tag("tetris")]);
+        let parsed =
+            Activity::from_status("abc".to_string(), &activity.text(), &tags, 1000).unwrap();
+        assert_eq!(parsed, activity);
+
+        // Cleared and foreign statuses are skipped
+        assert!(Activity::from_status("abc".to_string(), "", &tags, 1000).is_none());
+        let music = vec![vec!["d".to_string(), "music".to_string()]];
+        assert!(Activity::from_status("abc".to_string(), "song", &music, 1000).is_none());
+    }
+
+    #[test]
+    fn test_match_record_roundtrip() {
+        use crate::record::{MatchRecord, MatchResult, PlayerOutcome};
+
+        let mut record: MatchRecord<serde_json::Value> =
+            MatchRecord::new("tetris", "room42", 42, 1000)
+                .player("abc")
+                .player("def");
+        record.push_input(1200, "def", serde_json::json!({"dx": -1}));
+        record.push_input(1100, "abc", serde_json::json!({"dx": 1, "note": "a b"}));
+        record.push_keyframe(1300, "abc", serde_json::json!({"score": 10}));
+        record.finish(
+            2000,
+            MatchResult {
+                winner: Some("abc".to_string()),
+                outcomes: vec![PlayerOutcome {
+                    pubkey: "def".to_string(),
+                    reason: "topped \"out\"".to_string(),
+                    final_score: Some(5),
+                }],
+            },
+        );
+        assert_eq!(record.frames[0].at, 1100);
+
+        let json = record.to_json().unwrap();
+        assert_eq!(MatchRecord::from_json(&json).unwrap(), record);
+
+        let text = record.to_text().unwrap();
+        assert!(text.starts_with("[Schema \"nostr-arena.match/1\"]\n"));
+        assert_eq!(MatchRecord::from_text(&text).unwrap(), record);
+
+        // Deterministic replay over inputs only
+        let x = record.replay(0i64, |x, frame| *x += frame.data["dx"].as_i64().unwrap());
+        assert_eq!(x, 0);
+
+        // Archives keep the result and keyframes only
+        let compact = record.compact();
+        assert_eq!(compact.frames.len(), 1);
+        assert_eq!(compact.frames[0].at, 1300);
+        assert_eq!(compact.result, record.result);
+
+        assert!(
+            MatchRecord::<serde_json::Value>::from_json(r#"{"schema":"other/1","game_id":"g","room_id":"r","seed":0,"players":[],"started_at":0}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_peer_latency_estimate() {
+        use crate::latency::{ArenaStats, PeerLatency};
+
+        // Peer clock 500 ms ahead, 40 ms each way:
+        // their heartbeats look 460 ms early, ours look 540 ms late to them
+        let mut latency = PeerLatency::default();
+        latency.record(-460, None);
+        assert_eq!(latency.one_way_ms, 0);
+
+        latency.record(-460, Some(540));
+        for _ in 0..50 {
+            latency.record(-460, Some(540));
+        }
+        assert!(latency.one_way_ms.abs_diff(40) <= 5, "{latency:?}");
+        assert!((latency.clock_offset_ms - 500).abs() <= 20, "{latency:?}");
+        assert_eq!(latency.last_delay_ms, -460);
+
+        // Without echoes the known offset is still applied
+        latency.record(-450, None);
+        assert!(latency.one_way_ms.abs_diff(40) <= 10, "{latency:?}");
+
+        let mut stats = ArenaStats::default();
+        assert_eq!(stats.mean_one_way_ms(), 0);
+        stats.peers.insert("abc".to_string(), latency);
+        assert_eq!(stats.mean_one_way_ms(), latency.one_way_ms);
+    }
+
+    #[test]
+    fn test_roster_compression() {
+        use crate::arena::room_content;
+        use crate::migrate::{decode_event, decode_room, encode_event, encode_room};
+
+        let players: Vec<PlayerPresence> = (0..20)
+            .map(|i| PlayerPresence {
+                pubkey: format!("{i:064x}"),
+                joined_at: 1704000000000,
+                last_seen: 1704000000000,
+                seen_at: 0,
+                ready: false,
+                latency_ms: None,
+                away: false,
+                session: None,
+                capabilities: None,
+                identity: None,
+            })
+            .collect();
+        let config = ArenaConfig::new("test-game").max_players(32);
+        let mut state = RoomState {
+            peer_versions: players.iter().map(|p| (p.pubkey.clone(), 3)).collect(),
+            ..Default::default()
+        };
+
+        // Peers that may not read a compressed roster get the full list
+        state.peer_versions.insert(players[7].pubkey.clone(), 2);
+        let content_of = |state: &RoomState| {
+            room_content(
+                &config,
+                "abc".to_string(),
+                RoomStatus::Waiting,
+                state,
+                players.clone(),
+            )
+        };
+        assert_eq!(content_of(&state).players.len(), 20);
+        state.peer_versions.remove(&players[7].pubkey);
+        assert_eq!(content_of(&state).players.len(), 20);
+        state.peer_versions.insert(players[7].pubkey.clone(), 3);
+
+        let content = room_content(
+            &config,
+            "abc".to_string(),
+            RoomStatus::Waiting,
+            &state,
+            players.clone(),
+        );
+        assert!(content.players.is_empty());
+        assert_eq!(content.player_count(), 20);
+        assert!(content.is_roster_compressed());
+        let hash = content.roster_hash.clone().unwrap();
+        let mut reversed = players.clone();
+        reversed.reverse();
+        assert_eq!(
+            roster_hash(reversed.iter().map(|p| p.pubkey.as_str())),
+            hash
+        );
+
+        let room = decode_room(&encode_room(&content).unwrap()).unwrap();
+        assert_eq!(room.player_count(), 20);
+        assert_eq!(room.roster_hash.as_deref(), Some(hash.as_str()));
+
+        // Small rooms and rooms with compression turned off keep the list
+        let content = room_content(
+            &config,
+            "abc".to_string(),
+            RoomStatus::Waiting,
+            &state,
+            players[..4].to_vec(),
+        );
+        assert_eq!(content.players.len(), 4);
+        assert!(content.player_count.is_none() && !content.is_roster_compressed());
+        let content = room_content(
+            &config.clone().roster_threshold(0),
+            "abc".to_string(),
+            RoomStatus::Waiting,
+            &state,
+            players.clone(),
+        );
+        assert_eq!(content.players.len(), 20);
+
+        let json = encode_event(&EventContent::Roster(RosterEventContent {
+            action: RosterAction::Response,
+            players: players[..2].to_vec(),
+        }))
+        .unwrap();
+        assert!(json.contains(r#""action":"response""#));
+        match decode_event(&json).unwrap() {
+            EventContent::Roster(roster) => assert_eq!(roster.players.len(), 2),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_hosted_traffic_for_32_players() {
+        use crate::manifest::estimate_traffic;
+
+        // Conservative per-connection publish limit of public relays
+        const RELAY_LIMIT_PER_MINUTE: u32 = 10 * 60;
+
+        let mesh = ArenaConfig::new("party-game")
+            .preset(Preset::Party)
+            .max_players(32);
+        let hosted = mesh.clone().sync_mode(SyncMode::Hosted);
+
+        for playing in [false, true] {
+            let m = estimate_traffic(&mesh, 32, playing);
+            let h = estimate_traffic(&hosted, 32, playing);
+            assert!(h.max_per_publisher <= RELAY_LIMIT_PER_MINUTE, "{h:?}");
+            // Fan-out stops growing with the square of the room size
+            assert!(
+                h.delivered_per_minute * 10 < m.delivered_per_minute,
+                "{h:?} {m:?}"
+            );
+            let h64 = estimate_traffic(&hosted, 64, playing);
+            assert!(h64.delivered_per_minute <= h.delivered_per_minute * 21 / 10);
+        }
+
+        // Lobby: 31 idle heartbeats to the host, presence digests to everyone
+        let lobby = estimate_traffic(&hosted, 32, false);
+        assert_eq!(lobby.max_per_publisher, 4);
+        assert_eq!(lobby.delivered_per_minute, 4 * 31 + 2 * 31);
+    }
+
+    #[test]
+    fn test_digest_event_roundtrip() {
+        use crate::migrate::{decode_event, decode_room, encode_event};
+
+        let digest = DigestEventContent {
+            states: [("abc".to_string(), serde_json::json!({"x": 1}))].into(),
+            presence: [("abc".to_string(), 1704000000000)].into(),
+        };
+        let json = encode_event(&EventContent::Digest(digest)).unwrap();
+        assert!(json.contains(r#""type":"digest""#));
+        match decode_event(&json).unwrap() {
+            EventContent::Digest(digest) => {
+                assert_eq!(digest.states["abc"]["x"], 1);
+                assert_eq!(digest.presence["abc"], 1704000000000);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let room = decode_room(
+            r#"{"version":2,"status":"waiting","seed":1,"host_pubkey":"abc","max_players":32,"sync_mode":"hosted"}"#,
+        )
+        .unwrap();
+        assert_eq!(room.sync_mode, SyncMode::Hosted);
+        assert_eq!(create_host_tag("game", "room"), "game-room-host");
+
+        let state = RoomState {
+            sync_mode: SyncMode::Hosted,
+            ..Default::default()
+        };
+        assert!(state.sends_to_host());
+    }
+
+    #[test]
+    fn test_idle_backoff() {
+        let config = ArenaConfig::new("test-game");
+        assert_eq!(config.heartbeat_interval_for(false), 3000);
+        assert_eq!(config.heartbeat_interval_for(true), 30000);
+        assert_eq!(config.presence_interval_for(true), 120000);
+        assert_eq!(config.disconnect_threshold_for(false), 10000);
+        assert_eq!(config.disconnect_threshold_for(true), 100000);
+
+        // Never faster than the playing cadence, and 0 turns it off
+        let config = ArenaConfig::new("test-game").preset(Preset::Async);
+        assert_eq!(config.heartbeat_interval_for(true), 60_000);
+        let config = ArenaConfig::new("test-game").idle_intervals(0, 0);
+        assert_eq!(config.heartbeat_interval_for(true), 3000);
+        assert_eq!(config.disconnect_threshold_for(true), 10000);
+
+        let mut state = RoomState {
+            status: RoomStatus::Waiting,
+            ..Default::default()
+        };
+        assert!(state.is_idle());
+        state.countdown_at = Some(1704000003000);
+        assert!(!state.is_idle());
+        state.countdown_at = None;
+        state.status = RoomStatus::Playing;
+        assert!(!state.is_idle());
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+        use crate::rng::{SeededRng, derive_seed};
+
+        // Reference values for bindings reimplementing the scheme
+        let mut rng = SeededRng::new(42);
+        assert_eq!(rng.next_u64(), 0xbdd7_3226_2feb_6e95);
+        assert_eq!(derive_seed(42, "player:abc"), 0xeab2_29e1_3a87_6d69);
+
+        let base = SeededRng::new(42);
+        let mut a = base.round(1).player("abc");
+        let mut b = base.round(1).player("abc");
+        assert_eq!(a.next_u64(), b.next_u64());
+
+        // Streams don't depend on what was drawn from the parent
+        let mut drawn = SeededRng::new(42);
+        drawn.next_u64();
+        assert_eq!(drawn.player("abc"), base.player("abc"));
+
+        let seeds = [
+            base.player("abc").seed(),
+            base.player("def").seed(),
+            base.round(1).seed(),
+            base.round(2).seed(),
+            base.round(1).player("abc").seed(),
+            base.player("abc").round(1).seed(),
+        ];
+        for (i, x) in seeds.iter().enumerate() {
+            assert!(seeds[i + 1..].iter().all(|y| x != y));
+        }
+
+        let mut rng = SeededRng::new(7);
+        assert!((0..1000).all(|_| rng.below(6) < 6));
+        assert!((0..1000).all(|_| (0.0..1.0).contains(&rng.unit())));
+        let mut deck: Vec<u32> = (0..52).collect();
+        rng.shuffle(&mut deck);
+        let mut sorted = deck.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..52).collect::<Vec<_>>());
+        assert_ne!(deck, sorted);
+    }
+
+    #[test]
+    fn test_phase_event_roundtrip() {
+        use crate::migrate::{decode_event, decode_room, encode_event};
+
+        let json = encode_event(&EventContent::Phase(PhaseEventContent {
+            round: 2,
+            phase: Some("draft".to_string()),
+        }))
+        .unwrap();
+        assert!(json.contains(r#""type":"phase""#));
+        match decode_event(&json).unwrap() {
+            EventContent::Phase(phase) => {
+                assert_eq!(phase.round, 2);
+                assert_eq!(phase.phase.as_deref(), Some("draft"));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let room = decode_room(
+            r#"{"version":2,"status":"playing","seed":1,"host_pubkey":"abc","max_players":4,"round":3,"phase":"score"}"#,
+        )
+        .unwrap();
+        assert_eq!(room.round, 3);
+        assert_eq!(room.phase.as_deref(), Some("score"));
+
+        // Rooms of games without rounds stay as they were
+        let room = decode_room(
+            r#"{"version":2,"status":"waiting","seed":1,"host_pubkey":"abc","max_players":2}"#,
+        )
+        .unwrap();
+        let json = crate::migrate::encode_room(&room).unwrap();
+        assert!(!json.contains("round") && !json.contains("phase"));
+    }
+
+    #[test]
+    fn test_round_seed_event_roundtrip() {
+        use crate::migrate::{decode_event, encode_event};
+
+        let json = encode_event(&EventContent::Rematch(RematchEventContent {
+            action: RematchAction::Round,
+            new_seed: Some(99),
+            round: Some(2),
+        }))
+        .unwrap();
+        assert!(json.contains(r#""action":"round""#));
+        match decode_event(&json).unwrap() {
+            EventContent::Rematch(rm) => {
+                assert_eq!(rm.action, RematchAction::Round);
+                assert_eq!((rm.round, rm.new_seed), (Some(2), Some(99)));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // Rematch messages from older peers have no round
+        match decode_event(r#"{"type":"rematch","action":"accept","new_seed":5}"#).unwrap() {
+            EventContent::Rematch(rm) => assert!(rm.round.is_none()),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_scoreboard() {
+        use crate::migrate::{decode_room, encode_room};
+
+        let mut state = RoomState::default();
+        assert!(state.record_score("abc", Some(10)));
+        assert!(state.record_score("def", Some(25)));
+        // A resent game over doesn't count again; the next game does
+        assert!(!state.record_score("abc", Some(10)));
+        state.seed = 2;
+        assert!(state.record_score("abc", Some(20)));
+        assert!(state.record_score("ghi", None));
+
+        let standings: Vec<(&str, i64, u32)> = state
+            .scoreboard
+            .iter()
+            .map(|s| (s.pubkey.as_str(), s.score, s.games))
+            .collect();
+        assert_eq!(standings, [("abc", 30, 2), ("def", 25, 1), ("ghi", 0, 1)]);
+
+        let room = decode_room(
+            r#"{"version":2,"status":"playing","seed":1,"host_pubkey":"abc","max_players":8,"scoreboard":[{"pubkey":"abc","score":30,"games":2}]}"#,
+        )
+        .unwrap();
+        assert_eq!(room.scoreboard, state.scoreboard[..1]);
+        assert!(encode_room(&room).unwrap().contains(r#""score":30"#));
+    }
+
+    #[test]
+    fn test_chess_clock() {
+        use crate::clock::{ChessClock, TimeControl};
+        use crate::types::{ClockEventContent, EventContent};
+
+        let control = TimeControl::new(60_000, 2_000);
+        assert_eq!(control.low_time_ms, 6_000);
+        let mut clock = ChessClock::new(control, ["abc".to_string(), "def".to_string()]);
+        clock.start("abc", 1_000);
+        assert_eq!(clock.remaining("abc", 11_000), Some(50_000));
+        assert_eq!(clock.remaining("def", 11_000), Some(60_000));
+
+        clock.switch("def", 11_000);
+        assert_eq!(clock.remaining("abc", 20_000), Some(52_000));
+        assert_eq!(clock.remaining("def", 20_000), Some(51_000));
+        assert!(clock.low_players(20_000).is_empty());
+        assert_eq!(clock.low_players(66_000), ["def"]);
+
+        assert_eq!(clock.check_flag(70_000), None);
+        assert_eq!(clock.check_flag(71_000).as_deref(), Some("def"));
+        assert_eq!(clock.check_flag(72_000), None);
+        assert_eq!(clock.remaining("def", 90_000), Some(0));
+
+        let json = r#"{"type":"clock","remaining":{"abc":52000},"running":"abc"}"#;
+        match serde_json::from_str::<EventContent>(json).unwrap() {
+            EventContent::Clock(ClockEventContent {
+                remaining,
+                running,
+                flagged,
+            }) => {
+                assert_eq!(remaining["abc"], 52_000);
+                assert_eq!(running.as_deref(), Some("abc"));
+                assert!(flagged.is_none());
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_input_chain() {
+        use crate::types::{AuditEventContent, EventContent, InputChain};
+
+        let mut sent = InputChain::default();
+        let mut received = InputChain::default();
+        for input in [
+            r#"{"type":"state","game_state":1}"#,
+            r#"{"type":"state","game_state":2}"#,
+        ] {
+            sent.push(input);
+            received.push(input);
+        }
+        assert_eq!(sent, received);
+        assert_eq!(sent.count, 2);
+
+        let mut altered = InputChain::default();
+        altered.push(r#"{"type":"state","game_state":2}"#);
+        altered.push(r#"{"type":"state","game_state":1}"#);
+        assert_ne!(altered.hash, sent.hash);
+
+        let json = r#"{"type":"audit","inputs":2,"chain":"ab12"}"#;
+        match serde_json::from_str::<EventContent>(json).unwrap() {
+            EventContent::Audit(AuditEventContent { inputs, chain }) => {
+                assert_eq!(inputs, 2);
+                assert_eq!(chain, "ab12");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_channel_message_roundtrip() {
+        use crate::migrate::{decode_event, encode_event};
+        use crate::types::{EventContent, MessageEventContent};
+
+        let json = encode_event(&EventContent::Message(MessageEventContent {
+            channel: "cursor".to_string(),
+            payload: serde_json::json!({"x": 120, "y": 48}),
+        }))
+        .unwrap();
+        match decode_event(&json).unwrap() {
+            EventContent::Message(message) => {
+                assert_eq!(message.channel, "cursor");
+                assert_eq!(message.payload["x"], 120);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    /// Bindings run these futures on a runtime thread while releasing the
+    /// interpreter lock (`py.allow_threads`), so they must stay `Send`
+    #[test]
+    fn test_arena_futures_are_send() {
+        use crate::arena::Arena;
+        use crate::types::ArenaConfig;
+
+        fn send<F: Send>(_: F) {}
+        #[allow(dead_code)]
+        fn check(arena: &Arena<serde_json::Value>) {
+            send(arena.connect());
+            send(arena.create());
+            send(arena.join("room"));
+            #[cfg(feature = "discovery")]
+            send(arena.find_rooms(None, 10));
+            send(arena.recv());
+            send(arena.send_state(&serde_json::Value::Null));
+            send(arena.leave());
+        }
+        send(Arena::<serde_json::Value>::new(ArenaConfig::new("game")));
+        #[cfg(feature = "discovery")]
+        send(Arena::<serde_json::Value>::list_rooms(
+            "game",
+            Vec::new(),
+            None,
+            10,
+        ));
+    }
+
+    #[test]
+    fn test_notice_text() {
+        use crate::notify::Notice;
+
+        let invite = Notice::Invite {
+            from: "abcdef0123456789".to_string(),
+            room_id: "r1".to_string(),
+        };
+        assert_eq!(invite.title(), "Game invitation");
+        assert_eq!(invite.body(), "abcdef01 invited you to room r1");
+
+        let turn = Notice::TurnStart {
+            room_id: "r1".to_string(),
+        };
+        assert_eq!(turn.body(), "It's your move in room r1");
+    }
+
+    #[test]
+    fn test_game_start_config() {
+        use crate::migrate::{decode_event, decode_room, encode_event};
+        use crate::types::{EventContent, GameStartEventContent};
+
+        // Older peers send an empty gamestart
+        match decode_event(r#"{"type":"gamestart"}"#).unwrap() {
+            EventContent::GameStart(start) => {
+                assert!(start.config.is_none() && start.seed.is_none())
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let json = encode_event(&EventContent::GameStart(GameStartEventContent {
+            config: Some(serde_json::json!({"map": "canyon"})),
+            seed: Some(7),
+            turn_order: vec!["def".to_string(), "abc".to_string()],
+        }))
+        .unwrap();
+        match decode_event(&json).unwrap() {
+            EventContent::GameStart(start) => {
+                assert_eq!(start.config.unwrap()["map"], "canyon");
+                assert_eq!(start.seed, Some(7));
+                assert_eq!(start.turn_order, ["def", "abc"]);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let room = decode_room(
+            r#"{"version":2,"status":"waiting","seed":1,"host_pubkey":"abc","max_players":2,"start_config":{"laps":3}}"#,
+        )
+        .unwrap();
+        assert_eq!(room.start_config.unwrap()["laps"], 3);
+    }
+
+    #[test]
+    fn test_turn_order() {
+        use crate::rng::turn_order;
+
+        let players: Vec<String> = ["c", "a", "d", "b"].iter().map(|s| s.to_string()).collect();
+        let order = turn_order(42, &players);
+
+        // Same order whatever order the peers know the players in
+        let mut reversed = players.clone();
+        reversed.reverse();
+        assert_eq!(turn_order(42, &reversed), order);
+
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, ["a", "b", "c", "d"]);
+
+        // Other seeds give other orders
+        assert!((0..20).any(|seed| turn_order(seed, &players) != order));
+    }
+
+    #[test]
+    fn test_vote_tally() {
+        use crate::types::{EventContent, Vote, VoteAction};
+
+        let options = vec![
+            "desert".to_string(),
+            "forest".to_string(),
+            "ice".to_string(),
+        ];
+        let mut vote = Vote::new("map", options);
+        vote.ballots.insert("a".to_string(), "forest".to_string());
+        vote.ballots.insert("b".to_string(), "desert".to_string());
+        assert_eq!(
+            vote.tally(),
+            vec![
+                ("desert".to_string(), 1),
+                ("forest".to_string(), 1),
+                ("ice".to_string(), 0)
+            ]
+        );
+        // Ties go to the option listed first
+        assert_eq!(vote.leader().as_deref(), Some("desert"));
+
+        vote.ballots.insert("c".to_string(), "forest".to_string());
+        assert_eq!(vote.leader().as_deref(), Some("forest"));
+
+        let json = r#"{"type":"vote","action":"result","topic":"map","choice":"forest","tally":{"desert":1,"forest":2}}"#;
+        let content = serde_json::from_str::<EventContent>(json).unwrap();
+        let EventContent::Vote(result) = content else {
+            panic!("expected a vote");
+        };
+        assert_eq!(result.action, VoteAction::Result);
+        assert_eq!(result.choice.as_deref(), Some("forest"));
+        assert_eq!(result.tally["forest"], 2);
+    }
+
+    #[test]
+    fn test_capabilities_intersect() {
+        use crate::types::{Capabilities, JoinEventContent};
+
+        let ours = Capabilities {
+            compression: true,
+            wire_formats: vec!["cbor".to_string(), "json".to_string()],
+            input_sync: true,
+            encryption: false,
+            batch: true,
+        };
+        let theirs = Capabilities {
+            compression: true,
+            wire_formats: vec!["json".to_string(), "cbor".to_string()],
+            input_sync: false,
+            encryption: true,
+            batch: true,
+        };
+        let common = ours.intersect(&theirs);
+        assert!(common.compression);
+        assert!(!common.input_sync);
+        assert!(!common.encryption);
+        // Our preference order wins
+        assert_eq!(common.wire_formats, ["cbor", "json"]);
+
+        // Older peers announce nothing and speak JSON only
+        let join: JoinEventContent = serde_json::from_str(r#"{"player_pubkey":"abc"}"#).unwrap();
+        let common = ours.intersect(&join.capabilities.unwrap_or_default());
+        assert_eq!(common, Capabilities::default());
+
+        let caps: Capabilities = serde_json::from_str(r#"{"input_sync":true}"#).unwrap();
+        assert_eq!(caps.wire_formats, ["json"]);
+    }
+
+    #[test]
+    fn test_relay_clock_skew() {
+        use crate::latency::{RelayClocks, SKEW_THRESHOLD_MS};
+
+        let mut clocks = RelayClocks::default();
+        let now = 1_704_000_000_000;
+        for i in 0..10 {
+            // A healthy relay: created_at truncated to seconds, some latency
+            clocks.record("wss://good", now + i * 1000 - 700, now + i * 1000);
+            // A relay clamping timestamps a minute into the past
+            clocks.record("wss://clamp", now + i * 1000 - 60_000, now + i * 1000);
+        }
+
+        assert!(!clocks.relays["wss://good"].skewed);
+        assert!(clocks.relays["wss://clamp"].skewed);
+        assert!(clocks.skew_ms() < -SKEW_THRESHOLD_MS);
+
+        // Alerted once, when the relay became skewed
+        let alerts = clocks.take_alerts();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].0, "wss://clamp");
+        assert!(clocks.take_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_lobby_state_machine() {
+        use crate::arena::ArenaEvent;
+        use crate::lobby::{LobbyPhase, LobbyStateMachine};
+
+        let presence = |pubkey: &str, joined_at| PlayerPresence {
+            pubkey: pubkey.to_string(),
+            joined_at,
+            last_seen: joined_at,
+            seen_at: 0,
+            ready: false,
+            latency_ms: None,
+            away: false,
+            session: None,
+            capabilities: None,
+            identity: None,
+        };
+        let mut lobby = LobbyStateMachine::new();
+        lobby.sync(&[presence("bob", 2), presence("alice", 1)]);
+        assert_eq!(lobby.view().players[0].pubkey, "alice");
+
+        let events: Vec<ArenaEvent<()>> = vec![
+            ArenaEvent::PlayerJoin(presence("carol", 3)),
+            ArenaEvent::PlayerReady {
+                pubkey: "bob".to_string(),
+                ready: true,
+            },
+            ArenaEvent::PlayerLeave("carol".to_string()),
+            ArenaEvent::Error(Reason::from_text("relay timeout")),
+        ];
+        for event in &events {
+            lobby.apply(event);
+        }
+        let view = lobby.view();
+        assert_eq!(view.players.len(), 2);
+        assert_eq!(view.status, "1/2 ready");
+        assert_eq!(view.banners, ["relay timeout"]);
+
+        lobby.set_ready("alice", true);
+        assert_eq!(lobby.view().status, "Everyone is ready");
+
+        lobby.apply(&ArenaEvent::<()>::CountdownStart(3));
+        lobby.apply(&ArenaEvent::<()>::CountdownTick(2));
+        let view = lobby.view();
+        assert_eq!(view.phase, LobbyPhase::Countdown);
+        assert_eq!(view.status, "Starting in 2");
+
+        lobby.apply(&ArenaEvent::<()>::GameStart { config: None });
+        // A late tick doesn't take the lobby back to the countdown
+        lobby.apply(&ArenaEvent::<()>::CountdownTick(0));
+        assert_eq!(lobby.view().phase, LobbyPhase::Playing);
+        assert_eq!(lobby.view().countdown, None);
+
+        lobby.apply(&ArenaEvent::<()>::MatchAbandoned);
+        assert_eq!(lobby.view().status, "Match abandoned");
+
+        // A rematch clears ready flags
+        lobby.apply(&ArenaEvent::<()>::RematchStart(7));
+        let view = lobby.view();
+        assert_eq!(view.phase, LobbyPhase::Waiting);
+        assert_eq!(view.ready_count, 0);
+
+        lobby.dismiss_banners();
+        assert!(lobby.view().banners.is_empty());
+    }
+
+    #[test]
+    fn test_claim_arbitration() {
+        use crate::types::{Claim, claim_time};
+
+        // A peer 500 ms ahead claimed 300 ms before its claim arrived
+        assert_eq!(claim_time(10_800, 500, 10_600, 250), 10_350);
+        // Claims can't predate their receipt by more than the window...
+        assert_eq!(claim_time(1_000, 0, 10_000, 250), 9_750);
+        // ...or be dated after it
+        assert_eq!(claim_time(20_000, 0, 10_000, 250), 10_000);
+
+        let mut claim = Claim::default();
+        claim.add("near", 10_500);
+        claim.add("far", 10_350);
+        // Only a player's first claim counts
+        claim.add("near", 10_000);
+        assert_eq!(claim.earliest().as_deref(), Some("far"));
+
+        // Equal times go to the lower public key
+        claim.add("alpha", 10_350);
+        assert_eq!(claim.earliest().as_deref(), Some("alpha"));
+    }
+
+    #[test]
+    fn test_reason_codes() {
+        use crate::error::ArenaError;
+
+        // Old peers send text only; known texts map to codes
+        let legacy: GameOverEventContent =
+            serde_json::from_str(r#"{"reason":"out of time","final_score":3}"#).unwrap();
+        assert_eq!(legacy.reason().code, ReasonCode::OutOfTime);
+        let custom: GameOverEventContent =
+            serde_json::from_str(r#"{"reason":"topped out"}"#).unwrap();
+        let reason = custom.reason();
+        assert_eq!(reason.code, ReasonCode::Custom);
+        assert_eq!(reason.to_string(), "topped out");
+
+        // New peers send the code and detail alongside the text
+        let content = GameOverEventContent::new(
+            Reason::new(ReasonCode::Resigned).with_detail("tilted"),
+            None,
+        );
+        assert_eq!(content.reason, "Resigned: tilted");
+        let json = serde_json::to_string(&content).unwrap();
+        let parsed: GameOverEventContent = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.reason(), content.reason());
+
+        // Codes from newer versions fall back to Custom
+        let future: Reason = serde_json::from_str(r#"{"code":"surrendered"}"#).unwrap();
+        assert_eq!(future.code, ReasonCode::Custom);
+
+        let error = Reason::from(&ArenaError::NotAuthorized("host only".to_string()));
+        assert_eq!(error.code, ReasonCode::NotAuthorized);
+        assert_eq!(error.to_string(), "Not authorized: host only");
+    }
+
+    #[test]
+    fn test_leave_event_roundtrip() {
+        use crate::arena::ShutdownReport;
+        use crate::migrate::{decode_event, encode_event};
+
+        let json = encode_event(&EventContent::Leave(LeaveEventContent::default())).unwrap();
+        assert!(json.contains(r#""type":"leave""#));
+        assert!(matches!(
+            decode_event(&json).unwrap(),
+            EventContent::Leave(LeaveEventContent { reason: None })
+        ));
+
+        let report = ShutdownReport {
+            room_id: Some("room42".to_string()),
+            delivered: vec!["leave"],
+            undelivered: vec!["room"],
+        };
+        assert!(!report.is_complete());
+    }
+
+    #[test]
+    fn test_reconcile_roster() {
+        let presence = |pubkey: &str, joined_at| PlayerPresence {
+            pubkey: pubkey.to_string(),
+            joined_at,
+            last_seen: joined_at,
+            seen_at: 0,
+            ready: false,
+            latency_ms: None,
+            away: false,
+            session: None,
+            capabilities: None,
+            identity: None,
+        };
+        // We missed carol's join; dave left without us noticing; erin
+        // joined after the host published
+        let local = [
+            presence("me", 100),
+            presence("host", 50),
+            presence("dave", 200),
+            presence("erin", 2000),
+        ];
+        let host = [
+            presence("host", 50),
+            presence("me", 100),
+            presence("carol", 300),
+        ];
+
+        let diff = reconcile_roster(&local, &host, 1000, "me");
+        let added: Vec<&str> = diff.added.iter().map(|p| p.pubkey.as_str()).collect();
+        assert_eq!(added, ["carol"]);
+        assert_eq!(diff.removed, ["dave"]);
+
+        // Never drop ourselves, even when the host doesn't list us yet
+        let diff = reconcile_roster(&local[..2], &host[..1], 1000, "me");
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_event_shadow() {
+        use crate::migrate::{decode_event, encode_event};
+        use crate::shadow::EventShadow;
+        use nostr_sdk::{EventBuilder, Keys, Kind, Timestamp};
+
+        let keys = Keys::generate();
+        let ready = encode_event(&EventContent::Ready(ReadyEventContent { ready: true })).unwrap();
+        let event = |secs: u64| {
+            EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), &ready)
+                .custom_created_at(Timestamp::from_secs(secs))
+                .sign_with_keys(&keys)
+                .unwrap()
+        };
+        let (early, late) = (event(1000), event(1005));
+
+        let mut shadow = EventShadow::new();
+        assert!(shadow.record(&early, 1_000_000, 10_000));
+        assert!(shadow.record(&late, 1_005_000, 10_000));
+        // A resent copy is recognized
+        assert!(!shadow.record(&early, 1_006_000, 10_000));
+        assert_eq!(shadow.since(1_003_000).len(), 1);
+        assert_eq!(shadow.since(0).len(), 2);
+
+        // Events older than the window are dropped
+        assert!(shadow.record(&event(1012), 1_012_000, 10_000));
+        assert!(!shadow.contains(&early.id));
+        assert_eq!(shadow.len(), 2);
+
+        assert!(decode_event(&ready).unwrap().is_control());
+        let request = encode_event(&EventContent::ResendRequest(ResendRequestEventContent {
+            since: 1_003_000,
+        }))
+        .unwrap();
+        assert!(request.contains(r#""type":"resendrequest""#));
+        assert!(!decode_event(&request).unwrap().is_control());
+    }
+
+    #[test]
+    fn test_middleware_order() {
+        use crate::middleware::EventMiddleware;
+
+        struct Tag(&'static str);
+        impl EventMiddleware for Tag {
+            fn outbound(&self, content: String) -> Option<String> {
+                Some(format!("{content}{}", self.0))
+            }
+            fn inbound(&self, _pubkey: &str, content: String) -> Option<String> {
+                content.strip_suffix(self.0).map(str::to_string)
+            }
+        }
+
+        let config = ArenaConfig::new("test");
+        assert_eq!(config.middleware.outbound("x").unwrap(), "x");
+
+        let config = config
+            .with_middleware(Box::new(Tag("a")))
+            .with_middleware(Box::new(Tag("b")));
+        let middleware = &config.middleware;
+        assert_eq!(middleware.len(), 2);
+        // Outbound in the order added, inbound in reverse
+        assert_eq!(middleware.outbound("x").unwrap(), "xab");
+        assert_eq!(middleware.inbound("peer", "xab").unwrap(), "x");
+        // A layer that returns None drops the message
+        assert!(middleware.inbound("peer", "xba").is_none());
+    }
+
+    mod fuzz {
+        use crate::clock::{ChessClock, TimeControl};
+        use crate::error::ArenaError;
+        use crate::latency::{PeerLatency, RelaySkew};
+        use crate::migrate::{MAX_CONTENT_BYTES, decode_event, decode_room, decode_state};
+        use crate::types::*;
+        use proptest::prelude::*;
+        use serde_json::{Value, json};
+
+        const TYPES: &[&str] = &[
+            "room",
+            "join",
+            "state",
+            "gameover",
+            "rematch",
+            "heartbeat",
+            "ready",
+            "gamestart",
+            "seat",
+            "chat",
+            "turn",
+            "countdown",
+            "roster",
+            "digest",
+            "phase",
+            "clock",
+            "audit",
+            "message",
+            "vote",
+            "claim",
+            "gameended",
+            "leave",
+            "resendrequest",
+            "snapshotrequest",
+            "snapshot",
+            "unknown",
+        ];
+
+        /// Any JSON, with extreme numbers mixed in
+        fn json_value() -> impl Strategy<Value = Value> {
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::from),
+                any::<i64>().prop_map(Value::from),
+                any::<u64>().prop_map(Value::from),
+                any::<f64>().prop_map(Value::from),
+                Just(json!(u64::MAX)),
+                Just(json!(i64::MIN)),
+                Just(json!(1e300)),
+                ".{0,12}".prop_map(Value::from),
+            ];
+            leaf.prop_recursive(6, 64, 8, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..8).prop_map(Value::from),
+                    prop::collection::hash_map("[a-z_]{1,12}", inner, 0..8)
+                        .prop_map(|map| Value::Object(map.into_iter().collect())),
+                ]
+            })
+        }
+
+        /// A message of a real type whose fields hold anything
+        fn message() -> impl Strategy<Value = String> {
+            (
+                prop::sample::select(TYPES),
+                prop::collection::hash_map(
+                    prop::sample::select(
+                        &[
+                            "version",
+                            "ready",
+                            "seat",
+                            "seats",
+                            "players",
+                            "game_state",
+                            "timestamp",
+                            "seen",
+                            "since",
+                            "starts_at",
+                            "remaining",
+                            "final_score",
+                            "new_seed",
+                            "round",
+                            "player_pubkey",
+                            "at",
+                            "claim_id",
+                            "reason",
+                            "code",
+                        ][..],
+                    ),
+                    json_value(),
+                    0..8,
+                ),
+            )
+                .prop_map(|(message_type, fields)| {
+                    let mut map: serde_json::Map<String, Value> = fields
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v))
+                        .collect();
+                    map.insert("type".to_string(), Value::from(message_type));
+                    Value::Object(map).to_string()
+                })
+        }
+
+        proptest! {
+            #[test]
+            fn decoding_arbitrary_text_never_panics(text in ".{0,256}") {
+                let _ = decode_event(&text);
+                let _ = decode_room(&text);
+                let _ = decode_state::<Value>(&text);
+            }
+
+            #[test]
+            fn decoding_arbitrary_messages_never_panics(content in message()) {
+                let _ = decode_event(&content);
+                let _ = decode_room(&content);
+                let _ = decode_state::<Value>(&content);
+                if let Ok(event) = decode_event(&content) {
+                    let _ = event.is_control();
+                }
+            }
+
+            #[test]
+            fn decoding_arbitrary_rooms_never_panics(
+                value in json_value(),
+                players in 0usize..64,
+            ) {
+                let player = json!({
+                    "pubkey": "p",
+                    "joined_at": u64::MAX,
+                    "last_seen": 0,
+                    "ready": true,
+                });
+                let mut room = json!({
+                    "status": "waiting",
+                    "seed": 1,
+                    "host_pubkey": "host",
+                    "max_players": usize::MAX,
+                    "players": vec![player; players],
+                });
+                room["start_config"] = value;
+                if let Ok(room) = decode_room(&room.to_string()) {
+                    prop_assert_eq!(room.player_count(), players);
+                }
+            }
+
+            #[test]
+            fn peer_timings_never_overflow(
+                delays in prop::collection::vec((any::<i64>(), any::<Option<i64>>()), 1..16),
+                created in any::<u64>(),
+                received in any::<u64>(),
+                at in any::<u64>(),
+                offset in any::<i64>(),
+            ) {
+                let mut latency = PeerLatency::default();
+                for (delay, echoed) in delays {
+                    latency.record(delay, echoed);
+                }
+                let mut skew = RelaySkew::default();
+                for _ in 0..8 {
+                    skew.record(created, received);
+                }
+                let time = claim_time(at, offset, received, 250);
+                prop_assert!(time <= received);
+            }
+
+            #[test]
+            fn hostile_scores_and_clocks_never_overflow(
+                scores in prop::collection::vec(any::<Option<i64>>(), 1..8),
+                remaining in any::<u64>(),
+                now in any::<u64>(),
+            ) {
+                let mut state = RoomState::default();
+                for score in scores {
+                    state.record_score("alice", score);
+                }
+                let control = TimeControl::new(1000, u64::MAX);
+                let mut clock = ChessClock::new(control, ["a".to_string()]);
+                clock.remaining.insert("a".to_string(), remaining);
+                clock.start("a", 0);
+                clock.switch("b", now);
+                let _ = clock.check_flag(now);
+            }
+        }
+
+        #[test]
+        fn test_deeply_nested_content_is_an_error() {
+            let deep = format!(
+                "{{\"type\":\"chat\",\"text\":{}{}}}",
+                "[".repeat(100_000),
+                "]".repeat(100_000)
+            );
+            assert!(matches!(
+                decode_event(&deep),
+                Err(ArenaError::InvalidEventData(_))
+            ));
+            let deep = format!("{}{}", "[".repeat(10_000), "]".repeat(10_000));
+            assert!(matches!(
+                decode_room(&deep),
+                Err(ArenaError::InvalidRoomData(_))
+            ));
+            assert!(decode_state::<Value>(&deep).is_none());
+        }
+
+        #[test]
+        fn test_oversized_content_is_rejected() {
+            let huge = json!({"type": "chat", "text": "x".repeat(MAX_CONTENT_BYTES)}).to_string();
+            assert!(matches!(
+                decode_event(&huge),
+                Err(ArenaError::InvalidEventData(_))
+            ));
+            assert!(matches!(
+                decode_state::<Value>(&huge),
+                Some(Err(ArenaError::InvalidEventData(_)))
+            ));
+            assert!(matches!(
+                decode_room(&huge),
+                Err(ArenaError::InvalidRoomData(_))
+            ));
+        }
+
+        #[test]
+        fn test_wrong_field_types_are_errors() {
+            for content in [
+                r#"{"type":"ready","ready":"yes"}"#,
+                r#"{"type":7}"#,
+                r#"{"type":"seat","player_pubkey":"a","seat":-1}"#,
+                r#"{"type":"heartbeat","timestamp":1e400}"#,
+                r#"{"type":"resendrequest","since":18446744073709551616}"#,
+                r#"[]"#,
+            ] {
+                assert!(
+                    matches!(decode_event(content), Err(ArenaError::InvalidEventData(_))),
+                    "{content}"
+                );
+            }
+            assert!(matches!(
+                decode_event(r#"{"type":"ready","ready":true,"version":99999999999}"#),
+                Ok(EventContent::Ready(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_inject_event() {
+        use crate::arena::{Arena, ArenaEvent};
+
+        tokio_test::block_on(async {
+            let arena = Arena::<u32>::new(ArenaConfig::new("game")).await.unwrap();
+            let bob = PlayerPresence {
+                pubkey: "bob".to_string(),
+                joined_at: 1,
+                last_seen: 1,
+                seen_at: 0,
+                ready: false,
+                latency_ms: None,
+                away: false,
+                session: None,
+                capabilities: None,
+                identity: None,
+            };
+
+            arena.inject_event(ArenaEvent::PlayerJoin(bob)).await;
+            arena
+                .inject_event(ArenaEvent::PlayerReady {
+                    pubkey: "bob".to_string(),
+                    ready: true,
+                })
+                .await;
+            arena
+                .inject_event(ArenaEvent::PlayerState {
+                    pubkey: "bob".to_string(),
+                    state: 7,
+                })
+                .await;
+            assert_eq!(arena.player_count().await, 1);
+            assert_eq!(arena.ready_count().await, 1);
+
+            assert!(
+                matches!(arena.recv().await, Some(ArenaEvent::PlayerJoin(p)) if p.pubkey == "bob")
+            );
+            assert!(matches!(
+                arena.recv().await,
+                Some(ArenaEvent::PlayerReady { ready: true, .. })
+            ));
+            assert!(matches!(
+                arena.recv().await,
+                Some(ArenaEvent::PlayerState { state: 7, .. })
+            ));
+
+            arena
+                .inject_event(ArenaEvent::PlayerLeave("bob".to_string()))
+                .await;
+            assert_eq!(arena.player_count().await, 0);
+            assert!(arena.try_recv().await.is_some());
+            assert!(arena.try_recv().await.is_none());
+        });
+    }
+
+    #[test]
+    fn test_chat_moderation() {
+        use crate::migrate::{decode_event, encode_event};
+        use crate::types::{EventContent, ModerationAction, ModerationEventContent, RoomState};
+
+        let mute = ModerationEventContent {
+            action: ModerationAction::Mute,
+            seconds: None,
+            player_pubkey: Some("abc".to_string()),
+        };
+        let json = encode_event(&EventContent::Moderation(mute.clone())).unwrap();
+        assert!(json.contains("\"type\":\"moderation\""));
+        assert!(json.contains("\"action\":\"mute\""));
+        assert!(
+            matches!(decode_event(&json).unwrap(), EventContent::Moderation(m)
+            if m.action == ModerationAction::Mute && m.player_pubkey == mute.player_pubkey)
+        );
+
+        let mut state = RoomState::default();
+        assert!(state.apply_moderation(&mute));
+        assert!(state.apply_moderation(&mute));
+        assert_eq!(state.chat_muted, vec!["abc".to_string()]);
+
+        let slow = ModerationEventContent {
+            action: ModerationAction::SlowMode,
+            seconds: Some(10),
+            player_pubkey: None,
+        };
+        assert!(state.apply_moderation(&slow));
+        assert_eq!(state.chat_slow_mode, 10);
+
+        let unmute = ModerationEventContent {
+            action: ModerationAction::Unmute,
+            ..mute.clone()
+        };
+        assert!(state.apply_moderation(&unmute));
+        assert!(state.chat_muted.is_empty());
+
+        // A rule without its value is refused
+        let broken = ModerationEventContent {
+            player_pubkey: None,
+            ..mute
+        };
+        assert!(!state.apply_moderation(&broken));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_render_prometheus() {
+        use crate::metrics::{ArenaMetrics, MetricKind, render_prometheus};
+
+        let snapshot = |room: &str, players: f64| {
+            let mut m = ArenaMetrics::new();
+            let labels = vec![("game", "chess".to_string()), ("room", room.to_string())];
+            m.push(
+                "nostr_arena_players",
+                "Players in the room",
+                MetricKind::Gauge,
+                labels,
+                players,
+            );
+            m
+        };
+        let text = render_prometheus(&[snapshot("a\"b", 2.0), snapshot("c", 3.0)]);
+
+        assert_eq!(text.matches("# TYPE nostr_arena_players gauge").count(), 1);
+        assert!(text.contains("nostr_arena_players{game=\"chess\",room=\"a\\\"b\"} 2\n"));
+        assert!(text.contains("nostr_arena_players{game=\"chess\",room=\"c\"} 3\n"));
+        assert_eq!(snapshot("c", 3.0).get("nostr_arena_players").count(), 1);
+    }
+
+    #[test]
+    fn test_join_blocker() {
+        use crate::error::ArenaError;
+        use crate::types::JoinBlocker;
+
+        assert!(matches!(
+            ArenaError::from(JoinBlocker::Full),
+            ArenaError::RoomFull
+        ));
+        assert!(matches!(
+            ArenaError::from(JoinBlocker::HostBlocked),
+            ArenaError::NotAuthorized(_)
+        ));
+        assert_eq!(
+            serde_json::to_string(&JoinBlocker::HostBlocked).unwrap(),
+            "\"hostblocked\""
+        );
+    }
+
+    #[test]
+    fn test_verify_replay() {
+        use crate::record::{
+            MatchRecord, MatchResult, RecordFrame, RulesEngine, Verdict, verify_replay,
+        };
+        use std::collections::BTreeMap;
+
+        /// Highest total of `points` wins; negative points are illegal
+        struct Race;
+        impl RulesEngine<serde_json::Value> for Race {
+            type State = BTreeMap<String, i64>;
+
+            fn initial(&self, _seed: u64, players: &[String]) -> Self::State {
+                players.iter().map(|p| (p.clone(), 0)).collect()
+            }
+
+            fn apply(
+                &self,
+                state: &mut Self::State,
+                frame: &RecordFrame<serde_json::Value>,
+            ) -> Result<(), String> {
+                let points = frame.data["points"].as_i64().ok_or("no points")?;
+                if points < 0 {
+                    return Err("negative points".to_string());
+                }
+                *state.entry(frame.pubkey.clone()).or_default() += points;
+                Ok(())
+            }
+
+            fn result(&self, state: &Self::State) -> MatchResult {
+                MatchResult {
+                    winner: state
+                        .iter()
+                        .max_by_key(|(_, s)| **s)
+                        .map(|(p, _)| p.clone()),
+                    outcomes: Vec::new(),
+                }
+            }
+        }
+
+        let mut record: MatchRecord<serde_json::Value> = MatchRecord::new("race", "room1", 7, 0)
+            .player("abc")
+            .player("def");
+        record.push_input(10, "abc", serde_json::json!({"points": 1}));
+        record.push_input(20, "def", serde_json::json!({"points": 3}));
+        record.push_keyframe(25, "abc", serde_json::json!({"forged": true}));
+        assert_eq!(verify_replay(&record, &Race).verdict, Verdict::Unclaimed);
+
+        let claim = |winner: &str| MatchResult {
+            winner: Some(winner.to_string()),
+            outcomes: Vec::new(),
+        };
+        record.finish(30, claim("def"));
+        let verified = verify_replay(&record, &Race);
+        assert!(verified.is_verified());
+        assert_eq!(verified.inputs, 2);
+
+        record.finish(30, claim("abc"));
+        assert_eq!(verify_replay(&record, &Race).verdict, Verdict::Mismatch);
+
+        // Inputs from outsiders and illegal moves fail the replay
+        record.push_input(15, "eve", serde_json::json!({"points": 9}));
+        assert!(matches!(
+            verify_replay(&record, &Race).verdict,
+            Verdict::Illegal { frame: 1, .. }
+        ));
+        record.frames.remove(1);
+        record.push_input(40, "def", serde_json::json!({"points": -5}));
+        assert!(verify_replay(&record, &Race).computed.is_none());
+    }
+
+    #[test]
+    fn test_spectator_count() {
+        use crate::arena::room_content;
+        use crate::migrate::{decode_room, encode_event, encode_room};
+        use crate::types::{EventContent, SpectateEventContent};
+
+        let config = ArenaConfig::new("test-game");
+        let mut state = RoomState::default();
+        let content = room_content(
+            &config,
+            "abc".to_string(),
+            RoomStatus::Waiting,
+            &state,
+            Vec::new(),
+        );
+        assert!(!encode_room(&content).unwrap().contains("spectator_count"));
+
+        state.spectators.insert("def".to_string(), 1000);
+        state.spectators.insert("ghi".to_string(), 1000);
+        let content = room_content(
+            &config,
+            "abc".to_string(),
+            RoomStatus::Waiting,
+            &state,
+            Vec::new(),
+        );
+        let room = decode_room(&encode_room(&content).unwrap()).unwrap();
+        assert_eq!(room.spectator_count, 2);
+        assert_eq!(room.player_count(), 0);
+
+        let json = encode_event(&EventContent::Spectate(SpectateEventContent {})).unwrap();
+        assert!(json.contains("\"type\":\"spectate\""));
+    }
+
+    #[test]
+    fn test_afk_vote() {
+        use crate::arena::next_in_turn;
+        use crate::types::{afk_vote_target, afk_vote_topic};
+
+        let topic = afk_vote_topic("def");
+        assert_eq!(topic, "afk:def");
+        assert_eq!(afk_vote_target(&topic), Some("def"));
+        assert_eq!(afk_vote_target("map"), None);
+
+        let order: Vec<String> = ["abc", "def", "ghi"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(next_in_turn(&order, "def").as_deref(), Some("ghi"));
+        assert_eq!(next_in_turn(&order, "ghi").as_deref(), Some("abc"));
+        assert_eq!(next_in_turn(&order, "xyz").as_deref(), Some("abc"));
+        assert_eq!(next_in_turn(&order[..1], "abc"), None);
+    }
+
+    #[test]
+    fn test_room_template() {
+        use crate::arena::room_content;
+        use crate::types::{RoomTemplate, create_template_tag};
+
+        let config = ArenaConfig::new("test-game")
+            .max_players(2)
+            .relays(vec!["wss://a.example".to_string()]);
+        let template = RoomTemplate::from_config("friday", &config)
+            .with_start_config(&serde_json::json!({ "map": "forest" }))
+            .unwrap();
+        let template = RoomTemplate {
+            max_players: 8,
+            start_mode: StartMode::Host,
+            relays: vec!["wss://night.example".to_string()],
+            ..template
+        };
+        let json = serde_json::to_string(&template).unwrap();
+        assert_eq!(
+            serde_json::from_str::<RoomTemplate>(&json).unwrap(),
+            template
+        );
+        assert_eq!(
+            create_template_tag("test-game", "friday"),
+            "test-game-template-friday"
+        );
+
+        let mut state = RoomState::default();
+        assert_eq!(state.max_players(&config), 2);
+        assert_eq!(state.start_mode(&config), StartMode::Auto);
+
+        state.template = Some(template);
+        assert_eq!(state.max_players(&config), 8);
+        assert_eq!(state.start_mode(&config), StartMode::Host);
+        let content = room_content(
+            &config,
+            "abc".to_string(),
+            RoomStatus::Waiting,
+            &state,
+            Vec::new(),
+        );
+        assert_eq!(content.max_players, 8);
+        assert_eq!(content.relays, vec!["wss://night.example".to_string()]);
+    }
+
+    #[test]
+    fn test_monotonic_clock() {
+        use crate::time::{monotonic_at, monotonic_ms, now_ms};
+
+        let a = monotonic_ms();
+        let b = monotonic_ms();
+        assert!(b >= a);
+        // Anchored to the wall clock, so peers' timestamps map onto it
+        assert!(monotonic_ms().abs_diff(now_ms()) < 1000);
+        assert!(monotonic_at(now_ms() - 5000) <= monotonic_ms() - 4000);
+
+        let mut presence: PlayerPresence =
+            serde_json::from_str(r#"{"pubkey":"abc","joined_at":1,"last_seen":1,"ready":false}"#)
+                .unwrap();
+        assert_eq!(presence.seen_at, 0);
+        presence.touch();
+        assert!(presence.silent_ms() < 1000);
+        assert!(
+            !serde_json::to_string(&presence)
+                .unwrap()
+                .contains("seen_at")
+        );
+    }
+
+    #[test]
+    fn test_batch_roundtrip() {
+        use crate::arena::MessageBatch;
+        use crate::migrate::{decode_event, decode_state, encode_event};
+        use crate::types::{BatchEventContent, EventContent, StateEventContent};
+
+        let batch = MessageBatch::new()
+            .state(vec![1u32, 2])
+            .message("emote", &"wave")
+            .unwrap()
+            .heartbeat();
+        assert_eq!(batch.len(), 3);
+
+        let state = encode_event(&EventContent::State(StateEventContent {
+            game_state: serde_json::json!([7, 1]),
+        }))
+        .unwrap();
+        let json = encode_event(&EventContent::Batch(BatchEventContent {
+            messages: vec![state.clone()],
+        }))
+        .unwrap();
+        // Not mistaken for a state message
+        assert!(decode_state::<Vec<u32>>(&json).is_none());
+        let Ok(EventContent::Batch(batch)) = decode_event(&json) else {
+            panic!("expected a batch");
+        };
+        // Inner messages keep their exact bytes, for input audits
+        assert_eq!(batch.messages, [state]);
+        let decoded = decode_state::<Vec<u32>>(&batch.messages[0]);
+        assert_eq!(decoded.unwrap().unwrap(), [7, 1]);
+    }
+
+    #[test]
+    fn test_summary_channel() {
+        use crate::arena::room_content;
+        use crate::migrate::{decode_event, encode_event};
+        use crate::types::{EventContent, SummaryEventContent, create_summary_tag};
+
+        assert_eq!(
+            create_summary_tag("test-game", "abc"),
+            "test-game-abc-summary"
+        );
+
+        let config = ArenaConfig::new("test-game");
+        assert_eq!(config.throttle_for("summary"), 1000);
+        assert_eq!(
+            config
+                .clone()
+                .throttle("summary", 0)
+                .throttle_for("summary"),
+            0
+        );
+
+        // Only advertised when on
+        let state = RoomState::default();
+        let content = room_content(
+            &config,
+            "abc".into(),
+            RoomStatus::Playing,
+            &state,
+            Vec::new(),
+        );
+        assert!(
+            !serde_json::to_string(&content)
+                .unwrap()
+                .contains("summaries")
+        );
+        let config = config.spectator_summaries(true);
+        let content = room_content(
+            &config,
+            "abc".into(),
+            RoomStatus::Playing,
+            &state,
+            Vec::new(),
+        );
+        let json = serde_json::to_string(&content).unwrap();
+        assert!(
+            serde_json::from_str::<RoomEventContent>(&json)
+                .unwrap()
+                .summaries
+        );
+
+        let json = encode_event(&EventContent::Summary(SummaryEventContent {
+            summary: serde_json::json!({ "round": 3, "score": [12, 9] }),
+        }))
+        .unwrap();
+        let Ok(EventContent::Summary(summary)) = decode_event(&json) else {
+            panic!("expected a summary");
+        };
+        assert_eq!(summary.summary["round"], 3);
+    }
+
+    #[test]
+    fn test_room_password() {
+        use crate::arena::room_content;
+        use crate::invite::Invite;
+
+        // Proofs are salted with the room id and bound to the joiner's key
+        let proof = room_password_proof("abc", "hunter2", "alice");
+        assert_eq!(proof, room_password_proof("abc", "hunter2", "alice"));
+        assert_ne!(proof, room_password_proof("def", "hunter2", "alice"));
+        assert_ne!(proof, room_password_proof("abc", "hunter2", "bob"));
+
+        assert!(
+            ArenaConfig::new("test-game")
+                .room_password("")
+                .room_password
+                .is_none()
+        );
+        let config = ArenaConfig::new("test-game")
+            .room_password("hunter2")
+            .visibility(RoomVisibility::Private);
+        let state = RoomState {
+            room_id: Some("abc".to_string()),
+            ..Default::default()
+        };
+        let content = room_content(
+            &config,
+            "host".into(),
+            RoomStatus::Waiting,
+            &state,
+            Vec::new(),
+        );
+        let json = serde_json::to_string(&content).unwrap();
+        assert!(json.contains(r#""visibility":"private""#));
+        // Nothing to check a guess against
+        assert!(!json.contains("hunter2"));
+        assert!(json.contains(r#""password":true"#));
+        assert!(!json.contains("password_hash"));
+
+        let config = config.hide_private_rooms(true);
+        assert!(config.lists_visibility(RoomVisibility::Public));
+        assert!(!config.lists_visibility(RoomVisibility::Private));
+
+        let invite = Invite::new("test-game", "abc").secret("hunter2");
+        let decoded = Invite::decode(&invite.encode()).unwrap();
+        assert_eq!(decoded.secret.as_deref(), Some("hunter2"));
+    }
+
+    #[test]
+    fn test_sealed_state() {
+        use crate::migrate::{encode_event, message_type};
+        use crate::seal::{RoomKey, RoomSeal};
+        use crate::types::{ChatEventContent, EventContent, StateEventContent};
+        use std::borrow::Cow;
+
+        let key = RoomKey::generate();
+        assert_eq!(RoomKey::from_hex(&key.to_hex()).unwrap(), key);
+        assert!(RoomKey::from_hex("zz").is_err());
+        // The key never shows up in logs
+        assert_eq!(format!("{key:?}"), "RoomKey(..)");
+
+        let state = encode_event(&EventContent::State(StateEventContent {
+            game_state: serde_json::json!({ "x": 3, "hand": ["A", "K"] }),
+        }))
+        .unwrap();
+        let chat = encode_event(&EventContent::Chat(ChatEventContent {
+            text: "gg".to_string(),
+        }))
+        .unwrap();
+
+        let seal = RoomSeal::Key(key.clone());
+        let sealed = seal.seal_message(Cow::Borrowed(&state)).unwrap();
+        assert_eq!(message_type(&sealed).as_deref(), Some("sealed"));
+        assert!(!sealed.contains("hand"));
+        assert_eq!(seal.open_message(sealed.clone()).unwrap(), state);
+        // Chat stays readable
+        assert_eq!(seal.seal_message(Cow::Borrowed(&chat)).unwrap(), chat);
+
+        // Another room's key can't open it, and plain state is dropped
+        let other = RoomSeal::Key(RoomKey::generate());
+        assert!(other.open_message(sealed.clone()).is_none());
+        assert!(seal.open_message(Cow::Borrowed(&state)).is_none());
+        // Without the key yet, state can neither be sent nor read
+        assert!(
+            RoomSeal::Pending
+                .seal_message(Cow::Borrowed(&state))
+                .is_err()
+        );
+        assert!(RoomSeal::Pending.open_message(sealed).is_none());
+        assert_eq!(
+            RoomSeal::Off.seal_message(Cow::Borrowed(&state)).unwrap(),
+            state
+        );
+
+        // A transforming middleware layer sits above the seal both ways
+        let wire: String = state.chars().rev().collect();
+        assert!(seal.seals(&state).unwrap());
+        let sealed = seal.seal(Cow::Borrowed(&wire)).unwrap();
+        let (opened, was_sealed) = seal.unseal(sealed).unwrap();
+        assert!(was_sealed);
+        let plain: String = opened.chars().rev().collect();
+        assert!(seal.admits(&plain, was_sealed));
+        assert!(!seal.admits(&plain, false));
+        assert!(!seal.admits(&chat, true));
+    }
+
+    #[test]
+    fn test_room_read_repair() {
+        use crate::client::RoomCopies;
+        use nostr_sdk::{EventBuilder, Keys, Kind, Timestamp};
+
+        let keys = Keys::generate();
+        let revision = |secs: u64| {
+            EventBuilder::new(Kind::Custom(kinds::ROOM), "{}")
+                .custom_created_at(Timestamp::from_secs(secs))
+                .sign_with_keys(&keys)
+                .unwrap()
+        };
+        let (old, new) = (revision(1000), revision(1005));
+
+        let mut copies = RoomCopies::default();
+        assert!(copies.is_empty());
+        copies.insert("wss://a".to_string(), vec![new.clone()]);
+        copies.insert("wss://b".to_string(), vec![old.clone()]);
+        copies.insert("wss://c".to_string(), Vec::new());
+        copies.insert("wss://d".to_string(), vec![new.clone()]);
+
+        // Each revision once, whichever relays hold it
+        let ids: Vec<_> = copies.events().iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![new.id, old.id]);
+        // Stale and empty relays are repaired, up to date ones left alone
+        assert_eq!(copies.lagging(&new), vec!["wss://b", "wss://c"]);
+        assert_eq!(copies.lagging(&old), vec!["wss://a", "wss://c", "wss://d"]);
+
+        assert!(ArenaConfig::new("g").read_repair);
+        assert!(!ArenaConfig::new("g").read_repair(false).read_repair);
+    }
+
+    #[test]
+    fn test_event_class() {
+        use crate::arena::{ArenaEvent, EventClass};
+
+        let state = ArenaEvent::PlayerState {
+            pubkey: "bob".to_string(),
+            state: 1,
+        };
+        let summary = ArenaEvent::PlayerSummary {
+            pubkey: "bob".to_string(),
+            summary: serde_json::json!({ "score": 3 }),
+        };
+        assert_eq!(ArenaEvent::<i32>::AllReady.class(), EventClass::Control);
+        assert_eq!(state.class(), EventClass::Gameplay);
+        assert_eq!(summary.class(), EventClass::Telemetry);
+
+        // Sorting by class puts control events first
+        let mut events = [summary, state, ArenaEvent::GameStart { config: None }];
+        events.sort_by_key(ArenaEvent::class);
+        assert!(matches!(events[0], ArenaEvent::GameStart { .. }));
+        assert_eq!(events[2].class().as_str(), "telemetry");
+        assert_eq!(EventClass::Gameplay.to_string(), "gameplay");
+    }
+
+    #[test]
+    fn test_lifecycle_hooks() {
+        use crate::hooks::{LifecycleHook, MatchEnd, MatchFinished, PlayerJoined};
+        use std::sync::{Arc, Mutex};
+
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        impl LifecycleHook for Recorder {
+            fn on_player_joined(&self, join: &PlayerJoined) {
+                self.0.lock().unwrap().push(format!("join {}", join.pubkey));
+            }
+            fn on_match_finished(&self, finish: &MatchFinished) {
+                let json = serde_json::to_value(finish).unwrap();
+                self.0.lock().unwrap().push(json["end"]["type"].to_string());
+            }
+        }
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let config = ArenaConfig::new("g").with_hook(Box::new(Recorder(log.clone())));
+        assert_eq!(config.hooks.len(), 1);
+
+        let mut state = RoomState {
+            room_id: Some("r1".to_string()),
+            is_host: true,
+            seats: vec!["alice".to_string(), "bob".to_string()],
+            ..Default::default()
+        };
+        state.record_score("bob", Some(10));
+        config.hooks.player_joined(&PlayerJoined {
+            room_id: "r1".to_string(),
+            game_id: "g".to_string(),
+            pubkey: "bob".to_string(),
+            seat: 1,
+            players: 2,
+            max_players: 2,
+            joined_at: 0,
+        });
+        let end = MatchEnd::GameOver {
+            pubkey: "bob".to_string(),
+            final_score: Some(10),
+        };
+        let finish = MatchFinished::from_state(&config, &state, end).unwrap();
+        assert_eq!(finish.players, vec!["alice", "bob"]);
+        assert_eq!(finish.scoreboard[0].score, 10);
+        config.hooks.match_finished(&finish);
+        // Hooks without an override ignore the other transitions
+        config.hooks.room_created(&crate::hooks::RoomCreated {
+            room_id: "r1".to_string(),
+            game_id: "g".to_string(),
+            host_pubkey: "alice".to_string(),
+            seed: 1,
+            max_players: 2,
+            visibility: RoomVisibility::Public,
+            created_at: 0,
+        });
+
+        assert_eq!(*log.lock().unwrap(), vec!["join bob", "\"game_over\""]);
+        state.room_id = None;
+        assert!(MatchFinished::from_state(&config, &state, MatchEnd::Abandoned).is_none());
+    }
+
+    #[test]
+    fn test_turn_manager() {
+        use crate::arena::{Arena, ArenaEvent, EventClass};
+        use crate::migrate::{decode_event, encode_event};
+        use crate::turns::TurnManager;
+
+        tokio_test::block_on(async {
+            let free = Arena::<u32>::new(ArenaConfig::new("game")).await.unwrap();
+            assert!(TurnManager::new(&free).is_err());
+
+            let arena = Arena::<u32>::new(ArenaConfig::new("game").enforce_turns(true))
+                .await
+                .unwrap();
+            let turns = TurnManager::new(&arena).unwrap();
+            assert_eq!(turns.current().await, None);
+            assert!(!turns.is_my_turn().await);
+            // Refused locally, nothing is published
+            assert!(turns.submit_turn(&"e4").await.is_err());
+        });
+
+        let json = encode_event(&EventContent::Turn(TurnEventContent {
+            player_pubkey: "bob".to_string(),
+            player_move: Some(serde_json::json!({ "from": "e2", "to": "e4" })),
+        }))
+        .unwrap();
+        assert!(json.contains(r#""move":{"#));
+        match decode_event(&json).unwrap() {
+            EventContent::Turn(turn) => assert_eq!(turn.player_move.unwrap()["to"], "e4"),
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let received = ArenaEvent::<u32>::TurnReceived {
+            pubkey: "alice".to_string(),
+            payload: serde_json::json!("e4"),
+        };
+        assert_eq!(received.class(), EventClass::Control);
+    }
+
+    #[test]
+    fn test_resolve_finishes() {
+        use crate::migrate::{decode_event, encode_event};
+        use crate::rng::turn_order;
+
+        let finish = |pubkey: &str, at: u64, code: ReasonCode| Finish {
+            pubkey: pubkey.to_string(),
+            at,
+            code,
+        };
+        let seated = vec!["alice".to_string(), "bob".to_string()];
+
+        // Race: the first to finish wins
+        let race = [
+            finish("bob", 1010, ReasonCode::Won),
+            finish("alice", 1000, ReasonCode::Won),
+        ];
+        let (order, winner) = resolve_finishes(&race, &seated, 7);
+        assert_eq!(order, vec!["alice", "bob"]);
+        assert_eq!(winner.as_deref(), Some("alice"));
+
+        // The loser's opponent wins even if they never sent game over
+        let ko = [finish("alice", 1000, ReasonCode::Lost)];
+        assert_eq!(resolve_finishes(&ko, &seated, 7).1.as_deref(), Some("bob"));
+
+        // Double KO: whoever went down last wins
+        let double = [
+            finish("alice", 1005, ReasonCode::Lost),
+            finish("bob", 1000, ReasonCode::Lost),
+        ];
+        assert_eq!(
+            resolve_finishes(&double, &seated, 7).1.as_deref(),
+            Some("alice")
+        );
+
+        // A simultaneous finish goes by the seed, the same on every host
+        let tie = [
+            finish("alice", 1000, ReasonCode::Won),
+            finish("bob", 1000, ReasonCode::Won),
+        ];
+        let swapped = [tie[1].clone(), tie[0].clone()];
+        for seed in 0..8 {
+            let (order, winner) = resolve_finishes(&tie, &seated, seed);
+            assert_eq!(
+                resolve_finishes(&swapped, &seated, seed),
+                (order.clone(), winner.clone())
+            );
+            assert_eq!(order, turn_order(seed, &seated));
+            assert_eq!(winner.as_ref(), order.first());
+        }
+
+        let draw = [
+            finish("alice", 1000, ReasonCode::Draw),
+            finish("bob", 1002, ReasonCode::Won),
+        ];
+        assert_eq!(resolve_finishes(&draw, &seated, 7).1, None);
+
+        // Three players: the first knockout leaves two in the game
+        let trio = vec!["alice".to_string(), "bob".to_string(), "carol".to_string()];
+        let mut finishes = vec![finish("alice", 1000, ReasonCode::Lost)];
+        assert!(!match_decided(&finishes, &trio));
+        finishes.push(finish("carol", 5000, ReasonCode::Lost));
+        assert!(match_decided(&finishes, &trio));
+        let (order, winner) = resolve_finishes(&finishes, &trio, 7);
+        assert_eq!(order, vec!["alice", "carol"]);
+        assert_eq!(winner.as_deref(), Some("bob"));
+        // A win ends it at once
+        assert!(match_decided(
+            &[finish("bob", 1000, ReasonCode::Won)],
+            &trio
+        ));
+
+        let json = encode_event(&EventContent::GameEnded(GameEndedEventContent {
+            order: vec!["alice".to_string()],
+            winner: None,
+        }))
+        .unwrap();
+        assert!(json.contains(r#""type":"gameended""#));
+        assert!(decode_event(&json).unwrap().is_control());
+    }
+
+    #[test]
+    fn test_late_join_snapshot() {
+        use crate::arena::{ArenaEvent, EventClass};
+        use crate::migrate::{decode_event, encode_event};
+        use crate::seal::is_sealed_type;
+        use std::collections::HashMap;
+
+        assert!(!ArenaConfig::new("game").late_join);
+        assert!(ArenaConfig::new("game").late_join(true).late_join);
+
+        let request = encode_event(&EventContent::SnapshotRequest(
+            SnapshotRequestEventContent { peer: None },
+        ))
+        .unwrap();
+        assert!(request.contains(r#""type":"snapshotrequest""#));
+        assert!(!request.contains("peer"));
+
+        let mut states = HashMap::new();
+        states.insert("alice".to_string(), serde_json::json!(42));
+        let json = encode_event(&EventContent::Snapshot(SnapshotEventContent {
+            player_pubkey: "carol".to_string(),
+            states,
+            turn: Some("alice".to_string()),
+            round: 2,
+            phase: None,
+        }))
+        .unwrap();
+        assert!(!json.contains("phase"));
+        match decode_event(&json).unwrap() {
+            EventContent::Snapshot(snapshot) => {
+                assert_eq!(snapshot.player_pubkey, "carol");
+                assert_eq!(snapshot.states["alice"], 42);
+                assert_eq!(snapshot.turn.as_deref(), Some("alice"));
+                assert_eq!(snapshot.round, 2);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(is_sealed_type("snapshot"));
+        assert!(!is_sealed_type("snapshotrequest"));
+
+        let received = ArenaEvent::<u32>::SnapshotReceived {
+            from: "alice".to_string(),
+            states: HashMap::new(),
+        };
+        assert_eq!(received.class(), EventClass::Gameplay);
+    }
+
+    #[test]
+    fn test_encrypted_key_backup() {
+        use crate::arena::Arena;
+        use crate::error::ArenaError;
+
+        tokio_test::block_on(async {
+            let arena = Arena::<u32>::new(ArenaConfig::new("game")).await.unwrap();
+            let ncryptsec = arena.export_encrypted_key("correct horse").unwrap();
+            assert!(ncryptsec.starts_with("ncryptsec1"));
+
+            let restored = Arena::<u32>::with_encrypted_key(
+                ArenaConfig::new("game"),
+                &ncryptsec,
+                "correct horse",
+            )
+            .await
+            .unwrap();
+            assert_eq!(restored.public_key(), arena.public_key());
+
+            let wrong =
+                Arena::<u32>::with_encrypted_key(ArenaConfig::new("game"), &ncryptsec, "battery")
+                    .await;
+            assert!(matches!(wrong, Err(ArenaError::NotAuthorized(_))));
+        });
+    }
+
+    #[test]
+    fn test_identity_link_stays_private() {
+        use crate::identity::IdentityLink;
+        use nostr_sdk::Keys;
+
+        let identity = Keys::generate();
+        let link = IdentityLink::sign(&identity, "game", "abc123").unwrap();
+        assert_eq!(
+            link.verify("game", "abc123"),
+            Some(identity.public_key().to_hex())
+        );
+        assert_eq!(link.verify("game", "def456"), None);
+
+        // The roster in the room event never carries the link
+        let player = PlayerPresence {
+            pubkey: "abc123".to_string(),
+            joined_at: 0,
+            last_seen: 0,
+            seen_at: 0,
+            ready: false,
+            latency_ms: None,
+            away: false,
+            session: None,
+            capabilities: None,
+            identity: Some(Box::new(link)),
+        };
+        let json = serde_json::to_string(&player).unwrap();
+        assert!(!json.contains("identity"));
+        assert!(!json.contains(&identity.public_key().to_hex()));
+    }
+
+    #[test]
+    fn test_room_relays_cover_discovery() {
+        use crate::client::NostrClient;
+
+        tokio_test::block_on(async {
+            let client = NostrClient::new(vec![]).await.unwrap().with_relay_sets(
+                vec!["wss://fast".to_string()],
+                vec!["wss://index".to_string(), "wss://fast".to_string()],
+            );
+            // Peers configured the same way fetch rooms from the read relays
+            assert_eq!(client.metadata_relays(), ["wss://index", "wss://fast"]);
+        });
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_snapshot_only_from_asked_peer() {
+        use crate::arena::{Arena, ArenaEvent};
+        use crate::migrate::encode_event;
+        use nostr_sdk::{EventBuilder, Keys, Kind};
+        use std::collections::HashMap;
+
+        tokio_test::block_on(async {
+            let (host, mallory) = (Keys::generate(), Keys::generate());
+            let carol = Arena::<u32>::new(ArenaConfig::new("game").relays(vec![]))
+                .await
+                .unwrap();
+            let peers = [host.public_key().to_hex(), mallory.public_key().to_hex()];
+            carol.bench_enter("room", &peers).await;
+            while carol.try_recv().await.is_some() {}
+
+            let snapshot = |keys: &Keys| {
+                let states = HashMap::from([(peers[1].clone(), serde_json::json!(99))]);
+                let content = encode_event(&EventContent::Snapshot(SnapshotEventContent {
+                    player_pubkey: carol.public_key(),
+                    states,
+                    turn: None,
+                    round: 0,
+                    phase: None,
+                }))
+                .unwrap();
+                EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), content)
+                    .sign_with_keys(keys)
+                    .unwrap()
+            };
+
+            // Unasked snapshots are ignored, even from the host
+            carol.bench_receive([snapshot(&host)]).await;
+            assert!(carol.try_recv().await.is_none());
+
+            // Without relays this only records whom we asked: the host
+            let _ = carol.request_snapshot(None).await;
+            carol.bench_receive([snapshot(&mallory)]).await;
+            assert!(carol.try_recv().await.is_none());
+            carol
+                .bench_receive([snapshot(&host), snapshot(&host)])
+                .await;
+            assert!(matches!(
+                carol.try_recv().await,
+                Some(ArenaEvent::SnapshotReceived { from, .. }) if from == peers[0]
+            ));
+            assert!(carol.try_recv().await.is_none());
+        });
+    }
+
+    #[test]
+    fn test_match_abandoned() {
+        use crate::arena::match_abandoned;
+        use crate::time::monotonic_ms;
+
+        let config = ArenaConfig::new("test-game").disconnect_threshold(1000);
+        let presence = |pubkey: &str, silent: u64| PlayerPresence {
+            pubkey: pubkey.to_string(),
+            joined_at: 0,
+            last_seen: 0,
+            seen_at: monotonic_ms().saturating_sub(silent),
+            ready: true,
+            latency_ms: None,
+            away: false,
+            session: None,
+            capabilities: None,
+            identity: None,
+        };
+
+        // Alone in the room: nobody to lose
+        assert!(!match_abandoned(&config, &[presence("me", 0)], "me"));
+        assert!(!match_abandoned(&config, &[], "me"));
+
+        // Every opponent gone quiet
+        let players = [presence("me", 0), presence("a", 5000), presence("b", 5000)];
+        assert!(match_abandoned(&config, &players, "me"));
+
+        // One opponent still around
+        let players = [presence("me", 0), presence("a", 5000), presence("b", 10)];
+        assert!(!match_abandoned(&config, &players, "me"));
+
+        // Away players get the longer timeout
+        let mut away = presence("a", 5000);
+        away.away = true;
+        assert!(!match_abandoned(&config, &[presence("me", 0), away], "me"));
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_restore_after_long_restart() {
+        use crate::arena::Arena;
+        use crate::error::ArenaError;
+        use nostr_sdk::Keys;
+
+        tokio_test::block_on(async {
+            let keys = Keys::generate();
+            let secret = keys.secret_key().to_secret_hex();
+            let config = ArenaConfig::new("game").relays(vec![]);
+            let arena = Arena::<u32>::with_secret_key(config.clone(), &secret)
+                .await
+                .unwrap();
+            let peer = Keys::generate().public_key().to_hex();
+            arena.bench_enter("room", std::slice::from_ref(&peer)).await;
+
+            // Already in the room: restoring again would double the tasks
+            let mut snapshot = arena.snapshot().await;
+            assert!(matches!(
+                arena.restore(snapshot.clone()).await,
+                Err(ArenaError::AlreadyInRoom)
+            ));
+
+            // Restored an hour later, nobody has timed out yet
+            for player in &mut snapshot.players {
+                player.last_seen -= 3_600_000;
+            }
+            // Without a room id no background tasks start
+            snapshot.room.room_id = None;
+            let restored = Arena::<u32>::with_secret_key(config, &secret)
+                .await
+                .unwrap();
+            restored.restore(snapshot).await.unwrap();
+            let players = restored.players().await;
+            assert_eq!(players.len(), 2);
+            assert!(players.iter().all(|p| p.silent_ms() < 1000));
+        });
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_kicked_player_stays_out() {
+        use crate::arena::{Arena, ArenaEvent};
+        use crate::migrate::encode_event;
+        use nostr_sdk::{EventBuilder, Keys, Kind};
+
+        tokio_test::block_on(async {
+            let (host, mallory) = (Keys::generate(), Keys::generate());
+            let carol = Arena::<u32>::new(ArenaConfig::new("game").relays(vec![]))
+                .await
+                .unwrap();
+            let peers = [host.public_key().to_hex(), mallory.public_key().to_hex()];
+            carol.bench_enter("room", &peers).await;
+            while carol.try_recv().await.is_some() {}
+
+            let sign = |content: EventContent, keys: &Keys| {
+                EventBuilder::new(
+                    Kind::Custom(kinds::EPHEMERAL),
+                    encode_event(&content).unwrap(),
+                )
+                .sign_with_keys(keys)
+                .unwrap()
+            };
+            let state = |keys: &Keys| {
+                sign(
+                    EventContent::State(StateEventContent {
+                        game_state: serde_json::json!(7),
+                    }),
+                    keys,
+                )
+            };
+
+            carol.bench_receive([state(&mallory)]).await;
+            assert!(carol.try_recv().await.is_some());
+
+            let kick = EventContent::Kick(KickEventContent {
+                player_pubkey: peers[1].clone(),
+            });
+            carol.bench_receive([sign(kick, &host)]).await;
+            assert!(matches!(
+                carol.try_recv().await,
+                Some(ArenaEvent::PlayerKicked(pubkey)) if pubkey == peers[1]
+            ));
+            assert_eq!(carol.room_state().await.kicked, [peers[1].clone()]);
+
+            // Whatever they still send is dropped
+            carol.bench_receive([state(&mallory)]).await;
+            assert!(carol.try_recv().await.is_none());
+            assert_eq!(carol.players().await.len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_locked_room_authors() {
+        use crate::arena::room_authors;
+        use std::collections::HashMap;
+
+        // Opt-in: a running game still hears newcomers by default
+        assert!(!ArenaConfig::new("game").lock_on_start);
+
+        let state = RoomState {
+            host_pubkey: Some("host".to_string()),
+            co_hosts: vec!["cohost".to_string()],
+            spectators: HashMap::from([("watcher".to_string(), 0)]),
+            kicked: vec!["mallory".to_string()],
+            ..Default::default()
+        };
+        let players = ["host", "bob", "mallory"].map(String::from);
+        assert_eq!(
+            room_authors(&state, &players),
+            ["bob", "cohost", "host", "watcher"]
+        );
+    }
+
+    #[test]
+    fn test_room_author_lock_lifts() {
+        use crate::client::NostrClient;
+        use nostr_sdk::Keys;
+        use tokio::sync::mpsc;
+
+        tokio_test::block_on(async {
+            // Subscriptions wait for the relay; it never has to answer
+            let client = NostrClient::new(vec!["ws://127.0.0.1:1".to_string()])
+                .await
+                .unwrap();
+            client.connect().await.unwrap();
+            let d_tags = ["game-room".to_string()];
+            let player = Keys::generate().public_key().to_hex();
+
+            // Nothing to narrow outside a room
+            assert!(
+                client
+                    .restrict_room_authors(&d_tags, std::slice::from_ref(&player))
+                    .await
+                    .is_err()
+            );
+
+            let (tx, _rx) = mpsc::channel(1);
+            client.subscribe_room(&d_tags, tx).await.unwrap();
+            client
+                .restrict_room_authors(&d_tags, std::slice::from_ref(&player))
+                .await
+                .unwrap();
+            assert_eq!(client.room_authors().await, Some(vec![player]));
+
+            // Renewing the subscription keeps the lock; a rematch lifts it
+            client.resubscribe_room(&d_tags).await.unwrap();
+            assert!(client.room_authors().await.is_some());
+            client.open_room_authors(&d_tags).await.unwrap();
+            assert_eq!(client.room_authors().await, None);
+        });
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_rekey_from_host() {
+        use crate::arena::Arena;
+        use crate::client::NostrClient;
+        use crate::migrate::encode_event;
+        use crate::seal::{RoomKey, RoomSeal};
+        use nostr_sdk::{EventBuilder, Keys, Kind};
+        use std::collections::HashMap;
+
+        tokio_test::block_on(async {
+            let (host, mallory) = (Keys::generate(), Keys::generate());
+            let carol = Arena::<u32>::new(ArenaConfig::new("game").relays(vec![]))
+                .await
+                .unwrap();
+            let peers = [host.public_key().to_hex(), mallory.public_key().to_hex()];
+            carol.bench_enter("room", &peers).await;
+            let old = RoomKey::generate();
+            carol
+                .client()
+                .set_room_seal(RoomSeal::Key(old.clone()))
+                .await;
+
+            let next = RoomKey::generate();
+            let rekey = |keys: &Keys| {
+                let keys = keys.clone();
+                let carol = carol.public_key();
+                let next = next.to_hex();
+                async move {
+                    let sender =
+                        NostrClient::with_secret_key(&keys.secret_key().to_secret_hex(), vec![])
+                            .await
+                            .unwrap();
+                    let payload = sender.nip44_encrypt(&carol, &next).await.unwrap();
+                    let content = encode_event(&EventContent::Rekey(RekeyEventContent {
+                        keys: HashMap::from([(carol, payload)]),
+                    }))
+                    .unwrap();
+                    EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), content)
+                        .sign_with_keys(&keys)
+                        .unwrap()
+                }
+            };
+
+            // Only the host hands out keys
+            carol.bench_receive([rekey(&mallory).await]).await;
+            assert_eq!(carol.client().room_seal().await, RoomSeal::Key(old));
+            carol.bench_receive([rekey(&host).await]).await;
+            assert_eq!(carol.client().room_seal().await, RoomSeal::Key(next));
+        });
+    }
+
+    #[test]
+    fn test_thread_tags_address_room() {
+        use crate::client::NostrClient;
+
+        tokio_test::block_on(async {
+            let client = NostrClient::new(vec![]).await.unwrap();
+            let address = room_address("abc", "game-room");
+            assert_eq!(address, "30078:abc:game-room");
+            client.set_room_address(Some(address.clone())).await;
+            assert!(client.room_tags().await.is_empty());
+
+            // The `a` tag stays valid however often the host republishes
+            let client = client.with_thread_tags(true);
+            let tags: Vec<Vec<String>> = client
+                .room_tags()
+                .await
+                .into_iter()
+                .map(|tag| tag.to_vec())
+                .collect();
+            assert_eq!(tags, [vec!["a".to_string(), address]]);
+        });
+    }
+
+    #[test]
+    fn test_latest_room_pins_host() {
+        use crate::arena::latest_room;
+        use crate::types::kinds;
+        use nostr_sdk::{EventBuilder, Keys, Kind, Timestamp};
+
+        let room_event = |keys: &Keys, status: &str, at: u64| {
+            let content = format!(
+                r#"{{"version":3,"status":"{status}","seed":1,"host_pubkey":"{}","max_players":2}}"#,
+                keys.public_key().to_hex()
+            );
+            EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), content)
+                .custom_created_at(Timestamp::from(at))
+                .sign_with_keys(keys)
+                .unwrap()
+        };
+        let host = Keys::generate();
+        let stranger = Keys::generate();
+        let events = vec![
+            room_event(&host, "waiting", 100),
+            room_event(&stranger, "deleted", 200),
+        ];
+
+        // The stranger's newer event names itself host but doesn't count
+        let host_hex = host.public_key().to_hex();
+        let (event, content) = latest_room(events.clone(), Some(&host_hex)).unwrap();
+        assert_eq!(event.pubkey, host.public_key());
+        assert_eq!(content.status, RoomStatus::Waiting);
+
+        // Without a known host, two self-declared hosts are ambiguous
+        assert!(latest_room(events.clone(), None).is_none());
+        assert!(latest_room(events[..1].to_vec(), None).is_some());
+    }
+
+    #[cfg(feature = "relay")]
+    #[test]
+    fn test_embedded_relay_loopback() {
+        use crate::relay::{EmbeddedRelay, MAX_ROOM_AUTHORS};
+        use crate::types::kinds;
+        use futures_util::{SinkExt, StreamExt};
+        use nostr_sdk::{EventBuilder, Keys, Kind, Tag, Timestamp};
+        use serde_json::{Value, json};
+        use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+        tokio_test::block_on(async {
+            let relay = EmbeddedRelay::bind("127.0.0.1:0").await.unwrap();
+            let (mut publisher, _) = connect_async(relay.url()).await.unwrap();
+            let (mut subscriber, _) = connect_async(relay.url()).await.unwrap();
+
+            async fn next<S>(ws: &mut S) -> Value
+            where
+                S: StreamExt<Item = tokio_tungstenite::tungstenite::Result<Message>> + Unpin,
+            {
+                let Some(Ok(Message::Text(text))) = ws.next().await else {
+                    panic!("relay closed");
+                };
+                serde_json::from_str(&text).unwrap()
+            }
+            let send =
+                |event: &nostr_sdk::Event| Message::Text(json!(["EVENT", event]).to_string());
+
+            let host = Keys::generate();
+            // A room event naming `host`, signed by `keys`
+            let room_event = |keys: &Keys, d_tag: &str, at: Option<u64>| {
+                let content = format!(
+                    r#"{{"version":3,"status":"waiting","seed":1,"host_pubkey":"{}","max_players":2}}"#,
+                    host.public_key().to_hex()
+                );
+                let builder = EventBuilder::new(Kind::Custom(kinds::ROOM), content)
+                    .tag(Tag::identifier(d_tag));
+                match at {
+                    Some(at) => builder.custom_created_at(Timestamp::from(at)),
+                    None => builder,
+                }
+                .sign_with_keys(keys)
+                .unwrap()
+            };
+            let room = room_event(&host, "game-room", None);
+            publisher.send(send(&room)).await.unwrap();
+            assert_eq!(next(&mut publisher).await[2], json!(true));
+
+            // Stored room events come first, then EOSE
+            let req = json!(["REQ", "sub", {"kinds": [kinds::ROOM, kinds::EPHEMERAL]}]);
+            subscriber
+                .send(Message::Text(req.to_string()))
+                .await
+                .unwrap();
+            let stored = next(&mut subscriber).await;
+            assert_eq!(stored[0], "EVENT");
+            assert_eq!(stored[2]["id"], room.id.to_hex());
+            assert_eq!(next(&mut subscriber).await, json!(["EOSE", "sub"]));
+
+            // Ephemeral events are relayed live
+            let state = EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), "state")
+                .sign_with_keys(&host)
+                .unwrap();
+            publisher.send(send(&state)).await.unwrap();
+            assert_eq!(next(&mut publisher).await[2], json!(true));
+            let live = next(&mut subscriber).await;
+            assert_eq!(live[2]["id"], state.id.to_hex());
+
+            // A room stores a bounded number of authors; copies can't push
+            // out the host's own event
+            for i in 1..=MAX_ROOM_AUTHORS {
+                let copy = room_event(&Keys::generate(), "game-room", Some(1000));
+                publisher.send(send(&copy)).await.unwrap();
+                let ok = next(&mut publisher).await;
+                assert_eq!(ok[2], json!(i < MAX_ROOM_AUTHORS), "author {i}");
+            }
+
+            // Nor can strangers who got to a room first keep its host out
+            for _ in 0..MAX_ROOM_AUTHORS {
+                let copy = room_event(&Keys::generate(), "game-squat", Some(1000));
+                publisher.send(send(&copy)).await.unwrap();
+                assert_eq!(next(&mut publisher).await[2], json!(true));
+            }
+            let squatted = room_event(&host, "game-squat", None);
+            publisher.send(send(&squatted)).await.unwrap();
+            assert_eq!(next(&mut publisher).await[2], json!(true));
+            relay.shutdown();
+
+            // Past the relay's capacity the oldest room events go
+            let relay = EmbeddedRelay::bind_with_capacity("127.0.0.1:0", 2)
+                .await
+                .unwrap();
+            let (mut ws, _) = connect_async(relay.url()).await.unwrap();
+            let rooms: Vec<_> = [("game-a", 100), ("game-b", 200), ("game-c", 300)]
+                .into_iter()
+                .map(|(d_tag, at)| room_event(&host, d_tag, Some(at)))
+                .collect();
+            for room in &rooms {
+                ws.send(send(room)).await.unwrap();
+                assert_eq!(next(&mut ws).await[2], json!(true));
+            }
+            let req = json!(["REQ", "all", {"kinds": [kinds::ROOM]}]);
+            ws.send(Message::Text(req.to_string())).await.unwrap();
+            let mut stored = Vec::new();
+            loop {
+                let reply = next(&mut ws).await;
+                if reply[0] == "EOSE" {
+                    break;
+                }
+                stored.push(reply[2]["id"].as_str().unwrap().to_string());
+            }
+            stored.sort();
+            let mut newest = vec![rooms[1].id.to_hex(), rooms[2].id.to_hex()];
+            newest.sort();
+            assert_eq!(stored, newest);
+            relay.shutdown();
+        });
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_forged_join_is_dropped() {
+        use crate::arena::Arena;
+        use crate::middleware::EventMiddleware;
+        use crate::migrate::encode_event;
+        use nostr_sdk::{EventBuilder, Keys, Kind};
+        use std::sync::{Arc, Mutex};
+
+        /// Records every message the arena publishes
+        struct Outbox(Arc<Mutex<Vec<String>>>);
+        impl EventMiddleware for Outbox {
+            fn outbound(&self, content: String) -> Option<String> {
+                self.0.lock().unwrap().push(content.clone());
+                Some(content)
+            }
+        }
+
+        tokio_test::block_on(async {
+            let sent = Arc::new(Mutex::new(Vec::new()));
+            let config = ArenaConfig::new("game")
+                .relays(vec![])
+                .with_middleware(Box::new(Outbox(sent.clone())));
+            let host = Arena::<u32>::new(config).await.unwrap();
+            let (victim, mallory) = (Keys::generate(), Keys::generate());
+            let victim_hex = victim.public_key().to_hex();
+            host.bench_host("room", std::slice::from_ref(&victim_hex))
+                .await;
+
+            let join = |session: &str, keys: &Keys| {
+                let content = EventContent::Join(JoinEventContent {
+                    player_pubkey: victim_hex.clone(),
+                    capabilities: None,
+                    identity: None,
+                    session: Some(session.to_string()),
+                    proof: None,
+                });
+                EventBuilder::new(
+                    Kind::Custom(kinds::EPHEMERAL),
+                    encode_event(&content).unwrap(),
+                )
+                .sign_with_keys(keys)
+                .unwrap()
+            };
+            host.bench_receive([join("tab-1", &victim)]).await;
+            let seats = host.room_state().await.seats;
+            let answered = sent.lock().unwrap().len();
+            assert_eq!(answered, 1);
+
+            // A stranger joining in the victim's name gets no seat message,
+            // so the victim's seat reply and session stay as they were
+            host.bench_receive([join("forged", &mallory)]).await;
+            assert_eq!(sent.lock().unwrap().len(), answered);
+            let state = host.room_state().await;
+            assert_eq!(state.seats, seats);
+            assert!(
+                !state
+                    .peer_versions
+                    .contains_key(&mallory.public_key().to_hex())
+            );
+            let session = host
+                .players()
+                .await
+                .into_iter()
+                .find(|p| p.pubkey == victim_hex)
+                .and_then(|p| p.session);
+            assert_eq!(session.as_deref(), Some("tab-1"));
+        });
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_game_over_scores_players_once() {
+        use crate::arena::Arena;
+        use crate::migrate::encode_event;
+        use nostr_sdk::{EventBuilder, Keys, Kind};
+
+        tokio_test::block_on(async {
+            let host = Arena::<u32>::new(ArenaConfig::new("game").relays(vec![]))
+                .await
+                .unwrap();
+            let (bob, mallory) = (Keys::generate(), Keys::generate());
+            let bob_hex = bob.public_key().to_hex();
+            host.bench_host("room", std::slice::from_ref(&bob_hex))
+                .await;
+
+            let game_over = |score: i64, keys: &Keys| {
+                let content = EventContent::GameOver(GameOverEventContent::new(
+                    ReasonCode::Won.into(),
+                    Some(score),
+                ));
+                EventBuilder::new(
+                    Kind::Custom(kinds::EPHEMERAL),
+                    encode_event(&content).unwrap(),
+                )
+                .sign_with_keys(keys)
+                .unwrap()
+            };
+
+            // A stranger can't finish the match or enter the scoreboard
+            host.bench_receive([game_over(99, &mallory)]).await;
+            let state = host.room_state().await;
+            assert_eq!(state.status, RoomStatus::Playing);
+            assert!(state.scoreboard.is_empty());
+
+            // A player's resent game over is scored once
+            host.bench_receive([game_over(10, &bob), game_over(10, &bob)])
+                .await;
+            let scoreboard = host.scoreboard().await;
+            assert_eq!(scoreboard.len(), 1);
+            assert_eq!((scoreboard[0].score, scoreboard[0].games), (10, 1));
+            assert_eq!(host.room_state().await.status, RoomStatus::Finished);
+        });
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_rematch_accept_from_host_only() {
+        use crate::arena::Arena;
+        use crate::migrate::encode_event;
+        use nostr_sdk::{EventBuilder, Keys, Kind};
+        use tokio::sync::mpsc;
+
+        tokio_test::block_on(async {
+            let config = ArenaConfig::new("game").relays(vec!["ws://127.0.0.1:1".to_string()]);
+            let carol = Arena::<u32>::new(config).await.unwrap();
+            let (host, mallory) = (Keys::generate(), Keys::generate());
+            let peers = [host.public_key().to_hex(), mallory.public_key().to_hex()];
+            carol.bench_enter("room", &peers).await;
+
+            // The game started with the room locked to its players
+            let client = carol.client();
+            client.connect().await.unwrap();
+            let d_tags = [create_room_tag("game", "room")];
+            let (tx, _rx) = mpsc::channel(1);
+            client.subscribe_room(&d_tags, tx).await.unwrap();
+            client.restrict_room_authors(&d_tags, &peers).await.unwrap();
+
+            let sign = |content: EventContent, keys: &Keys| {
+                EventBuilder::new(
+                    Kind::Custom(kinds::EPHEMERAL),
+                    encode_event(&content).unwrap(),
+                )
+                .sign_with_keys(keys)
+                .unwrap()
+            };
+            let accept = |seed: u64| {
+                EventContent::Rematch(RematchEventContent {
+                    action: RematchAction::Accept,
+                    new_seed: Some(seed),
+                    round: None,
+                })
+            };
+            let ready = EventContent::Ready(ReadyEventContent { ready: true });
+            carol.bench_receive([sign(ready, &mallory)]).await;
+            while carol.try_recv().await.is_some() {}
+            assert!(carol.players().await.iter().any(|p| p.ready));
+
+            // A player can't reset the match
+            carol.bench_receive([sign(accept(7), &mallory)]).await;
+            let state = carol.room_state().await;
+            assert_eq!(state.status, RoomStatus::Playing);
+            assert_ne!(state.seed, 7);
+
+            // The host's accept resets everything a local rematch does
+            carol.bench_receive([sign(accept(9), &host)]).await;
+            let state = carol.room_state().await;
+            assert_eq!((state.status, state.seed), (RoomStatus::Ready, 9));
+            assert!(carol.players().await.iter().all(|p| !p.ready));
+            assert_eq!(client.room_authors().await, None);
+        });
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_game_starts_once() {
+        use crate::arena::Arena;
+        use crate::error::ArenaError;
+        use crate::migrate::encode_event;
+        use nostr_sdk::{EventBuilder, Keys, Kind};
+
+        tokio_test::block_on(async {
+            let host = Keys::generate();
+            let carol = Arena::<u32>::new(ArenaConfig::new("game").relays(vec![]))
+                .await
+                .unwrap();
+            let peers = [host.public_key().to_hex()];
+            carol.bench_enter("room", &peers).await;
+            let before = carol.room_state().await;
+
+            // A second gamestart mid-match changes nothing
+            let start = EventContent::GameStart(GameStartEventContent {
+                config: None,
+                seed: Some(before.seed.wrapping_add(1)),
+                turn_order: vec![peers[0].clone(), carol.public_key()],
+            });
+            let event = EventBuilder::new(
+                Kind::Custom(kinds::EPHEMERAL),
+                encode_event(&start).unwrap(),
+            )
+            .sign_with_keys(&host)
+            .unwrap();
+            carol.bench_receive([event]).await;
+            let after = carol.room_state().await;
+            assert_eq!(after.seed, before.seed);
+            assert_eq!(after.turn_order, before.turn_order);
+
+            // Nor can the host start again
+            let hosting = Arena::<u32>::new(ArenaConfig::new("game").relays(vec![]))
+                .await
+                .unwrap();
+            hosting.bench_host("room", &peers).await;
+            assert!(matches!(
+                hosting.start_game().await,
+                Err(ArenaError::InvalidValue(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn test_offload_signer() {
+        use crate::arena::Arena;
+        use crate::offload::{Offload, OffloadFuture, OffloadSigner, handle_job};
+        use nostr_sdk::prelude::*;
+
+        // Stands in for the web worker holding the keys
+        struct Worker(Keys);
+        impl Offload for Worker {
+            fn run(&self, request: Vec<u8>) -> OffloadFuture<'_> {
+                Box::pin(async move { Ok(handle_job(&self.0, &request).await) })
+            }
+        }
+
+        tokio_test::block_on(async {
+            let keys = Keys::generate();
+            let peer = Keys::generate();
+            let signer = OffloadSigner::new(Worker(keys.clone())).await.unwrap();
+            assert_eq!(signer.get_public_key().await.unwrap(), keys.public_key());
+
+            let event = EventBuilder::text_note("hi").sign(&signer).await.unwrap();
+            assert_eq!(event.pubkey, keys.public_key());
+            assert!(event.verify().is_ok());
+
+            let sealed = signer
+                .nip44_encrypt(&peer.public_key(), "secret")
+                .await
+                .unwrap();
+            let opened = peer.nip44_decrypt(&keys.public_key(), &sealed).await;
+            assert_eq!(opened.unwrap(), "secret");
+            assert!(
+                signer
+                    .nip44_decrypt(&peer.public_key(), "junk")
+                    .await
+                    .is_err()
+            );
+
+            let arena = Arena::<u32>::with_signer(ArenaConfig::new("game").relays(vec![]), signer)
+                .await
+                .unwrap();
+            assert_eq!(arena.public_key(), keys.public_key().to_hex());
+        });
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_hosted_publish_counts_for_32_players() {
+        use crate::arena::Arena;
+        use crate::manifest::estimate_traffic;
+        use crate::middleware::EventMiddleware;
+        use crate::migrate::encode_event;
+        use nostr_sdk::{EventBuilder, Keys, Kind};
+        use std::sync::{Arc, Mutex};
+        use std::time::{Duration, Instant};
+
+        struct Outbox(Arc<Mutex<Vec<String>>>);
+        impl EventMiddleware for Outbox {
+            fn outbound(&self, content: String) -> Option<String> {
+                self.0.lock().unwrap().push(content.clone());
+                Some(content)
+            }
+        }
+
+        // The party preset's timers, sped up; state goes out when the game
+        // sends it, so only the heartbeat and digest loops are measured
+        let mut config = ArenaConfig::new("party-game")
+            .preset(Preset::Party)
+            .max_players(32)
+            .sync_mode(SyncMode::Hosted)
+            .relays(vec![])
+            .heartbeat_interval(100)
+            .digest_interval(50)
+            .state_throttle(0);
+        config.idle_heartbeat_interval = 400;
+
+        tokio_test::block_on(async {
+            let peers: Vec<Keys> = (0..31).map(|_| Keys::generate()).collect();
+            let peer_keys: Vec<String> = peers.iter().map(|k| k.public_key().to_hex()).collect();
+
+            for playing in [false, true] {
+                let (host_sent, peer_sent) = (Arc::default(), Arc::default());
+                let host = config
+                    .clone()
+                    .with_middleware(Box::new(Outbox(Arc::clone(&host_sent))));
+                let host = Arena::<u32>::new(host).await.unwrap();
+                host.bench_host("room", &peer_keys).await;
+                let peer = config
+                    .clone()
+                    .with_middleware(Box::new(Outbox(Arc::clone(&peer_sent))));
+                let peer = Arena::<u32>::new(peer).await.unwrap();
+                peer.bench_enter("room", &[host.public_key()]).await;
+
+                let start = Instant::now();
+                host.bench_timers(playing).await;
+                peer.bench_timers(playing).await;
+                let mut tick = 0;
+                while start.elapsed() < Duration::from_millis(1000) {
+                    if playing {
+                        // Every player's state reaches the host between digests
+                        let content = EventContent::State(StateEventContent {
+                            game_state: serde_json::json!(tick),
+                        });
+                        let content = encode_event(&content).unwrap();
+                        host.bench_receive(peers.iter().map(|keys| {
+                            EventBuilder::new(Kind::Custom(kinds::EPHEMERAL), &content)
+                                .sign_with_keys(keys)
+                                .unwrap()
+                        }))
+                        .await;
+                        while host.try_recv().await.is_some() {}
+                    }
+                    tick += 1;
+                    tokio::time::sleep(Duration::from_millis(25)).await;
+                }
+                let elapsed = start.elapsed().as_millis() as u32;
+                let host_sent = host_sent.lock().unwrap().len() as u32;
+                let peer_sent = peer_sent.lock().unwrap().len() as u32;
+                host.leave().await.ok();
+                peer.leave().await.ok();
+
+                // Every loop publishes once right away, then at its rate
+                let estimate = estimate_traffic(&config, 32, playing);
+                let per_peer = (estimate.published_per_minute - estimate.max_per_publisher) / 31;
+                let expected_host = estimate.max_per_publisher * elapsed / 60_000 + 2;
+                let expected_peer = per_peer * elapsed / 60_000 + 1;
+                assert!(
+                    host_sent.abs_diff(expected_host) <= 2,
+                    "playing: {playing}, host sent {host_sent}, expected {expected_host}"
+                );
+                assert!(
+                    peer_sent.abs_diff(expected_peer) <= 1,
+                    "playing: {playing}, peer sent {peer_sent}, expected {expected_peer}"
+                );
+            }
+        });
     }
 }
@@ -1,7 +1,72 @@
 //! Cross-platform time helpers for native and WASM targets
+//!
+//! Two clocks, both in milliseconds since the Unix epoch:
+//!
+//! - [`now_ms`] is the wall clock. Use it for timestamps shared with peers
+//!   (heartbeats, room expiry, countdown starts, claims).
+//! - [`monotonic_ms`] starts at the wall time of its first use and then only
+//!   moves forward at a steady pace, whatever happens to the system clock.
+//!   Use it for local intervals, throttles and thresholds, so an NTP step
+//!   mid-game doesn't drop every player or lift a throttle.
 
 pub use std::time::Duration;
 
+/// Current wall-clock time in milliseconds
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Current wall-clock time in milliseconds (WASM version)
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> u64 {
+    js_sys::Date::now() as u64
+}
+
+/// Current monotonic time in milliseconds.
+///
+/// On native platforms, wall time at first use plus a `std::time::Instant`.
+/// On WASM, `performance.timeOrigin + performance.now()`, falling back to
+/// the wall clock where `performance` is missing.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn monotonic_ms() -> u64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static ORIGIN: OnceLock<(Instant, u64)> = OnceLock::new();
+    let (start, wall) = ORIGIN.get_or_init(|| (Instant::now(), now_ms()));
+    wall + start.elapsed().as_millis() as u64
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn monotonic_ms() -> u64 {
+    use js_sys::{Function, JsString, Reflect};
+
+    let Ok(performance) = Reflect::get(&js_sys::global(), &JsString::from("performance")) else {
+        return now_ms();
+    };
+    let origin = Reflect::get(&performance, &JsString::from("timeOrigin"))
+        .ok()
+        .and_then(|v| v.as_f64());
+    let elapsed = Reflect::get(&performance, &JsString::from("now"))
+        .ok()
+        .and_then(|now| Function::from(now).call0(&performance).ok())
+        .and_then(|v| v.as_f64());
+    match (origin, elapsed) {
+        (Some(origin), Some(elapsed)) => (origin + elapsed) as u64,
+        _ => now_ms(),
+    }
+}
+
+/// Monotonic time of the wall-clock moment `wall_ms`, for local bookkeeping
+/// of a timestamp received from a peer
+pub fn monotonic_at(wall_ms: u64) -> u64 {
+    monotonic_ms().saturating_sub(now_ms().saturating_sub(wall_ms))
+}
+
 /// Sleep for a duration.
 ///
 /// On native platforms, uses `tokio::time::sleep`.
@@ -42,3 +107,27 @@ impl Interval {
 pub fn interval(period: Duration) -> Interval {
     Interval { period }
 }
+
+/// Run `future` for at most `duration`; `None` if it didn't finish in time.
+///
+/// On native platforms, uses `tokio::time::timeout`.
+/// On WASM, races the future against [`sleep`].
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn timeout<F: std::future::Future>(duration: Duration, future: F) -> Option<F::Output> {
+    tokio::time::timeout(duration, future).await.ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn timeout<F: std::future::Future>(duration: Duration, future: F) -> Option<F::Output> {
+    use std::task::Poll;
+
+    let mut future = std::pin::pin!(future);
+    let mut deadline = std::pin::pin!(sleep(duration));
+    std::future::poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        deadline.as_mut().poll(cx).map(|()| None)
+    })
+    .await
+}
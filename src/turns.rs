@@ -0,0 +1,70 @@
+//! Turn-based play
+//!
+//! A [`TurnManager`] runs a strictly turn-based game on an arena configured
+//! with `enforce_turns`. The order comes from the seed and the seated
+//! players (or the host's `set_turn_order`), so every peer agrees on it
+//! without a round trip: the first player starts when the game does, and
+//! each `submit_turn()` publishes the move together with the next player.
+//!
+//! Out-of-turn moves are refused locally before anything is sent, and
+//! peers drop turn messages from anyone but the player to move. Moves
+//! arrive as `ArenaEvent::TurnReceived`, followed by `ArenaEvent::TurnStart`
+//! for the next player.
+
+use crate::arena::Arena;
+use crate::error::{ArenaError, Result};
+use crate::types::RoomStatus;
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Turn enforcement on top of an [`Arena`]
+#[derive(Clone)]
+pub struct TurnManager<T> {
+    arena: Arena<T>,
+}
+
+impl<T> TurnManager<T>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    /// Wrap `arena`, which must have `enforce_turns` on
+    pub fn new(arena: &Arena<T>) -> Result<Self> {
+        if !arena.config().enforce_turns {
+            return Err(ArenaError::InvalidValue(
+                "TurnManager needs enforce_turns".to_string(),
+            ));
+        }
+        Ok(Self {
+            arena: arena.clone(),
+        })
+    }
+
+    pub fn arena(&self) -> &Arena<T> {
+        &self.arena
+    }
+
+    /// Player to move, once the game has started
+    pub async fn current(&self) -> Option<String> {
+        let state = self.arena.room_state().await;
+        if state.status != RoomStatus::Playing {
+            return None;
+        }
+        state.turn
+    }
+
+    pub async fn is_my_turn(&self) -> bool {
+        self.current().await.as_deref() == Some(self.arena.public_key().as_str())
+    }
+
+    /// Turn order of the current game
+    pub async fn order(&self) -> Vec<String> {
+        self.arena.turn_order().await
+    }
+
+    /// Publish our move and pass the turn on, returning the next player.
+    /// Fails with `NotAuthorized` if it isn't our turn.
+    pub async fn submit_turn<M: Serialize>(&self, player_move: &M) -> Result<String> {
+        self.arena
+            .submit_move(serde_json::to_value(player_move)?)
+            .await
+    }
+}
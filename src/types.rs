@@ -1,6 +1,17 @@
 //! Type definitions for nostr-arena
 
+use crate::clock::{ChessClock, TimeControl};
+use crate::error::ArenaError;
+use crate::hooks::{Hooks, LifecycleHook};
+use crate::identity::IdentityLink;
+use crate::middleware::{EventMiddleware, Middleware};
+use crate::rng::turn_order;
+use crate::time::monotonic_ms;
+use nostr_sdk::hashes::{Hash, sha256::Hash as Sha256Hash};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
 /// Nostr event kinds used by the library
 pub mod kinds {
@@ -8,8 +19,25 @@ pub mod kinds {
     pub const ROOM: u16 = 30078;
     /// Ephemeral event for game state (not stored by relays)
     pub const EPHEMERAL: u16 = 25000;
+    /// Report event (NIP-56)
+    pub const REPORT: u16 = 1984;
+    /// User status (NIP-38), used for cross-game activity
+    pub const USER_STATUS: u16 = 30315;
+    /// Identity link proof, carried in join metadata and never published
+    pub const IDENTITY_LINK: u16 = 25001;
+    /// Addressable archive of a finished match, for match history
+    pub const MATCH_ARCHIVE: u16 = 30079;
+    /// Addressable room template saved by a host
+    pub const ROOM_TEMPLATE: u16 = 30080;
 }
 
+/// Wire protocol version stamped on every published content payload.
+///
+/// Payloads without a `version` field are treated as version 0 and upgraded
+/// by [`crate::migrate`] before being deserialized. Bump it with every change
+/// older peers would misread, and add the migration step.
+pub const PROTOCOL_VERSION: u32 = 3;
+
 /// Room status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -25,6 +53,48 @@ pub enum RoomStatus {
     Deleted,
 }
 
+impl RoomStatus {
+    /// Wire name of the status
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoomStatus::Idle => "idle",
+            RoomStatus::Creating => "creating",
+            RoomStatus::Waiting => "waiting",
+            RoomStatus::Joining => "joining",
+            RoomStatus::Ready => "ready",
+            RoomStatus::Playing => "playing",
+            RoomStatus::Finished => "finished",
+            RoomStatus::Deleted => "deleted",
+        }
+    }
+}
+
+impl fmt::Display for RoomStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RoomStatus {
+    type Err = ArenaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "idle" => Ok(RoomStatus::Idle),
+            "creating" => Ok(RoomStatus::Creating),
+            "waiting" => Ok(RoomStatus::Waiting),
+            "joining" => Ok(RoomStatus::Joining),
+            "ready" => Ok(RoomStatus::Ready),
+            "playing" => Ok(RoomStatus::Playing),
+            "finished" => Ok(RoomStatus::Finished),
+            "deleted" => Ok(RoomStatus::Deleted),
+            _ => Err(ArenaError::InvalidValue(format!(
+                "unknown room status: {s}"
+            ))),
+        }
+    }
+}
+
 /// Start mode for game initiation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -40,6 +110,319 @@ pub enum StartMode {
     Host,
 }
 
+impl StartMode {
+    /// Wire name of the start mode
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StartMode::Auto => "auto",
+            StartMode::Ready => "ready",
+            StartMode::Countdown => "countdown",
+            StartMode::Host => "host",
+        }
+    }
+}
+
+impl fmt::Display for StartMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StartMode {
+    type Err = ArenaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(StartMode::Auto),
+            "ready" => Ok(StartMode::Ready),
+            "countdown" => Ok(StartMode::Countdown),
+            "host" => Ok(StartMode::Host),
+            _ => Err(ArenaError::InvalidValue(format!("unknown start mode: {s}"))),
+        }
+    }
+}
+
+/// Report type for NIP-56 reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportReason {
+    Nudity,
+    Malware,
+    Spam,
+    Profanity,
+    Impersonation,
+    Illegal,
+    Other,
+}
+
+impl ReportReason {
+    /// NIP-56 report type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportReason::Nudity => "nudity",
+            ReportReason::Malware => "malware",
+            ReportReason::Spam => "spam",
+            ReportReason::Profanity => "profanity",
+            ReportReason::Impersonation => "impersonation",
+            ReportReason::Illegal => "illegal",
+            ReportReason::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for ReportReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReportReason {
+    type Err = ArenaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "nudity" => Ok(ReportReason::Nudity),
+            "malware" => Ok(ReportReason::Malware),
+            "spam" => Ok(ReportReason::Spam),
+            "profanity" => Ok(ReportReason::Profanity),
+            "impersonation" => Ok(ReportReason::Impersonation),
+            "illegal" => Ok(ReportReason::Illegal),
+            "other" => Ok(ReportReason::Other),
+            _ => Err(ArenaError::InvalidValue(format!(
+                "unknown report reason: {s}"
+            ))),
+        }
+    }
+}
+
+/// Machine-readable reason for a game over, a denied seat or an error, so
+/// frontends can show their own localized text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonCode {
+    Won,
+    Lost,
+    Draw,
+    Resigned,
+    /// Ran out of time on the clock
+    OutOfTime,
+    /// Every opponent stopped responding
+    Abandoned,
+    /// Removed from the room by the host
+    Kicked,
+    Blocked,
+    RoomFull,
+    Expired,
+    Disconnected,
+    NotConnected,
+    Timeout,
+    NotAuthorized,
+    RoomNotFound,
+    Relay,
+    /// Game-specific reason, or a code from a newer version; the text is
+    /// in the detail
+    #[serde(other)]
+    Custom,
+}
+
+impl ReasonCode {
+    const ALL: [ReasonCode; 17] = [
+        ReasonCode::Won,
+        ReasonCode::Lost,
+        ReasonCode::Draw,
+        ReasonCode::Resigned,
+        ReasonCode::OutOfTime,
+        ReasonCode::Abandoned,
+        ReasonCode::Kicked,
+        ReasonCode::Blocked,
+        ReasonCode::RoomFull,
+        ReasonCode::Expired,
+        ReasonCode::Disconnected,
+        ReasonCode::NotConnected,
+        ReasonCode::Timeout,
+        ReasonCode::NotAuthorized,
+        ReasonCode::RoomNotFound,
+        ReasonCode::Relay,
+        ReasonCode::Custom,
+    ];
+
+    /// Wire form, also a stable key for translation tables
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReasonCode::Won => "won",
+            ReasonCode::Lost => "lost",
+            ReasonCode::Draw => "draw",
+            ReasonCode::Resigned => "resigned",
+            ReasonCode::OutOfTime => "out_of_time",
+            ReasonCode::Abandoned => "abandoned",
+            ReasonCode::Kicked => "kicked",
+            ReasonCode::Blocked => "blocked",
+            ReasonCode::RoomFull => "room_full",
+            ReasonCode::Expired => "expired",
+            ReasonCode::Disconnected => "disconnected",
+            ReasonCode::NotConnected => "not_connected",
+            ReasonCode::Timeout => "timeout",
+            ReasonCode::NotAuthorized => "not_authorized",
+            ReasonCode::RoomNotFound => "room_not_found",
+            ReasonCode::Relay => "relay",
+            ReasonCode::Custom => "custom",
+        }
+    }
+
+    /// English text, the string form sent to older peers
+    pub fn message(&self) -> &'static str {
+        match self {
+            ReasonCode::Won => "Won",
+            ReasonCode::Lost => "Lost",
+            ReasonCode::Draw => "Draw",
+            ReasonCode::Resigned => "Resigned",
+            ReasonCode::OutOfTime => "Out of time",
+            ReasonCode::Abandoned => "Match abandoned",
+            ReasonCode::Kicked => "Kicked by the host",
+            ReasonCode::Blocked => "Blocked by the host",
+            ReasonCode::RoomFull => "Room is full",
+            ReasonCode::Expired => "Room expired",
+            ReasonCode::Disconnected => "Disconnected",
+            ReasonCode::NotConnected => "Not connected to relays",
+            ReasonCode::Timeout => "Operation timed out",
+            ReasonCode::NotAuthorized => "Not authorized",
+            ReasonCode::RoomNotFound => "Room not found",
+            ReasonCode::Relay => "Relay error",
+            ReasonCode::Custom => "Other",
+        }
+    }
+}
+
+impl fmt::Display for ReasonCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Reason code with an optional payload (e.g. the winner, or the text of a
+/// `Custom` reason). `Display` gives the English string form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reason {
+    pub code: ReasonCode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+}
+
+impl Reason {
+    pub fn new(code: ReasonCode) -> Self {
+        Self { code, detail: None }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<serde_json::Value>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Parse the string form: a code's wire form or English text, or else a
+    /// `Custom` reason carrying the text
+    pub fn from_text(text: &str) -> Self {
+        let trimmed = text.trim();
+        ReasonCode::ALL
+            .into_iter()
+            .filter(|code| *code != ReasonCode::Custom)
+            .find(|code| code.as_str() == trimmed || code.message().eq_ignore_ascii_case(trimmed))
+            .map(Reason::new)
+            .unwrap_or_else(|| Reason::new(ReasonCode::Custom).with_detail(text))
+    }
+}
+
+impl From<ReasonCode> for Reason {
+    fn from(code: ReasonCode) -> Self {
+        Reason::new(code)
+    }
+}
+
+impl From<&str> for Reason {
+    fn from(text: &str) -> Self {
+        Reason::from_text(text)
+    }
+}
+
+impl From<&ArenaError> for Reason {
+    fn from(error: &ArenaError) -> Self {
+        match error {
+            ArenaError::NotConnected => ReasonCode::NotConnected.into(),
+            ArenaError::RoomNotFound => ReasonCode::RoomNotFound.into(),
+            ArenaError::RoomExpired => ReasonCode::Expired.into(),
+            ArenaError::RoomFull => ReasonCode::RoomFull.into(),
+            ArenaError::Timeout => ReasonCode::Timeout.into(),
+            ArenaError::NotAuthorized(detail) => {
+                Reason::new(ReasonCode::NotAuthorized).with_detail(detail.as_str())
+            }
+            ArenaError::Relay(detail) => {
+                Reason::new(ReasonCode::Relay).with_detail(detail.as_str())
+            }
+            other => Reason::new(ReasonCode::Custom).with_detail(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.detail.as_ref().and_then(|d| d.as_str())) {
+            (ReasonCode::Custom, Some(text)) => f.write_str(text),
+            (code, Some(text)) => write!(f, "{}: {text}", code.message()),
+            (code, None) => f.write_str(code.message()),
+        }
+    }
+}
+
+/// How game state travels between the players of a room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMode {
+    /// Every player broadcasts state and heartbeats to everyone
+    #[default]
+    Mesh,
+    /// Players send state and heartbeats to the host only; the host
+    /// publishes them to the room as one `digest` at a fixed rate, with
+    /// everyone's presence. For rooms of 16+ players.
+    Hosted,
+}
+
+impl SyncMode {
+    pub fn is_mesh(&self) -> bool {
+        *self == SyncMode::Mesh
+    }
+}
+
+/// Who a room is meant for, advertised in the room event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoomVisibility {
+    /// Open to anyone browsing the game's rooms
+    #[default]
+    Public,
+    /// Friends only (a password or an invite); browsers can leave it out
+    /// with `hide_private_rooms`
+    Private,
+}
+
+impl RoomVisibility {
+    pub fn is_public(&self) -> bool {
+        *self == RoomVisibility::Public
+    }
+}
+
+/// Configuration presets for common game genres
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    /// Board/card games: unthrottled moves, tolerant presence, ready-up start
+    TurnBased,
+    /// Action games: fast updates, quick disconnect detection, countdown start
+    Realtime,
+    /// Casual groups: larger rooms, relaxed presence, host-controlled start
+    Party,
+    /// Correspondence play: rare heartbeats, players may be away for hours
+    Async,
+}
+
 /// Arena configuration
 #[derive(Debug, Clone)]
 pub struct ArenaConfig {
@@ -47,16 +430,75 @@ pub struct ArenaConfig {
     pub game_id: String,
     /// Nostr relay URLs
     pub relays: Vec<String>,
+    /// Relays for fetching rooms and receiving game traffic (default: `relays`).
+    /// Advertised in the room event so peers publish game traffic there.
+    pub read_relays: Vec<String>,
+    /// Relays for publishing room metadata (default: `relays`)
+    pub write_relays: Vec<String>,
+    /// Low-latency relay advertised in the room event. Joiners add it for the
+    /// duration of the match and drop it on leave.
+    pub preferred_relay: Option<String>,
     /// Room expiration time in ms (0 = never, default: 0)
     pub room_expiry: u64,
+    /// Expire the room this many ms after its host was last active (0 =
+    /// off, default: 0). The host pushes `expires_at` forward at every
+    /// presence sweep; `room_expiry` still caps the room's age.
+    pub idle_expiry: u64,
     /// Heartbeat interval in ms (default: 3000)
     pub heartbeat_interval: u64,
     /// Disconnect threshold in ms (default: 10000)
     pub disconnect_threshold: u64,
+    /// Disconnect timeout in ms for players whose app went to the
+    /// background, where heartbeat timers are throttled (default: 300000)
+    pub away_timeout: u64,
+    /// How often (ms) to check whether the network route changed, to
+    /// reconnect at once; native only (default: 2000, 0 = off)
+    pub network_watch_interval: u64,
+    /// Time (ms) `Arena::shutdown()` gives its final messages in total
+    /// before disconnecting anyway (default: 3000)
+    pub shutdown_timeout: u64,
+    /// How often (ms) peers check their roster against the host's room
+    /// event and add or drop players to match (default: 60000, 0 = off)
+    pub roster_reconcile_interval: u64,
+    /// How long (ms) peers keep the control messages they saw, so the host
+    /// can publish them again for a peer that missed them (default: 0 = off)
+    pub resend_window: u64,
+    /// Interval in ms at which every peer drops players silent for longer
+    /// than `disconnect_threshold`, and the host republishes the room
+    /// (default: 30000)
+    pub presence_interval: u64,
+    /// Heartbeat interval in ms while the room is idle, i.e. not playing and
+    /// no countdown running (default: 30000, 0 = same as playing)
+    pub idle_heartbeat_interval: u64,
+    /// Presence interval in ms while the room is idle (default: 120000,
+    /// 0 = same as playing)
+    pub idle_presence_interval: u64,
     /// State update throttle in ms (default: 100)
     pub state_throttle: u64,
+    /// Public summary throttle in ms (`Arena::send_summary`, default: 1000)
+    pub summary_throttle: u64,
+    /// Whether spectators follow only the public summaries the players send
+    /// with `Arena::send_summary` instead of the room's full traffic;
+    /// advertised in the room event (default: false)
+    pub spectator_summaries: bool,
+    /// How state and presence are fanned out (default: `Mesh`). The host's
+    /// mode is advertised in the room event and followed by every peer.
+    pub sync_mode: SyncMode,
+    /// Interval in ms at which a `Hosted` room's host publishes the digest
+    /// of player states (default: 250)
+    pub digest_interval: u64,
+    /// Per-message-type throttles in ms, keyed by wire `type` (e.g. "chat").
+    /// Overrides `state_throttle` for "state" and `summary_throttle` for
+    /// "summary"; unlisted types are unthrottled.
+    pub throttles: HashMap<String, u64>,
+    /// Interval in ms for checking whether all opponents went silent during
+    /// play (default: 5000, 0 = disabled)
+    pub watchdog_interval: u64,
     /// Join timeout in ms (default: 30000)
     pub join_timeout: u64,
+    /// How long room and activity fetches wait for relays in ms (default:
+    /// 5000); raise it for throttled background tabs
+    pub fetch_timeout: u64,
     /// Maximum players (default: 2)
     pub max_players: usize,
     /// Start mode (default: Auto)
@@ -65,6 +507,102 @@ pub struct ArenaConfig {
     pub countdown_seconds: u32,
     /// Base URL for room URLs
     pub base_url: Option<String>,
+    /// Custom tags added to every published event (e.g. `["client", "my-game"]`)
+    pub extra_tags: Vec<Vec<String>>,
+    /// Add `p` tags for participants and an `e` tag for the room event to
+    /// game traffic, so relays and generic clients can index and thread it
+    /// (default: false)
+    pub thread_tags: bool,
+    /// Once the game starts, only accept room traffic from the players,
+    /// hosts and spectators in the room at that moment, until a rematch;
+    /// new spectators aren't heard meanwhile (default: false)
+    pub lock_on_start: bool,
+    /// Deliver this player's own state, ready, game over, rematch and turn
+    /// messages to the event stream after they are published (default: false)
+    pub echo_self: bool,
+    /// Add the game relays to room URLs as `relay` query parameters, so
+    /// joiners with different relays can find the room (default: true)
+    pub relay_hints: bool,
+    /// When fetching a room, republish its newest revision to relays that
+    /// answered with an older one or none (default: true)
+    pub read_repair: bool,
+    /// Maximum chat message length in characters (default: 500)
+    pub chat_max_length: usize,
+    /// Chat messages accepted per player within `chat_flood_window`; extra
+    /// messages are dropped (default: 5, 0 = unlimited)
+    pub chat_flood_limit: u32,
+    /// Chat flood window in ms (default: 10000)
+    pub chat_flood_window: u64,
+    /// Public keys that are never seated, shown in discovery or listened to
+    pub blocklist: Vec<String>,
+    /// Send the next player a NIP-17 direct message when a turn passes to
+    /// them while they are offline (default: false)
+    pub turn_notifications: bool,
+    /// Per-player time banks for turn-based games, started when the game
+    /// starts and switched by `end_turn` (default: none)
+    pub time_control: Option<TimeControl>,
+    /// Time a player has for a turn before the host counts a strike, in ms;
+    /// 0 turns the turn timer off (default: 0)
+    pub turn_timeout: u64,
+    /// Turn timeouts in a row after which the host opens a vote to wait
+    /// for, skip or kick the player (default: 2)
+    pub turn_strikes: u32,
+    /// Strict turn-based play for [`crate::turns::TurnManager`]: the first
+    /// player in the turn order starts, and turn messages from anyone but
+    /// the player to move are dropped (default: false)
+    pub enforce_turns: bool,
+    /// Hash every player's `state` messages into a chain and check it
+    /// against the chain they declare at game over (default: false)
+    pub input_audit: bool,
+    /// How long (ms) the host collects competing claims after the first
+    /// one before resolving them (default: 250)
+    pub claim_window: u64,
+    /// How long (ms) the host collects game overs after the first one
+    /// before deciding the match with `gameended` (default: 500)
+    pub game_over_window: u64,
+    /// Publish a compact archive of the match (result and keyframes) when
+    /// we send game over; see `Arena::archive_match()` (default: false)
+    pub archive_matches: bool,
+    /// Optional protocol features we announce when joining (default: JSON
+    /// only); see `Arena::negotiated_capabilities()`
+    pub capabilities: Capabilities,
+    /// Link from our match key to a long-term identity, set by
+    /// `Arena::with_linked_identity()`
+    pub identity_link: Option<IdentityLink>,
+    /// App name for the NIP-89 `client` tag on room and result events
+    pub client_name: Option<String>,
+    /// App version, added to the `client` tag as `{name}/{version}`
+    pub client_version: Option<String>,
+    /// Version prefixes (e.g. `"1."`) of our client whose rooms `find_rooms`
+    /// returns; empty = any client
+    pub compatible_versions: Vec<String>,
+    /// Leave the game hashtag off room events, so rooms are only reachable
+    /// through their link, invite or QR code and never show up in
+    /// `list_rooms` (default: false)
+    pub unlisted: bool,
+    /// Password joiners of our rooms must give to `join_with_secret()`; only
+    /// a salted hash is published (default: none)
+    pub room_password: Option<String>,
+    /// Seal game state in the rooms we create with a room key handed to
+    /// seated players only (see [`crate::seal`], default: false)
+    pub encrypt_state: bool,
+    /// Seat joiners in our rooms while the game is running; they catch up
+    /// from a state snapshot (default: false)
+    pub late_join: bool,
+    /// Visibility advertised for our rooms (default: `Public`)
+    pub visibility: RoomVisibility,
+    /// Leave `Private` rooms out of `find_rooms` and `watch_rooms`
+    /// (default: false)
+    pub hide_private_rooms: bool,
+    /// Leave the player list out of the room event above this many players,
+    /// publishing only the count and a roster hash; peers ask the host for
+    /// the roster when they join (default: 16, 0 = always include)
+    pub roster_threshold: usize,
+    /// Hooks on room messages, added with `with_middleware()` (default:
+    /// none)
+    pub middleware: Middleware,
+    /// Host lifecycle hooks, added with `with_hook()` (default: none)
+    pub hooks: Hooks,
 }
 
 impl Default for ArenaConfig {
@@ -76,15 +614,67 @@ impl Default for ArenaConfig {
                 "wss://nos.lol".to_string(),
                 "wss://relay.nostr.band".to_string(),
             ],
+            read_relays: Vec::new(),
+            write_relays: Vec::new(),
+            preferred_relay: None,
             room_expiry: 0, // Never expire by default
+            idle_expiry: 0,
             heartbeat_interval: 3000,
             disconnect_threshold: 10000,
+            away_timeout: 300_000,
+            network_watch_interval: 2000,
+            shutdown_timeout: 3000,
+            roster_reconcile_interval: 60000,
+            resend_window: 0,
+            presence_interval: 30000,
+            idle_heartbeat_interval: 30000,
+            idle_presence_interval: 120000,
             state_throttle: 100,
+            summary_throttle: 1000,
+            spectator_summaries: false,
+            sync_mode: SyncMode::Mesh,
+            digest_interval: 250,
+            throttles: HashMap::new(),
+            watchdog_interval: 5000,
             join_timeout: 30000,
+            fetch_timeout: 5000,
             max_players: 2,
             start_mode: StartMode::Auto,
             countdown_seconds: 3,
             base_url: None,
+            extra_tags: Vec::new(),
+            thread_tags: false,
+            lock_on_start: false,
+            echo_self: false,
+            relay_hints: true,
+            read_repair: true,
+            chat_max_length: 500,
+            chat_flood_limit: 5,
+            chat_flood_window: 10000,
+            blocklist: Vec::new(),
+            turn_notifications: false,
+            time_control: None,
+            turn_timeout: 0,
+            turn_strikes: 2,
+            enforce_turns: false,
+            input_audit: false,
+            claim_window: 250,
+            game_over_window: 500,
+            archive_matches: false,
+            capabilities: Capabilities::default(),
+            identity_link: None,
+            unlisted: false,
+            room_password: None,
+            encrypt_state: false,
+            late_join: false,
+            visibility: RoomVisibility::Public,
+            hide_private_rooms: false,
+            client_name: None,
+            client_version: None,
+            compatible_versions: Vec::new(),
+            roster_threshold: 16,
+            middleware: Middleware::new(),
+            hooks: Hooks::new(),
         }
     }
 }
@@ -97,70 +687,1076 @@ impl ArenaConfig {
         }
     }
 
-    pub fn relays(mut self, relays: Vec<String>) -> Self {
-        self.relays = relays;
-        self
+    /// Apply a genre preset. Builder calls after this one override it.
+    pub fn preset(mut self, preset: Preset) -> Self {
+        match preset {
+            Preset::TurnBased => {
+                self.state_throttle = 0;
+                self.heartbeat_interval = 5000;
+                self.disconnect_threshold = 30000;
+                self.start_mode = StartMode::Ready;
+            }
+            Preset::Realtime => {
+                self.state_throttle = 50;
+                self.heartbeat_interval = 2000;
+                self.disconnect_threshold = 6000;
+                self.start_mode = StartMode::Countdown;
+                self.countdown_seconds = 3;
+            }
+            Preset::Party => {
+                self.state_throttle = 200;
+                self.heartbeat_interval = 5000;
+                self.disconnect_threshold = 20000;
+                self.start_mode = StartMode::Host;
+                self.max_players = 8;
+            }
+            Preset::Async => {
+                self.state_throttle = 0;
+                self.heartbeat_interval = 60_000;
+                self.disconnect_threshold = 24 * 60 * 60 * 1000;
+                self.start_mode = StartMode::Auto;
+                self.room_expiry = 7 * 24 * 60 * 60 * 1000;
+                self.turn_notifications = true;
+            }
+        }
+        self
+    }
+
+    pub fn relays(mut self, relays: Vec<String>) -> Self {
+        self.relays = relays;
+        self
+    }
+
+    pub fn read_relays(mut self, relays: Vec<String>) -> Self {
+        self.read_relays = relays;
+        self
+    }
+
+    pub fn write_relays(mut self, relays: Vec<String>) -> Self {
+        self.write_relays = relays;
+        self
+    }
+
+    pub fn preferred_relay(mut self, url: impl Into<String>) -> Self {
+        self.preferred_relay = Some(url.into());
+        self
+    }
+
+    pub fn room_expiry(mut self, ms: u64) -> Self {
+        self.room_expiry = ms;
+        self
+    }
+
+    pub fn idle_expiry(mut self, ms: u64) -> Self {
+        self.idle_expiry = ms;
+        self
+    }
+
+    pub fn heartbeat_interval(mut self, ms: u64) -> Self {
+        self.heartbeat_interval = ms;
+        self
+    }
+
+    pub fn disconnect_threshold(mut self, ms: u64) -> Self {
+        self.disconnect_threshold = ms;
+        self
+    }
+
+    pub fn away_timeout(mut self, ms: u64) -> Self {
+        self.away_timeout = ms;
+        self
+    }
+
+    pub fn network_watch_interval(mut self, ms: u64) -> Self {
+        self.network_watch_interval = ms;
+        self
+    }
+
+    pub fn shutdown_timeout(mut self, ms: u64) -> Self {
+        self.shutdown_timeout = ms;
+        self
+    }
+
+    pub fn roster_reconcile_interval(mut self, ms: u64) -> Self {
+        self.roster_reconcile_interval = ms;
+        self
+    }
+
+    pub fn resend_window(mut self, ms: u64) -> Self {
+        self.resend_window = ms;
+        self
+    }
+
+    pub fn presence_interval(mut self, ms: u64) -> Self {
+        self.presence_interval = ms;
+        self
+    }
+
+    /// Heartbeat and presence intervals while the room is idle (0 = no
+    /// backoff)
+    pub fn idle_intervals(mut self, heartbeat_ms: u64, presence_ms: u64) -> Self {
+        self.idle_heartbeat_interval = heartbeat_ms;
+        self.idle_presence_interval = presence_ms;
+        self
+    }
+
+    /// Effective heartbeat interval in ms. Idle rooms back off, but never
+    /// below the playing cadence.
+    pub fn heartbeat_interval_for(&self, idle: bool) -> u64 {
+        if idle {
+            self.heartbeat_interval.max(self.idle_heartbeat_interval)
+        } else {
+            self.heartbeat_interval
+        }
+    }
+
+    /// Effective presence interval in ms
+    pub fn presence_interval_for(&self, idle: bool) -> u64 {
+        if idle {
+            self.presence_interval.max(self.idle_presence_interval)
+        } else {
+            self.presence_interval
+        }
+    }
+
+    /// Effective disconnect threshold in ms, stretched by the same factor as
+    /// the heartbeat interval
+    pub fn disconnect_threshold_for(&self, idle: bool) -> u64 {
+        let heartbeat = self.heartbeat_interval.max(1);
+        self.disconnect_threshold * self.heartbeat_interval_for(idle).max(heartbeat) / heartbeat
+    }
+
+    pub fn state_throttle(mut self, ms: u64) -> Self {
+        self.state_throttle = ms;
+        self
+    }
+
+    pub fn summary_throttle(mut self, ms: u64) -> Self {
+        self.summary_throttle = ms;
+        self
+    }
+
+    pub fn spectator_summaries(mut self, enabled: bool) -> Self {
+        self.spectator_summaries = enabled;
+        self
+    }
+
+    pub fn sync_mode(mut self, mode: SyncMode) -> Self {
+        self.sync_mode = mode;
+        self
+    }
+
+    pub fn digest_interval(mut self, ms: u64) -> Self {
+        self.digest_interval = ms;
+        self
+    }
+
+    pub fn watchdog_interval(mut self, ms: u64) -> Self {
+        self.watchdog_interval = ms;
+        self
+    }
+
+    /// Throttle a message type (wire `type`, e.g. "state" or "chat")
+    pub fn throttle(mut self, message_type: impl Into<String>, ms: u64) -> Self {
+        self.throttles.insert(message_type.into(), ms);
+        self
+    }
+
+    /// Effective throttle in ms for a message type
+    pub fn throttle_for(&self, message_type: &str) -> u64 {
+        match self.throttles.get(message_type) {
+            Some(&ms) => ms,
+            None if message_type == "state" => self.state_throttle,
+            None if message_type == "summary" => self.summary_throttle,
+            None => 0,
+        }
+    }
+
+    pub fn join_timeout(mut self, ms: u64) -> Self {
+        self.join_timeout = ms;
+        self
+    }
+
+    pub fn fetch_timeout(mut self, ms: u64) -> Self {
+        self.fetch_timeout = ms;
+        self
+    }
+
+    pub fn max_players(mut self, n: usize) -> Self {
+        self.max_players = n;
+        self
+    }
+
+    pub fn start_mode(mut self, mode: StartMode) -> Self {
+        self.start_mode = mode;
+        self
+    }
+
+    pub fn countdown_seconds(mut self, secs: u32) -> Self {
+        self.countdown_seconds = secs;
+        self
+    }
+
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    pub fn thread_tags(mut self, enabled: bool) -> Self {
+        self.thread_tags = enabled;
+        self
+    }
+
+    pub fn lock_on_start(mut self, enabled: bool) -> Self {
+        self.lock_on_start = enabled;
+        self
+    }
+
+    pub fn echo_self(mut self, enabled: bool) -> Self {
+        self.echo_self = enabled;
+        self
+    }
+
+    pub fn relay_hints(mut self, enabled: bool) -> Self {
+        self.relay_hints = enabled;
+        self
+    }
+
+    pub fn read_repair(mut self, enabled: bool) -> Self {
+        self.read_repair = enabled;
+        self
+    }
+
+    pub fn chat_max_length(mut self, chars: usize) -> Self {
+        self.chat_max_length = chars;
+        self
+    }
+
+    /// Accept at most `messages` chat messages per player every `window_ms`
+    pub fn chat_flood_limit(mut self, messages: u32, window_ms: u64) -> Self {
+        self.chat_flood_limit = messages;
+        self.chat_flood_window = window_ms;
+        self
+    }
+
+    pub fn blocklist(mut self, pubkeys: Vec<String>) -> Self {
+        self.blocklist = pubkeys;
+        self
+    }
+
+    pub fn turn_notifications(mut self, enabled: bool) -> Self {
+        self.turn_notifications = enabled;
+        self
+    }
+
+    pub fn time_control(mut self, control: TimeControl) -> Self {
+        self.time_control = Some(control);
+        self
+    }
+
+    pub fn turn_timeout(mut self, ms: u64) -> Self {
+        self.turn_timeout = ms;
+        self
+    }
+
+    pub fn turn_strikes(mut self, strikes: u32) -> Self {
+        self.turn_strikes = strikes.max(1);
+        self
+    }
+
+    pub fn enforce_turns(mut self, enabled: bool) -> Self {
+        self.enforce_turns = enabled;
+        self
+    }
+
+    pub fn input_audit(mut self, enabled: bool) -> Self {
+        self.input_audit = enabled;
+        self
+    }
+
+    pub fn claim_window(mut self, ms: u64) -> Self {
+        self.claim_window = ms;
+        self
+    }
+
+    pub fn game_over_window(mut self, ms: u64) -> Self {
+        self.game_over_window = ms;
+        self
+    }
+
+    pub fn archive_matches(mut self, enabled: bool) -> Self {
+        self.archive_matches = enabled;
+        self
+    }
+
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn unlisted(mut self, enabled: bool) -> Self {
+        self.unlisted = enabled;
+        self
+    }
+
+    pub fn room_password(mut self, password: impl Into<String>) -> Self {
+        self.room_password = Some(password.into()).filter(|p: &String| !p.is_empty());
+        self
+    }
+
+    pub fn encrypt_state(mut self, enabled: bool) -> Self {
+        self.encrypt_state = enabled;
+        self
+    }
+
+    pub fn late_join(mut self, enabled: bool) -> Self {
+        self.late_join = enabled;
+        self
+    }
+
+    pub fn visibility(mut self, visibility: RoomVisibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    pub fn hide_private_rooms(mut self, enabled: bool) -> Self {
+        self.hide_private_rooms = enabled;
+        self
+    }
+
+    pub fn client_name(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.client_name = Some(name.into());
+        self.client_version = Some(version.into()).filter(|v: &String| !v.is_empty());
+        self
+    }
+
+    pub fn roster_threshold(mut self, players: usize) -> Self {
+        self.roster_threshold = players;
+        self
+    }
+
+    pub fn compatible_versions(mut self, prefixes: Vec<String>) -> Self {
+        self.compatible_versions = prefixes;
+        self
+    }
+
+    /// Add a middleware layer; layers added later sit closer to the wire
+    pub fn with_middleware(mut self, middleware: Box<dyn EventMiddleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Add a host lifecycle hook (see [`crate::hooks`])
+    pub fn with_hook(mut self, hook: Box<dyn LifecycleHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Value of the `client` tag, `{name}/{version}` or just `{name}`
+    pub fn client_tag(&self) -> Option<String> {
+        let name = self.client_name.as_ref()?;
+        Some(match &self.client_version {
+            Some(version) => format!("{name}/{version}"),
+            None => name.clone(),
+        })
+    }
+
+    /// Whether a room published with `client` tag value `client` can be
+    /// joined by this app, per `compatible_versions`
+    pub fn is_compatible_client(&self, client: Option<&str>) -> bool {
+        if self.compatible_versions.is_empty() {
+            return true;
+        }
+        let Some((name, version)) = client.and_then(|c| c.rsplit_once('/')) else {
+            return false;
+        };
+        self.client_name.as_deref().is_none_or(|ours| ours == name)
+            && self
+                .compatible_versions
+                .iter()
+                .any(|prefix| version.starts_with(prefix.as_str()))
+    }
+
+    /// Whether room browsers show rooms of this visibility, per
+    /// `hide_private_rooms`
+    pub fn lists_visibility(&self, visibility: RoomVisibility) -> bool {
+        !self.hide_private_rooms || visibility.is_public()
+    }
+
+    /// Discovery hashtag for room events, `None` for unlisted rooms
+    pub fn room_hashtag(&self) -> Option<&str> {
+        (!self.unlisted).then_some(self.game_id.as_str())
+    }
+
+    /// Add a custom tag to every published event
+    pub fn extra_tag<I, S>(mut self, tag: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extra_tags
+            .push(tag.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+/// Room state (game-agnostic)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoomState {
+    pub room_id: Option<String>,
+    pub status: RoomStatus,
+    pub is_host: bool,
+    pub seed: u64,
+    pub created_at: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub rematch_requested: bool,
+    #[serde(default)]
+    pub host_pubkey: Option<String>,
+    /// Confirmed seats assigned by the host, in seat order
+    #[serde(default)]
+    pub seats: Vec<String>,
+    /// Players the host authorized to update the room and start the game
+    #[serde(default)]
+    pub co_hosts: Vec<String>,
+    /// Player whose move it is (turn-based games using `end_turn`)
+    #[serde(default)]
+    pub turn: Option<String>,
+    /// Start time (ms) of the running countdown, shared by every peer
+    #[serde(default)]
+    pub countdown_at: Option<u64>,
+    /// Fan-out mode advertised by the host
+    #[serde(default)]
+    pub sync_mode: SyncMode,
+    /// Round counter of multi-round games (0 = not started)
+    #[serde(default)]
+    pub round: u32,
+    /// Named phase within the round (e.g. "draft", "play", "score")
+    #[serde(default)]
+    pub phase: Option<String>,
+    /// Cumulative standings of the session, best first
+    #[serde(default)]
+    pub scoreboard: Vec<Standing>,
+    /// Host's start settings, delivered with `ArenaEvent::GameStart`
+    #[serde(default)]
+    pub start_config: Option<serde_json::Value>,
+    /// Turn order set by the host, or derived from the seed at start
+    #[serde(default)]
+    pub turn_order: Vec<String>,
+    /// Lobby votes by topic
+    #[serde(default)]
+    pub votes: HashMap<String, Vote>,
+    /// Claims the host is collecting or has resolved, by claim id
+    #[serde(default)]
+    pub claims: HashMap<String, Claim>,
+    /// Game overs the host collected so far, to decide the match
+    #[serde(default)]
+    pub finishes: Vec<Finish>,
+    /// Set once the match was decided (`gameended`)
+    #[serde(default)]
+    pub game_ended: bool,
+    /// Time banks, with `time_control`
+    #[serde(default)]
+    pub clock: Option<ChessClock>,
+    /// Seconds each player must wait between chat messages (0 = off)
+    #[serde(default)]
+    pub chat_slow_mode: u32,
+    /// Players the host muted for everyone
+    #[serde(default)]
+    pub chat_muted: Vec<String>,
+    /// Players kicked from the room: their traffic is dropped and the host
+    /// denies their joins
+    #[serde(default)]
+    pub kicked: Vec<String>,
+    /// Protocol version each player joined with, as far as we saw their
+    /// `join`
+    #[serde(default)]
+    pub peer_versions: HashMap<String, u32>,
+    /// Seed and round of each player's last scored game over, so a resent
+    /// `gameover` counts once
+    #[serde(default)]
+    pub scored: HashMap<String, (u64, u32)>,
+    /// Whether we follow the room as a spectator, without a seat
+    #[serde(default)]
+    pub spectating: bool,
+    /// Spectators and when they were last seen (ms), kept by the host
+    #[serde(default)]
+    pub spectators: HashMap<String, u64>,
+    /// Template the host created the room from; its settings win over the
+    /// config's
+    #[serde(default)]
+    pub template: Option<RoomTemplate>,
+    /// Whether the room's spectators follow only public summaries
+    #[serde(default)]
+    pub summaries: bool,
+    /// Whether game state in the room is sealed with a room key
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Whether the host seats players while the game is running
+    #[serde(default)]
+    pub late_join: bool,
+}
+
+impl RoomState {
+    /// Whether `pubkey` is the host or a co-host of the room. Rooms without
+    /// a known host (pre-v2 snapshots) accept anyone.
+    pub fn is_authority(&self, pubkey: &str) -> bool {
+        match &self.host_pubkey {
+            Some(host) => host == pubkey || self.co_hosts.iter().any(|p| p == pubkey),
+            None => true,
+        }
+    }
+
+    /// Player limit of the room
+    pub fn max_players(&self, config: &ArenaConfig) -> usize {
+        self.template
+            .as_ref()
+            .map_or(config.max_players, |t| t.max_players)
+    }
+
+    /// Start mode of the room
+    pub fn start_mode(&self, config: &ArenaConfig) -> StartMode {
+        self.template
+            .as_ref()
+            .map_or(config.start_mode, |t| t.start_mode)
+    }
+
+    /// Countdown length of the room, in seconds
+    pub fn countdown_seconds(&self, config: &ArenaConfig) -> u32 {
+        self.template
+            .as_ref()
+            .map_or(config.countdown_seconds, |t| t.countdown_seconds)
+    }
+
+    /// Relays advertised for the room's game traffic
+    pub fn relays<'a>(&'a self, config: &'a ArenaConfig) -> &'a [String] {
+        match &self.template {
+            Some(t) if !t.relays.is_empty() => &t.relays,
+            _ => &config.read_relays,
+        }
+    }
+
+    /// Whether the room is idle: not playing and no countdown running, so
+    /// heartbeats and presence sweeps back off
+    pub fn is_idle(&self) -> bool {
+        self.status != RoomStatus::Playing && self.countdown_at.is_none()
+    }
+
+    /// Whether our state and heartbeats go to the host's channel instead of
+    /// the room (`Hosted` rooms, except for the host itself)
+    pub fn sends_to_host(&self) -> bool {
+        self.sync_mode == SyncMode::Hosted && !self.is_host
+    }
+
+    /// Add a game over report to the scoreboard, keeping it sorted. A
+    /// player scores once per seed and round; false for a repeated report.
+    pub fn record_score(&mut self, pubkey: &str, score: Option<i64>) -> bool {
+        let game = (self.seed, self.round);
+        if self.scored.insert(pubkey.to_string(), game) == Some(game) {
+            return false;
+        }
+        let index = match self.scoreboard.iter().position(|s| s.pubkey == pubkey) {
+            Some(index) => index,
+            None => {
+                self.scoreboard.push(Standing {
+                    pubkey: pubkey.to_string(),
+                    score: 0,
+                    games: 0,
+                });
+                self.scoreboard.len() - 1
+            }
+        };
+        let standing = &mut self.scoreboard[index];
+        standing.score = standing.score.saturating_add(score.unwrap_or(0));
+        standing.games += 1;
+        self.scoreboard
+            .sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.pubkey.cmp(&b.pubkey)));
+        true
+    }
+
+    /// Apply a chat rule from the host; false if the message lacks the
+    /// field its action needs
+    pub fn apply_moderation(&mut self, content: &ModerationEventContent) -> bool {
+        match (content.action, content.seconds, &content.player_pubkey) {
+            (ModerationAction::SlowMode, Some(seconds), _) => self.chat_slow_mode = seconds,
+            (ModerationAction::Mute, _, Some(pubkey)) => {
+                if !self.chat_muted.contains(pubkey) {
+                    self.chat_muted.push(pubkey.clone());
+                }
+            }
+            (ModerationAction::Unmute, _, Some(pubkey)) => self.chat_muted.retain(|p| p != pubkey),
+            (ModerationAction::Clear, _, _) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Seat index of a player, if seated
+    pub fn seat_of(&self, pubkey: &str) -> Option<usize> {
+        self.seats.iter().position(|p| p == pubkey)
+    }
+}
+
+/// A player's cumulative result over the session, including players who
+/// have left
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Standing {
+    pub pubkey: String,
+    /// Sum of `final_score` over the player's game over reports
+    pub score: i64,
+    /// Number of game over reports
+    pub games: u32,
+}
+
+/// Lobby vote on one topic (map, ruleset, ...)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vote {
+    pub topic: String,
+    pub options: Vec<String>,
+    /// Choice per player
+    pub ballots: HashMap<String, String>,
+    /// Set once the vote is closed
+    pub winner: Option<String>,
+}
+
+impl Vote {
+    pub fn new(topic: impl Into<String>, options: Vec<String>) -> Self {
+        Self {
+            topic: topic.into(),
+            options,
+            ballots: HashMap::new(),
+            winner: None,
+        }
+    }
+
+    /// Votes per option, in option order
+    pub fn tally(&self) -> Vec<(String, usize)> {
+        self.options
+            .iter()
+            .map(|option| {
+                let votes = self.ballots.values().filter(|c| *c == option).count();
+                (option.clone(), votes)
+            })
+            .collect()
+    }
+
+    /// Option with the most votes; ties go to the option listed first
+    pub fn leader(&self) -> Option<String> {
+        self.tally()
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, votes)| *votes)
+            .map(|(option, _)| option)
+    }
+}
+
+/// Topic prefix of the votes the host opens on a player who keeps letting
+/// the turn timer run out; the rest of the topic is the player's public key
+pub const AFK_VOTE_PREFIX: &str = "afk:";
+
+/// Options of an AFK vote: keep waiting, pass the turn on, or remove the
+/// player
+pub const AFK_VOTE_OPTIONS: [&str; 3] = ["wait", "skip", "kick"];
+
+/// Topic of the AFK vote on `pubkey`
+pub fn afk_vote_topic(pubkey: &str) -> String {
+    format!("{AFK_VOTE_PREFIX}{pubkey}")
+}
+
+/// Player an AFK vote topic is about
+pub fn afk_vote_target(topic: &str) -> Option<&str> {
+    topic.strip_prefix(AFK_VOTE_PREFIX)
+}
+
+/// Competing claims on one thing ("first to buzz", a tile), collected by
+/// the host
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claim {
+    /// Claimant and claim time on the host's clock (ms), in arrival order
+    pub entries: Vec<(String, u64)>,
+    /// Set once the host resolved the claim
+    pub winner: Option<String>,
+}
+
+impl Claim {
+    /// Record a claim; a player's first claim counts
+    pub fn add(&mut self, pubkey: &str, at: u64) {
+        if !self.entries.iter().any(|(p, _)| p == pubkey) {
+            self.entries.push((pubkey.to_string(), at));
+        }
+    }
+
+    /// Earliest claimant; equal times go to the lower public key so every
+    /// host would pick the same winner
+    pub fn earliest(&self) -> Option<String> {
+        self.entries
+            .iter()
+            .min_by(|(a, at_a), (b, at_b)| at_a.cmp(at_b).then_with(|| a.cmp(b)))
+            .map(|(pubkey, _)| pubkey.clone())
+    }
+}
+
+/// A player's game over, as the host recorded it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finish {
+    pub pubkey: String,
+    /// Finish time on the host's clock (ms), see [`claim_time`]
+    pub at: u64,
+    pub code: ReasonCode,
+}
+
+/// Decide a match from the game overs in `finishes`, returning the finish
+/// order and the winner, if any.
+///
+/// Finishes are ordered by time; equal times go by the seed's turn order,
+/// so every host would decide the same way. The first finisher who `Won`
+/// wins, and one who reported a `Draw` first makes the match a draw.
+/// Otherwise the finishers lost: the only seated player who didn't finish
+/// wins, or, if everyone went down (a double KO), the one who went down
+/// last.
+pub fn resolve_finishes(
+    finishes: &[Finish],
+    seated: &[String],
+    seed: u64,
+) -> (Vec<String>, Option<String>) {
+    let pubkeys: Vec<String> = finishes.iter().map(|f| f.pubkey.clone()).collect();
+    let ranks = turn_order(seed, &pubkeys);
+    let rank = |pubkey: &str| ranks.iter().position(|p| p == pubkey);
+    let mut sorted: Vec<&Finish> = finishes.iter().collect();
+    sorted.sort_by(|a, b| {
+        a.at.cmp(&b.at)
+            .then_with(|| rank(&a.pubkey).cmp(&rank(&b.pubkey)))
+    });
+    let order: Vec<String> = sorted.iter().map(|f| f.pubkey.clone()).collect();
+
+    let decisive = sorted
+        .iter()
+        .find(|f| matches!(f.code, ReasonCode::Won | ReasonCode::Draw));
+    let winner = match decisive {
+        Some(finish) if finish.code == ReasonCode::Won => Some(finish.pubkey.clone()),
+        Some(_) => None,
+        None => {
+            let survivors: Vec<&String> = seated.iter().filter(|p| !order.contains(p)).collect();
+            match survivors.as_slice() {
+                [survivor] => Some((*survivor).clone()),
+                [] if order.len() > 1 => order.last().cloned(),
+                _ => None,
+            }
+        }
+    };
+    (order, winner)
+}
+
+/// Whether `finishes` decide the match: someone reported `Won` or `Draw`,
+/// or at most one seated player is still playing. Until then a knockout
+/// only takes that player out.
+pub fn match_decided(finishes: &[Finish], seated: &[String]) -> bool {
+    finishes
+        .iter()
+        .any(|f| matches!(f.code, ReasonCode::Won | ReasonCode::Draw))
+        || seated
+            .iter()
+            .filter(|p| !finishes.iter().any(|f| f.pubkey == **p))
+            .count()
+            <= 1
+}
+
+/// Claim time (ms) of a message stamped `at` by a peer whose clock runs
+/// `offset_ms` ahead of ours, received at `received_at`. Claims can't be
+/// dated after their receipt or more than `window` ms before it, so a
+/// wrong clock can't win every race.
+pub fn claim_time(at: u64, offset_ms: i64, received_at: u64, window: u64) -> u64 {
+    at.saturating_add_signed(offset_ms.saturating_neg())
+        .clamp(received_at.saturating_sub(window), received_at)
+}
+
+/// Player presence information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerPresence {
+    pub pubkey: String,
+    pub joined_at: u64,
+    pub last_seen: u64,
+    /// When we last heard from the player, on the monotonic clock (see
+    /// `time::monotonic_ms`); local only, for disconnect thresholds
+    #[serde(skip)]
+    pub seen_at: u64,
+    pub ready: bool,
+    /// Heartbeat-based one-way latency estimate (ms); local only, not
+    /// published in the room event
+    #[serde(default, skip_serializing)]
+    pub latency_ms: Option<u64>,
+    /// The player's app is in the background (from their heartbeats);
+    /// local only
+    #[serde(default, skip_serializing)]
+    pub away: bool,
+    /// Session the player last joined with; local only
+    #[serde(default, skip_serializing)]
+    pub session: Option<String>,
+    /// What the player announced when joining; `None` for players on
+    /// versions without capability exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<Capabilities>,
+    /// Link from the player's match key to their long-term identity; see
+    /// `Arena::player_identity()`. Local only: links travel encrypted in
+    /// `join` and `seat`, never in the public roster
+    #[serde(default, skip_serializing)]
+    pub identity: Option<Box<IdentityLink>>,
+}
+
+impl PlayerPresence {
+    /// Note that we just heard from the player
+    pub fn touch(&mut self) {
+        self.last_seen = now_ms();
+        self.seen_at = monotonic_ms();
+    }
+
+    /// Milliseconds since we last heard from the player
+    pub fn silent_ms(&self) -> u64 {
+        monotonic_ms().saturating_sub(self.seen_at)
+    }
+}
+
+/// Optional protocol features a peer supports, exchanged in the join
+/// handshake. Peers that announce nothing get the default: JSON only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Compressed payloads
+    #[serde(default)]
+    pub compression: bool,
+    /// Wire formats the peer can decode, most preferred first
+    #[serde(default = "default_wire_formats")]
+    pub wire_formats: Vec<String>,
+    /// Input-based synchronization
+    #[serde(default)]
+    pub input_sync: bool,
+    /// Encrypted room traffic
+    #[serde(default)]
+    pub encryption: bool,
+    /// Several messages in one event (`batch`)
+    #[serde(default)]
+    pub batch: bool,
+}
+
+fn default_wire_formats() -> Vec<String> {
+    vec!["json".to_string()]
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            compression: false,
+            wire_formats: default_wire_formats(),
+            input_sync: false,
+            encryption: false,
+            batch: false,
+        }
+    }
+}
+
+impl Capabilities {
+    /// Features both sides support; wire formats keep our preference order
+    pub fn intersect(&self, other: &Capabilities) -> Capabilities {
+        Capabilities {
+            compression: self.compression && other.compression,
+            wire_formats: self
+                .wire_formats
+                .iter()
+                .filter(|f| other.wire_formats.contains(f))
+                .cloned()
+                .collect(),
+            input_sync: self.input_sync && other.input_sync,
+            encryption: self.encryption && other.encryption,
+            batch: self.batch && other.batch,
+        }
+    }
+}
+
+/// Room info for discovery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub room_id: String,
+    pub game_id: String,
+    pub status: RoomStatus,
+    pub host_pubkey: String,
+    pub player_count: usize,
+    pub max_players: usize,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub seed: u64,
+    /// `client` tag of the room event (`{name}/{version}`), if any
+    #[serde(default)]
+    pub client: Option<String>,
+    /// Current round (0 = not started)
+    #[serde(default)]
+    pub round: u32,
+    /// Current phase within the round
+    #[serde(default)]
+    pub phase: Option<String>,
+    /// Spectators following the room, not counted in `player_count`
+    #[serde(default)]
+    pub spectator_count: usize,
+    #[serde(default)]
+    pub visibility: RoomVisibility,
+    /// Whether joining needs the room password
+    #[serde(default)]
+    pub locked: bool,
+}
+
+impl RoomInfo {
+    /// Number of free player slots
+    pub fn free_slots(&self) -> usize {
+        self.max_players.saturating_sub(self.player_count)
     }
 
-    pub fn room_expiry(mut self, ms: u64) -> Self {
-        self.room_expiry = ms;
-        self
+    /// Whether the room has passed its expiry time
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| now_ms() > expires_at)
     }
 
-    pub fn max_players(mut self, n: usize) -> Self {
-        self.max_players = n;
-        self
+    /// Whether the room is waiting for players, not expired and has a free slot
+    pub fn is_joinable(&self) -> bool {
+        self.status == RoomStatus::Waiting && !self.is_expired() && self.free_slots() > 0
     }
 
-    pub fn start_mode(mut self, mode: StartMode) -> Self {
-        self.start_mode = mode;
-        self
+    /// Time since the room was created in ms
+    pub fn age(&self) -> u64 {
+        now_ms().saturating_sub(self.created_at)
     }
+}
 
-    pub fn countdown_seconds(mut self, secs: u32) -> Self {
-        self.countdown_seconds = secs;
-        self
-    }
+/// A public summary seen by `Arena::watch_summaries()`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchSummary {
+    pub room_id: String,
+    /// Player who published the summary
+    pub pubkey: String,
+    pub summary: serde_json::Value,
+    /// When the summary was published (ms)
+    pub created_at: u64,
+}
 
-    pub fn base_url(mut self, url: impl Into<String>) -> Self {
-        self.base_url = Some(url.into());
-        self
+/// What `Arena::join()` would run into, from `Arena::plan_join()`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinPlan {
+    pub room: RoomInfo,
+    /// Why joining would fail, `None` if it should succeed
+    pub blocker: Option<JoinBlocker>,
+    /// Whether a seat is free
+    pub seat_available: bool,
+    /// Whether the host's protocol version is one we speak and its client
+    /// passes `compatible_versions`
+    pub protocol_compatible: bool,
+    /// Protocol version of the room event
+    pub protocol_version: u32,
+    /// Relays the match would run on
+    pub relays: Vec<String>,
+    /// Time it took to fetch the room (ms), a rough relay round trip
+    pub estimated_latency_ms: u64,
+}
+
+impl JoinPlan {
+    /// Whether `join()` is expected to succeed
+    pub fn can_join(&self) -> bool {
+        self.blocker.is_none()
     }
 }
 
-/// Room state (game-agnostic)
-#[derive(Debug, Clone, Default)]
-pub struct RoomState {
-    pub room_id: Option<String>,
-    pub status: RoomStatus,
-    pub is_host: bool,
-    pub seed: u64,
-    pub created_at: Option<u64>,
-    pub expires_at: Option<u64>,
-    pub rematch_requested: bool,
+/// Reason a room can't be joined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinBlocker {
+    /// The host is on our block list
+    HostBlocked,
+    Deleted,
+    Expired,
+    Full,
+    /// The host runs a newer protocol or a client outside
+    /// `compatible_versions`
+    Incompatible,
 }
 
-/// Player presence information
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PlayerPresence {
-    pub pubkey: String,
-    pub joined_at: u64,
-    pub last_seen: u64,
-    pub ready: bool,
+impl From<JoinBlocker> for ArenaError {
+    fn from(blocker: JoinBlocker) -> Self {
+        match blocker {
+            JoinBlocker::HostBlocked => {
+                ArenaError::NotAuthorized("room host is blocked".to_string())
+            }
+            JoinBlocker::Deleted => ArenaError::RoomDeleted,
+            JoinBlocker::Expired => ArenaError::RoomExpired,
+            JoinBlocker::Full => ArenaError::RoomFull,
+            JoinBlocker::Incompatible => {
+                ArenaError::InvalidRoomData("incompatible protocol or client".to_string())
+            }
+        }
+    }
 }
 
-/// Room info for discovery
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RoomInfo {
-    pub room_id: String,
+/// Room settings a host saved to recreate identical rooms, published under
+/// their key (see `Arena::save_template`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomTemplate {
+    /// Name the host picked, unique among their templates for the game
+    pub id: String,
     pub game_id: String,
-    pub status: RoomStatus,
-    pub host_pubkey: String,
-    pub player_count: usize,
     pub max_players: usize,
-    pub created_at: u64,
-    pub expires_at: Option<u64>,
-    pub seed: u64,
+    #[serde(default)]
+    pub start_mode: StartMode,
+    #[serde(default)]
+    pub countdown_seconds: u32,
+    #[serde(default)]
+    pub sync_mode: SyncMode,
+    /// Relays advertised for game traffic (empty = the host's read relays)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<String>,
+    /// Start settings (map, rules), defined by the game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_config: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub chat_slow_mode: u32,
+}
+
+impl RoomTemplate {
+    /// Template of the room settings in `config`
+    pub fn from_config(id: impl Into<String>, config: &ArenaConfig) -> Self {
+        Self {
+            id: id.into(),
+            game_id: config.game_id.clone(),
+            max_players: config.max_players,
+            start_mode: config.start_mode,
+            countdown_seconds: config.countdown_seconds,
+            sync_mode: config.sync_mode,
+            relays: config.read_relays.clone(),
+            start_config: None,
+            chat_slow_mode: 0,
+        }
+    }
+
+    pub fn with_start_config<C: Serialize>(mut self, config: &C) -> Result<Self, ArenaError> {
+        self.start_config = Some(serde_json::to_value(config)?);
+        Ok(self)
+    }
+
+    pub fn with_chat_slow_mode(mut self, seconds: u32) -> Self {
+        self.chat_slow_mode = seconds;
+        self
+    }
+}
+
+/// Serializable snapshot of an arena session (see `Arena::snapshot`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArenaSnapshot<T> {
+    /// Public key of the arena that took the snapshot
+    pub public_key: String,
+    pub room: RoomState,
+    pub players: Vec<PlayerPresence>,
+    /// Latest game state received from each player
+    pub player_states: HashMap<String, T>,
+    /// Snapshot time in ms
+    pub taken_at: u64,
+    /// Key of an encrypted room, as hex. Keep snapshots as private as the
+    /// secret key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_key: Option<String>,
 }
 
 /// Opponent state with generic game state
@@ -190,7 +1786,7 @@ impl<T> OpponentState<T> {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum EventContent {
-    Room(RoomEventContent),
+    Room(Box<RoomEventContent>),
     Join(JoinEventContent),
     State(StateEventContent),
     GameOver(GameOverEventContent),
@@ -198,10 +1794,85 @@ pub enum EventContent {
     Heartbeat(HeartbeatEventContent),
     Ready(ReadyEventContent),
     GameStart(GameStartEventContent),
+    Seat(SeatEventContent),
+    Chat(ChatEventContent),
+    Turn(TurnEventContent),
+    Invite(InviteEventContent),
+    Party(PartyEventContent),
+    Countdown(CountdownEventContent),
+    Roster(RosterEventContent),
+    Digest(DigestEventContent),
+    Phase(PhaseEventContent),
+    Clock(ClockEventContent),
+    Audit(AuditEventContent),
+    Message(MessageEventContent),
+    Vote(VoteEventContent),
+    Claim(ClaimEventContent),
+    GameEnded(GameEndedEventContent),
+    Moderation(ModerationEventContent),
+    Kick(KickEventContent),
+    Leave(LeaveEventContent),
+    Spectate(SpectateEventContent),
+    ResendRequest(ResendRequestEventContent),
+    SnapshotRequest(SnapshotRequestEventContent),
+    Snapshot(SnapshotEventContent),
+    Batch(BatchEventContent),
+    Summary(SummaryEventContent),
+    Sealed(SealedEventContent),
+    Rekey(RekeyEventContent),
+    /// Message with a `type` this version does not know (e.g. a newer peer's
+    /// extension). Produced by [`crate::migrate::decode_event`] only.
+    #[serde(skip)]
+    Unknown {
+        message_type: String,
+        payload: serde_json::Value,
+    },
+}
+
+impl EventContent {
+    /// Wire `type` of every message this version knows
+    pub const TYPES: &[&str] = &[
+        "room",
+        "join",
+        "state",
+        "gameover",
+        "rematch",
+        "heartbeat",
+        "ready",
+        "gamestart",
+        "seat",
+        "chat",
+        "turn",
+        "invite",
+        "party",
+        "countdown",
+        "roster",
+        "digest",
+        "phase",
+        "clock",
+        "audit",
+        "message",
+        "vote",
+        "claim",
+        "gameended",
+        "moderation",
+        "kick",
+        "leave",
+        "spectate",
+        "resendrequest",
+        "snapshotrequest",
+        "snapshot",
+        "batch",
+        "summary",
+        "sealed",
+        "rekey",
+    ];
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoomEventContent {
+    #[serde(default)]
+    pub version: u32,
     pub status: RoomStatus,
     pub seed: u64,
     pub host_pubkey: String,
@@ -210,11 +1881,123 @@ pub struct RoomEventContent {
     pub expires_at: Option<u64>,
     #[serde(default)]
     pub players: Vec<PlayerPresence>,
+    /// Relays agreed upon for ephemeral game traffic (empty = any)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<String>,
+    /// Low-latency relay preferred by the host for this match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_relay: Option<String>,
+    /// Public keys allowed to publish room updates and start the game
+    /// besides the host
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub co_hosts: Vec<String>,
+    /// Player whose move it is, for correspondence play
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn: Option<String>,
+    /// Start time (ms) of a running countdown, for peers arriving late
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub countdown_at: Option<u64>,
+    /// Number of players when `players` is left out of a large room's event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_count: Option<usize>,
+    /// [`roster_hash`] of the left-out players, so peers can tell whether
+    /// their copy is current
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roster_hash: Option<String>,
+    /// Fan-out mode of the room (absent = `mesh`)
+    #[serde(default, skip_serializing_if = "SyncMode::is_mesh")]
+    pub sync_mode: SyncMode,
+    /// Current round, for spectators and the lobby
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub round: u32,
+    /// Current phase within the round
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
+    /// Cumulative standings, so late joiners see them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scoreboard: Vec<Standing>,
+    /// Start settings chosen by the host (map, rules), defined by the game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_config: Option<serde_json::Value>,
+    /// Turn order set by the host; empty = derived from the seed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turn_order: Vec<String>,
+    /// Seconds each player must wait between chat messages
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub chat_slow_mode: u32,
+    /// Players the host muted for everyone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chat_muted: Vec<String>,
+    /// Spectators following the room
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub spectator_count: usize,
+    /// Spectators follow only the public summaries, on the summary channel
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub summaries: bool,
+    #[serde(default, skip_serializing_if = "RoomVisibility::is_public")]
+    pub visibility: RoomVisibility,
+    /// Joining needs the room password. Joiners prove it to the host in
+    /// their `join`; nothing that could check a guess is published
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub password: bool,
+    /// Game state is sealed; seated players get the key from the host
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+    /// The host seats players while the game is running
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub late_join: bool,
+}
+
+fn is_zero<N: Default + PartialEq>(n: &N) -> bool {
+    *n == N::default()
+}
+
+impl RoomEventContent {
+    /// Number of players, whether or not the roster is included
+    pub fn player_count(&self) -> usize {
+        self.player_count.unwrap_or(self.players.len())
+    }
+
+    /// Whether the roster was left out and must be requested from the host
+    pub fn is_roster_compressed(&self) -> bool {
+        self.players.is_empty() && self.player_count.is_some_and(|n| n > 0)
+    }
+
+    /// Relays to use for the room's ephemeral traffic.
+    ///
+    /// The preferred relay comes first, followed by the advertised relays
+    /// (or `fallback` when none are advertised).
+    pub fn game_relays(&self, fallback: &[String]) -> Vec<String> {
+        let mut relays = if self.relays.is_empty() {
+            fallback.to_vec()
+        } else {
+            self.relays.clone()
+        };
+        if let Some(preferred) = &self.preferred_relay
+            && !relays.contains(preferred)
+        {
+            relays.insert(0, preferred.clone());
+        }
+        relays
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JoinEventContent {
     pub player_pubkey: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<Capabilities>,
+    /// The joiner's `IdentityLink` as JSON, NIP-44 encrypted to the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// Random id of the joining arena, telling apart two sessions of the
+    /// same key (a second tab, a client rejoining after a crash)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Proof of the room password, bound to the joiner's key
+    /// (`room_password_proof`), NIP-44 encrypted to the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -224,11 +2007,148 @@ pub struct StateEventContent {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameOverEventContent {
+    /// String form of the reason, for peers without `code`
     pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ReasonCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub final_score: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub winner: Option<String>,
+    /// Sender's clock (ms) when their game ended, for the host to order
+    /// simultaneous game overs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<u64>,
+}
+
+impl GameOverEventContent {
+    pub fn new(reason: Reason, final_score: Option<i64>) -> Self {
+        Self {
+            reason: reason.to_string(),
+            code: Some(reason.code),
+            detail: reason.detail,
+            final_score,
+            winner: None,
+            at: None,
+        }
+    }
+
+    /// The structured reason, parsed from the string form for older peers
+    pub fn reason(&self) -> Reason {
+        match self.code {
+            Some(code) => Reason {
+                code,
+                detail: self.detail.clone(),
+            },
+            None => Reason::from_text(&self.reason),
+        }
+    }
+}
+
+impl EventContent {
+    /// Whether the message changes room or match state and is kept for
+    /// resending (see [`crate::shadow`]); state, heartbeats, chat and other
+    /// traffic that is superseded or merely informative are not
+    pub fn is_control(&self) -> bool {
+        matches!(
+            self,
+            Self::GameOver(_)
+                | Self::GameEnded(_)
+                | Self::Rematch(_)
+                | Self::Ready(_)
+                | Self::GameStart(_)
+                | Self::Seat(_)
+                | Self::Turn(_)
+                | Self::Countdown(_)
+                | Self::Phase(_)
+                | Self::Clock(_)
+                | Self::Vote(_)
+                | Self::Claim(_)
+                | Self::Moderation(_)
+                | Self::Kick(_)
+                | Self::Leave(_)
+                | Self::Rekey(_)
+        )
+    }
+}
+
+/// Messages published together as one event (`Arena::send_batch`), handled
+/// in order. Control messages are never batched, so they can be resent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchEventContent {
+    /// Content of each message, as it would be published on its own
+    pub messages: Vec<String>,
+}
+
+/// The host removed `player_pubkey` from the match, after a vote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KickEventContent {
+    pub player_pubkey: String,
+}
+
+/// The host's next room key after a player left or was kicked, NIP-44
+/// encrypted to each remaining player, by public key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RekeyEventContent {
+    pub keys: HashMap<String, String>,
+}
+
+/// Player leaving the room for good, sent by `Arena::shutdown()`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LeaveEventContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<Reason>,
+}
+
+/// Compact public view of a player's game (score, round, key positions),
+/// published on the summary channel for spectators and match browsers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummaryEventContent {
+    pub summary: serde_json::Value,
+}
+
+/// A message sealed with the room key (see [`crate::seal`])
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedEventContent {
+    /// Base64 NIP-44 v2 payload of the message's content
+    pub payload: String,
+}
+
+/// Spectator following the room, repeated every heartbeat interval
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpectateEventContent {}
+
+/// Ask the host to publish again the control messages it kept that were
+/// created at or after `since` (ms)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResendRequestEventContent {
+    pub since: u64,
+}
+
+/// Ask for the game state, e.g. after joining a running game. The host
+/// answers, or `peer` if set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotRequestEventContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<String>,
+}
+
+/// Game state handed to `player_pubkey`, who asked for it: every player's
+/// last state and where the match stands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEventContent {
+    pub player_pubkey: String,
+    /// Last state of each player, by public key
+    #[serde(default)]
+    pub states: HashMap<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub round: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -236,6 +2156,86 @@ pub struct RematchEventContent {
     pub action: RematchAction,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub new_seed: Option<u64>,
+    /// Round the new seed belongs to (`round` action)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<u32>,
+}
+
+/// Lobby vote: the host or a co-host proposes, players cast, the host
+/// closes with the result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoteEventContent {
+    pub action: VoteAction,
+    pub topic: String,
+    /// Options of a `propose`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    /// Chosen option of a `cast`, winner of a `result`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choice: Option<String>,
+    /// Votes per option of a `result`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tally: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteAction {
+    Propose,
+    Cast,
+    Result,
+}
+
+/// Claim arbitration: players claim, the host answers with the winner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimEventContent {
+    pub action: ClaimAction,
+    pub claim_id: String,
+    /// Claimant's clock (ms) when claiming
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<u64>,
+    /// Winner of a `resolved`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub winner: Option<String>,
+}
+
+/// The host's decision on a match after collecting its game overs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameEndedEventContent {
+    /// Players in the order their games ended
+    pub order: Vec<String>,
+    /// `None` for a draw or a match the rules can't decide
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub winner: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaimAction {
+    Claim,
+    Resolved,
+}
+
+/// Chat rule set by the host, followed by every peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationEventContent {
+    pub action: ModerationAction,
+    /// Wait between messages for `slowmode` (0 = off)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seconds: Option<u32>,
+    /// Target of `mute` and `unmute`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_pubkey: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    SlowMode,
+    Mute,
+    Unmute,
+    /// Peers drop the chat log they show
+    Clear,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -243,11 +2243,20 @@ pub struct RematchEventContent {
 pub enum RematchAction {
     Request,
     Accept,
+    /// Fresh seed for the next round of the running match
+    Round,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HeartbeatEventContent {
     pub timestamp: u64,
+    /// Delay (ms) the sender last measured for each peer's heartbeats, for
+    /// clock offset estimation
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub seen: HashMap<String, i64>,
+    /// The sender's app is in the background
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub away: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -255,14 +2264,316 @@ pub struct ReadyEventContent {
     pub ready: bool,
 }
 
+/// Host-mode start, with the start settings and the seed to confirm
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameStartEventContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turn_order: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatEventContent {
+    pub text: String,
+}
+
+/// App-defined message on a named channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageEventContent {
+    pub channel: String,
+    pub payload: serde_json::Value,
+}
+
+/// Message received on a channel opened with `Arena::channel()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMessage {
+    pub pubkey: String,
+    pub channel: String,
+    pub payload: serde_json::Value,
+}
+
+/// Countdown started by the host; the game starts at `starts_at` (ms)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CountdownEventContent {
+    pub starts_at: u64,
+}
+
+/// Player states and presence collected by the host of a `Hosted` room
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DigestEventContent {
+    /// Latest state of each player that sent one since the last digest
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub states: HashMap<String, serde_json::Value>,
+    /// Last time (ms) the host heard from each player, sent once per
+    /// heartbeat interval
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub presence: HashMap<String, u64>,
+}
+
+/// Roster request, or the host's answer listing every player
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosterEventContent {
+    pub action: RosterAction,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub players: Vec<PlayerPresence>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RosterAction {
+    Request,
+    Response,
+}
+
+/// Host's time banks after a turn change or flag fall
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockEventContent {
+    /// Time left per player in ms, as of sending
+    pub remaining: HashMap<String, u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub running: Option<String>,
+    /// Player who ran out of time and forfeits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flagged: Option<String>,
+}
+
+/// Player's declared input chain, sent after their game over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEventContent {
+    /// Number of `state` messages in the chain
+    pub inputs: u64,
+    /// Hex hash of the last link
+    pub chain: String,
+}
+
+/// Round and phase set by the host or a co-host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseEventContent {
+    pub round: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
+}
+
+/// The turn passed to `player_pubkey`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnEventContent {
+    pub player_pubkey: String,
+    /// Game-defined move the sender made before passing the turn
+    /// (`TurnManager::submit_turn`)
+    #[serde(default, rename = "move", skip_serializing_if = "Option::is_none")]
+    pub player_move: Option<serde_json::Value>,
+}
+
+/// Direct room invitation and its answer, sent to a player's inbox rather
+/// than a room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteEventContent {
+    pub action: InviteAction,
+    pub game_id: String,
+    pub room_id: String,
+    /// Relays the room's traffic uses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<String>,
+    /// Password proof for the invitee's key, when the room has a password,
+    /// NIP-44 encrypted to the invitee
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InviteAction {
+    Invite,
+    Accept,
+    Decline,
+}
+
+/// Party channel message (see [`crate::party`])
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyEventContent {
+    pub action: PartyAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_id: Option<String>,
+    /// Relays of the room to join (`match` only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartyAction {
+    /// Member is online
+    Presence,
+    /// Member left the party
+    Leave,
+    /// Leader asks members to join `room_id`
+    Match,
+    /// Member got a seat
+    Joined,
+    /// Member could not get a seat
+    Failed,
+    /// Every member is seated
+    Matched,
+    /// Leader gave up on `room_id`; members leave it
+    Abort,
+}
+
+/// Host's answer to a join: the assigned seat (`None` = room full) and the
+/// full list of confirmed seats
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GameStartEventContent {}
+pub struct SeatEventContent {
+    pub player_pubkey: String,
+    pub seat: Option<usize>,
+    pub seats: Vec<String>,
+    /// The joiner's capabilities, passed on by the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<Capabilities>,
+    /// The joiner's identity link, NIP-44 encrypted by the host to each
+    /// seated player, keyed by that player's public key
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub identity: HashMap<String, String>,
+    /// Identity links of the players already in the room, keyed by public
+    /// key, as JSON NIP-44 encrypted to the joiner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identities: Option<String>,
+    /// Why the seat was denied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<Reason>,
+    /// Session of the join this answers; other sessions of the same key
+    /// are superseded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Key of an encrypted room, NIP-44 encrypted to the seated player
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_key: Option<String>,
+}
+
+/// Changes that bring a peer's roster in line with the host's
+#[derive(Debug, Clone, Default)]
+pub struct RosterDiff {
+    /// Players the host lists that we don't know
+    pub added: Vec<PlayerPresence>,
+    /// Players we know that the host no longer lists
+    pub removed: Vec<String>,
+}
+
+impl RosterDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compare our roster with the players of the host's room event, published
+/// at `published_at` (ms). Players who joined after that are kept, as the
+/// event predates them; `me` is never added or removed.
+pub fn reconcile_roster(
+    local: &[PlayerPresence],
+    host: &[PlayerPresence],
+    published_at: u64,
+    me: &str,
+) -> RosterDiff {
+    let added = host
+        .iter()
+        .filter(|p| p.pubkey != me && !local.iter().any(|l| l.pubkey == p.pubkey))
+        .cloned()
+        .collect();
+    let removed = local
+        .iter()
+        .filter(|l| {
+            l.pubkey != me
+                && l.joined_at < published_at
+                && !host.iter().any(|p| p.pubkey == l.pubkey)
+        })
+        .map(|l| l.pubkey.clone())
+        .collect();
+    RosterDiff { added, removed }
+}
+
+/// Order-independent hash of a roster's public keys (64-bit FNV-1a over the
+/// sorted keys, hex). Detects changes; it is not a commitment.
+pub fn roster_hash<'a>(pubkeys: impl IntoIterator<Item = &'a str>) -> String {
+    let mut pubkeys: Vec<&str> = pubkeys.into_iter().collect();
+    pubkeys.sort_unstable();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for pubkey in pubkeys {
+        for byte in pubkey.bytes().chain([b',']) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+/// Hash chain over the `state` messages a player sent: each link is the
+/// SHA-256 of the previous link (hex, empty for the first) followed by the
+/// message content exactly as published
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputChain {
+    pub count: u64,
+    pub hash: String,
+}
+
+impl InputChain {
+    pub fn push(&mut self, content: &str) {
+        let mut link = std::mem::take(&mut self.hash).into_bytes();
+        link.extend_from_slice(content.as_bytes());
+        self.hash = Sha256Hash::hash(&link).to_string();
+        self.count += 1;
+    }
+}
+
+/// Secret both sides derive from the room password, salted with the room id
+fn room_password_key(room_id: &str, password: &str) -> String {
+    Sha256Hash::hash(format!("{room_id}:{password}").as_bytes()).to_string()
+}
+
+/// Proof of the room password sent, encrypted to the host, in `pubkey`'s
+/// join. It is useless to other keys.
+pub fn room_password_proof(room_id: &str, password: &str, pubkey: &str) -> String {
+    let key = room_password_key(room_id, password);
+    Sha256Hash::hash(format!("{key}{pubkey}").as_bytes()).to_string()
+}
+
+/// Address of a room event (`30078:<host>:<d>`), for `a` tags; unlike the
+/// event id it survives republishing
+pub fn room_address(host_pubkey: &str, room_tag: &str) -> String {
+    format!("{}:{host_pubkey}:{room_tag}", kinds::ROOM)
+}
+
+/// First characters of a public key, for display
+pub(crate) fn short_key(pubkey: &str) -> &str {
+    pubkey.get(..8).unwrap_or(pubkey)
+}
 
 /// Generate room tag from game ID and room ID
 pub fn create_room_tag(game_id: &str, room_id: &str) -> String {
     format!("{game_id}-{room_id}")
 }
 
+/// `d` tag of a match archive; the seed tells rematches in a room apart
+pub fn create_archive_tag(game_id: &str, room_id: &str, seed: u64) -> String {
+    format!("{game_id}-{room_id}-{seed}")
+}
+
+/// `d` tag of a room template
+pub fn create_template_tag(game_id: &str, template_id: &str) -> String {
+    format!("{game_id}-template-{template_id}")
+}
+
+/// Tag of the channel public summaries are published on
+pub fn create_summary_tag(game_id: &str, room_id: &str) -> String {
+    format!("{game_id}-{room_id}-summary")
+}
+
+/// Tag of the channel players of a `Hosted` room send state and heartbeats
+/// on; only the host subscribes to it
+pub fn create_host_tag(game_id: &str, room_id: &str) -> String {
+    format!("{game_id}-{room_id}-host")
+}
+
 /// Generate a random seed
 pub fn generate_seed() -> u64 {
     use rand::Rng;
@@ -279,17 +2590,4 @@ pub fn generate_room_id() -> String {
         .collect()
 }
 
-/// Current time in milliseconds
-#[cfg(not(target_arch = "wasm32"))]
-pub fn now_ms() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64
-}
-
-/// Current time in milliseconds (WASM version)
-#[cfg(target_arch = "wasm32")]
-pub fn now_ms() -> u64 {
-    js_sys::Date::now() as u64
-}
+pub use crate::time::now_ms;